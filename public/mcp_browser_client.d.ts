@@ -1,26 +1,513 @@
 declare namespace wasm_bindgen {
 	/* tslint:disable */
 	/* eslint-disable */
-	export function get_timestamp(): bigint;
+	/**
+	 * Call a tool on several servers at once and merge the results.
+	 *
+	 * `servers` is an array of URLs or aliases; when empty or omitted every
+	 * registered provider is used. `strategy` is `concatenate` (default),
+	 * `first_success` or `plurality` (alias `majority`). Returns
+	 * `{ tool_name, strategy, outcomes, merged }` where `merged` is null when
+	 * no server succeeded.
+	 */
+	export function call_tool_fanout(name: string, args: any, servers: any, strategy?: string | null, timeout_ms?: number | null, engram_id?: string | null): Promise<any>;
+	/**
+	 * Render a tool result as CBus text.
+	 */
+	export function render_tool_result_text(result_json: string): string;
+	/**
+	 * Decode a tool result into typed content blocks, each annotated with its
+	 * rendered `text` and, where applicable, a `dataUrl` for the UI.
+	 */
+	export function decode_tool_result(result_json: string): any;
+	/**
+	 * Extract JSON-RPC calls from model output. Returns a JSON array of
+	 * `{ call, start, end, repairs, batch_index? }`.
+	 */
+	export function extract_json_rpc_calls(text: string): string;
+	/**
+	 * Prune an engram's messages with `{ max_messages, max_age_ms, max_bytes }`,
+	 * or the configured policy when `policy_json` is null. Resolves to how many
+	 * messages were removed.
+	 */
+	export function prune_conversation(engram_id: string, policy_json?: string | null): Promise<any>;
+	/**
+	 * Delete a conversation and its messages.
+	 */
+	export function delete_conversation(engram_id: string): Promise<any>;
+	/**
+	 * Load an engram's messages, oldest first unless `newest_first`.
+	 * `options_json` may give `{ since, until, limit, after, newest_first }`;
+	 * resolves to `{ engramId, messages, next }` as JSON, where `next` is
+	 * passed back as `after` for the following page and is null on the last.
+	 */
+	export function load_engram_messages(engram_id: string, options_json?: string | null): Promise<any>;
+	export function get_conversation_prune_policy(): string;
+	/**
+	 * Resolves to the value saved under `key`, or null.
+	 */
+	export function load_state(key: string): Promise<any>;
+	/**
+	 * Open the store, migrating its schema; resolves to the schema version.
+	 */
+	export function open_conversation_store(): Promise<any>;
+	/**
+	 * A new RFC 9562 UUIDv7; ids minted in this worker are strictly
+	 * increasing.
+	 */
+	export function uuid_v7(): string;
+	/**
+	 * Save `value` under `key`; resolves once written.
+	 */
+	export function save_state(key: string, value: any): Promise<any>;
+	/**
+	 * Append `{ engramId, role?, text, timestamp?, id?, toolName?, serverUrl?,
+	 * structuredContent?, outputValidation? }` to its engram, creating the
+	 * conversation on first use. Resolves to the stored message as JSON, with
+	 * its id and timestamp filled in. The prune policy runs afterwards.
+	 */
+	export function append_engram_message(message_json: string): Promise<any>;
+	/**
+	 * Resolves to the conversations' `{ engramId, created, updated }` as a
+	 * JSON array.
+	 */
+	export function list_conversations(): Promise<any>;
+	/**
+	 * Set the policy applied to an engram after each append:
+	 * `{ max_messages?, max_age_ms?, max_bytes? }`.
+	 */
+	export function set_conversation_prune_policy(policy_json: string): void;
+	/**
+	 * Markdown tool catalog as it appears in the bootrom.
+	 */
+	export function get_tool_catalog_text(): string;
+	export function get_bootrom_sections(): string;
+	/**
+	 * Restore the built-in sections.
+	 */
+	export function reset_bootrom_sections(): void;
+	/**
+	 * Replace the bootrom sections. Takes a JSON array of
+	 * `{ id, template, enabled? }`, composed in order.
+	 */
+	export function set_bootrom_sections(sections_json: string): void;
+	/**
+	 * Check a template's syntax without rendering it. Returns `{ ok: true }`
+	 * or `{ ok: false, error }`.
+	 */
+	export function check_tap_template(template: string): string;
+	/**
+	 * Render a tap template. Returns `{ output }` or `{ error }` where the
+	 * error carries `message`, `offset`, `line`, `column` and `source_line`.
+	 */
+	export function render_tap_template(template: string, context_json: string): string;
+	/**
+	 * Render every templated string in a tap's `args`. Returns
+	 * `{ args, errors }`; each error names the argument by JSON Pointer.
+	 */
+	export function render_tap_args(args_json: string, context_json: string): string;
+	/**
+	 * Register the function behind the `js` provider: it receives a JSON array
+	 * of texts and returns (a promise of) an array of vectors, or its JSON.
+	 */
+	export function set_embedding_callback(callback?: Function | null): void;
+	/**
+	 * `{ syncing, pending, indexed }`: whether a sync is running, how many live
+	 * events still lack a vector and how many have one.
+	 */
+	export function get_vector_sync_status(): string;
+	/**
+	 * Embed memory events not yet in the index, up to 512 per call; resolves
+	 * to how many were.
+	 */
+	export function sync_vector_index(): Promise<any>;
+	/**
+	 * Takes `{ provider, index, top_k, min_score }`. `provider` is
+	 * `{ kind: "js" }`, `{ kind: "tool", server_url, tool_name, text_argument? }`
+	 * or `{ kind: "hashing", dimensions? }`; `index` is `{ kind: "flat" }` or
+	 * `{ kind: "hnsw", m?, ef_construction?, ef_search? }`. Changing the
+	 * provider drops every vector.
+	 */
+	export function set_vector_config(config_json: string): void;
+	/**
+	 * Restore a snapshot saved from `get_vector_index` or the persistence
+	 * hook.
+	 */
+	export function restore_vector_index(snapshot_json: string): void;
+	/**
+	 * The config and vectors as JSON, for storage.
+	 */
+	export function get_vector_index(): string;
+	/**
+	 * Register a function called with the snapshot as JSON whenever the
+	 * index changes; pass `null` to unregister.
+	 */
+	export function set_vector_persistence(hook?: Function | null): void;
+	export function get_vector_config(): string;
+	/**
+	 * Top-k memory events by cosine similarity to `query`. Takes
+	 * `{ query, k?, min_score?, engram_id?, tags?, source?, since?, until?, text? }`
+	 * and resolves to `{ hits: [{ score, event }] }`, best first.
+	 */
+	export function vector_search(query_json: string): Promise<any>;
+	/**
+	 * Check a call against the breaker, counting it when admitted. Returns
+	 * `{ allowed, trip?, message? }`.
+	 */
+	export function check_tool_call_breaker(engram_id: string | null | undefined, server: string, tool: string, args_json: string): string;
+	/**
+	 * The breaker's state as JSON, for storage.
+	 */
+	export function get_breaker_state(): string;
+	/**
+	 * Register a function called with the config as JSON whenever it is set;
+	 * pass `null` to unregister. Restore a saved config with
+	 * `set_breaker_config` before registering.
+	 */
+	export function set_breaker_config_persistence(hook?: Function | null): void;
+	/**
+	 * Forget the state of one engram, or of all when `engram_id` is null.
+	 */
+	export function reset_breaker(engram_id?: string | null): void;
+	/**
+	 * Restore state saved from `get_breaker_state` or the persistence hook.
+	 */
+	export function restore_breaker_state(state_json: string): void;
+	export function set_breaker_config(config_json: string): void;
+	export function get_breaker_config(): string;
+	/**
+	 * Register a function called with the state as JSON shortly after it
+	 * changes; pass `null` to unregister.
+	 */
+	export function set_breaker_persistence(hook?: Function | null): void;
 	export function get_uptime(): bigint;
+	export function get_metadata(): string;
+	export function get_server_url(): string;
 	export function increment_uptime(): void;
-	export function get_version(): string;
+	export function set_debug_mode(enabled: boolean): void;
+	export function get_compiled_info(): string;
+	export function query_tools(): Promise<any>;
 	export function set_server_url(url: string): void;
-	export function get_server_url(): string;
-	export function health_check(): Promise<number>;
-	export function check_mcp_server(): Promise<number>;
+	export function get_version(): string;
+	/**
+	 * The bootrom imprint, composed from its sections against the current
+	 * tool registry.
+	 */
+	export function get_bootrom(): string;
+	/**
+	 * Call a tool and return a JSON-encoded `ToolCallOutcome`.
+	 *
+	 * Arguments are coerced and validated against the tool's cached
+	 * `inputSchema` first, then checked against the server's access rules for
+	 * `engram_id` and the confirmation policy, which may hold the call until it
+	 * is approved. The outcome's `kind` is one of `success`, `tool_error`,
+	 * `rpc_error`, `transport_error`, `invalid_arguments`, `policy` or
+	 * `rejected`; only undecodable `args` are reported as a rejected promise.
+	 */
+	export function call_tool(url: string, tool_name: string, args: any, engram_id?: string | null): Promise<any>;
+	export function get_server_info(): any;
+	export function get_timestamp(): bigint;
 	export function handle_message(message: string): Promise<any>;
-	export function get_metadata(): string;
-	export function add_memory_event(text: string): void;
-	export function clear_memory_events(): void;
 	export function initialize_mcp_server(url: string): Promise<any>;
-	export function get_server_info(): any;
-	export function query_tools(): Promise<any>;
 	export function list_tools(url: string): Promise<any>;
-	export function get_compiled_info(): string;
-	export function call_tool(url: string, tool_name: string, args: any): Promise<any>;
-	export function set_debug_mode(enabled: boolean): void;
-	export function get_bootrom(): string;
+	export function health_check(): Promise<number>;
+	export function check_mcp_server(): Promise<number>;
+	export function resume_agent_run(id: bigint): void;
+	/**
+	 * Drop finished runs from the registry.
+	 */
+	export function clear_finished_agent_runs(): void;
+	/**
+	 * The full record of a run: steps, transcript and stop reason.
+	 */
+	export function get_agent_run(id: bigint): string;
+	/**
+	 * Summaries of every run: `[{ id, engram_id, status, started_at, steps, stop_reason }]`.
+	 */
+	export function list_agent_runs(): string;
+	/**
+	 * Stop a run at its next checkpoint. Approvals its calls wait on are
+	 * rejected; a call already sent is left to finish but no further step
+	 * starts.
+	 */
+	export function abort_agent_run(id: bigint, reason?: string | null): void;
+	/**
+	 * Pause a run at its next checkpoint (between steps).
+	 */
+	export function pause_agent_run(id: bigint): void;
+	export function get_simulation_config(): string;
+	/**
+	 * Takes `{ enabled, engrams, record }`.
+	 */
+	export function set_simulation_config(config_json: string): void;
+	/**
+	 * Add fixtures from a JSON array of `{ server?, tool, arguments?, result }`.
+	 * A fixture replaces one with the same server, tool and arguments.
+	 */
+	export function add_simulation_fixtures(fixtures_json: string): number;
+	export function list_simulation_fixtures(): string;
+	/**
+	 * Simulated calls, oldest first, as `{ calls: [...] }`.
+	 */
+	export function get_simulation_log(): string;
+	export function clear_simulation_log(): void;
+	/**
+	 * Turn simulation on or off for one engram.
+	 */
+	export function set_engram_simulation(engram_id: string, enabled: boolean): void;
+	/**
+	 * Drop the fixtures of one tool, or all when `tool` is null.
+	 */
+	export function clear_simulation_fixtures(tool?: string | null): void;
+	/**
+	 * Store an event and return its id. `options_json` may give
+	 * `{ tags, source, ttl_ms, expires_at, engram_id }`; `source` is `user`,
+	 * `tool` or `resource` and defaults to `user`.
+	 */
+	export function add_memory_event(text: string, options_json?: string | null): bigint;
+	/**
+	 * Register a function called with the store as JSON whenever it changes;
+	 * pass `null` to unregister.
+	 */
+	export function set_memory_persistence(hook?: Function | null): void;
+	/**
+	 * The store as JSON, for storage.
+	 */
+	export function export_memory_events(): string;
+	/**
+	 * Events matching `{ since, until, tags, text, source, engram_id, limit,
+	 * oldest_first }`, as `{ events: [...] }`.
+	 */
+	export function query_memory_events(query_json: string): string;
+	export function remove_memory_event(id: bigint): boolean;
+	/**
+	 * Forget the events scoped to one engram, or every event when `engram_id`
+	 * is null.
+	 */
+	export function clear_memory_events(engram_id?: string | null): void;
+	/**
+	 * Restore a store saved from `export_memory_events` or the persistence
+	 * hook. Expired events are dropped.
+	 */
+	export function import_memory_events(store_json: string): void;
+	/**
+	 * Replace the remembered rules with a JSON array saved by the persistence
+	 * hook.
+	 */
+	export function restore_approval_rules(rules_json: string): void;
+	/**
+	 * Reject a pending call; `remember` as for `approve_tool_call`, denying.
+	 */
+	export function reject_tool_call(id: bigint, reason?: string | null, remember?: string | null): void;
+	export function set_extracted_call_policy(policy_json: string): void;
+	export function get_tool_policy(): string;
+	export function list_approval_rules(): string;
+	/**
+	 * Replace the arguments of a pending extracted call before approving it.
+	 * Calls queued by the confirmation policy were already validated and
+	 * cannot be edited.
+	 */
+	export function edit_pending_arguments(id: bigint, arguments_json: string): void;
+	/**
+	 * Attach tool access rules to a registered server. `policy_json` has the
+	 * shape `{ global: { allow, deny, constraints }, engrams: { <id>: {...} } }`.
+	 */
+	export function set_server_tool_policy(url: string, policy_json: string): void;
+	export function get_server_tool_policy(url: string): any;
+	/**
+	 * Approve a pending call. `remember` is `tool` to always allow this tool
+	 * on this server, or `server` to allow every tool on it.
+	 */
+	export function approve_tool_call(id: bigint, remember?: string | null): void;
+	/**
+	 * Register a function called with the rules as a JSON array whenever they
+	 * change; pass `null` to unregister.
+	 */
+	export function set_approval_rule_persistence(hook?: Function | null): void;
+	/**
+	 * Register a function called with a JSON event whenever a call is queued
+	 * for approval (`tool_approval_pending`) or settled, by a user or by the
+	 * approval timeout (`tool_approval_resolved`); pass `null` to unregister.
+	 */
+	export function set_approval_listener(listener?: Function | null): void;
+	/**
+	 * Remember a decision. Takes `{ server, tool, engram_id?, decision }` with
+	 * globs for `server` and `tool`; returns the rule's id.
+	 */
+	export function add_approval_rule(rule_json: string): bigint;
+	export function set_tool_policy(policy_json: string): void;
+	export function remove_approval_rule(id: bigint): boolean;
+	export function list_pending_approvals(): string;
+	export function get_extracted_call_policy(): string;
+	/**
+	 * The exported-to-original name mapping for `format` from the last export.
+	 */
+	export function get_exported_tool_names(format: string): string;
+	/**
+	 * Register a function called with every format's name mapping as JSON
+	 * whenever an export changes one; pass `null` to unregister.
+	 */
+	export function set_catalog_persistence(hook?: Function | null): void;
+	/**
+	 * Map a function name from a model response back to the tool name
+	 * `route_tool_call` accepts. Unknown names are returned unchanged.
+	 */
+	export function resolve_exported_tool_name(format: string | null | undefined, name: string): string;
+	/**
+	 * Replace the exported name mappings with a snapshot saved by the
+	 * persistence hook, keyed by format.
+	 */
+	export function restore_exported_tool_names(names_json: string): void;
+	/**
+	 * Render every cached tool as a function-calling catalog. `format` is
+	 * `openai` (a `tools` array), `anthropic` (a `tools` array) or `gemini`
+	 * (`{ functionDeclarations }`). Names are sanitised for the target API;
+	 * use `resolve_exported_tool_name` to map a model's choice back.
+	 */
+	export function export_tool_catalog(format: string): string;
+	/**
+	 * Flatten a raw `inputSchema` into `ToolParameter` entries.
+	 */
+	export function flatten_input_schema(schema_json: string): any;
+	/**
+	 * Parameters and resolved `inputSchema` for a tool cached in the registry.
+	 */
+	export function get_tool_parameters(url: string, tool_name: string): any;
+	/**
+	 * Route a JSON-RPC tool call to a registered server and execute it.
+	 * Returns `{ route, outcome, served_by, attempts }`; unknown tools yield a
+	 * `-32601` outcome.
+	 */
+	export function route_tool_call(method: string, params: any, engram_id?: string | null): Promise<any>;
+	/**
+	 * Set the alias and priority used when routing to a registered server.
+	 */
+	export function set_server_routing(url: string, alias: string | null | undefined, priority: number): void;
+	/**
+	 * Resolve a tool name (optionally `alias/tool`) without calling it.
+	 */
+	export function resolve_tool_route(method: string): any;
+	export function set_router_config(config_json: string): void;
+	export function get_router_config(): string;
+	export function list_tool_conflicts(): string;
+	/**
+	 * Check arguments for a cached tool without calling it. Returns
+	 * `{ valid, arguments, errors }` where `arguments` are the coerced values.
+	 */
+	export function validate_tool_arguments(url: string, tool_name: string, args: any): any;
+	/**
+	 * Drop the indexed messages of an engram.
+	 */
+	export function remove_engram_from_search_index(engram_id: string): void;
+	export function get_search_index_stats(): string;
+	/**
+	 * Rank memory events and messages against
+	 * `{ query, engram_id?, roles?, limit? }`. Queries combine words,
+	 * `"quoted phrases"` (required) and `prefix*` terms. Returns
+	 * `{ hits: [{ kind, id | message_id, engram_id?, role, timestamp, score, text }] }`,
+	 * best first.
+	 */
+	export function search_context(query_json: string): string;
+	/**
+	 * Index a JSON array of stored messages; returns how many were indexed.
+	 */
+	export function index_messages(messages_json: string): number;
+	/**
+	 * Index a stored message `{ id, engramId, role, text, timestamp }`,
+	 * replacing the message with the same id. Messages without string text
+	 * are skipped.
+	 */
+	export function index_message(message_json: string): boolean;
+	/**
+	 * Assemble a context within the token budget. Takes
+	 * `{ bootrom?, compose_bootrom?, tool_catalog?, imprints, messages, budget? }`
+	 * and returns `{ messages, total_tokens, budget_tokens, dropped, truncated }`.
+	 */
+	export function assemble_context(input_json: string): string;
+	export function get_context_budget(): string;
+	/**
+	 * Register the function `{"kind": "js"}` tokenizers call. It receives the
+	 * text and returns a token count; pass `null` to unregister.
+	 */
+	export function set_context_tokenizer(tokenizer?: Function | null): void;
+	/**
+	 * Set the default budget used when `assemble_context` is given none.
+	 */
+	export function set_context_budget(budget_json: string): void;
+	/**
+	 * Token estimate for `text` with the configured tokenizer.
+	 */
+	export function estimate_tokens(text: string): number;
+	/**
+	 * Names of the registered dialects, in the order they are tried.
+	 */
+	export function list_tool_call_dialects(): string;
+	/**
+	 * Parse tool invocations in any registered dialect. Returns
+	 * `{ calls, rejected }`: `calls` are JSON-RPC shaped and name a tool some
+	 * registered server provides; `rejected` lists recognised calls that did
+	 * not validate.
+	 */
+	export function parse_tool_calls(text: string): string;
+	/**
+	 * Restore the built-in dialects, dropping any JS parsers.
+	 */
+	export function reset_tool_call_dialects(): void;
+	/**
+	 * Remove a dialect, built-in or registered. Returns whether it existed.
+	 */
+	export function unregister_tool_call_dialect(name: string): boolean;
+	/**
+	 * Register a JS tool-call parser, replacing any dialect with the same name.
+	 * New dialects are tried after the existing ones.
+	 */
+	export function register_tool_call_dialect(name: string, parser: Function): void;
+	/**
+	 * Drives agent runs: model turn, tool calls, model turn, ... until the model
+	 * answers without calling a tool or a budget runs out. Runs are controlled
+	 * by id with `pause_agent_run`, `resume_agent_run` and `abort_agent_run`.
+	 */
+	export class AgentRunner {
+	  free(): void;
+	  /**
+	   * `emit`, `load` and `persist` are the `TapPipeline` hooks. `model`
+	   * serves `{"kind": "js"}` providers: it receives
+	   * `{ run_id, engram_id, messages, memory }` as JSON and returns (a
+	   * promise of) the reply text.
+	   */
+	  constructor(emit: Function, load?: Function | null, persist?: Function | null, model?: Function | null);
+	  /**
+	   * Start a run in the background and return its id. Takes
+	   * `{ engram_id?, model, budget?, prompt?, messages?, memory? }`; progress
+	   * is emitted as `agent_run`, `agent_step` and `cbus_message` events, the
+	   * last `agent_run` event carrying the stop reason.
+	   */
+	  start(request_json: string): bigint;
+	}
+	/**
+	 * Runs taps end to end: context assembly, connected arguments, templates,
+	 * the tool call, CBus posting and persistence, and dispatch of tool calls
+	 * found in the output. The service worker supplies the hooks and forwards
+	 * the emitted events to clients.
+	 */
+	export class TapPipeline {
+	  free(): void;
+	  /**
+	   * `emit` receives each event as JSON `{ seq, target, engram_id?, message }`
+	   * where `target` is `engram`, `reply` or `broadcast`. `load` receives an
+	   * engram id and returns (a promise of) its messages as a JSON array;
+	   * `persist` receives a CBus message as JSON and may return a promise.
+	   */
+	  constructor(emit: Function, load?: Function | null, persist?: Function | null);
+	  /**
+	   * Run a tap, or with `call` set dispatch a JSON-RPC call as if it had
+	   * been extracted from tool output. Takes
+	   * `{ source, tap_config, engram_id?, request_id?, reply?, messages?, memory?, call? }`
+	   * and resolves to `{ calls, events, skipped }` once every extracted
+	   * call has run.
+	   */
+	  run(request_json: string): Promise<any>;
+	}
 	
 }
 
@@ -28,26 +515,135 @@ declare type InitInput = RequestInfo | URL | Response | BufferSource | WebAssemb
 
 declare interface InitOutput {
   readonly memory: WebAssembly.Memory;
+  readonly call_tool_fanout: (a: number, b: number, c: any, d: any, e: number, f: number, g: number, h: number, i: number) => any;
+  readonly decode_tool_result: (a: number, b: number) => [number, number, number];
+  readonly render_tool_result_text: (a: number, b: number) => [number, number];
+  readonly extract_json_rpc_calls: (a: number, b: number) => [number, number];
+  readonly append_engram_message: (a: number, b: number) => [number, number, number];
+  readonly check_tap_template: (a: number, b: number) => [number, number];
+  readonly delete_conversation: (a: number, b: number) => any;
+  readonly get_bootrom_sections: () => [number, number];
+  readonly get_conversation_prune_policy: () => [number, number];
+  readonly get_tool_catalog_text: () => [number, number];
+  readonly list_conversations: () => any;
+  readonly load_engram_messages: (a: number, b: number, c: number, d: number) => [number, number, number];
+  readonly load_state: (a: number, b: number) => any;
+  readonly open_conversation_store: () => any;
+  readonly prune_conversation: (a: number, b: number, c: number, d: number) => [number, number, number];
+  readonly render_tap_args: (a: number, b: number, c: number, d: number) => [number, number, number, number];
+  readonly render_tap_template: (a: number, b: number, c: number, d: number) => [number, number, number, number];
+  readonly reset_bootrom_sections: () => void;
+  readonly save_state: (a: number, b: number, c: any) => any;
+  readonly set_bootrom_sections: (a: number, b: number) => [number, number];
+  readonly set_conversation_prune_policy: (a: number, b: number) => [number, number];
+  readonly uuid_v7: () => [number, number];
+  readonly get_vector_config: () => [number, number];
+  readonly get_vector_index: () => [number, number];
+  readonly get_vector_sync_status: () => [number, number];
+  readonly restore_vector_index: (a: number, b: number) => [number, number];
+  readonly set_embedding_callback: (a: number) => void;
+  readonly set_vector_config: (a: number, b: number) => [number, number];
+  readonly set_vector_persistence: (a: number) => void;
+  readonly sync_vector_index: () => any;
+  readonly vector_search: (a: number, b: number) => [number, number, number];
+  readonly check_tool_call_breaker: (a: number, b: number, c: number, d: number, e: number, f: number, g: number, h: number) => [number, number, number, number];
+  readonly get_breaker_config: () => [number, number];
+  readonly get_breaker_state: () => [number, number];
+  readonly reset_breaker: (a: number, b: number) => void;
+  readonly restore_breaker_state: (a: number, b: number) => [number, number];
+  readonly set_breaker_config: (a: number, b: number) => [number, number];
+  readonly set_breaker_config_persistence: (a: number) => void;
+  readonly set_breaker_persistence: (a: number) => void;
+  readonly __wbg_agentrunner_free: (a: number, b: number) => void;
+  readonly abort_agent_run: (a: bigint, b: number, c: number) => [number, number];
+  readonly add_simulation_fixtures: (a: number, b: number) => [number, number, number];
+  readonly agentrunner_new: (a: any, b: number, c: number, d: number) => number;
+  readonly agentrunner_start: (a: number, b: number, c: number) => [bigint, number, number];
+  readonly call_tool: (a: number, b: number, c: number, d: number, e: any, f: number, g: number) => any;
+  readonly check_mcp_server: () => any;
+  readonly clear_finished_agent_runs: () => void;
+  readonly clear_simulation_fixtures: (a: number, b: number) => void;
+  readonly clear_simulation_log: () => void;
+  readonly get_agent_run: (a: bigint) => [number, number, number, number];
+  readonly get_bootrom: () => [number, number];
+  readonly get_compiled_info: () => [number, number];
+  readonly get_metadata: () => [number, number];
+  readonly get_server_info: () => [number, number, number];
+  readonly get_server_url: () => [number, number];
+  readonly get_simulation_config: () => [number, number];
+  readonly get_simulation_log: () => [number, number];
   readonly get_timestamp: () => bigint;
   readonly get_uptime: () => bigint;
-  readonly increment_uptime: () => void;
   readonly get_version: () => [number, number];
-  readonly set_server_url: (a: number, b: number) => void;
-  readonly get_server_url: () => [number, number];
-  readonly health_check: () => any;
-  readonly check_mcp_server: () => any;
   readonly handle_message: (a: number, b: number) => any;
-  readonly get_metadata: () => [number, number];
-  readonly add_memory_event: (a: number, b: number) => void;
-  readonly clear_memory_events: () => [number, number];
+  readonly health_check: () => any;
+  readonly increment_uptime: () => void;
   readonly initialize_mcp_server: (a: number, b: number) => any;
-  readonly get_server_info: () => [number, number, number];
-  readonly query_tools: () => any;
+  readonly list_agent_runs: () => [number, number];
+  readonly list_simulation_fixtures: () => [number, number];
   readonly list_tools: (a: number, b: number) => any;
-  readonly get_compiled_info: () => [number, number];
-  readonly call_tool: (a: number, b: number, c: number, d: number, e: any) => any;
+  readonly pause_agent_run: (a: bigint) => [number, number];
+  readonly query_tools: () => any;
+  readonly resume_agent_run: (a: bigint) => [number, number];
   readonly set_debug_mode: (a: number) => void;
-  readonly get_bootrom: () => [number, number];
+  readonly set_engram_simulation: (a: number, b: number, c: number) => void;
+  readonly set_server_url: (a: number, b: number) => void;
+  readonly set_simulation_config: (a: number, b: number) => [number, number];
+  readonly add_approval_rule: (a: number, b: number) => [bigint, number, number];
+  readonly add_memory_event: (a: number, b: number, c: number, d: number) => [bigint, number, number];
+  readonly approve_tool_call: (a: bigint, b: number, c: number) => [number, number];
+  readonly clear_memory_events: (a: number, b: number) => [number, number];
+  readonly edit_pending_arguments: (a: bigint, b: number, c: number) => [number, number];
+  readonly export_memory_events: () => [number, number];
+  readonly export_tool_catalog: (a: number, b: number) => [number, number, number, number];
+  readonly get_exported_tool_names: (a: number, b: number) => [number, number, number, number];
+  readonly get_extracted_call_policy: () => [number, number];
+  readonly get_server_tool_policy: (a: number, b: number) => [number, number, number];
+  readonly get_tool_policy: () => [number, number];
+  readonly import_memory_events: (a: number, b: number) => [number, number];
+  readonly list_approval_rules: () => [number, number];
+  readonly list_pending_approvals: () => [number, number];
+  readonly query_memory_events: (a: number, b: number) => [number, number, number, number];
+  readonly reject_tool_call: (a: bigint, b: number, c: number, d: number, e: number) => [number, number];
+  readonly remove_approval_rule: (a: bigint) => number;
+  readonly remove_memory_event: (a: bigint) => number;
+  readonly resolve_exported_tool_name: (a: number, b: number, c: number, d: number) => [number, number, number, number];
+  readonly restore_approval_rules: (a: number, b: number) => [number, number];
+  readonly restore_exported_tool_names: (a: number, b: number) => [number, number];
+  readonly set_approval_listener: (a: number) => void;
+  readonly set_approval_rule_persistence: (a: number) => void;
+  readonly set_catalog_persistence: (a: number) => void;
+  readonly set_extracted_call_policy: (a: number, b: number) => [number, number];
+  readonly set_memory_persistence: (a: number) => void;
+  readonly set_server_tool_policy: (a: number, b: number, c: number, d: number) => [number, number];
+  readonly set_tool_policy: (a: number, b: number) => [number, number];
+  readonly __wbg_tappipeline_free: (a: number, b: number) => void;
+  readonly flatten_input_schema: (a: number, b: number) => [number, number, number];
+  readonly get_tool_parameters: (a: number, b: number, c: number, d: number) => [number, number, number];
+  readonly tappipeline_new: (a: any, b: number, c: number) => number;
+  readonly tappipeline_run: (a: number, b: number, c: number) => [number, number, number];
+  readonly get_router_config: () => [number, number];
+  readonly list_tool_conflicts: () => [number, number];
+  readonly resolve_tool_route: (a: number, b: number) => [number, number, number];
+  readonly route_tool_call: (a: number, b: number, c: any, d: number, e: number) => any;
+  readonly set_router_config: (a: number, b: number) => [number, number];
+  readonly set_server_routing: (a: number, b: number, c: number, d: number, e: number) => [number, number];
+  readonly validate_tool_arguments: (a: number, b: number, c: number, d: number, e: any) => [number, number, number];
+  readonly assemble_context: (a: number, b: number) => [number, number, number, number];
+  readonly estimate_tokens: (a: number, b: number) => number;
+  readonly get_context_budget: () => [number, number];
+  readonly get_search_index_stats: () => [number, number];
+  readonly index_message: (a: number, b: number) => [number, number, number];
+  readonly index_messages: (a: number, b: number) => [number, number, number];
+  readonly list_tool_call_dialects: () => [number, number];
+  readonly parse_tool_calls: (a: number, b: number) => [number, number];
+  readonly register_tool_call_dialect: (a: number, b: number, c: any) => void;
+  readonly remove_engram_from_search_index: (a: number, b: number) => void;
+  readonly reset_tool_call_dialects: () => void;
+  readonly search_context: (a: number, b: number) => [number, number, number, number];
+  readonly set_context_budget: (a: number, b: number) => [number, number];
+  readonly set_context_tokenizer: (a: number) => void;
+  readonly unregister_tool_call_dialect: (a: number, b: number) => number;
   readonly __wbindgen_malloc: (a: number, b: number) => number;
   readonly __wbindgen_realloc: (a: number, b: number, c: number, d: number) => number;
   readonly __wbindgen_exn_store: (a: number) => void;
@@ -56,8 +652,10 @@ declare interface InitOutput {
   readonly __wbindgen_export_5: WebAssembly.Table;
   readonly __wbindgen_free: (a: number, b: number, c: number) => void;
   readonly __externref_table_dealloc: (a: number) => void;
-  readonly closure70_externref_shim: (a: number, b: number, c: any) => void;
-  readonly closure92_externref_shim: (a: number, b: number, c: any, d: any) => void;
+  readonly closure72_externref_shim: (a: number, b: number, c: any) => void;
+  readonly _dyn_core__ops__function__FnMut_____Output___R_as_wasm_bindgen__closure__WasmClosure___describe__invoke__hed171b9ef44df934: (a: number, b: number) => void;
+  readonly closure409_externref_shim: (a: number, b: number, c: any) => void;
+  readonly closure423_externref_shim: (a: number, b: number, c: any, d: any) => void;
   readonly __wbindgen_start: () => void;
 }
 
@@ -205,33 +205,155 @@ let wasm_bindgen;
         return className;
     }
     /**
-     * @returns {bigint}
+     * Call a tool on several servers at once and merge the results.
+     *
+     * `servers` is an array of URLs or aliases; when empty or omitted every
+     * registered provider is used. `strategy` is `concatenate` (default),
+     * `first_success` or `plurality` (alias `majority`). Returns
+     * `{ tool_name, strategy, outcomes, merged }` where `merged` is null when
+     * no server succeeded.
+     * @param {string} name
+     * @param {any} args
+     * @param {any} servers
+     * @param {string | null} [strategy]
+     * @param {number | null} [timeout_ms]
+     * @param {string | null} [engram_id]
+     * @returns {Promise<any>}
      */
-    __exports.get_timestamp = function() {
-        const ret = wasm.get_timestamp();
-        return BigInt.asUintN(64, ret);
+    __exports.call_tool_fanout = function(name, args, servers, strategy, timeout_ms, engram_id) {
+        const ptr0 = passStringToWasm0(name, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        const len0 = WASM_VECTOR_LEN;
+        var ptr1 = isLikeNone(strategy) ? 0 : passStringToWasm0(strategy, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        var len1 = WASM_VECTOR_LEN;
+        var ptr2 = isLikeNone(engram_id) ? 0 : passStringToWasm0(engram_id, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        var len2 = WASM_VECTOR_LEN;
+        const ret = wasm.call_tool_fanout(ptr0, len0, args, servers, ptr1, len1, isLikeNone(timeout_ms) ? 0x100000001 : (timeout_ms) >>> 0, ptr2, len2);
+        return ret;
     };
 
     /**
-     * @returns {bigint}
+     * Render a tool result as CBus text.
+     * @param {string} result_json
+     * @returns {string}
      */
-    __exports.get_uptime = function() {
-        const ret = wasm.get_uptime();
-        return BigInt.asUintN(64, ret);
+    __exports.render_tool_result_text = function(result_json) {
+        let deferred2_0;
+        let deferred2_1;
+        try {
+            const ptr0 = passStringToWasm0(result_json, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+            const len0 = WASM_VECTOR_LEN;
+            const ret = wasm.render_tool_result_text(ptr0, len0);
+            deferred2_0 = ret[0];
+            deferred2_1 = ret[1];
+            return getStringFromWasm0(ret[0], ret[1]);
+        } finally {
+            wasm.__wbindgen_free(deferred2_0, deferred2_1, 1);
+        }
     };
 
-    __exports.increment_uptime = function() {
-        wasm.increment_uptime();
+    function takeFromExternrefTable0(idx) {
+        const value = wasm.__wbindgen_export_4.get(idx);
+        wasm.__externref_table_dealloc(idx);
+        return value;
+    }
+    /**
+     * Decode a tool result into typed content blocks, each annotated with its
+     * rendered `text` and, where applicable, a `dataUrl` for the UI.
+     * @param {string} result_json
+     * @returns {any}
+     */
+    __exports.decode_tool_result = function(result_json) {
+        const ptr0 = passStringToWasm0(result_json, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        const len0 = WASM_VECTOR_LEN;
+        const ret = wasm.decode_tool_result(ptr0, len0);
+        if (ret[2]) {
+            throw takeFromExternrefTable0(ret[1]);
+        }
+        return takeFromExternrefTable0(ret[0]);
     };
 
     /**
+     * Extract JSON-RPC calls from model output. Returns a JSON array of
+     * `{ call, start, end, repairs, batch_index? }`.
+     * @param {string} text
      * @returns {string}
      */
-    __exports.get_version = function() {
+    __exports.extract_json_rpc_calls = function(text) {
+        let deferred2_0;
+        let deferred2_1;
+        try {
+            const ptr0 = passStringToWasm0(text, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+            const len0 = WASM_VECTOR_LEN;
+            const ret = wasm.extract_json_rpc_calls(ptr0, len0);
+            deferred2_0 = ret[0];
+            deferred2_1 = ret[1];
+            return getStringFromWasm0(ret[0], ret[1]);
+        } finally {
+            wasm.__wbindgen_free(deferred2_0, deferred2_1, 1);
+        }
+    };
+
+    /**
+     * Prune an engram's messages with `{ max_messages, max_age_ms, max_bytes }`,
+     * or the configured policy when `policy_json` is null. Resolves to how many
+     * messages were removed.
+     * @param {string} engram_id
+     * @param {string | null} [policy_json]
+     * @returns {Promise<any>}
+     */
+    __exports.prune_conversation = function(engram_id, policy_json) {
+        const ptr0 = passStringToWasm0(engram_id, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        const len0 = WASM_VECTOR_LEN;
+        var ptr1 = isLikeNone(policy_json) ? 0 : passStringToWasm0(policy_json, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        var len1 = WASM_VECTOR_LEN;
+        const ret = wasm.prune_conversation(ptr0, len0, ptr1, len1);
+        if (ret[2]) {
+            throw takeFromExternrefTable0(ret[1]);
+        }
+        return takeFromExternrefTable0(ret[0]);
+    };
+
+    /**
+     * Delete a conversation and its messages.
+     * @param {string} engram_id
+     * @returns {Promise<any>}
+     */
+    __exports.delete_conversation = function(engram_id) {
+        const ptr0 = passStringToWasm0(engram_id, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        const len0 = WASM_VECTOR_LEN;
+        const ret = wasm.delete_conversation(ptr0, len0);
+        return ret;
+    };
+
+    /**
+     * Load an engram's messages, oldest first unless `newest_first`.
+     * `options_json` may give `{ since, until, limit, after, newest_first }`;
+     * resolves to `{ engramId, messages, next }` as JSON, where `next` is
+     * passed back as `after` for the following page and is null on the last.
+     * @param {string} engram_id
+     * @param {string | null} [options_json]
+     * @returns {Promise<any>}
+     */
+    __exports.load_engram_messages = function(engram_id, options_json) {
+        const ptr0 = passStringToWasm0(engram_id, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        const len0 = WASM_VECTOR_LEN;
+        var ptr1 = isLikeNone(options_json) ? 0 : passStringToWasm0(options_json, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        var len1 = WASM_VECTOR_LEN;
+        const ret = wasm.load_engram_messages(ptr0, len0, ptr1, len1);
+        if (ret[2]) {
+            throw takeFromExternrefTable0(ret[1]);
+        }
+        return takeFromExternrefTable0(ret[0]);
+    };
+
+    /**
+     * @returns {string}
+     */
+    __exports.get_conversation_prune_policy = function() {
         let deferred1_0;
         let deferred1_1;
         try {
-            const ret = wasm.get_version();
+            const ret = wasm.get_conversation_prune_policy();
             deferred1_0 = ret[0];
             deferred1_1 = ret[1];
             return getStringFromWasm0(ret[0], ret[1]);
@@ -241,22 +363,36 @@ let wasm_bindgen;
     };
 
     /**
-     * @param {string} url
+     * Resolves to the value saved under `key`, or null.
+     * @param {string} key
+     * @returns {Promise<any>}
      */
-    __exports.set_server_url = function(url) {
-        const ptr0 = passStringToWasm0(url, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+    __exports.load_state = function(key) {
+        const ptr0 = passStringToWasm0(key, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
         const len0 = WASM_VECTOR_LEN;
-        wasm.set_server_url(ptr0, len0);
+        const ret = wasm.load_state(ptr0, len0);
+        return ret;
+    };
+
+    /**
+     * Open the store, migrating its schema; resolves to the schema version.
+     * @returns {Promise<any>}
+     */
+    __exports.open_conversation_store = function() {
+        const ret = wasm.open_conversation_store();
+        return ret;
     };
 
     /**
+     * A new RFC 9562 UUIDv7; ids minted in this worker are strictly
+     * increasing.
      * @returns {string}
      */
-    __exports.get_server_url = function() {
+    __exports.uuid_v7 = function() {
         let deferred1_0;
         let deferred1_1;
         try {
-            const ret = wasm.get_server_url();
+            const ret = wasm.uuid_v7();
             deferred1_0 = ret[0];
             deferred1_1 = ret[1];
             return getStringFromWasm0(ret[0], ret[1]);
@@ -266,40 +402,69 @@ let wasm_bindgen;
     };
 
     /**
-     * @returns {Promise<number>}
+     * Save `value` under `key`; resolves once written.
+     * @param {string} key
+     * @param {any} value
+     * @returns {Promise<any>}
      */
-    __exports.health_check = function() {
-        const ret = wasm.health_check();
+    __exports.save_state = function(key, value) {
+        const ptr0 = passStringToWasm0(key, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        const len0 = WASM_VECTOR_LEN;
+        const ret = wasm.save_state(ptr0, len0, value);
         return ret;
     };
 
     /**
-     * @returns {Promise<number>}
+     * Append `{ engramId, role?, text, timestamp?, id?, toolName?, serverUrl?,
+     * structuredContent?, outputValidation? }` to its engram, creating the
+     * conversation on first use. Resolves to the stored message as JSON, with
+     * its id and timestamp filled in. The prune policy runs afterwards.
+     * @param {string} message_json
+     * @returns {Promise<any>}
      */
-    __exports.check_mcp_server = function() {
-        const ret = wasm.check_mcp_server();
-        return ret;
+    __exports.append_engram_message = function(message_json) {
+        const ptr0 = passStringToWasm0(message_json, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        const len0 = WASM_VECTOR_LEN;
+        const ret = wasm.append_engram_message(ptr0, len0);
+        if (ret[2]) {
+            throw takeFromExternrefTable0(ret[1]);
+        }
+        return takeFromExternrefTable0(ret[0]);
     };
 
     /**
-     * @param {string} message
+     * Resolves to the conversations' `{ engramId, created, updated }` as a
+     * JSON array.
      * @returns {Promise<any>}
      */
-    __exports.handle_message = function(message) {
-        const ptr0 = passStringToWasm0(message, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
-        const len0 = WASM_VECTOR_LEN;
-        const ret = wasm.handle_message(ptr0, len0);
+    __exports.list_conversations = function() {
+        const ret = wasm.list_conversations();
         return ret;
     };
 
     /**
+     * Set the policy applied to an engram after each append:
+     * `{ max_messages?, max_age_ms?, max_bytes? }`.
+     * @param {string} policy_json
+     */
+    __exports.set_conversation_prune_policy = function(policy_json) {
+        const ptr0 = passStringToWasm0(policy_json, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        const len0 = WASM_VECTOR_LEN;
+        const ret = wasm.set_conversation_prune_policy(ptr0, len0);
+        if (ret[1]) {
+            throw takeFromExternrefTable0(ret[0]);
+        }
+    };
+
+    /**
+     * Markdown tool catalog as it appears in the bootrom.
      * @returns {string}
      */
-    __exports.get_metadata = function() {
+    __exports.get_tool_catalog_text = function() {
         let deferred1_0;
         let deferred1_1;
         try {
-            const ret = wasm.get_metadata();
+            const ret = wasm.get_tool_catalog_text();
             deferred1_0 = ret[0];
             deferred1_1 = ret[1];
             return getStringFromWasm0(ret[0], ret[1]);
@@ -309,43 +474,244 @@ let wasm_bindgen;
     };
 
     /**
-     * @param {string} text
+     * @returns {string}
      */
-    __exports.add_memory_event = function(text) {
-        const ptr0 = passStringToWasm0(text, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
-        const len0 = WASM_VECTOR_LEN;
-        wasm.add_memory_event(ptr0, len0);
+    __exports.get_bootrom_sections = function() {
+        let deferred1_0;
+        let deferred1_1;
+        try {
+            const ret = wasm.get_bootrom_sections();
+            deferred1_0 = ret[0];
+            deferred1_1 = ret[1];
+            return getStringFromWasm0(ret[0], ret[1]);
+        } finally {
+            wasm.__wbindgen_free(deferred1_0, deferred1_1, 1);
+        }
     };
 
-    function takeFromExternrefTable0(idx) {
-        const value = wasm.__wbindgen_export_4.get(idx);
-        wasm.__externref_table_dealloc(idx);
-        return value;
-    }
+    /**
+     * Restore the built-in sections.
+     */
+    __exports.reset_bootrom_sections = function() {
+        wasm.reset_bootrom_sections();
+    };
 
-    __exports.clear_memory_events = function() {
-        const ret = wasm.clear_memory_events();
+    /**
+     * Replace the bootrom sections. Takes a JSON array of
+     * `{ id, template, enabled? }`, composed in order.
+     * @param {string} sections_json
+     */
+    __exports.set_bootrom_sections = function(sections_json) {
+        const ptr0 = passStringToWasm0(sections_json, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        const len0 = WASM_VECTOR_LEN;
+        const ret = wasm.set_bootrom_sections(ptr0, len0);
         if (ret[1]) {
             throw takeFromExternrefTable0(ret[0]);
         }
     };
 
     /**
-     * @param {string} url
+     * Check a template's syntax without rendering it. Returns `{ ok: true }`
+     * or `{ ok: false, error }`.
+     * @param {string} template
+     * @returns {string}
+     */
+    __exports.check_tap_template = function(template) {
+        let deferred2_0;
+        let deferred2_1;
+        try {
+            const ptr0 = passStringToWasm0(template, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+            const len0 = WASM_VECTOR_LEN;
+            const ret = wasm.check_tap_template(ptr0, len0);
+            deferred2_0 = ret[0];
+            deferred2_1 = ret[1];
+            return getStringFromWasm0(ret[0], ret[1]);
+        } finally {
+            wasm.__wbindgen_free(deferred2_0, deferred2_1, 1);
+        }
+    };
+
+    /**
+     * Render a tap template. Returns `{ output }` or `{ error }` where the
+     * error carries `message`, `offset`, `line`, `column` and `source_line`.
+     * @param {string} template
+     * @param {string} context_json
+     * @returns {string}
+     */
+    __exports.render_tap_template = function(template, context_json) {
+        let deferred4_0;
+        let deferred4_1;
+        try {
+            const ptr0 = passStringToWasm0(template, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+            const len0 = WASM_VECTOR_LEN;
+            const ptr1 = passStringToWasm0(context_json, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+            const len1 = WASM_VECTOR_LEN;
+            const ret = wasm.render_tap_template(ptr0, len0, ptr1, len1);
+            var ptr3 = ret[0];
+            var len3 = ret[1];
+            if (ret[3]) {
+                ptr3 = 0; len3 = 0;
+                throw takeFromExternrefTable0(ret[2]);
+            }
+            deferred4_0 = ptr3;
+            deferred4_1 = len3;
+            return getStringFromWasm0(ptr3, len3);
+        } finally {
+            wasm.__wbindgen_free(deferred4_0, deferred4_1, 1);
+        }
+    };
+
+    /**
+     * Render every templated string in a tap's `args`. Returns
+     * `{ args, errors }`; each error names the argument by JSON Pointer.
+     * @param {string} args_json
+     * @param {string} context_json
+     * @returns {string}
+     */
+    __exports.render_tap_args = function(args_json, context_json) {
+        let deferred4_0;
+        let deferred4_1;
+        try {
+            const ptr0 = passStringToWasm0(args_json, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+            const len0 = WASM_VECTOR_LEN;
+            const ptr1 = passStringToWasm0(context_json, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+            const len1 = WASM_VECTOR_LEN;
+            const ret = wasm.render_tap_args(ptr0, len0, ptr1, len1);
+            var ptr3 = ret[0];
+            var len3 = ret[1];
+            if (ret[3]) {
+                ptr3 = 0; len3 = 0;
+                throw takeFromExternrefTable0(ret[2]);
+            }
+            deferred4_0 = ptr3;
+            deferred4_1 = len3;
+            return getStringFromWasm0(ptr3, len3);
+        } finally {
+            wasm.__wbindgen_free(deferred4_0, deferred4_1, 1);
+        }
+    };
+
+    /**
+     * Register the function behind the `js` provider: it receives a JSON array
+     * of texts and returns (a promise of) an array of vectors, or its JSON.
+     * @param {Function | null} [callback]
+     */
+    __exports.set_embedding_callback = function(callback) {
+        wasm.set_embedding_callback(isLikeNone(callback) ? 0 : addToExternrefTable0(callback));
+    };
+
+    /**
+     * `{ syncing, pending, indexed }`: whether a sync is running, how many live
+     * events still lack a vector and how many have one.
+     * @returns {string}
+     */
+    __exports.get_vector_sync_status = function() {
+        let deferred1_0;
+        let deferred1_1;
+        try {
+            const ret = wasm.get_vector_sync_status();
+            deferred1_0 = ret[0];
+            deferred1_1 = ret[1];
+            return getStringFromWasm0(ret[0], ret[1]);
+        } finally {
+            wasm.__wbindgen_free(deferred1_0, deferred1_1, 1);
+        }
+    };
+
+    /**
+     * Embed memory events not yet in the index, up to 512 per call; resolves
+     * to how many were.
      * @returns {Promise<any>}
      */
-    __exports.initialize_mcp_server = function(url) {
-        const ptr0 = passStringToWasm0(url, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
-        const len0 = WASM_VECTOR_LEN;
-        const ret = wasm.initialize_mcp_server(ptr0, len0);
+    __exports.sync_vector_index = function() {
+        const ret = wasm.sync_vector_index();
         return ret;
     };
 
     /**
-     * @returns {any}
+     * Takes `{ provider, index, top_k, min_score }`. `provider` is
+     * `{ kind: "js" }`, `{ kind: "tool", server_url, tool_name, text_argument? }`
+     * or `{ kind: "hashing", dimensions? }`; `index` is `{ kind: "flat" }` or
+     * `{ kind: "hnsw", m?, ef_construction?, ef_search? }`. Changing the
+     * provider drops every vector.
+     * @param {string} config_json
      */
-    __exports.get_server_info = function() {
-        const ret = wasm.get_server_info();
+    __exports.set_vector_config = function(config_json) {
+        const ptr0 = passStringToWasm0(config_json, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        const len0 = WASM_VECTOR_LEN;
+        const ret = wasm.set_vector_config(ptr0, len0);
+        if (ret[1]) {
+            throw takeFromExternrefTable0(ret[0]);
+        }
+    };
+
+    /**
+     * Restore a snapshot saved from `get_vector_index` or the persistence
+     * hook.
+     * @param {string} snapshot_json
+     */
+    __exports.restore_vector_index = function(snapshot_json) {
+        const ptr0 = passStringToWasm0(snapshot_json, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        const len0 = WASM_VECTOR_LEN;
+        const ret = wasm.restore_vector_index(ptr0, len0);
+        if (ret[1]) {
+            throw takeFromExternrefTable0(ret[0]);
+        }
+    };
+
+    /**
+     * The config and vectors as JSON, for storage.
+     * @returns {string}
+     */
+    __exports.get_vector_index = function() {
+        let deferred1_0;
+        let deferred1_1;
+        try {
+            const ret = wasm.get_vector_index();
+            deferred1_0 = ret[0];
+            deferred1_1 = ret[1];
+            return getStringFromWasm0(ret[0], ret[1]);
+        } finally {
+            wasm.__wbindgen_free(deferred1_0, deferred1_1, 1);
+        }
+    };
+
+    /**
+     * Register a function called with the snapshot as JSON whenever the
+     * index changes; pass `null` to unregister.
+     * @param {Function | null} [hook]
+     */
+    __exports.set_vector_persistence = function(hook) {
+        wasm.set_vector_persistence(isLikeNone(hook) ? 0 : addToExternrefTable0(hook));
+    };
+
+    /**
+     * @returns {string}
+     */
+    __exports.get_vector_config = function() {
+        let deferred1_0;
+        let deferred1_1;
+        try {
+            const ret = wasm.get_vector_config();
+            deferred1_0 = ret[0];
+            deferred1_1 = ret[1];
+            return getStringFromWasm0(ret[0], ret[1]);
+        } finally {
+            wasm.__wbindgen_free(deferred1_0, deferred1_1, 1);
+        }
+    };
+
+    /**
+     * Top-k memory events by cosine similarity to `query`. Takes
+     * `{ query, k?, min_score?, engram_id?, tags?, source?, since?, until?, text? }`
+     * and resolves to `{ hits: [{ score, event }] }`, best first.
+     * @param {string} query_json
+     * @returns {Promise<any>}
+     */
+    __exports.vector_search = function(query_json) {
+        const ptr0 = passStringToWasm0(query_json, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        const len0 = WASM_VECTOR_LEN;
+        const ret = wasm.vector_search(ptr0, len0);
         if (ret[2]) {
             throw takeFromExternrefTable0(ret[1]);
         }
@@ -353,32 +719,1242 @@ let wasm_bindgen;
     };
 
     /**
-     * @returns {Promise<any>}
+     * Check a call against the breaker, counting it when admitted. Returns
+     * `{ allowed, trip?, message? }`.
+     * @param {string | null | undefined} engram_id
+     * @param {string} server
+     * @param {string} tool
+     * @param {string} args_json
+     * @returns {string}
      */
-    __exports.query_tools = function() {
-        const ret = wasm.query_tools();
-        return ret;
+    __exports.check_tool_call_breaker = function(engram_id, server, tool, args_json) {
+        let deferred6_0;
+        let deferred6_1;
+        try {
+            var ptr0 = isLikeNone(engram_id) ? 0 : passStringToWasm0(engram_id, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+            var len0 = WASM_VECTOR_LEN;
+            const ptr1 = passStringToWasm0(server, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+            const len1 = WASM_VECTOR_LEN;
+            const ptr2 = passStringToWasm0(tool, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+            const len2 = WASM_VECTOR_LEN;
+            const ptr3 = passStringToWasm0(args_json, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+            const len3 = WASM_VECTOR_LEN;
+            const ret = wasm.check_tool_call_breaker(ptr0, len0, ptr1, len1, ptr2, len2, ptr3, len3);
+            var ptr5 = ret[0];
+            var len5 = ret[1];
+            if (ret[3]) {
+                ptr5 = 0; len5 = 0;
+                throw takeFromExternrefTable0(ret[2]);
+            }
+            deferred6_0 = ptr5;
+            deferred6_1 = len5;
+            return getStringFromWasm0(ptr5, len5);
+        } finally {
+            wasm.__wbindgen_free(deferred6_0, deferred6_1, 1);
+        }
+    };
+
+    /**
+     * The breaker's state as JSON, for storage.
+     * @returns {string}
+     */
+    __exports.get_breaker_state = function() {
+        let deferred1_0;
+        let deferred1_1;
+        try {
+            const ret = wasm.get_breaker_state();
+            deferred1_0 = ret[0];
+            deferred1_1 = ret[1];
+            return getStringFromWasm0(ret[0], ret[1]);
+        } finally {
+            wasm.__wbindgen_free(deferred1_0, deferred1_1, 1);
+        }
+    };
+
+    /**
+     * Register a function called with the config as JSON whenever it is set;
+     * pass `null` to unregister. Restore a saved config with
+     * `set_breaker_config` before registering.
+     * @param {Function | null} [hook]
+     */
+    __exports.set_breaker_config_persistence = function(hook) {
+        wasm.set_breaker_config_persistence(isLikeNone(hook) ? 0 : addToExternrefTable0(hook));
+    };
+
+    /**
+     * Forget the state of one engram, or of all when `engram_id` is null.
+     * @param {string | null} [engram_id]
+     */
+    __exports.reset_breaker = function(engram_id) {
+        var ptr0 = isLikeNone(engram_id) ? 0 : passStringToWasm0(engram_id, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        var len0 = WASM_VECTOR_LEN;
+        wasm.reset_breaker(ptr0, len0);
+    };
+
+    /**
+     * Restore state saved from `get_breaker_state` or the persistence hook.
+     * @param {string} state_json
+     */
+    __exports.restore_breaker_state = function(state_json) {
+        const ptr0 = passStringToWasm0(state_json, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        const len0 = WASM_VECTOR_LEN;
+        const ret = wasm.restore_breaker_state(ptr0, len0);
+        if (ret[1]) {
+            throw takeFromExternrefTable0(ret[0]);
+        }
+    };
+
+    /**
+     * @param {string} config_json
+     */
+    __exports.set_breaker_config = function(config_json) {
+        const ptr0 = passStringToWasm0(config_json, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        const len0 = WASM_VECTOR_LEN;
+        const ret = wasm.set_breaker_config(ptr0, len0);
+        if (ret[1]) {
+            throw takeFromExternrefTable0(ret[0]);
+        }
+    };
+
+    /**
+     * @returns {string}
+     */
+    __exports.get_breaker_config = function() {
+        let deferred1_0;
+        let deferred1_1;
+        try {
+            const ret = wasm.get_breaker_config();
+            deferred1_0 = ret[0];
+            deferred1_1 = ret[1];
+            return getStringFromWasm0(ret[0], ret[1]);
+        } finally {
+            wasm.__wbindgen_free(deferred1_0, deferred1_1, 1);
+        }
+    };
+
+    /**
+     * Register a function called with the state as JSON shortly after it
+     * changes; pass `null` to unregister.
+     * @param {Function | null} [hook]
+     */
+    __exports.set_breaker_persistence = function(hook) {
+        wasm.set_breaker_persistence(isLikeNone(hook) ? 0 : addToExternrefTable0(hook));
+    };
+
+    /**
+     * @returns {bigint}
+     */
+    __exports.get_uptime = function() {
+        const ret = wasm.get_uptime();
+        return BigInt.asUintN(64, ret);
+    };
+
+    /**
+     * @returns {string}
+     */
+    __exports.get_metadata = function() {
+        let deferred1_0;
+        let deferred1_1;
+        try {
+            const ret = wasm.get_metadata();
+            deferred1_0 = ret[0];
+            deferred1_1 = ret[1];
+            return getStringFromWasm0(ret[0], ret[1]);
+        } finally {
+            wasm.__wbindgen_free(deferred1_0, deferred1_1, 1);
+        }
+    };
+
+    /**
+     * @returns {string}
+     */
+    __exports.get_server_url = function() {
+        let deferred1_0;
+        let deferred1_1;
+        try {
+            const ret = wasm.get_server_url();
+            deferred1_0 = ret[0];
+            deferred1_1 = ret[1];
+            return getStringFromWasm0(ret[0], ret[1]);
+        } finally {
+            wasm.__wbindgen_free(deferred1_0, deferred1_1, 1);
+        }
+    };
+
+    __exports.increment_uptime = function() {
+        wasm.increment_uptime();
+    };
+
+    /**
+     * @param {boolean} enabled
+     */
+    __exports.set_debug_mode = function(enabled) {
+        wasm.set_debug_mode(enabled);
+    };
+
+    /**
+     * @returns {string}
+     */
+    __exports.get_compiled_info = function() {
+        let deferred1_0;
+        let deferred1_1;
+        try {
+            const ret = wasm.get_compiled_info();
+            deferred1_0 = ret[0];
+            deferred1_1 = ret[1];
+            return getStringFromWasm0(ret[0], ret[1]);
+        } finally {
+            wasm.__wbindgen_free(deferred1_0, deferred1_1, 1);
+        }
+    };
+
+    /**
+     * @returns {Promise<any>}
+     */
+    __exports.query_tools = function() {
+        const ret = wasm.query_tools();
+        return ret;
+    };
+
+    /**
+     * @param {string} url
+     */
+    __exports.set_server_url = function(url) {
+        const ptr0 = passStringToWasm0(url, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        const len0 = WASM_VECTOR_LEN;
+        wasm.set_server_url(ptr0, len0);
+    };
+
+    /**
+     * @returns {string}
+     */
+    __exports.get_version = function() {
+        let deferred1_0;
+        let deferred1_1;
+        try {
+            const ret = wasm.get_version();
+            deferred1_0 = ret[0];
+            deferred1_1 = ret[1];
+            return getStringFromWasm0(ret[0], ret[1]);
+        } finally {
+            wasm.__wbindgen_free(deferred1_0, deferred1_1, 1);
+        }
+    };
+
+    /**
+     * The bootrom imprint, composed from its sections against the current
+     * tool registry.
+     * @returns {string}
+     */
+    __exports.get_bootrom = function() {
+        let deferred1_0;
+        let deferred1_1;
+        try {
+            const ret = wasm.get_bootrom();
+            deferred1_0 = ret[0];
+            deferred1_1 = ret[1];
+            return getStringFromWasm0(ret[0], ret[1]);
+        } finally {
+            wasm.__wbindgen_free(deferred1_0, deferred1_1, 1);
+        }
+    };
+
+    /**
+     * Call a tool and return a JSON-encoded `ToolCallOutcome`.
+     *
+     * Arguments are coerced and validated against the tool's cached
+     * `inputSchema` first, then checked against the server's access rules for
+     * `engram_id` and the confirmation policy, which may hold the call until it
+     * is approved. The outcome's `kind` is one of `success`, `tool_error`,
+     * `rpc_error`, `transport_error`, `invalid_arguments`, `policy` or
+     * `rejected`; only undecodable `args` are reported as a rejected promise.
+     * @param {string} url
+     * @param {string} tool_name
+     * @param {any} args
+     * @param {string | null} [engram_id]
+     * @returns {Promise<any>}
+     */
+    __exports.call_tool = function(url, tool_name, args, engram_id) {
+        const ptr0 = passStringToWasm0(url, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        const len0 = WASM_VECTOR_LEN;
+        const ptr1 = passStringToWasm0(tool_name, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        const len1 = WASM_VECTOR_LEN;
+        var ptr2 = isLikeNone(engram_id) ? 0 : passStringToWasm0(engram_id, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        var len2 = WASM_VECTOR_LEN;
+        const ret = wasm.call_tool(ptr0, len0, ptr1, len1, args, ptr2, len2);
+        return ret;
+    };
+
+    /**
+     * @returns {any}
+     */
+    __exports.get_server_info = function() {
+        const ret = wasm.get_server_info();
+        if (ret[2]) {
+            throw takeFromExternrefTable0(ret[1]);
+        }
+        return takeFromExternrefTable0(ret[0]);
+    };
+
+    /**
+     * @returns {bigint}
+     */
+    __exports.get_timestamp = function() {
+        const ret = wasm.get_timestamp();
+        return BigInt.asUintN(64, ret);
+    };
+
+    /**
+     * @param {string} message
+     * @returns {Promise<any>}
+     */
+    __exports.handle_message = function(message) {
+        const ptr0 = passStringToWasm0(message, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        const len0 = WASM_VECTOR_LEN;
+        const ret = wasm.handle_message(ptr0, len0);
+        return ret;
+    };
+
+    /**
+     * @param {string} url
+     * @returns {Promise<any>}
+     */
+    __exports.initialize_mcp_server = function(url) {
+        const ptr0 = passStringToWasm0(url, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        const len0 = WASM_VECTOR_LEN;
+        const ret = wasm.initialize_mcp_server(ptr0, len0);
+        return ret;
+    };
+
+    /**
+     * @param {string} url
+     * @returns {Promise<any>}
+     */
+    __exports.list_tools = function(url) {
+        const ptr0 = passStringToWasm0(url, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        const len0 = WASM_VECTOR_LEN;
+        const ret = wasm.list_tools(ptr0, len0);
+        return ret;
+    };
+
+    /**
+     * @returns {Promise<number>}
+     */
+    __exports.health_check = function() {
+        const ret = wasm.health_check();
+        return ret;
+    };
+
+    /**
+     * @returns {Promise<number>}
+     */
+    __exports.check_mcp_server = function() {
+        const ret = wasm.check_mcp_server();
+        return ret;
+    };
+
+    /**
+     * @param {bigint} id
+     */
+    __exports.resume_agent_run = function(id) {
+        const ret = wasm.resume_agent_run(id);
+        if (ret[1]) {
+            throw takeFromExternrefTable0(ret[0]);
+        }
+    };
+
+    /**
+     * Drop finished runs from the registry.
+     */
+    __exports.clear_finished_agent_runs = function() {
+        wasm.clear_finished_agent_runs();
+    };
+
+    /**
+     * The full record of a run: steps, transcript and stop reason.
+     * @param {bigint} id
+     * @returns {string}
+     */
+    __exports.get_agent_run = function(id) {
+        let deferred2_0;
+        let deferred2_1;
+        try {
+            const ret = wasm.get_agent_run(id);
+            var ptr1 = ret[0];
+            var len1 = ret[1];
+            if (ret[3]) {
+                ptr1 = 0; len1 = 0;
+                throw takeFromExternrefTable0(ret[2]);
+            }
+            deferred2_0 = ptr1;
+            deferred2_1 = len1;
+            return getStringFromWasm0(ptr1, len1);
+        } finally {
+            wasm.__wbindgen_free(deferred2_0, deferred2_1, 1);
+        }
+    };
+
+    /**
+     * Summaries of every run: `[{ id, engram_id, status, started_at, steps, stop_reason }]`.
+     * @returns {string}
+     */
+    __exports.list_agent_runs = function() {
+        let deferred1_0;
+        let deferred1_1;
+        try {
+            const ret = wasm.list_agent_runs();
+            deferred1_0 = ret[0];
+            deferred1_1 = ret[1];
+            return getStringFromWasm0(ret[0], ret[1]);
+        } finally {
+            wasm.__wbindgen_free(deferred1_0, deferred1_1, 1);
+        }
+    };
+
+    /**
+     * Stop a run at its next checkpoint. Approvals its calls wait on are
+     * rejected; a call already sent is left to finish but no further step
+     * starts.
+     * @param {bigint} id
+     * @param {string | null} [reason]
+     */
+    __exports.abort_agent_run = function(id, reason) {
+        var ptr0 = isLikeNone(reason) ? 0 : passStringToWasm0(reason, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        var len0 = WASM_VECTOR_LEN;
+        const ret = wasm.abort_agent_run(id, ptr0, len0);
+        if (ret[1]) {
+            throw takeFromExternrefTable0(ret[0]);
+        }
+    };
+
+    /**
+     * Pause a run at its next checkpoint (between steps).
+     * @param {bigint} id
+     */
+    __exports.pause_agent_run = function(id) {
+        const ret = wasm.pause_agent_run(id);
+        if (ret[1]) {
+            throw takeFromExternrefTable0(ret[0]);
+        }
+    };
+
+    /**
+     * @returns {string}
+     */
+    __exports.get_simulation_config = function() {
+        let deferred1_0;
+        let deferred1_1;
+        try {
+            const ret = wasm.get_simulation_config();
+            deferred1_0 = ret[0];
+            deferred1_1 = ret[1];
+            return getStringFromWasm0(ret[0], ret[1]);
+        } finally {
+            wasm.__wbindgen_free(deferred1_0, deferred1_1, 1);
+        }
+    };
+
+    /**
+     * Takes `{ enabled, engrams, record }`.
+     * @param {string} config_json
+     */
+    __exports.set_simulation_config = function(config_json) {
+        const ptr0 = passStringToWasm0(config_json, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        const len0 = WASM_VECTOR_LEN;
+        const ret = wasm.set_simulation_config(ptr0, len0);
+        if (ret[1]) {
+            throw takeFromExternrefTable0(ret[0]);
+        }
+    };
+
+    /**
+     * Add fixtures from a JSON array of `{ server?, tool, arguments?, result }`.
+     * A fixture replaces one with the same server, tool and arguments.
+     * @param {string} fixtures_json
+     * @returns {number}
+     */
+    __exports.add_simulation_fixtures = function(fixtures_json) {
+        const ptr0 = passStringToWasm0(fixtures_json, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        const len0 = WASM_VECTOR_LEN;
+        const ret = wasm.add_simulation_fixtures(ptr0, len0);
+        if (ret[2]) {
+            throw takeFromExternrefTable0(ret[1]);
+        }
+        return ret[0] >>> 0;
+    };
+
+    /**
+     * @returns {string}
+     */
+    __exports.list_simulation_fixtures = function() {
+        let deferred1_0;
+        let deferred1_1;
+        try {
+            const ret = wasm.list_simulation_fixtures();
+            deferred1_0 = ret[0];
+            deferred1_1 = ret[1];
+            return getStringFromWasm0(ret[0], ret[1]);
+        } finally {
+            wasm.__wbindgen_free(deferred1_0, deferred1_1, 1);
+        }
+    };
+
+    /**
+     * Simulated calls, oldest first, as `{ calls: [...] }`.
+     * @returns {string}
+     */
+    __exports.get_simulation_log = function() {
+        let deferred1_0;
+        let deferred1_1;
+        try {
+            const ret = wasm.get_simulation_log();
+            deferred1_0 = ret[0];
+            deferred1_1 = ret[1];
+            return getStringFromWasm0(ret[0], ret[1]);
+        } finally {
+            wasm.__wbindgen_free(deferred1_0, deferred1_1, 1);
+        }
+    };
+
+    __exports.clear_simulation_log = function() {
+        wasm.clear_simulation_log();
+    };
+
+    /**
+     * Turn simulation on or off for one engram.
+     * @param {string} engram_id
+     * @param {boolean} enabled
+     */
+    __exports.set_engram_simulation = function(engram_id, enabled) {
+        const ptr0 = passStringToWasm0(engram_id, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        const len0 = WASM_VECTOR_LEN;
+        wasm.set_engram_simulation(ptr0, len0, enabled);
+    };
+
+    /**
+     * Drop the fixtures of one tool, or all when `tool` is null.
+     * @param {string | null} [tool]
+     */
+    __exports.clear_simulation_fixtures = function(tool) {
+        var ptr0 = isLikeNone(tool) ? 0 : passStringToWasm0(tool, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        var len0 = WASM_VECTOR_LEN;
+        wasm.clear_simulation_fixtures(ptr0, len0);
+    };
+
+    /**
+     * Store an event and return its id. `options_json` may give
+     * `{ tags, source, ttl_ms, expires_at, engram_id }`; `source` is `user`,
+     * `tool` or `resource` and defaults to `user`.
+     * @param {string} text
+     * @param {string | null} [options_json]
+     * @returns {bigint}
+     */
+    __exports.add_memory_event = function(text, options_json) {
+        const ptr0 = passStringToWasm0(text, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        const len0 = WASM_VECTOR_LEN;
+        var ptr1 = isLikeNone(options_json) ? 0 : passStringToWasm0(options_json, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        var len1 = WASM_VECTOR_LEN;
+        const ret = wasm.add_memory_event(ptr0, len0, ptr1, len1);
+        if (ret[2]) {
+            throw takeFromExternrefTable0(ret[1]);
+        }
+        return BigInt.asUintN(64, ret[0]);
+    };
+
+    /**
+     * Register a function called with the store as JSON whenever it changes;
+     * pass `null` to unregister.
+     * @param {Function | null} [hook]
+     */
+    __exports.set_memory_persistence = function(hook) {
+        wasm.set_memory_persistence(isLikeNone(hook) ? 0 : addToExternrefTable0(hook));
+    };
+
+    /**
+     * The store as JSON, for storage.
+     * @returns {string}
+     */
+    __exports.export_memory_events = function() {
+        let deferred1_0;
+        let deferred1_1;
+        try {
+            const ret = wasm.export_memory_events();
+            deferred1_0 = ret[0];
+            deferred1_1 = ret[1];
+            return getStringFromWasm0(ret[0], ret[1]);
+        } finally {
+            wasm.__wbindgen_free(deferred1_0, deferred1_1, 1);
+        }
+    };
+
+    /**
+     * Events matching `{ since, until, tags, text, source, engram_id, limit,
+     * oldest_first }`, as `{ events: [...] }`.
+     * @param {string} query_json
+     * @returns {string}
+     */
+    __exports.query_memory_events = function(query_json) {
+        let deferred3_0;
+        let deferred3_1;
+        try {
+            const ptr0 = passStringToWasm0(query_json, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+            const len0 = WASM_VECTOR_LEN;
+            const ret = wasm.query_memory_events(ptr0, len0);
+            var ptr2 = ret[0];
+            var len2 = ret[1];
+            if (ret[3]) {
+                ptr2 = 0; len2 = 0;
+                throw takeFromExternrefTable0(ret[2]);
+            }
+            deferred3_0 = ptr2;
+            deferred3_1 = len2;
+            return getStringFromWasm0(ptr2, len2);
+        } finally {
+            wasm.__wbindgen_free(deferred3_0, deferred3_1, 1);
+        }
+    };
+
+    /**
+     * @param {bigint} id
+     * @returns {boolean}
+     */
+    __exports.remove_memory_event = function(id) {
+        const ret = wasm.remove_memory_event(id);
+        return ret !== 0;
+    };
+
+    /**
+     * Forget the events scoped to one engram, or every event when `engram_id`
+     * is null.
+     * @param {string | null} [engram_id]
+     */
+    __exports.clear_memory_events = function(engram_id) {
+        var ptr0 = isLikeNone(engram_id) ? 0 : passStringToWasm0(engram_id, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        var len0 = WASM_VECTOR_LEN;
+        const ret = wasm.clear_memory_events(ptr0, len0);
+        if (ret[1]) {
+            throw takeFromExternrefTable0(ret[0]);
+        }
+    };
+
+    /**
+     * Restore a store saved from `export_memory_events` or the persistence
+     * hook. Expired events are dropped.
+     * @param {string} store_json
+     */
+    __exports.import_memory_events = function(store_json) {
+        const ptr0 = passStringToWasm0(store_json, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        const len0 = WASM_VECTOR_LEN;
+        const ret = wasm.import_memory_events(ptr0, len0);
+        if (ret[1]) {
+            throw takeFromExternrefTable0(ret[0]);
+        }
+    };
+
+    /**
+     * Replace the remembered rules with a JSON array saved by the persistence
+     * hook.
+     * @param {string} rules_json
+     */
+    __exports.restore_approval_rules = function(rules_json) {
+        const ptr0 = passStringToWasm0(rules_json, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        const len0 = WASM_VECTOR_LEN;
+        const ret = wasm.restore_approval_rules(ptr0, len0);
+        if (ret[1]) {
+            throw takeFromExternrefTable0(ret[0]);
+        }
+    };
+
+    /**
+     * Reject a pending call; `remember` as for `approve_tool_call`, denying.
+     * @param {bigint} id
+     * @param {string | null} [reason]
+     * @param {string | null} [remember]
+     */
+    __exports.reject_tool_call = function(id, reason, remember) {
+        var ptr0 = isLikeNone(reason) ? 0 : passStringToWasm0(reason, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        var len0 = WASM_VECTOR_LEN;
+        var ptr1 = isLikeNone(remember) ? 0 : passStringToWasm0(remember, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        var len1 = WASM_VECTOR_LEN;
+        const ret = wasm.reject_tool_call(id, ptr0, len0, ptr1, len1);
+        if (ret[1]) {
+            throw takeFromExternrefTable0(ret[0]);
+        }
+    };
+
+    /**
+     * @param {string} policy_json
+     */
+    __exports.set_extracted_call_policy = function(policy_json) {
+        const ptr0 = passStringToWasm0(policy_json, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        const len0 = WASM_VECTOR_LEN;
+        const ret = wasm.set_extracted_call_policy(ptr0, len0);
+        if (ret[1]) {
+            throw takeFromExternrefTable0(ret[0]);
+        }
+    };
+
+    /**
+     * @returns {string}
+     */
+    __exports.get_tool_policy = function() {
+        let deferred1_0;
+        let deferred1_1;
+        try {
+            const ret = wasm.get_tool_policy();
+            deferred1_0 = ret[0];
+            deferred1_1 = ret[1];
+            return getStringFromWasm0(ret[0], ret[1]);
+        } finally {
+            wasm.__wbindgen_free(deferred1_0, deferred1_1, 1);
+        }
+    };
+
+    /**
+     * @returns {string}
+     */
+    __exports.list_approval_rules = function() {
+        let deferred1_0;
+        let deferred1_1;
+        try {
+            const ret = wasm.list_approval_rules();
+            deferred1_0 = ret[0];
+            deferred1_1 = ret[1];
+            return getStringFromWasm0(ret[0], ret[1]);
+        } finally {
+            wasm.__wbindgen_free(deferred1_0, deferred1_1, 1);
+        }
+    };
+
+    /**
+     * Replace the arguments of a pending extracted call before approving it.
+     * Calls queued by the confirmation policy were already validated and
+     * cannot be edited.
+     * @param {bigint} id
+     * @param {string} arguments_json
+     */
+    __exports.edit_pending_arguments = function(id, arguments_json) {
+        const ptr0 = passStringToWasm0(arguments_json, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        const len0 = WASM_VECTOR_LEN;
+        const ret = wasm.edit_pending_arguments(id, ptr0, len0);
+        if (ret[1]) {
+            throw takeFromExternrefTable0(ret[0]);
+        }
+    };
+
+    /**
+     * Attach tool access rules to a registered server. `policy_json` has the
+     * shape `{ global: { allow, deny, constraints }, engrams: { <id>: {...} } }`.
+     * @param {string} url
+     * @param {string} policy_json
+     */
+    __exports.set_server_tool_policy = function(url, policy_json) {
+        const ptr0 = passStringToWasm0(url, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        const len0 = WASM_VECTOR_LEN;
+        const ptr1 = passStringToWasm0(policy_json, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        const len1 = WASM_VECTOR_LEN;
+        const ret = wasm.set_server_tool_policy(ptr0, len0, ptr1, len1);
+        if (ret[1]) {
+            throw takeFromExternrefTable0(ret[0]);
+        }
+    };
+
+    /**
+     * @param {string} url
+     * @returns {any}
+     */
+    __exports.get_server_tool_policy = function(url) {
+        const ptr0 = passStringToWasm0(url, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        const len0 = WASM_VECTOR_LEN;
+        const ret = wasm.get_server_tool_policy(ptr0, len0);
+        if (ret[2]) {
+            throw takeFromExternrefTable0(ret[1]);
+        }
+        return takeFromExternrefTable0(ret[0]);
+    };
+
+    /**
+     * Approve a pending call. `remember` is `tool` to always allow this tool
+     * on this server, or `server` to allow every tool on it.
+     * @param {bigint} id
+     * @param {string | null} [remember]
+     */
+    __exports.approve_tool_call = function(id, remember) {
+        var ptr0 = isLikeNone(remember) ? 0 : passStringToWasm0(remember, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        var len0 = WASM_VECTOR_LEN;
+        const ret = wasm.approve_tool_call(id, ptr0, len0);
+        if (ret[1]) {
+            throw takeFromExternrefTable0(ret[0]);
+        }
+    };
+
+    /**
+     * Register a function called with the rules as a JSON array whenever they
+     * change; pass `null` to unregister.
+     * @param {Function | null} [hook]
+     */
+    __exports.set_approval_rule_persistence = function(hook) {
+        wasm.set_approval_rule_persistence(isLikeNone(hook) ? 0 : addToExternrefTable0(hook));
+    };
+
+    /**
+     * Register a function called with a JSON event whenever a call is queued
+     * for approval (`tool_approval_pending`) or settled, by a user or by the
+     * approval timeout (`tool_approval_resolved`); pass `null` to unregister.
+     * @param {Function | null} [listener]
+     */
+    __exports.set_approval_listener = function(listener) {
+        wasm.set_approval_listener(isLikeNone(listener) ? 0 : addToExternrefTable0(listener));
+    };
+
+    /**
+     * Remember a decision. Takes `{ server, tool, engram_id?, decision }` with
+     * globs for `server` and `tool`; returns the rule's id.
+     * @param {string} rule_json
+     * @returns {bigint}
+     */
+    __exports.add_approval_rule = function(rule_json) {
+        const ptr0 = passStringToWasm0(rule_json, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        const len0 = WASM_VECTOR_LEN;
+        const ret = wasm.add_approval_rule(ptr0, len0);
+        if (ret[2]) {
+            throw takeFromExternrefTable0(ret[1]);
+        }
+        return BigInt.asUintN(64, ret[0]);
+    };
+
+    /**
+     * @param {string} policy_json
+     */
+    __exports.set_tool_policy = function(policy_json) {
+        const ptr0 = passStringToWasm0(policy_json, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        const len0 = WASM_VECTOR_LEN;
+        const ret = wasm.set_tool_policy(ptr0, len0);
+        if (ret[1]) {
+            throw takeFromExternrefTable0(ret[0]);
+        }
+    };
+
+    /**
+     * @param {bigint} id
+     * @returns {boolean}
+     */
+    __exports.remove_approval_rule = function(id) {
+        const ret = wasm.remove_approval_rule(id);
+        return ret !== 0;
+    };
+
+    /**
+     * @returns {string}
+     */
+    __exports.list_pending_approvals = function() {
+        let deferred1_0;
+        let deferred1_1;
+        try {
+            const ret = wasm.list_pending_approvals();
+            deferred1_0 = ret[0];
+            deferred1_1 = ret[1];
+            return getStringFromWasm0(ret[0], ret[1]);
+        } finally {
+            wasm.__wbindgen_free(deferred1_0, deferred1_1, 1);
+        }
+    };
+
+    /**
+     * @returns {string}
+     */
+    __exports.get_extracted_call_policy = function() {
+        let deferred1_0;
+        let deferred1_1;
+        try {
+            const ret = wasm.get_extracted_call_policy();
+            deferred1_0 = ret[0];
+            deferred1_1 = ret[1];
+            return getStringFromWasm0(ret[0], ret[1]);
+        } finally {
+            wasm.__wbindgen_free(deferred1_0, deferred1_1, 1);
+        }
+    };
+
+    /**
+     * The exported-to-original name mapping for `format` from the last export.
+     * @param {string} format
+     * @returns {string}
+     */
+    __exports.get_exported_tool_names = function(format) {
+        let deferred3_0;
+        let deferred3_1;
+        try {
+            const ptr0 = passStringToWasm0(format, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+            const len0 = WASM_VECTOR_LEN;
+            const ret = wasm.get_exported_tool_names(ptr0, len0);
+            var ptr2 = ret[0];
+            var len2 = ret[1];
+            if (ret[3]) {
+                ptr2 = 0; len2 = 0;
+                throw takeFromExternrefTable0(ret[2]);
+            }
+            deferred3_0 = ptr2;
+            deferred3_1 = len2;
+            return getStringFromWasm0(ptr2, len2);
+        } finally {
+            wasm.__wbindgen_free(deferred3_0, deferred3_1, 1);
+        }
+    };
+
+    /**
+     * Register a function called with every format's name mapping as JSON
+     * whenever an export changes one; pass `null` to unregister.
+     * @param {Function | null} [hook]
+     */
+    __exports.set_catalog_persistence = function(hook) {
+        wasm.set_catalog_persistence(isLikeNone(hook) ? 0 : addToExternrefTable0(hook));
+    };
+
+    /**
+     * Map a function name from a model response back to the tool name
+     * `route_tool_call` accepts. Unknown names are returned unchanged.
+     * @param {string | null | undefined} format
+     * @param {string} name
+     * @returns {string}
+     */
+    __exports.resolve_exported_tool_name = function(format, name) {
+        let deferred4_0;
+        let deferred4_1;
+        try {
+            var ptr0 = isLikeNone(format) ? 0 : passStringToWasm0(format, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+            var len0 = WASM_VECTOR_LEN;
+            const ptr1 = passStringToWasm0(name, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+            const len1 = WASM_VECTOR_LEN;
+            const ret = wasm.resolve_exported_tool_name(ptr0, len0, ptr1, len1);
+            var ptr3 = ret[0];
+            var len3 = ret[1];
+            if (ret[3]) {
+                ptr3 = 0; len3 = 0;
+                throw takeFromExternrefTable0(ret[2]);
+            }
+            deferred4_0 = ptr3;
+            deferred4_1 = len3;
+            return getStringFromWasm0(ptr3, len3);
+        } finally {
+            wasm.__wbindgen_free(deferred4_0, deferred4_1, 1);
+        }
+    };
+
+    /**
+     * Replace the exported name mappings with a snapshot saved by the
+     * persistence hook, keyed by format.
+     * @param {string} names_json
+     */
+    __exports.restore_exported_tool_names = function(names_json) {
+        const ptr0 = passStringToWasm0(names_json, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        const len0 = WASM_VECTOR_LEN;
+        const ret = wasm.restore_exported_tool_names(ptr0, len0);
+        if (ret[1]) {
+            throw takeFromExternrefTable0(ret[0]);
+        }
+    };
+
+    /**
+     * Render every cached tool as a function-calling catalog. `format` is
+     * `openai` (a `tools` array), `anthropic` (a `tools` array) or `gemini`
+     * (`{ functionDeclarations }`). Names are sanitised for the target API;
+     * use `resolve_exported_tool_name` to map a model's choice back.
+     * @param {string} format
+     * @returns {string}
+     */
+    __exports.export_tool_catalog = function(format) {
+        let deferred3_0;
+        let deferred3_1;
+        try {
+            const ptr0 = passStringToWasm0(format, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+            const len0 = WASM_VECTOR_LEN;
+            const ret = wasm.export_tool_catalog(ptr0, len0);
+            var ptr2 = ret[0];
+            var len2 = ret[1];
+            if (ret[3]) {
+                ptr2 = 0; len2 = 0;
+                throw takeFromExternrefTable0(ret[2]);
+            }
+            deferred3_0 = ptr2;
+            deferred3_1 = len2;
+            return getStringFromWasm0(ptr2, len2);
+        } finally {
+            wasm.__wbindgen_free(deferred3_0, deferred3_1, 1);
+        }
+    };
+
+    /**
+     * Flatten a raw `inputSchema` into `ToolParameter` entries.
+     * @param {string} schema_json
+     * @returns {any}
+     */
+    __exports.flatten_input_schema = function(schema_json) {
+        const ptr0 = passStringToWasm0(schema_json, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        const len0 = WASM_VECTOR_LEN;
+        const ret = wasm.flatten_input_schema(ptr0, len0);
+        if (ret[2]) {
+            throw takeFromExternrefTable0(ret[1]);
+        }
+        return takeFromExternrefTable0(ret[0]);
+    };
+
+    /**
+     * Parameters and resolved `inputSchema` for a tool cached in the registry.
+     * @param {string} url
+     * @param {string} tool_name
+     * @returns {any}
+     */
+    __exports.get_tool_parameters = function(url, tool_name) {
+        const ptr0 = passStringToWasm0(url, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        const len0 = WASM_VECTOR_LEN;
+        const ptr1 = passStringToWasm0(tool_name, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        const len1 = WASM_VECTOR_LEN;
+        const ret = wasm.get_tool_parameters(ptr0, len0, ptr1, len1);
+        if (ret[2]) {
+            throw takeFromExternrefTable0(ret[1]);
+        }
+        return takeFromExternrefTable0(ret[0]);
+    };
+
+    /**
+     * Route a JSON-RPC tool call to a registered server and execute it.
+     * Returns `{ route, outcome, served_by, attempts }`; unknown tools yield a
+     * `-32601` outcome.
+     * @param {string} method
+     * @param {any} params
+     * @param {string | null} [engram_id]
+     * @returns {Promise<any>}
+     */
+    __exports.route_tool_call = function(method, params, engram_id) {
+        const ptr0 = passStringToWasm0(method, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        const len0 = WASM_VECTOR_LEN;
+        var ptr1 = isLikeNone(engram_id) ? 0 : passStringToWasm0(engram_id, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        var len1 = WASM_VECTOR_LEN;
+        const ret = wasm.route_tool_call(ptr0, len0, params, ptr1, len1);
+        return ret;
+    };
+
+    /**
+     * Set the alias and priority used when routing to a registered server.
+     * @param {string} url
+     * @param {string | null | undefined} alias
+     * @param {number} priority
+     */
+    __exports.set_server_routing = function(url, alias, priority) {
+        const ptr0 = passStringToWasm0(url, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        const len0 = WASM_VECTOR_LEN;
+        var ptr1 = isLikeNone(alias) ? 0 : passStringToWasm0(alias, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        var len1 = WASM_VECTOR_LEN;
+        const ret = wasm.set_server_routing(ptr0, len0, ptr1, len1, priority);
+        if (ret[1]) {
+            throw takeFromExternrefTable0(ret[0]);
+        }
+    };
+
+    /**
+     * Resolve a tool name (optionally `alias/tool`) without calling it.
+     * @param {string} method
+     * @returns {any}
+     */
+    __exports.resolve_tool_route = function(method) {
+        const ptr0 = passStringToWasm0(method, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        const len0 = WASM_VECTOR_LEN;
+        const ret = wasm.resolve_tool_route(ptr0, len0);
+        if (ret[2]) {
+            throw takeFromExternrefTable0(ret[1]);
+        }
+        return takeFromExternrefTable0(ret[0]);
+    };
+
+    /**
+     * @param {string} config_json
+     */
+    __exports.set_router_config = function(config_json) {
+        const ptr0 = passStringToWasm0(config_json, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        const len0 = WASM_VECTOR_LEN;
+        const ret = wasm.set_router_config(ptr0, len0);
+        if (ret[1]) {
+            throw takeFromExternrefTable0(ret[0]);
+        }
+    };
+
+    /**
+     * @returns {string}
+     */
+    __exports.get_router_config = function() {
+        let deferred1_0;
+        let deferred1_1;
+        try {
+            const ret = wasm.get_router_config();
+            deferred1_0 = ret[0];
+            deferred1_1 = ret[1];
+            return getStringFromWasm0(ret[0], ret[1]);
+        } finally {
+            wasm.__wbindgen_free(deferred1_0, deferred1_1, 1);
+        }
+    };
+
+    /**
+     * @returns {string}
+     */
+    __exports.list_tool_conflicts = function() {
+        let deferred1_0;
+        let deferred1_1;
+        try {
+            const ret = wasm.list_tool_conflicts();
+            deferred1_0 = ret[0];
+            deferred1_1 = ret[1];
+            return getStringFromWasm0(ret[0], ret[1]);
+        } finally {
+            wasm.__wbindgen_free(deferred1_0, deferred1_1, 1);
+        }
+    };
+
+    /**
+     * Check arguments for a cached tool without calling it. Returns
+     * `{ valid, arguments, errors }` where `arguments` are the coerced values.
+     * @param {string} url
+     * @param {string} tool_name
+     * @param {any} args
+     * @returns {any}
+     */
+    __exports.validate_tool_arguments = function(url, tool_name, args) {
+        const ptr0 = passStringToWasm0(url, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        const len0 = WASM_VECTOR_LEN;
+        const ptr1 = passStringToWasm0(tool_name, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        const len1 = WASM_VECTOR_LEN;
+        const ret = wasm.validate_tool_arguments(ptr0, len0, ptr1, len1, args);
+        if (ret[2]) {
+            throw takeFromExternrefTable0(ret[1]);
+        }
+        return takeFromExternrefTable0(ret[0]);
+    };
+
+    /**
+     * Drop the indexed messages of an engram.
+     * @param {string} engram_id
+     */
+    __exports.remove_engram_from_search_index = function(engram_id) {
+        const ptr0 = passStringToWasm0(engram_id, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        const len0 = WASM_VECTOR_LEN;
+        wasm.remove_engram_from_search_index(ptr0, len0);
+    };
+
+    /**
+     * @returns {string}
+     */
+    __exports.get_search_index_stats = function() {
+        let deferred1_0;
+        let deferred1_1;
+        try {
+            const ret = wasm.get_search_index_stats();
+            deferred1_0 = ret[0];
+            deferred1_1 = ret[1];
+            return getStringFromWasm0(ret[0], ret[1]);
+        } finally {
+            wasm.__wbindgen_free(deferred1_0, deferred1_1, 1);
+        }
+    };
+
+    /**
+     * Rank memory events and messages against
+     * `{ query, engram_id?, roles?, limit? }`. Queries combine words,
+     * `"quoted phrases"` (required) and `prefix*` terms. Returns
+     * `{ hits: [{ kind, id | message_id, engram_id?, role, timestamp, score, text }] }`,
+     * best first.
+     * @param {string} query_json
+     * @returns {string}
+     */
+    __exports.search_context = function(query_json) {
+        let deferred3_0;
+        let deferred3_1;
+        try {
+            const ptr0 = passStringToWasm0(query_json, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+            const len0 = WASM_VECTOR_LEN;
+            const ret = wasm.search_context(ptr0, len0);
+            var ptr2 = ret[0];
+            var len2 = ret[1];
+            if (ret[3]) {
+                ptr2 = 0; len2 = 0;
+                throw takeFromExternrefTable0(ret[2]);
+            }
+            deferred3_0 = ptr2;
+            deferred3_1 = len2;
+            return getStringFromWasm0(ptr2, len2);
+        } finally {
+            wasm.__wbindgen_free(deferred3_0, deferred3_1, 1);
+        }
+    };
+
+    /**
+     * Index a JSON array of stored messages; returns how many were indexed.
+     * @param {string} messages_json
+     * @returns {number}
+     */
+    __exports.index_messages = function(messages_json) {
+        const ptr0 = passStringToWasm0(messages_json, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        const len0 = WASM_VECTOR_LEN;
+        const ret = wasm.index_messages(ptr0, len0);
+        if (ret[2]) {
+            throw takeFromExternrefTable0(ret[1]);
+        }
+        return ret[0] >>> 0;
     };
 
     /**
-     * @param {string} url
-     * @returns {Promise<any>}
+     * Index a stored message `{ id, engramId, role, text, timestamp }`,
+     * replacing the message with the same id. Messages without string text
+     * are skipped.
+     * @param {string} message_json
+     * @returns {boolean}
      */
-    __exports.list_tools = function(url) {
-        const ptr0 = passStringToWasm0(url, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+    __exports.index_message = function(message_json) {
+        const ptr0 = passStringToWasm0(message_json, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
         const len0 = WASM_VECTOR_LEN;
-        const ret = wasm.list_tools(ptr0, len0);
-        return ret;
+        const ret = wasm.index_message(ptr0, len0);
+        if (ret[2]) {
+            throw takeFromExternrefTable0(ret[1]);
+        }
+        return ret[0] !== 0;
     };
 
     /**
+     * Assemble a context within the token budget. Takes
+     * `{ bootrom?, compose_bootrom?, tool_catalog?, imprints, messages, budget? }`
+     * and returns `{ messages, total_tokens, budget_tokens, dropped, truncated }`.
+     * @param {string} input_json
      * @returns {string}
      */
-    __exports.get_compiled_info = function() {
+    __exports.assemble_context = function(input_json) {
+        let deferred3_0;
+        let deferred3_1;
+        try {
+            const ptr0 = passStringToWasm0(input_json, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+            const len0 = WASM_VECTOR_LEN;
+            const ret = wasm.assemble_context(ptr0, len0);
+            var ptr2 = ret[0];
+            var len2 = ret[1];
+            if (ret[3]) {
+                ptr2 = 0; len2 = 0;
+                throw takeFromExternrefTable0(ret[2]);
+            }
+            deferred3_0 = ptr2;
+            deferred3_1 = len2;
+            return getStringFromWasm0(ptr2, len2);
+        } finally {
+            wasm.__wbindgen_free(deferred3_0, deferred3_1, 1);
+        }
+    };
+
+    /**
+     * @returns {string}
+     */
+    __exports.get_context_budget = function() {
         let deferred1_0;
         let deferred1_1;
         try {
-            const ret = wasm.get_compiled_info();
+            const ret = wasm.get_context_budget();
             deferred1_0 = ret[0];
             deferred1_1 = ret[1];
             return getStringFromWasm0(ret[0], ret[1]);
@@ -388,35 +1964,48 @@ let wasm_bindgen;
     };
 
     /**
-     * @param {string} url
-     * @param {string} tool_name
-     * @param {any} args
-     * @returns {Promise<any>}
+     * Register the function `{"kind": "js"}` tokenizers call. It receives the
+     * text and returns a token count; pass `null` to unregister.
+     * @param {Function | null} [tokenizer]
      */
-    __exports.call_tool = function(url, tool_name, args) {
-        const ptr0 = passStringToWasm0(url, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+    __exports.set_context_tokenizer = function(tokenizer) {
+        wasm.set_context_tokenizer(isLikeNone(tokenizer) ? 0 : addToExternrefTable0(tokenizer));
+    };
+
+    /**
+     * Set the default budget used when `assemble_context` is given none.
+     * @param {string} budget_json
+     */
+    __exports.set_context_budget = function(budget_json) {
+        const ptr0 = passStringToWasm0(budget_json, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
         const len0 = WASM_VECTOR_LEN;
-        const ptr1 = passStringToWasm0(tool_name, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
-        const len1 = WASM_VECTOR_LEN;
-        const ret = wasm.call_tool(ptr0, len0, ptr1, len1, args);
-        return ret;
+        const ret = wasm.set_context_budget(ptr0, len0);
+        if (ret[1]) {
+            throw takeFromExternrefTable0(ret[0]);
+        }
     };
 
     /**
-     * @param {boolean} enabled
+     * Token estimate for `text` with the configured tokenizer.
+     * @param {string} text
+     * @returns {number}
      */
-    __exports.set_debug_mode = function(enabled) {
-        wasm.set_debug_mode(enabled);
+    __exports.estimate_tokens = function(text) {
+        const ptr0 = passStringToWasm0(text, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        const len0 = WASM_VECTOR_LEN;
+        const ret = wasm.estimate_tokens(ptr0, len0);
+        return ret >>> 0;
     };
 
     /**
+     * Names of the registered dialects, in the order they are tried.
      * @returns {string}
      */
-    __exports.get_bootrom = function() {
+    __exports.list_tool_call_dialects = function() {
         let deferred1_0;
         let deferred1_1;
         try {
-            const ret = wasm.get_bootrom();
+            const ret = wasm.list_tool_call_dialects();
             deferred1_0 = ret[0];
             deferred1_1 = ret[1];
             return getStringFromWasm0(ret[0], ret[1]);
@@ -425,13 +2014,194 @@ let wasm_bindgen;
         }
     };
 
-    function __wbg_adapter_48(arg0, arg1, arg2) {
-        wasm.closure70_externref_shim(arg0, arg1, arg2);
+    /**
+     * Parse tool invocations in any registered dialect. Returns
+     * `{ calls, rejected }`: `calls` are JSON-RPC shaped and name a tool some
+     * registered server provides; `rejected` lists recognised calls that did
+     * not validate.
+     * @param {string} text
+     * @returns {string}
+     */
+    __exports.parse_tool_calls = function(text) {
+        let deferred2_0;
+        let deferred2_1;
+        try {
+            const ptr0 = passStringToWasm0(text, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+            const len0 = WASM_VECTOR_LEN;
+            const ret = wasm.parse_tool_calls(ptr0, len0);
+            deferred2_0 = ret[0];
+            deferred2_1 = ret[1];
+            return getStringFromWasm0(ret[0], ret[1]);
+        } finally {
+            wasm.__wbindgen_free(deferred2_0, deferred2_1, 1);
+        }
+    };
+
+    /**
+     * Restore the built-in dialects, dropping any JS parsers.
+     */
+    __exports.reset_tool_call_dialects = function() {
+        wasm.reset_tool_call_dialects();
+    };
+
+    /**
+     * Remove a dialect, built-in or registered. Returns whether it existed.
+     * @param {string} name
+     * @returns {boolean}
+     */
+    __exports.unregister_tool_call_dialect = function(name) {
+        const ptr0 = passStringToWasm0(name, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        const len0 = WASM_VECTOR_LEN;
+        const ret = wasm.unregister_tool_call_dialect(ptr0, len0);
+        return ret !== 0;
+    };
+
+    /**
+     * Register a JS tool-call parser, replacing any dialect with the same name.
+     * New dialects are tried after the existing ones.
+     * @param {string} name
+     * @param {Function} parser
+     */
+    __exports.register_tool_call_dialect = function(name, parser) {
+        const ptr0 = passStringToWasm0(name, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        const len0 = WASM_VECTOR_LEN;
+        wasm.register_tool_call_dialect(ptr0, len0, parser);
+    };
+
+    function __wbg_adapter_54(arg0, arg1, arg2) {
+        wasm.closure72_externref_shim(arg0, arg1, arg2);
+    }
+
+    function __wbg_adapter_57(arg0, arg1) {
+        wasm._dyn_core__ops__function__FnMut_____Output___R_as_wasm_bindgen__closure__WasmClosure___describe__invoke__hed171b9ef44df934(arg0, arg1);
+    }
+
+    function __wbg_adapter_60(arg0, arg1, arg2) {
+        wasm.closure409_externref_shim(arg0, arg1, arg2);
+    }
+
+    function __wbg_adapter_356(arg0, arg1, arg2, arg3) {
+        wasm.closure423_externref_shim(arg0, arg1, arg2, arg3);
+    }
+
+    const __wbindgen_enum_IdbCursorDirection = ["next", "nextunique", "prev", "prevunique"];
+
+    const __wbindgen_enum_IdbTransactionMode = ["readonly", "readwrite", "versionchange", "readwriteflush", "cleanup"];
+
+    const AgentRunnerFinalization = (typeof FinalizationRegistry === 'undefined')
+        ? { register: () => {}, unregister: () => {} }
+        : new FinalizationRegistry(ptr => wasm.__wbg_agentrunner_free(ptr >>> 0, 1));
+    /**
+     * Drives agent runs: model turn, tool calls, model turn, ... until the model
+     * answers without calling a tool or a budget runs out. Runs are controlled
+     * by id with `pause_agent_run`, `resume_agent_run` and `abort_agent_run`.
+     */
+    class AgentRunner {
+
+        __destroy_into_raw() {
+            const ptr = this.__wbg_ptr;
+            this.__wbg_ptr = 0;
+            AgentRunnerFinalization.unregister(this);
+            return ptr;
+        }
+
+        free() {
+            const ptr = this.__destroy_into_raw();
+            wasm.__wbg_agentrunner_free(ptr, 0);
+        }
+        /**
+         * `emit`, `load` and `persist` are the `TapPipeline` hooks. `model`
+         * serves `{"kind": "js"}` providers: it receives
+         * `{ run_id, engram_id, messages, memory }` as JSON and returns (a
+         * promise of) the reply text.
+         * @param {Function} emit
+         * @param {Function | null} [load]
+         * @param {Function | null} [persist]
+         * @param {Function | null} [model]
+         */
+        constructor(emit, load, persist, model) {
+            const ret = wasm.agentrunner_new(emit, isLikeNone(load) ? 0 : addToExternrefTable0(load), isLikeNone(persist) ? 0 : addToExternrefTable0(persist), isLikeNone(model) ? 0 : addToExternrefTable0(model));
+            this.__wbg_ptr = ret >>> 0;
+            AgentRunnerFinalization.register(this, this.__wbg_ptr, this);
+            return this;
+        }
+        /**
+         * Start a run in the background and return its id. Takes
+         * `{ engram_id?, model, budget?, prompt?, messages?, memory? }`; progress
+         * is emitted as `agent_run`, `agent_step` and `cbus_message` events, the
+         * last `agent_run` event carrying the stop reason.
+         * @param {string} request_json
+         * @returns {bigint}
+         */
+        start(request_json) {
+            const ptr0 = passStringToWasm0(request_json, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+            const len0 = WASM_VECTOR_LEN;
+            const ret = wasm.agentrunner_start(this.__wbg_ptr, ptr0, len0);
+            if (ret[2]) {
+                throw takeFromExternrefTable0(ret[1]);
+            }
+            return BigInt.asUintN(64, ret[0]);
+        }
     }
+    __exports.AgentRunner = AgentRunner;
+
+    const TapPipelineFinalization = (typeof FinalizationRegistry === 'undefined')
+        ? { register: () => {}, unregister: () => {} }
+        : new FinalizationRegistry(ptr => wasm.__wbg_tappipeline_free(ptr >>> 0, 1));
+    /**
+     * Runs taps end to end: context assembly, connected arguments, templates,
+     * the tool call, CBus posting and persistence, and dispatch of tool calls
+     * found in the output. The service worker supplies the hooks and forwards
+     * the emitted events to clients.
+     */
+    class TapPipeline {
+
+        __destroy_into_raw() {
+            const ptr = this.__wbg_ptr;
+            this.__wbg_ptr = 0;
+            TapPipelineFinalization.unregister(this);
+            return ptr;
+        }
 
-    function __wbg_adapter_129(arg0, arg1, arg2, arg3) {
-        wasm.closure92_externref_shim(arg0, arg1, arg2, arg3);
+        free() {
+            const ptr = this.__destroy_into_raw();
+            wasm.__wbg_tappipeline_free(ptr, 0);
+        }
+        /**
+         * `emit` receives each event as JSON `{ seq, target, engram_id?, message }`
+         * where `target` is `engram`, `reply` or `broadcast`. `load` receives an
+         * engram id and returns (a promise of) its messages as a JSON array;
+         * `persist` receives a CBus message as JSON and may return a promise.
+         * @param {Function} emit
+         * @param {Function | null} [load]
+         * @param {Function | null} [persist]
+         */
+        constructor(emit, load, persist) {
+            const ret = wasm.tappipeline_new(emit, isLikeNone(load) ? 0 : addToExternrefTable0(load), isLikeNone(persist) ? 0 : addToExternrefTable0(persist));
+            this.__wbg_ptr = ret >>> 0;
+            TapPipelineFinalization.register(this, this.__wbg_ptr, this);
+            return this;
+        }
+        /**
+         * Run a tap, or with `call` set dispatch a JSON-RPC call as if it had
+         * been extracted from tool output. Takes
+         * `{ source, tap_config, engram_id?, request_id?, reply?, messages?, memory?, call? }`
+         * and resolves to `{ calls, events, skipped }` once every extracted
+         * call has run.
+         * @param {string} request_json
+         * @returns {Promise<any>}
+         */
+        run(request_json) {
+            const ptr0 = passStringToWasm0(request_json, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+            const len0 = WASM_VECTOR_LEN;
+            const ret = wasm.tappipeline_run(this.__wbg_ptr, ptr0, len0);
+            if (ret[2]) {
+                throw takeFromExternrefTable0(ret[1]);
+            }
+            return takeFromExternrefTable0(ret[0]);
+        }
     }
+    __exports.TapPipeline = TapPipeline;
 
     async function __wbg_load(module, imports) {
         if (typeof Response === 'function' && module instanceof Response) {
@@ -474,6 +2244,21 @@ let wasm_bindgen;
             getDataViewMemory0().setInt32(arg0 + 4 * 1, len1, true);
             getDataViewMemory0().setInt32(arg0 + 4 * 0, ptr1, true);
         };
+        imports.wbg.__wbg_abort_99fc644e2c79c9fb = function() { return handleError(function (arg0) {
+            arg0.abort();
+        }, arguments) };
+        imports.wbg.__wbg_all_d5bf227e8f68795d = function(arg0) {
+            const ret = Promise.all(arg0);
+            return ret;
+        };
+        imports.wbg.__wbg_bound_55a8d08e0491e17a = function() { return handleError(function (arg0, arg1) {
+            const ret = IDBKeyRange.bound(arg0, arg1);
+            return ret;
+        }, arguments) };
+        imports.wbg.__wbg_bound_f2afc3766d4545cf = function() { return handleError(function (arg0, arg1, arg2, arg3) {
+            const ret = IDBKeyRange.bound(arg0, arg1, arg2 !== 0, arg3 !== 0);
+            return ret;
+        }, arguments) };
         imports.wbg.__wbg_buffer_609cc3eee51ed158 = function(arg0) {
             const ret = arg0.buffer;
             return ret;
@@ -486,6 +2271,44 @@ let wasm_bindgen;
             const ret = arg0.call(arg1, arg2);
             return ret;
         }, arguments) };
+        imports.wbg.__wbg_call_833bed5770ea2041 = function() { return handleError(function (arg0, arg1, arg2, arg3) {
+            const ret = arg0.call(arg1, arg2, arg3);
+            return ret;
+        }, arguments) };
+        imports.wbg.__wbg_close_26fc2e6856d8567a = function(arg0) {
+            arg0.close();
+        };
+        imports.wbg.__wbg_construct_b91ff0e53b60c0c3 = function() { return handleError(function (arg0, arg1) {
+            const ret = Reflect.construct(arg0, arg1);
+            return ret;
+        }, arguments) };
+        imports.wbg.__wbg_contains_b952b06daf857803 = function(arg0, arg1, arg2) {
+            const ret = arg0.contains(getStringFromWasm0(arg1, arg2));
+            return ret;
+        };
+        imports.wbg.__wbg_continue_c46c11d3dbe1b030 = function() { return handleError(function (arg0) {
+            arg0.continue();
+        }, arguments) };
+        imports.wbg.__wbg_createIndex_ef4e185744bed9a6 = function() { return handleError(function (arg0, arg1, arg2, arg3, arg4) {
+            const ret = arg0.createIndex(getStringFromWasm0(arg1, arg2), getStringFromWasm0(arg3, arg4));
+            return ret;
+        }, arguments) };
+        imports.wbg.__wbg_createIndex_fcfd513cf4581834 = function() { return handleError(function (arg0, arg1, arg2, arg3) {
+            const ret = arg0.createIndex(getStringFromWasm0(arg1, arg2), arg3);
+            return ret;
+        }, arguments) };
+        imports.wbg.__wbg_createObjectStore_d2f9e1016f4d81b9 = function() { return handleError(function (arg0, arg1, arg2, arg3) {
+            const ret = arg0.createObjectStore(getStringFromWasm0(arg1, arg2), arg3);
+            return ret;
+        }, arguments) };
+        imports.wbg.__wbg_createObjectStore_e566459f7161f82f = function() { return handleError(function (arg0, arg1, arg2) {
+            const ret = arg0.createObjectStore(getStringFromWasm0(arg1, arg2));
+            return ret;
+        }, arguments) };
+        imports.wbg.__wbg_delete_200677093b4cf756 = function() { return handleError(function (arg0, arg1) {
+            const ret = arg0.delete(arg1);
+            return ret;
+        }, arguments) };
         imports.wbg.__wbg_done_769e5ede4b31c67b = function(arg0) {
             const ret = arg0.done;
             return ret;
@@ -494,10 +2317,26 @@ let wasm_bindgen;
             const ret = Object.entries(arg0);
             return ret;
         };
+        imports.wbg.__wbg_error_e9332df4e7a14612 = function(arg0) {
+            const ret = arg0.error;
+            return isLikeNone(ret) ? 0 : addToExternrefTable0(ret);
+        };
+        imports.wbg.__wbg_error_ff4ddaabdfc5dbb3 = function() { return handleError(function (arg0) {
+            const ret = arg0.error;
+            return isLikeNone(ret) ? 0 : addToExternrefTable0(ret);
+        }, arguments) };
         imports.wbg.__wbg_fetch_08f78ad9de4403eb = function(arg0, arg1, arg2) {
             const ret = self.fetch(getStringFromWasm0(arg0, arg1), arg2);
             return ret;
         };
+        imports.wbg.__wbg_getAllKeys_fe32caefa775e395 = function() { return handleError(function (arg0, arg1) {
+            const ret = arg0.getAllKeys(arg1);
+            return ret;
+        }, arguments) };
+        imports.wbg.__wbg_getAll_1de5635a99f2dae8 = function() { return handleError(function (arg0) {
+            const ret = arg0.getAll();
+            return ret;
+        }, arguments) };
         imports.wbg.__wbg_get_123509460060ab98 = function() { return handleError(function (arg0, arg1, arg2, arg3) {
             const ret = arg1.get(getStringFromWasm0(arg2, arg3));
             var ptr1 = isLikeNone(ret) ? 0 : passStringToWasm0(ret, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
@@ -509,14 +2348,30 @@ let wasm_bindgen;
             const ret = Reflect.get(arg0, arg1);
             return ret;
         }, arguments) };
+        imports.wbg.__wbg_get_85c3d71662a108c8 = function() { return handleError(function (arg0, arg1) {
+            const ret = Reflect.get(arg0, arg1 >>> 0);
+            return ret;
+        }, arguments) };
+        imports.wbg.__wbg_get_8da03f81f6a1111e = function() { return handleError(function (arg0, arg1) {
+            const ret = arg0.get(arg1);
+            return ret;
+        }, arguments) };
         imports.wbg.__wbg_get_b9b93047fe3cf45b = function(arg0, arg1) {
             const ret = arg0[arg1 >>> 0];
             return ret;
         };
+        imports.wbg.__wbg_getwithrefkey_1dc361bd10053bfe = function(arg0, arg1) {
+            const ret = arg0[arg1];
+            return ret;
+        };
         imports.wbg.__wbg_headers_9cb51cfd2ac780a4 = function(arg0) {
             const ret = arg0.headers;
             return ret;
         };
+        imports.wbg.__wbg_index_e00ca5fff206ee3e = function() { return handleError(function (arg0, arg1, arg2) {
+            const ret = arg0.index(getStringFromWasm0(arg1, arg2));
+            return ret;
+        }, arguments) };
         imports.wbg.__wbg_instanceof_ArrayBuffer_e14585432e3737fc = function(arg0) {
             let result;
             try {
@@ -527,6 +2382,46 @@ let wasm_bindgen;
             const ret = result;
             return ret;
         };
+        imports.wbg.__wbg_instanceof_IdbCursorWithValue_18f39d69ed298f6f = function(arg0) {
+            let result;
+            try {
+                result = arg0 instanceof IDBCursorWithValue;
+            } catch (_) {
+                result = false;
+            }
+            const ret = result;
+            return ret;
+        };
+        imports.wbg.__wbg_instanceof_IdbCursor_4f02b0cddf69c141 = function(arg0) {
+            let result;
+            try {
+                result = arg0 instanceof IDBCursor;
+            } catch (_) {
+                result = false;
+            }
+            const ret = result;
+            return ret;
+        };
+        imports.wbg.__wbg_instanceof_IdbDatabase_a3ef009ca00059f9 = function(arg0) {
+            let result;
+            try {
+                result = arg0 instanceof IDBDatabase;
+            } catch (_) {
+                result = false;
+            }
+            const ret = result;
+            return ret;
+        };
+        imports.wbg.__wbg_instanceof_IdbFactory_12eaba3366f4302f = function(arg0) {
+            let result;
+            try {
+                result = arg0 instanceof IDBFactory;
+            } catch (_) {
+                result = false;
+            }
+            const ret = result;
+            return ret;
+        };
         imports.wbg.__wbg_instanceof_Map_f3469ce2244d2430 = function(arg0) {
             let result;
             try {
@@ -573,6 +2468,10 @@ let wasm_bindgen;
             const ret = arg0.json();
             return ret;
         }, arguments) };
+        imports.wbg.__wbg_key_29fefecef430db96 = function() { return handleError(function (arg0) {
+            const ret = arg0.key;
+            return ret;
+        }, arguments) };
         imports.wbg.__wbg_length_a446193dc22c12f8 = function(arg0) {
             const ret = arg0.length;
             return ret;
@@ -584,6 +2483,14 @@ let wasm_bindgen;
         imports.wbg.__wbg_log_a4bfb3158e81f8d3 = function(arg0, arg1) {
             console.log(getStringFromWasm0(arg0, arg1));
         };
+        imports.wbg.__wbg_lower_74cd3df65da0af96 = function() { return handleError(function (arg0) {
+            const ret = arg0.lower;
+            return ret;
+        }, arguments) };
+        imports.wbg.__wbg_new0_f788a2397c7ca929 = function() {
+            const ret = new Date();
+            return ret;
+        };
         imports.wbg.__wbg_new_018dcc2d6c8c2f6a = function() { return handleError(function () {
             const ret = new Headers();
             return ret;
@@ -595,7 +2502,7 @@ let wasm_bindgen;
                     const a = state0.a;
                     state0.a = 0;
                     try {
-                        return __wbg_adapter_129(a, state0.b, arg0, arg1);
+                        return __wbg_adapter_356(a, state0.b, arg0, arg1);
                     } finally {
                         state0.a = a;
                     }
@@ -610,6 +2517,14 @@ let wasm_bindgen;
             const ret = new Object();
             return ret;
         };
+        imports.wbg.__wbg_new_5e0be73521bc8c17 = function() {
+            const ret = new Map();
+            return ret;
+        };
+        imports.wbg.__wbg_new_78feb108b6472713 = function() {
+            const ret = new Array();
+            return ret;
+        };
         imports.wbg.__wbg_new_a12002a7f91c75be = function(arg0) {
             const ret = new Uint8Array(arg0);
             return ret;
@@ -618,6 +2533,10 @@ let wasm_bindgen;
             const ret = new Function(getStringFromWasm0(arg0, arg1));
             return ret;
         };
+        imports.wbg.__wbg_newwithlength_a381634e90c276d4 = function(arg0) {
+            const ret = new Uint8Array(arg0 >>> 0);
+            return ret;
+        };
         imports.wbg.__wbg_next_25feadfc0913fea9 = function(arg0) {
             const ret = arg0.next;
             return ret;
@@ -630,10 +2549,62 @@ let wasm_bindgen;
             const ret = Date.now();
             return ret;
         };
+        imports.wbg.__wbg_objectStoreNames_9bb1ab04a7012aaf = function(arg0) {
+            const ret = arg0.objectStoreNames;
+            return ret;
+        };
+        imports.wbg.__wbg_objectStore_21878d46d25b64b6 = function() { return handleError(function (arg0, arg1, arg2) {
+            const ret = arg0.objectStore(getStringFromWasm0(arg1, arg2));
+            return ret;
+        }, arguments) };
+        imports.wbg.__wbg_of_4a05197bfc89556f = function(arg0, arg1, arg2) {
+            const ret = Array.of(arg0, arg1, arg2);
+            return ret;
+        };
+        imports.wbg.__wbg_of_66b3ee656cbd962b = function(arg0, arg1) {
+            const ret = Array.of(arg0, arg1);
+            return ret;
+        };
         imports.wbg.__wbg_ok_3aaf32d069979723 = function(arg0) {
             const ret = arg0.ok;
             return ret;
         };
+        imports.wbg.__wbg_oldVersion_e8337811e52861c6 = function(arg0) {
+            const ret = arg0.oldVersion;
+            return ret;
+        };
+        imports.wbg.__wbg_openCursor_238e247d18bde2cd = function() { return handleError(function (arg0) {
+            const ret = arg0.openCursor();
+            return ret;
+        }, arguments) };
+        imports.wbg.__wbg_openCursor_d8ea5d621ec422f8 = function() { return handleError(function (arg0, arg1, arg2) {
+            const ret = arg0.openCursor(arg1, __wbindgen_enum_IdbCursorDirection[arg2]);
+            return ret;
+        }, arguments) };
+        imports.wbg.__wbg_openKeyCursor_ff825f1b3d4f33a2 = function() { return handleError(function (arg0, arg1, arg2) {
+            const ret = arg0.openKeyCursor(arg1, __wbindgen_enum_IdbCursorDirection[arg2]);
+            return ret;
+        }, arguments) };
+        imports.wbg.__wbg_open_e0c0b2993eb596e1 = function() { return handleError(function (arg0, arg1, arg2, arg3) {
+            const ret = arg0.open(getStringFromWasm0(arg1, arg2), arg3 >>> 0);
+            return ret;
+        }, arguments) };
+        imports.wbg.__wbg_primaryKey_2c15e9cdd3fb2d05 = function() { return handleError(function (arg0) {
+            const ret = arg0.primaryKey;
+            return ret;
+        }, arguments) };
+        imports.wbg.__wbg_push_737cfc8c1432c2c6 = function(arg0, arg1) {
+            const ret = arg0.push(arg1);
+            return ret;
+        };
+        imports.wbg.__wbg_put_066faa31a6a88f5b = function() { return handleError(function (arg0, arg1, arg2) {
+            const ret = arg0.put(arg1, arg2);
+            return ret;
+        }, arguments) };
+        imports.wbg.__wbg_put_9ef5363941008835 = function() { return handleError(function (arg0, arg1) {
+            const ret = arg0.put(arg1);
+            return ret;
+        }, arguments) };
         imports.wbg.__wbg_queueMicrotask_97d92b4fcc8a61c5 = function(arg0) {
             queueMicrotask(arg0);
         };
@@ -641,20 +2612,66 @@ let wasm_bindgen;
             const ret = arg0.queueMicrotask;
             return ret;
         };
+        imports.wbg.__wbg_race_ace53f9902587e09 = function(arg0) {
+            const ret = Promise.race(arg0);
+            return ret;
+        };
+        imports.wbg.__wbg_random_3ad904d98382defe = function() {
+            const ret = Math.random();
+            return ret;
+        };
         imports.wbg.__wbg_resolve_4851785c9c5f573d = function(arg0) {
             const ret = Promise.resolve(arg0);
             return ret;
         };
+        imports.wbg.__wbg_result_f29afabdf2c05826 = function() { return handleError(function (arg0) {
+            const ret = arg0.result;
+            return ret;
+        }, arguments) };
         imports.wbg.__wbg_set_11cd83f45504cedf = function() { return handleError(function (arg0, arg1, arg2, arg3, arg4) {
             arg0.set(getStringFromWasm0(arg1, arg2), getStringFromWasm0(arg3, arg4));
         }, arguments) };
+        imports.wbg.__wbg_set_37837023f3d740e8 = function(arg0, arg1, arg2) {
+            arg0[arg1 >>> 0] = arg2;
+        };
+        imports.wbg.__wbg_set_3f1d0b984ed272ed = function(arg0, arg1, arg2) {
+            arg0[arg1] = arg2;
+        };
         imports.wbg.__wbg_set_65595bdd868b3009 = function(arg0, arg1, arg2) {
             arg0.set(arg1, arg2 >>> 0);
         };
+        imports.wbg.__wbg_set_8fc6bf8a5b1071d1 = function(arg0, arg1, arg2) {
+            const ret = arg0.set(arg1, arg2);
+            return ret;
+        };
         imports.wbg.__wbg_set_bb8cecf6a62b9f46 = function() { return handleError(function (arg0, arg1, arg2) {
             const ret = Reflect.set(arg0, arg1, arg2);
             return ret;
         }, arguments) };
+        imports.wbg.__wbg_setkeypath_691179e313c26ae1 = function(arg0, arg1) {
+            arg0.keyPath = arg1;
+        };
+        imports.wbg.__wbg_setonabort_3bf4db6614fa98e9 = function(arg0, arg1) {
+            arg0.onabort = arg1;
+        };
+        imports.wbg.__wbg_setoncomplete_4d19df0dadb7c4d4 = function(arg0, arg1) {
+            arg0.oncomplete = arg1;
+        };
+        imports.wbg.__wbg_setonerror_b0d9d723b8fddbbb = function(arg0, arg1) {
+            arg0.onerror = arg1;
+        };
+        imports.wbg.__wbg_setonerror_d7e3056cc6e56085 = function(arg0, arg1) {
+            arg0.onerror = arg1;
+        };
+        imports.wbg.__wbg_setonsuccess_afa464ee777a396d = function(arg0, arg1) {
+            arg0.onsuccess = arg1;
+        };
+        imports.wbg.__wbg_setonupgradeneeded_fcf7ce4f2eb0cb5f = function(arg0, arg1) {
+            arg0.onupgradeneeded = arg1;
+        };
+        imports.wbg.__wbg_setonversionchange_6ee07fa49ee1e3a5 = function(arg0, arg1) {
+            arg0.onversionchange = arg1;
+        };
         imports.wbg.__wbg_static_accessor_GLOBAL_88a902d13a557d07 = function() {
             const ret = typeof global === 'undefined' ? null : global;
             return isLikeNone(ret) ? 0 : addToExternrefTable0(ret);
@@ -679,6 +2696,14 @@ let wasm_bindgen;
             const ret = JSON.stringify(arg0);
             return ret;
         }, arguments) };
+        imports.wbg.__wbg_test_7f0ac7b9d67b7a48 = function(arg0, arg1, arg2) {
+            const ret = arg0.test(getStringFromWasm0(arg1, arg2));
+            return ret;
+        };
+        imports.wbg.__wbg_text_7805bea50de2af49 = function() { return handleError(function (arg0) {
+            const ret = arg0.text();
+            return ret;
+        }, arguments) };
         imports.wbg.__wbg_then_44b73946d2fb3e7d = function(arg0, arg1) {
             const ret = arg0.then(arg1);
             return ret;
@@ -687,10 +2712,38 @@ let wasm_bindgen;
             const ret = arg0.then(arg1, arg2);
             return ret;
         };
+        imports.wbg.__wbg_toISOString_b015155a5a6fe219 = function(arg0) {
+            const ret = arg0.toISOString();
+            return ret;
+        };
+        imports.wbg.__wbg_transaction_d6d07c3c9963c49e = function() { return handleError(function (arg0, arg1, arg2) {
+            const ret = arg0.transaction(arg1, __wbindgen_enum_IdbTransactionMode[arg2]);
+            return ret;
+        }, arguments) };
+        imports.wbg.__wbg_transaction_e713aa7b07ccaedd = function(arg0) {
+            const ret = arg0.transaction;
+            return isLikeNone(ret) ? 0 : addToExternrefTable0(ret);
+        };
+        imports.wbg.__wbg_update_acd72607f506872a = function() { return handleError(function (arg0, arg1) {
+            const ret = arg0.update(arg1);
+            return ret;
+        }, arguments) };
+        imports.wbg.__wbg_upper_7789d6a6647853d3 = function() { return handleError(function (arg0) {
+            const ret = arg0.upper;
+            return ret;
+        }, arguments) };
+        imports.wbg.__wbg_value_68c4e9a54bb7fd5e = function() { return handleError(function (arg0) {
+            const ret = arg0.value;
+            return ret;
+        }, arguments) };
         imports.wbg.__wbg_value_cd1ffa7b1ab794f1 = function(arg0) {
             const ret = arg0.value;
             return ret;
         };
+        imports.wbg.__wbindgen_as_number = function(arg0) {
+            const ret = +arg0;
+            return ret;
+        };
         imports.wbg.__wbindgen_bigint_from_i64 = function(arg0) {
             const ret = arg0;
             return ret;
@@ -719,8 +2772,16 @@ let wasm_bindgen;
             const ret = false;
             return ret;
         };
-        imports.wbg.__wbindgen_closure_wrapper311 = function(arg0, arg1, arg2) {
-            const ret = makeMutClosure(arg0, arg1, 71, __wbg_adapter_48);
+        imports.wbg.__wbindgen_closure_wrapper2713 = function(arg0, arg1, arg2) {
+            const ret = makeMutClosure(arg0, arg1, 410, __wbg_adapter_60);
+            return ret;
+        };
+        imports.wbg.__wbindgen_closure_wrapper479 = function(arg0, arg1, arg2) {
+            const ret = makeMutClosure(arg0, arg1, 70, __wbg_adapter_54);
+            return ret;
+        };
+        imports.wbg.__wbindgen_closure_wrapper480 = function(arg0, arg1, arg2) {
+            const ret = makeMutClosure(arg0, arg1, 70, __wbg_adapter_57);
             return ret;
         };
         imports.wbg.__wbindgen_debug_string = function(arg0, arg1) {
@@ -756,11 +2817,19 @@ let wasm_bindgen;
             const ret = typeof(arg0) === 'function';
             return ret;
         };
+        imports.wbg.__wbindgen_is_null = function(arg0) {
+            const ret = arg0 === null;
+            return ret;
+        };
         imports.wbg.__wbindgen_is_object = function(arg0) {
             const val = arg0;
             const ret = typeof(val) === 'object' && val !== null;
             return ret;
         };
+        imports.wbg.__wbindgen_is_string = function(arg0) {
+            const ret = typeof(arg0) === 'string';
+            return ret;
+        };
         imports.wbg.__wbindgen_is_undefined = function(arg0) {
             const ret = arg0 === undefined;
             return ret;
@@ -1,26 +1,135 @@
 /* tslint:disable */
 /* eslint-disable */
 export const memory: WebAssembly.Memory;
+export const call_tool_fanout: (a: number, b: number, c: any, d: any, e: number, f: number, g: number, h: number, i: number) => any;
+export const decode_tool_result: (a: number, b: number) => [number, number, number];
+export const render_tool_result_text: (a: number, b: number) => [number, number];
+export const extract_json_rpc_calls: (a: number, b: number) => [number, number];
+export const append_engram_message: (a: number, b: number) => [number, number, number];
+export const check_tap_template: (a: number, b: number) => [number, number];
+export const delete_conversation: (a: number, b: number) => any;
+export const get_bootrom_sections: () => [number, number];
+export const get_conversation_prune_policy: () => [number, number];
+export const get_tool_catalog_text: () => [number, number];
+export const list_conversations: () => any;
+export const load_engram_messages: (a: number, b: number, c: number, d: number) => [number, number, number];
+export const load_state: (a: number, b: number) => any;
+export const open_conversation_store: () => any;
+export const prune_conversation: (a: number, b: number, c: number, d: number) => [number, number, number];
+export const render_tap_args: (a: number, b: number, c: number, d: number) => [number, number, number, number];
+export const render_tap_template: (a: number, b: number, c: number, d: number) => [number, number, number, number];
+export const reset_bootrom_sections: () => void;
+export const save_state: (a: number, b: number, c: any) => any;
+export const set_bootrom_sections: (a: number, b: number) => [number, number];
+export const set_conversation_prune_policy: (a: number, b: number) => [number, number];
+export const uuid_v7: () => [number, number];
+export const get_vector_config: () => [number, number];
+export const get_vector_index: () => [number, number];
+export const get_vector_sync_status: () => [number, number];
+export const restore_vector_index: (a: number, b: number) => [number, number];
+export const set_embedding_callback: (a: number) => void;
+export const set_vector_config: (a: number, b: number) => [number, number];
+export const set_vector_persistence: (a: number) => void;
+export const sync_vector_index: () => any;
+export const vector_search: (a: number, b: number) => [number, number, number];
+export const check_tool_call_breaker: (a: number, b: number, c: number, d: number, e: number, f: number, g: number, h: number) => [number, number, number, number];
+export const get_breaker_config: () => [number, number];
+export const get_breaker_state: () => [number, number];
+export const reset_breaker: (a: number, b: number) => void;
+export const restore_breaker_state: (a: number, b: number) => [number, number];
+export const set_breaker_config: (a: number, b: number) => [number, number];
+export const set_breaker_config_persistence: (a: number) => void;
+export const set_breaker_persistence: (a: number) => void;
+export const __wbg_agentrunner_free: (a: number, b: number) => void;
+export const abort_agent_run: (a: bigint, b: number, c: number) => [number, number];
+export const add_simulation_fixtures: (a: number, b: number) => [number, number, number];
+export const agentrunner_new: (a: any, b: number, c: number, d: number) => number;
+export const agentrunner_start: (a: number, b: number, c: number) => [bigint, number, number];
+export const call_tool: (a: number, b: number, c: number, d: number, e: any, f: number, g: number) => any;
+export const check_mcp_server: () => any;
+export const clear_finished_agent_runs: () => void;
+export const clear_simulation_fixtures: (a: number, b: number) => void;
+export const clear_simulation_log: () => void;
+export const get_agent_run: (a: bigint) => [number, number, number, number];
+export const get_bootrom: () => [number, number];
+export const get_compiled_info: () => [number, number];
+export const get_metadata: () => [number, number];
+export const get_server_info: () => [number, number, number];
+export const get_server_url: () => [number, number];
+export const get_simulation_config: () => [number, number];
+export const get_simulation_log: () => [number, number];
 export const get_timestamp: () => bigint;
 export const get_uptime: () => bigint;
-export const increment_uptime: () => void;
 export const get_version: () => [number, number];
-export const set_server_url: (a: number, b: number) => void;
-export const get_server_url: () => [number, number];
-export const health_check: () => any;
-export const check_mcp_server: () => any;
 export const handle_message: (a: number, b: number) => any;
-export const get_metadata: () => [number, number];
-export const add_memory_event: (a: number, b: number) => void;
-export const clear_memory_events: () => [number, number];
+export const health_check: () => any;
+export const increment_uptime: () => void;
 export const initialize_mcp_server: (a: number, b: number) => any;
-export const get_server_info: () => [number, number, number];
-export const query_tools: () => any;
+export const list_agent_runs: () => [number, number];
+export const list_simulation_fixtures: () => [number, number];
 export const list_tools: (a: number, b: number) => any;
-export const get_compiled_info: () => [number, number];
-export const call_tool: (a: number, b: number, c: number, d: number, e: any) => any;
+export const pause_agent_run: (a: bigint) => [number, number];
+export const query_tools: () => any;
+export const resume_agent_run: (a: bigint) => [number, number];
 export const set_debug_mode: (a: number) => void;
-export const get_bootrom: () => [number, number];
+export const set_engram_simulation: (a: number, b: number, c: number) => void;
+export const set_server_url: (a: number, b: number) => void;
+export const set_simulation_config: (a: number, b: number) => [number, number];
+export const add_approval_rule: (a: number, b: number) => [bigint, number, number];
+export const add_memory_event: (a: number, b: number, c: number, d: number) => [bigint, number, number];
+export const approve_tool_call: (a: bigint, b: number, c: number) => [number, number];
+export const clear_memory_events: (a: number, b: number) => [number, number];
+export const edit_pending_arguments: (a: bigint, b: number, c: number) => [number, number];
+export const export_memory_events: () => [number, number];
+export const export_tool_catalog: (a: number, b: number) => [number, number, number, number];
+export const get_exported_tool_names: (a: number, b: number) => [number, number, number, number];
+export const get_extracted_call_policy: () => [number, number];
+export const get_server_tool_policy: (a: number, b: number) => [number, number, number];
+export const get_tool_policy: () => [number, number];
+export const import_memory_events: (a: number, b: number) => [number, number];
+export const list_approval_rules: () => [number, number];
+export const list_pending_approvals: () => [number, number];
+export const query_memory_events: (a: number, b: number) => [number, number, number, number];
+export const reject_tool_call: (a: bigint, b: number, c: number, d: number, e: number) => [number, number];
+export const remove_approval_rule: (a: bigint) => number;
+export const remove_memory_event: (a: bigint) => number;
+export const resolve_exported_tool_name: (a: number, b: number, c: number, d: number) => [number, number, number, number];
+export const restore_approval_rules: (a: number, b: number) => [number, number];
+export const restore_exported_tool_names: (a: number, b: number) => [number, number];
+export const set_approval_listener: (a: number) => void;
+export const set_approval_rule_persistence: (a: number) => void;
+export const set_catalog_persistence: (a: number) => void;
+export const set_extracted_call_policy: (a: number, b: number) => [number, number];
+export const set_memory_persistence: (a: number) => void;
+export const set_server_tool_policy: (a: number, b: number, c: number, d: number) => [number, number];
+export const set_tool_policy: (a: number, b: number) => [number, number];
+export const __wbg_tappipeline_free: (a: number, b: number) => void;
+export const flatten_input_schema: (a: number, b: number) => [number, number, number];
+export const get_tool_parameters: (a: number, b: number, c: number, d: number) => [number, number, number];
+export const tappipeline_new: (a: any, b: number, c: number) => number;
+export const tappipeline_run: (a: number, b: number, c: number) => [number, number, number];
+export const get_router_config: () => [number, number];
+export const list_tool_conflicts: () => [number, number];
+export const resolve_tool_route: (a: number, b: number) => [number, number, number];
+export const route_tool_call: (a: number, b: number, c: any, d: number, e: number) => any;
+export const set_router_config: (a: number, b: number) => [number, number];
+export const set_server_routing: (a: number, b: number, c: number, d: number, e: number) => [number, number];
+export const validate_tool_arguments: (a: number, b: number, c: number, d: number, e: any) => [number, number, number];
+export const assemble_context: (a: number, b: number) => [number, number, number, number];
+export const estimate_tokens: (a: number, b: number) => number;
+export const get_context_budget: () => [number, number];
+export const get_search_index_stats: () => [number, number];
+export const index_message: (a: number, b: number) => [number, number, number];
+export const index_messages: (a: number, b: number) => [number, number, number];
+export const list_tool_call_dialects: () => [number, number];
+export const parse_tool_calls: (a: number, b: number) => [number, number];
+export const register_tool_call_dialect: (a: number, b: number, c: any) => void;
+export const remove_engram_from_search_index: (a: number, b: number) => void;
+export const reset_tool_call_dialects: () => void;
+export const search_context: (a: number, b: number) => [number, number, number, number];
+export const set_context_budget: (a: number, b: number) => [number, number];
+export const set_context_tokenizer: (a: number) => void;
+export const unregister_tool_call_dialect: (a: number, b: number) => number;
 export const __wbindgen_malloc: (a: number, b: number) => number;
 export const __wbindgen_realloc: (a: number, b: number, c: number, d: number) => number;
 export const __wbindgen_exn_store: (a: number) => void;
@@ -29,6 +138,8 @@ export const __wbindgen_export_4: WebAssembly.Table;
 export const __wbindgen_export_5: WebAssembly.Table;
 export const __wbindgen_free: (a: number, b: number, c: number) => void;
 export const __externref_table_dealloc: (a: number) => void;
-export const closure70_externref_shim: (a: number, b: number, c: any) => void;
-export const closure92_externref_shim: (a: number, b: number, c: any, d: any) => void;
+export const closure72_externref_shim: (a: number, b: number, c: any) => void;
+export const _dyn_core__ops__function__FnMut_____Output___R_as_wasm_bindgen__closure__WasmClosure___describe__invoke__hed171b9ef44df934: (a: number, b: number) => void;
+export const closure409_externref_shim: (a: number, b: number, c: any) => void;
+export const closure423_externref_shim: (a: number, b: number, c: any, d: any) => void;
 export const __wbindgen_start: () => void;
@@ -11,16 +11,9 @@ let currentTapConfig = {};
 // --- Memory/Imprints Store ---
 let currentImprints = [];
 
-// --- MCP Servers Index ---
-const mcpServersIndex = {};
-
-// --- Tool Call Circuit Breaker ---
-const toolCallHistory = {}; // { engramId: [timestamps] }
-
 const VERSION = '1.0.0';
 const BUILD_TIME = new Date().toISOString();
 
-import { handleOp, initDB, openDB } from './chatStorage.js';
 import { debugLog } from './logger.js';
 import {
     checkWasm,
@@ -131,65 +124,205 @@ mcpHandler.setDebugMode(isDebugMode);
 // --- Engram NAT Table ---
 const engramNAT = new Map(); // engramId -> clientId
 
-// --- UUIDv7 generator (browser-compatible, minimal) ---
-function uuidv7() {
-    // UUIDv7: 48 bits unix timestamp ms, 74 bits random
-    const now = Date.now();
-    const unixTs = now;
-    const tsHex = unixTs.toString(16).padStart(12, '0'); // 48 bits = 12 hex chars
-    // 74 bits random = 19 hex chars (but UUID is 36 chars with dashes)
-    const rand = crypto.getRandomValues(new Uint8Array(10));
-    let randHex = Array.from(rand).map(b => b.toString(16).padStart(2, '0')).join('');
-    randHex = randHex.padEnd(20, '0');
-    // Compose UUIDv7: xxxxxxxx-xxxx-7xxx-yxxx-xxxxxxxxxxxx
-    // Use timestamp for first 12 hex, then version, then random
-    const uuid = [
-        tsHex.slice(0, 8),
-        tsHex.slice(8, 12),
-        '7' + randHex.slice(0, 3),
-        (8 + (rand[3] & 0x3)).toString(16) + randHex.slice(3, 6),
-        randHex.slice(6, 18)
-    ].join('-');
-    return uuid;
+// --- Conversation store ---
+// Conversations and worker state live in IndexedDB behind the WASM store,
+// which assigns message ids (UUIDv7) and indexes messages for search. The
+// prune policy is restored once per instance.
+let prunePolicyInstance = null;
+async function conversationStore() {
+    if (!getWasmInstance()) await initializeWasm();
+    const instance = getWasmInstance();
+    if (typeof instance?.append_engram_message !== 'function') throw new Error('WASM module not initialized');
+    if (prunePolicyInstance !== instance) {
+        prunePolicyInstance = instance;
+        const saved = await instance.load_state('conversation_prune_policy');
+        if (saved) instance.set_conversation_prune_policy(saved);
+    }
+    return instance;
+}
+
+async function loadEngramMessages(engramId) {
+    if (!engramId) return [];
+    const store = await conversationStore();
+    return JSON.parse(await store.load_engram_messages(engramId, null)).messages;
+}
+
+async function loadState(key) {
+    return (await conversationStore()).load_state(key);
+}
+
+async function saveState(key, value) {
+    return (await conversationStore()).save_state(key, value);
 }
 
 // --- DRY helper for engram message persistence ---
 async function persistEngramMessage(msg) {
-    if (!msg.engramId) return;
-    const storedMsg = {
-        ...msg,
-        id: uuidv7(),
-        timestamp: msg.timestamp || Date.now(),
-    };
-    const db = await (await initDB(), openDB());
-    const convStore = db.transaction('conversations', 'readonly').objectStore('conversations');
-    const getReq = convStore.get(storedMsg.engramId);
-    const exists = await new Promise(resolve => {
-        getReq.onsuccess = () => resolve(!!getReq.result);
-        getReq.onerror = () => resolve(false);
+    if (!msg.engramId) return null;
+    const store = await conversationStore();
+    return JSON.parse(await store.append_engram_message(JSON.stringify(msg)));
+}
+
+// Index every stored conversation for search_context; messages persisted
+// later are indexed as they are appended. Runs once per instance.
+let searchIndexInstance = null;
+async function ensureSearchIndex() {
+    if (!wasmInstance || searchIndexInstance === wasmInstance || typeof wasmInstance.index_messages !== 'function') return;
+    searchIndexInstance = wasmInstance;
+    await attachMemoryPersistence();
+    const conversations = JSON.parse(await wasmInstance.list_conversations());
+    for (const meta of conversations) {
+        wasmInstance.index_messages(JSON.stringify(await loadEngramMessages(meta.engramId)));
+    }
+}
+
+
+
+
+// Restore the WASM breaker's config and its rate-limit and loop-detection
+// state, and keep them saved, so a worker restart does not reset them.
+// Runs once per instance.
+let breakerPersistenceInstance = null;
+async function attachBreakerPersistence() {
+    if (!wasmInstance || breakerPersistenceInstance === wasmInstance || typeof wasmInstance.set_breaker_persistence !== 'function') return;
+    breakerPersistenceInstance = wasmInstance;
+    try {
+        const savedConfig = await loadState('tool_call_breaker_config');
+        if (savedConfig) wasmInstance.set_breaker_config(savedConfig);
+        const saved = await loadState('tool_call_breaker');
+        if (saved) wasmInstance.restore_breaker_state(saved);
+    } catch (e) {
+        debugLog({ source: 'ServiceWorker', type: 'log', level: 'ERROR', message: '[SW] Failed to restore breaker state', data: { error: String(e) } });
+    }
+    wasmInstance.set_breaker_config_persistence((configJson) => {
+        saveState('tool_call_breaker_config', configJson).catch(e => {
+            debugLog({ source: 'ServiceWorker', type: 'log', level: 'ERROR', message: '[SW] Failed to save breaker config', data: { error: String(e) } });
+        });
     });
-    if (!exists) {
-        await handleOp('store', storedMsg.engramId, {
-            meta: { created: Date.now(), engramId: storedMsg.engramId },
-            messages: [storedMsg]
+    wasmInstance.set_breaker_persistence((stateJson) => {
+        saveState('tool_call_breaker', stateJson).catch(e => {
+            debugLog({ source: 'ServiceWorker', type: 'log', level: 'ERROR', message: '[SW] Failed to save breaker state', data: { error: String(e) } });
         });
-    } else {
-        await handleOp('append', storedMsg.engramId, { message: storedMsg });
+    });
+}
+
+// Restore the exported catalog's name mapping, so function names in a
+// model's reply resolve after a worker restart, and keep it saved. Runs
+// once per instance.
+let catalogPersistenceInstance = null;
+async function attachCatalogPersistence() {
+    if (!wasmInstance || catalogPersistenceInstance === wasmInstance || typeof wasmInstance.set_catalog_persistence !== 'function') return;
+    catalogPersistenceInstance = wasmInstance;
+    try {
+        const saved = await loadState('exported_tool_names');
+        if (saved) wasmInstance.restore_exported_tool_names(saved);
+    } catch (e) {
+        debugLog({ source: 'ServiceWorker', type: 'log', level: 'ERROR', message: '[SW] Failed to restore exported tool names', data: { error: String(e) } });
+    }
+    wasmInstance.set_catalog_persistence((namesJson) => {
+        saveState('exported_tool_names', namesJson).catch(e => {
+            debugLog({ source: 'ServiceWorker', type: 'log', level: 'ERROR', message: '[SW] Failed to save exported tool names', data: { error: String(e) } });
+        });
+    });
+}
+
+// Restore remembered approval decisions ("always allow tool X on server Y")
+// and keep them saved. Runs once per instance.
+let approvalRulesInstance = null;
+async function attachApprovalRulePersistence() {
+    if (!wasmInstance || approvalRulesInstance === wasmInstance || typeof wasmInstance.set_approval_rule_persistence !== 'function') return;
+    approvalRulesInstance = wasmInstance;
+    try {
+        const saved = await loadState('tool_approval_rules');
+        if (saved) wasmInstance.restore_approval_rules(saved);
+    } catch (e) {
+        debugLog({ source: 'ServiceWorker', type: 'log', level: 'ERROR', message: '[SW] Failed to restore approval rules', data: { error: String(e) } });
     }
+    wasmInstance.set_approval_rule_persistence((rulesJson) => {
+        saveState('tool_approval_rules', rulesJson).catch(e => {
+            debugLog({ source: 'ServiceWorker', type: 'log', level: 'ERROR', message: '[SW] Failed to save approval rules', data: { error: String(e) } });
+        });
+    });
 }
 
+// Announce calls waiting for approval, and their outcomes, to every client.
+// Runs once per instance.
+let approvalListenerInstance = null;
+function attachApprovalListener() {
+    if (!wasmInstance || approvalListenerInstance === wasmInstance || typeof wasmInstance.set_approval_listener !== 'function') return;
+    approvalListenerInstance = wasmInstance;
+    wasmInstance.set_approval_listener((eventJson) => broadcastToClients(JSON.parse(eventJson)));
+}
 
+// Restore the WASM memory store and keep it saved. Runs once per instance.
+let memoryPersistenceInstance = null;
+async function attachMemoryPersistence() {
+    if (!wasmInstance || memoryPersistenceInstance === wasmInstance || typeof wasmInstance.set_memory_persistence !== 'function') return;
+    memoryPersistenceInstance = wasmInstance;
+    try {
+        const saved = await loadState('memory_events');
+        if (saved) wasmInstance.import_memory_events(saved);
+    } catch (e) {
+        debugLog({ source: 'ServiceWorker', type: 'log', level: 'ERROR', message: '[SW] Failed to restore memory events', data: { error: String(e) } });
+    }
+    wasmInstance.set_memory_persistence((storeJson) => {
+        saveState('memory_events', storeJson).catch(e => {
+            debugLog({ source: 'ServiceWorker', type: 'log', level: 'ERROR', message: '[SW] Failed to save memory events', data: { error: String(e) } });
+        });
+    });
+}
 
+// Restore the WASM vector index of memory events and keep it saved. The
+// memory store comes first so vectors of events gone meanwhile are dropped
+// on the next sync. Runs once per instance.
+let vectorPersistenceInstance = null;
+async function attachVectorPersistence() {
+    if (!wasmInstance || vectorPersistenceInstance === wasmInstance || typeof wasmInstance.set_vector_persistence !== 'function') return;
+    vectorPersistenceInstance = wasmInstance;
+    await attachMemoryPersistence();
+    try {
+        const saved = await loadState('vector_index');
+        if (saved) wasmInstance.restore_vector_index(saved);
+    } catch (e) {
+        debugLog({ source: 'ServiceWorker', type: 'log', level: 'ERROR', message: '[SW] Failed to restore vector index', data: { error: String(e) } });
+    }
+    wasmInstance.set_vector_persistence((indexJson) => {
+        saveState('vector_index', indexJson).catch(e => {
+            debugLog({ source: 'ServiceWorker', type: 'log', level: 'ERROR', message: '[SW] Failed to save vector index', data: { error: String(e) } });
+        });
+    });
+}
 
-function shouldBreakCircuit(engramId) {
-    const now = Date.now();
-    if (!engramId) return false;
-    if (!toolCallHistory[engramId]) toolCallHistory[engramId] = [];
-    // Keep only timestamps from the last 10 seconds
-    toolCallHistory[engramId] = toolCallHistory[engramId].filter(ts => now - ts < 10000);
-    if (toolCallHistory[engramId].length >= 3) return true; // max 3 calls per 10s
-    toolCallHistory[engramId].push(now);
-    return false;
+// Append the memory events closest to the message to the imprints, when an
+// embedding provider is configured. `tapConfig.memoryFilter` narrows them by
+// `{ tags, source, since, until, k, min_score }`. memory[0] is the bootrom
+// slot, so a tap without imprints gets none. While events still lack vectors
+// they are embedded in the background and the tap goes without retrieval.
+async function withRetrievedMemories(memory, message, tapConfig) {
+    if (!Array.isArray(memory) || memory.length === 0 || !message?.text || typeof wasmInstance?.vector_search !== 'function') return memory;
+    await attachVectorPersistence();
+    if (!JSON.parse(wasmInstance.get_vector_config()).provider) return memory;
+    const { syncing, pending } = JSON.parse(wasmInstance.get_vector_sync_status());
+    if (syncing || pending > 0) {
+        if (!syncing) {
+            wasmInstance.sync_vector_index().catch(e => {
+                debugLog({ source: 'ServiceWorker', type: 'log', level: 'ERROR', message: '[SW] Vector index sync failed', data: { error: String(e) } });
+            });
+        }
+        debugLog({ source: 'ServiceWorker', type: 'log', level: 'DEBUG', message: '[SW] Skipping memory retrieval until the vector index is synced', data: { pending } });
+        return memory;
+    }
+    try {
+        const { hits } = JSON.parse(await wasmInstance.vector_search(JSON.stringify({
+            ...(tapConfig?.memoryFilter || {}),
+            query: message.text,
+            engram_id: message.engramId || null
+        })));
+        debugLog({ source: 'ServiceWorker', type: 'log', level: 'DEBUG', message: '[SW] Retrieved memories', data: { count: hits.length } });
+        return [...memory, ...hits.map(({ event }) => ({ id: `memory:${event.id}`, role: 'memory', text: event.text, timestamp: event.timestamp }))];
+    } catch (e) {
+        debugLog({ source: 'ServiceWorker', type: 'log', level: 'ERROR', message: '[SW] Memory retrieval failed', data: { error: String(e) } });
+        return memory;
+    }
 }
 
 // Handle messages from clients
@@ -214,11 +347,13 @@ self.addEventListener('message', async (event) => {
     }
 
     wasmInstance = getWasmInstance();
+    attachApprovalListener();
 
     // Handle legacy messages
     switch (message.type) {
         case 'check-wasm':
         case 'check_wasm':
+            await attachMemoryPersistence();
             const wasmState = await checkWasm(message.checkId);
             broadcastWasmStatus(wasmState);
             break;
@@ -346,7 +481,9 @@ self.addEventListener('message', async (event) => {
         case 'add_memory_event':
             if (wasmInstance && message && message.text) {
                 try {
-                    await wasmInstance.add_memory_event(message.text);
+                    await attachMemoryPersistence();
+                    // Optional { tags, source, ttl_ms, expires_at, engram_id }
+                    wasmInstance.add_memory_event(message.text, message.options ? JSON.stringify(message.options) : null);
                     debugLog({ source: 'ServiceWorker', type: 'log', level: 'DEBUG', message: "Memory event added", data: { text: message.text } });
                 } catch (error) {
                     debugLog({ source: 'ServiceWorker', type: 'log', level: 'ERROR', message: "Failed to add memory event", data: { 
@@ -359,7 +496,8 @@ self.addEventListener('message', async (event) => {
         case 'clear_memory_events':
             if (wasmInstance) {
                 try {
-                    await wasmInstance.clear_memory_events();
+                    await attachMemoryPersistence();
+                    wasmInstance.clear_memory_events(message.engramId ?? null);
                     debugLog({ source: 'ServiceWorker', type: 'log', level: 'DEBUG', message: "Memory events cleared" });
                 } catch (error) {
                     debugLog({ source: 'ServiceWorker', type: 'log', level: 'ERROR', message: "Failed to clear memory events", data: { 
@@ -369,6 +507,98 @@ self.addEventListener('message', async (event) => {
                 }
             }
             break;
+        case 'query_memory_events':
+            if (wasmInstance) {
+                try {
+                    await attachMemoryPersistence();
+                    const { events } = JSON.parse(wasmInstance.query_memory_events(JSON.stringify(message.query || {})));
+                    event.source?.postMessage({ type: 'memory_events', events, requestId: message.requestId || null });
+                } catch (error) {
+                    event.source?.postMessage({ type: 'memory_events', error: String(error), requestId: message.requestId || null });
+                }
+            }
+            break;
+        case 'load_engram_messages':
+            // { engramId, options: { since?, until?, limit?, after?, newest_first? } }; pass `next` back as `after`
+            try {
+                const store = await conversationStore();
+                const page = JSON.parse(await store.load_engram_messages(message.engramId, message.options ? JSON.stringify(message.options) : null));
+                event.source?.postMessage({ type: 'engram_messages', ...page, requestId: message.requestId || null });
+            } catch (error) {
+                event.source?.postMessage({ type: 'engram_messages', error: String(error), requestId: message.requestId || null });
+            }
+            break;
+        case 'delete_conversation':
+            try {
+                const removed = await (await conversationStore()).delete_conversation(message.engramId);
+                event.source?.postMessage({ type: 'conversation_deleted', engramId: message.engramId, removed, requestId: message.requestId || null });
+            } catch (error) {
+                event.source?.postMessage({ type: 'conversation_deleted', error: String(error), requestId: message.requestId || null });
+            }
+            break;
+        case 'prune_conversation':
+            // { engramId, policy?: { max_messages?, max_age_ms?, max_bytes? } }; the configured policy when omitted
+            try {
+                const store = await conversationStore();
+                const removed = await store.prune_conversation(message.engramId, message.policy ? JSON.stringify(message.policy) : null);
+                event.source?.postMessage({ type: 'conversation_pruned', engramId: message.engramId, removed, requestId: message.requestId || null });
+            } catch (error) {
+                event.source?.postMessage({ type: 'conversation_pruned', error: String(error), requestId: message.requestId || null });
+            }
+            break;
+        case 'set_conversation_prune_policy':
+            // Applied after every append
+            try {
+                const store = await conversationStore();
+                store.set_conversation_prune_policy(JSON.stringify(message.policy || {}));
+                await saveState('conversation_prune_policy', store.get_conversation_prune_policy());
+                event.source?.postMessage({ type: 'conversation_prune_policy', policy: JSON.parse(store.get_conversation_prune_policy()), requestId: message.requestId || null });
+            } catch (error) {
+                event.source?.postMessage({ type: 'conversation_prune_policy', error: String(error), requestId: message.requestId || null });
+            }
+            break;
+        case 'search_context':
+            // { query, engram_id?, roles?, limit? } over memory and stored messages
+            if (wasmInstance) {
+                try {
+                    await ensureSearchIndex();
+                    const { hits } = JSON.parse(wasmInstance.search_context(JSON.stringify(message.query || {})));
+                    event.source?.postMessage({ type: 'search_results', hits, requestId: message.requestId || null });
+                } catch (error) {
+                    event.source?.postMessage({ type: 'search_results', error: String(error), requestId: message.requestId || null });
+                }
+            }
+            break;
+        case 'set_vector_config':
+            // { provider: { kind: 'js' | 'tool' | 'hashing', ... }, index: { kind: 'flat' | 'hnsw', ... }, top_k?, min_score? }
+            if (wasmInstance) {
+                try {
+                    await attachVectorPersistence();
+                    wasmInstance.set_vector_config(JSON.stringify(message.config || {}));
+                    event.source?.postMessage({ type: 'vector_config', config: JSON.parse(wasmInstance.get_vector_config()), requestId: message.requestId || null });
+                } catch (error) {
+                    event.source?.postMessage({ type: 'vector_config', error: String(error), requestId: message.requestId || null });
+                }
+            }
+            break;
+        case 'vector_search':
+            // { query, k?, min_score?, engram_id?, tags?, source?, since?, until? }
+            if (wasmInstance) {
+                try {
+                    await attachVectorPersistence();
+                    const { hits } = JSON.parse(await wasmInstance.vector_search(JSON.stringify(message.query || {})));
+                    event.source?.postMessage({ type: 'vector_results', hits, requestId: message.requestId || null });
+                } catch (error) {
+                    event.source?.postMessage({ type: 'vector_results', error: String(error), requestId: message.requestId || null });
+                }
+            }
+            break;
+        case 'remove_memory_event':
+            if (wasmInstance) {
+                await attachMemoryPersistence();
+                wasmInstance.remove_memory_event(BigInt(message.id));
+            }
+            break;
         case 'list_tools':
             if (!wasmInstance) {
                 throw new Error('WASM module not initialized');
@@ -383,9 +613,6 @@ self.addEventListener('message', async (event) => {
                 if (parsedResult.error) {
                     throw new Error(`Failed to list tools: ${parsedResult.error.message}`);
                 }
-                // Update mcpServersIndex to match client structure
-                if (!mcpServersIndex[url]) mcpServersIndex[url] = { url };
-                mcpServersIndex[url].tools = parsedResult.result.tools;
                 broadcastToClients({
                     type: 'tools_list',
                     tools: parsedResult.result.tools,
@@ -426,6 +653,225 @@ self.addEventListener('message', async (event) => {
             // Only use message.tapConfig if present, do NOT fall back to currentTapConfig
             await handleToolCall({ source: 'console', tapConfig: message.tapConfig, message, event, memory: currentImprints });
             break;
+        case 'call_tool_fanout':
+            if (!wasmInstance) break;
+            try {
+                const fanout = JSON.parse(await wasmInstance.call_tool_fanout(
+                    message.toolName,
+                    message.args || {},
+                    message.servers || null,
+                    message.strategy || null,
+                    message.timeoutMs ?? null,
+                    message.engramId || null
+                ));
+                event.source?.postMessage({ type: 'tool_fanout_result', fanout, requestId: message.requestId || null });
+                // The merged result goes onto the CBus like a single tool result
+                if (fanout.merged && message.engramId) {
+                    const toolMsg = {
+                        text: extractToolResponseText(fanout.merged.result),
+                        role: 'tool',
+                        timestamp: Date.now(),
+                        engramId: message.engramId
+                    };
+                    sendToEngramClient(message.engramId, { type: 'cbus_message', message: toolMsg });
+                    await persistEngramMessage(toolMsg);
+                }
+            } catch (error) {
+                event.source?.postMessage({ type: 'tool_fanout_result', error: String(error), requestId: message.requestId || null });
+            }
+            break;
+        case 'list_pending_approvals':
+            if (wasmInstance) {
+                event.source.postMessage({
+                    type: 'pending_approvals',
+                    pending: JSON.parse(wasmInstance.list_pending_approvals()).pending
+                });
+            }
+            break;
+        case 'approve_tool_call':
+        case 'reject_tool_call':
+            if (wasmInstance) {
+                try {
+                    // `remember` is 'tool' or 'server' to keep the decision as a rule
+                    await attachApprovalRulePersistence();
+                    if (message.arguments) {
+                        wasmInstance.edit_pending_arguments(BigInt(message.id), JSON.stringify(message.arguments));
+                    }
+                    if (message.type === 'approve_tool_call') {
+                        wasmInstance.approve_tool_call(BigInt(message.id), message.remember ?? null);
+                    } else {
+                        wasmInstance.reject_tool_call(BigInt(message.id), message.reason ?? null, message.remember ?? null);
+                    }
+                } catch (error) {
+                    debugLog({ source: 'ServiceWorker', type: 'log', level: 'ERROR', message: 'Failed to resolve pending tool call', data: { id: message.id, error: String(error) } });
+                }
+            }
+            break;
+        case 'edit_pending_arguments':
+            if (wasmInstance && message.arguments) {
+                try {
+                    wasmInstance.edit_pending_arguments(BigInt(message.id), JSON.stringify(message.arguments));
+                } catch (error) {
+                    debugLog({ source: 'ServiceWorker', type: 'log', level: 'ERROR', message: 'Failed to edit pending tool call', data: { id: message.id, error: String(error) } });
+                }
+            }
+            break;
+        case 'set_extracted_call_policy':
+            if (wasmInstance && message.policy) {
+                try {
+                    wasmInstance.set_extracted_call_policy(JSON.stringify(message.policy));
+                } catch (error) {
+                    debugLog({ source: 'ServiceWorker', type: 'log', level: 'ERROR', message: 'Failed to set extracted call policy', data: { error: String(error) } });
+                }
+            }
+            break;
+        case 'set_simulation_config':
+        case 'set_engram_simulation':
+        case 'add_simulation_fixtures':
+            // Dry runs: calls are validated and logged, then answered from
+            // fixtures or schema examples instead of the server
+            if (wasmInstance) {
+                try {
+                    if (message.type === 'set_simulation_config') {
+                        wasmInstance.set_simulation_config(JSON.stringify(message.config || {}));
+                    } else if (message.type === 'set_engram_simulation') {
+                        wasmInstance.set_engram_simulation(message.engramId, !!message.enabled);
+                    } else {
+                        wasmInstance.add_simulation_fixtures(JSON.stringify(message.fixtures || []));
+                    }
+                    event.source?.postMessage({ type: 'simulation_config', config: JSON.parse(wasmInstance.get_simulation_config()) });
+                } catch (error) {
+                    debugLog({ source: 'ServiceWorker', type: 'log', level: 'ERROR', message: 'Failed to update simulation mode', data: { error: String(error) } });
+                }
+            }
+            break;
+        case 'get_simulation_log':
+            if (wasmInstance) {
+                event.source?.postMessage({ type: 'simulation_log', calls: JSON.parse(wasmInstance.get_simulation_log()).calls });
+                if (message.clear) wasmInstance.clear_simulation_log();
+            }
+            break;
+        case 'list_approval_rules':
+        case 'add_approval_rule':
+        case 'remove_approval_rule':
+            if (wasmInstance) {
+                try {
+                    await attachApprovalRulePersistence();
+                    if (message.type === 'add_approval_rule') {
+                        wasmInstance.add_approval_rule(JSON.stringify(message.rule));
+                    } else if (message.type === 'remove_approval_rule') {
+                        wasmInstance.remove_approval_rule(BigInt(message.id));
+                    }
+                    event.source?.postMessage({
+                        type: 'approval_rules',
+                        rules: JSON.parse(wasmInstance.list_approval_rules()).rules
+                    });
+                } catch (error) {
+                    debugLog({ source: 'ServiceWorker', type: 'log', level: 'ERROR', message: 'Failed to update approval rules', data: { error: String(error) } });
+                }
+            }
+            break;
+        case 'set_server_tool_policy':
+            if (wasmInstance && message.url && message.policy) {
+                try {
+                    wasmInstance.set_server_tool_policy(message.url, JSON.stringify(message.policy));
+                } catch (error) {
+                    debugLog({ source: 'ServiceWorker', type: 'log', level: 'ERROR', message: 'Failed to set server tool policy', data: { url: message.url, error: String(error) } });
+                }
+            }
+            break;
+        case 'set_tool_policy':
+            if (wasmInstance && message.policy) {
+                try {
+                    wasmInstance.set_tool_policy(JSON.stringify(message.policy));
+                } catch (error) {
+                    debugLog({ source: 'ServiceWorker', type: 'log', level: 'ERROR', message: 'Failed to set tool policy', data: { error: String(error) } });
+                }
+            }
+            break;
+        case 'set_context_budget':
+            if (wasmInstance && message.budget) {
+                try {
+                    wasmInstance.set_context_budget(JSON.stringify(message.budget));
+                } catch (error) {
+                    debugLog({ source: 'ServiceWorker', type: 'log', level: 'ERROR', message: 'Failed to set context budget', data: { error: String(error) } });
+                }
+            }
+            break;
+        case 'agent_start': {
+            // message.request: { engram_id?, model?, budget?, prompt? }; the
+            // model defaults to the configured CBus tap
+            if (typeof wasmInstance?.AgentRunner !== 'function') {
+                event.source?.postMessage({ type: 'agent_error', error: 'WASM module not loaded' });
+                break;
+            }
+            try {
+                await attachBreakerPersistence();
+                await attachApprovalRulePersistence();
+                await attachCatalogPersistence();
+                const runner = new wasmInstance.AgentRunner(
+                    (eventJson) => routePipelineEvent(eventJson, null),
+                    loadEngramMessagesJson,
+                    async (messageJson) => persistEngramMessage(JSON.parse(messageJson)),
+                    null
+                );
+                const request = {
+                    model: { kind: 'tap', tap_config: { ...(currentTapConfig || {}), args: currentTapConfig?.args || {} } },
+                    memory: currentImprints,
+                    ...(message.request || {})
+                };
+                const runId = runner.start(JSON.stringify(request));
+                event.source?.postMessage({ type: 'agent_started', runId: Number(runId), engramId: request.engram_id || null });
+            } catch (error) {
+                event.source?.postMessage({ type: 'agent_error', error: String(error) });
+            }
+            break;
+        }
+        case 'agent_pause':
+        case 'agent_resume':
+        case 'agent_abort':
+            try {
+                if (message.type === 'agent_pause') wasmInstance.pause_agent_run(BigInt(message.runId));
+                if (message.type === 'agent_resume') wasmInstance.resume_agent_run(BigInt(message.runId));
+                if (message.type === 'agent_abort') wasmInstance.abort_agent_run(BigInt(message.runId), message.reason || null);
+            } catch (error) {
+                event.source?.postMessage({ type: 'agent_error', runId: message.runId, error: String(error) });
+            }
+            break;
+        case 'agent_get':
+            try {
+                const run = message.runId !== undefined
+                    ? JSON.parse(wasmInstance.get_agent_run(BigInt(message.runId)))
+                    : JSON.parse(wasmInstance.list_agent_runs());
+                event.source?.postMessage({ type: 'agent_runs', runId: message.runId ?? null, run });
+            } catch (error) {
+                event.source?.postMessage({ type: 'agent_error', runId: message.runId, error: String(error) });
+            }
+            break;
+        case 'set_breaker_config':
+            if (wasmInstance && message.config) {
+                try {
+                    await attachBreakerPersistence();
+                    wasmInstance.set_breaker_config(JSON.stringify(message.config));
+                } catch (error) {
+                    debugLog({ source: 'ServiceWorker', type: 'log', level: 'ERROR', message: 'Failed to set breaker config', data: { error: String(error) } });
+                }
+            }
+            break;
+        case 'export_tool_catalog':
+            if (!wasmInstance) {
+                event.source.postMessage({ type: 'tool_catalog', format: message.format, error: 'WASM module not loaded' });
+                break;
+            }
+            try {
+                await attachCatalogPersistence();
+                const catalog = JSON.parse(wasmInstance.export_tool_catalog(message.format || 'openai'));
+                const names = JSON.parse(wasmInstance.get_exported_tool_names(message.format || 'openai'));
+                event.source.postMessage({ type: 'tool_catalog', format: message.format || 'openai', catalog, names });
+            } catch (error) {
+                event.source.postMessage({ type: 'tool_catalog', format: message.format, error: String(error) });
+            }
+            break;
         case 'get_bootrom':
             if (!wasmInstance) {
                 event.source.postMessage({
@@ -471,7 +917,7 @@ self.addEventListener('message', async (event) => {
                     const tapConfig = currentTapConfig || {};
                     if (tapConfig.serverUrl && tapConfig.toolName && (tapConfig.connectedStringArg || tapConfig.connectedArrayArg)) {
                         // Load full engram history
-                        const { messages: engramMessages = [] } = await handleOp('load', msg.engramId, null) || {};
+                        const engramMessages = await loadEngramMessages(msg.engramId);
                         await handleToolCall({ source: 'tap', tapConfig, message: msg, engramMessages, memory: currentImprints });
                     }
                 } catch (err) {
@@ -490,7 +936,7 @@ self.addEventListener('message', async (event) => {
                     // (Optional: you may want to pass engramId explicitly from the client)
                 }
                 if (engramId) {
-                    const { messages = [] } = await handleOp('load', engramId, null) || {};
+                    const messages = await loadEngramMessages(engramId);
                     event.source.postMessage({
                         type: 'cbus_queue',
                         queue: messages
@@ -515,18 +961,17 @@ self.addEventListener('message', async (event) => {
             }
             break;
         case 'init_mcp_servers_index':
-            if (message.servers && typeof message.servers === 'object') {
-                Object.assign(mcpServersIndex, message.servers);
-                debugLog({ source: 'ServiceWorker', type: 'log', level: 'DEBUG', message: '[SW] Initialized mcpServersIndex from client', data: { keys: Object.keys(mcpServersIndex) } });
-            }
+            // Servers and their tools are registered in WASM; nothing to keep here
             break;
-        case 'extracted_tool_call': {
-            // message.toolCall (the JSON-RPC object), message.engramId, message.tapConfig
-            const toolCall = message.toolCall;
-            const engramId = message.engramId || null;
-            await maybeCallExtractedTool(toolCall, engramId);
+        case 'extracted_tool_call':
+            // message.toolCall (the JSON-RPC object), message.engramId
+            if (!wasmInstance) break;
+            try {
+                await runTapPipeline({ source: 'extracted', message: { engramId: message.engramId || null }, event: null, call: message.toolCall });
+            } catch (err) {
+                debugLog({ source: 'ServiceWorker', type: 'log', level: 'ERROR', message: '[SW] Error dispatching extracted tool call', data: { error: String(err), toolCall: message.toolCall } });
+            }
             break;
-        }
         default:
             console.warn('Unknown message type:', message.type);
     }
@@ -550,8 +995,8 @@ function sendToEngramClient(engramId, message) {
 // Initialize on install
 self.addEventListener('install', event => {
     debugLog({ source: 'ServiceWorker', type: 'log', level: 'DEBUG', message: "Service worker installing..." });
-    event.waitUntil(initializeWasm());
-    event.waitUntil(initDB());
+    // The conversation store opens (and migrates) once WASM is up
+    event.waitUntil(initializeWasm().then(() => conversationStore()).then(store => store.open_conversation_store()));
 });
 
 // Handle activation
@@ -647,113 +1092,61 @@ function broadcastToClients(message) {
 //     );
 // });
 
-// Helper to extract text from tool response
+// Helper to extract text from tool response (decoding lives in WASM)
 function extractToolResponseText(parsedResult) {
-    if (parsedResult && parsedResult.result && Array.isArray(parsedResult.result.content)) {
-        return parsedResult.result.content.map(c => c.text || '').join('\n');
-    } else if (parsedResult && Array.isArray(parsedResult.content)) {
-        return parsedResult.content.map(c => c.text || '').join('\n');
-    } else if (Array.isArray(parsedResult)) {
-        return parsedResult.map(c => c.text || '').join('\n');
-    } else if (parsedResult && parsedResult.text) {
-        return parsedResult.text;
-    }
-    return '[No content]';
+    const toolResult = parsedResult && parsedResult.result ? parsedResult.result : parsedResult;
+    return wasmInstance.render_tool_result_text(JSON.stringify(toolResult ?? null));
 }
 
-// --- JSON-RPC Extraction Helper ---
-/**
- * Extracts all JSON-RPC objects from code blocks in a text blob (handles escaped quotes).
- * Returns an array of parsed JSON objects.
- */
-function extractJsonRpcCalls(text) {
-    const results = [];
-    if (!text || typeof text !== 'string') return results;
-    // Regex to match ```json ... ``` or ``` ... ```
-    const codeBlockRegex = /```(?:json)?\s*([\s\S]*?)```/gi;
-    let match;
-    while ((match = codeBlockRegex.exec(text)) !== null) {
-        let code = match[1].trim();
-        // Try to unescape if needed (handles double-escaped quotes)
-        try {
-            // Try as-is
-            let obj = JSON.parse(code);
-            if (obj && obj.jsonrpc === '2.0' && typeof obj.method === 'string') {
-                results.push(obj);
-                continue;
-            }
-        } catch (e) {}
-        try {
-            // Try unescaping quotes (for escaped JSON in markdown)
-            let unescaped = code.replace(/\\"/g, '"');
-            let obj = JSON.parse(unescaped);
-            if (obj && obj.jsonrpc === '2.0' && typeof obj.method === 'string') {
-                results.push(obj);
-            }
-        } catch (e) {}
+// --- Tap Pipeline (WASM) ---
+// Post an event emitted by the WASM tap pipeline or agent runner
+function routePipelineEvent(eventJson, event) {
+    const { target, engram_id, message: out } = JSON.parse(eventJson);
+    if (target === 'engram') {
+        sendToEngramClient(engram_id, out);
+    } else if (target === 'reply' && event?.source) {
+        event.source.postMessage(out);
+    } else {
+        broadcastToClients(out);
     }
-    return results;
 }
 
-// --- Tool Call Dispatch Helper ---
-async function maybeCallExtractedTool(toolCall, engramId) {
-    // Circuit breaker: prevent rapid-fire loops
-    if (shouldBreakCircuit(engramId)) {
-        debugLog({ source: 'ServiceWorker', type: 'log', level: 'WARN', message: '[SW] Circuit breaker: too many tool calls, skipping', data: { engramId } });
-        broadcastToClients({
-            type: 'tool_result',
-            error: 'Circuit breaker: too many tool calls in a short period',
-            engramId,
-            source: 'extracted'
-        });
-        return;
-    }
-    if (toolCall && toolCall.method && toolCall.jsonrpc === '2.0') {
-        // Find the tool and server
-        const found = findToolAndServerByMethod(toolCall.method);
-        if (!found) {
-            debugLog({ source: 'ServiceWorker', type: 'log', level: 'ERROR', message: '[SW] Tool not found for extracted tool call', data: { method: toolCall.method } });
-            broadcastToClients({
-                type: 'tool_result',
-                error: `Tool not found: ${toolCall.method}`,
-                engramId,
-                source: 'extracted'
-            });
-            return;
-        }
-        const { serverUrl, tool } = found;
-        // Extract args from toolCall.params
-        const args = toolCall.params || {};
-        const tapConfig = { ...buildTapConfigForTool(serverUrl, tool), args };
+async function loadEngramMessagesJson(engramId) {
+    return JSON.stringify(await loadEngramMessages(engramId));
+}
 
-        try {
-            debugLog({ source: 'ServiceWorker', type: 'log', level: 'DEBUG', message: '[SW] About to execute tool call', data: { toolCall, tapConfig } });
-            await handleToolCall({
-                source: 'extracted',
-                tapConfig,
-                message: { ...toolCall, engramId },
-                event: null,
-                engramMessages: null,
-                memory: null
-            });
-            debugLog({ source: 'ServiceWorker', type: 'log', level: 'DEBUG', message: '[SW] Tool call executed', data: { toolCall, tapConfig } });
-        } catch (err) {
-            debugLog({ source: 'ServiceWorker', type: 'log', level: 'ERROR', message: '[SW] Error dispatching extracted tool call', data: { error: err.message, toolCall } });
-            broadcastToClients({
-                type: 'tool_result',
-                error: err.message,
-                engramId,
-                source: 'extracted'
-            });
-        }
-    } else {
-        debugLog({ source: 'ServiceWorker', type: 'log', level: 'ERROR', message: '[SW] No tool call found in extracted_tool_call', data: { toolCall } });
+// Runs a tap, or with `call` an extracted JSON-RPC call, end to end in WASM:
+// context, templates, the tool call, persistence and dispatch of calls found
+// in the output. This side only routes the events it emits.
+async function runTapPipeline({ source, tapConfig, message, event, engramMessages, memory, call }) {
+    await attachBreakerPersistence();
+    await attachApprovalRulePersistence();
+    await attachCatalogPersistence();
+    const pipeline = new wasmInstance.TapPipeline(
+        (eventJson) => routePipelineEvent(eventJson, event),
+        loadEngramMessagesJson,
+        async (messageJson) => persistEngramMessage(JSON.parse(messageJson))
+    );
+    try {
+        const summary = JSON.parse(await pipeline.run(JSON.stringify({
+            source,
+            tap_config: { ...(tapConfig || {}), args: tapConfig?.args || {} },
+            engram_id: message?.engramId || null,
+            request_id: message?.requestId || null,
+            reply: !!event?.source,
+            messages: engramMessages || null,
+            memory: Array.isArray(memory) ? memory : [],
+            call: call || null
+        })));
+        debugLog({ source: 'ServiceWorker', type: 'log', level: 'DEBUG', message: '[SW] Tap pipeline finished', data: summary });
+    } finally {
+        pipeline.free();
     }
 }
 
 // --- Unified Tool Call Handler ---
 /**
- * Handles all tool calls, routing results to the correct output.
+ * Handles all tool calls; the WASM tap pipeline runs them and routes results.
  * @param {Object} opts - Options for the tool call.
  * @param {'tap'|'console'} opts.source - Source of the tool call.
  * @param {Object} opts.tapConfig - Tap config (if any).
@@ -763,147 +1156,6 @@ async function maybeCallExtractedTool(toolCall, engramId) {
  * @param {Array} [opts.memory] - Memory/imprints (if any).
  */
 async function handleToolCall({ source, tapConfig, message, event, engramMessages, memory }) {
-    // Use only tapConfig for all tool call parameters
-    const toolArgs = { ...(tapConfig.args || {}) };
-    const connectedStringArg = tapConfig.connectedStringArg;
-    const connectedArrayArg = tapConfig.connectedArrayArg;
-    if ((connectedStringArg || connectedArrayArg) && message.engramId) {
-        if (!engramMessages) {
-            const loaded = await handleOp('load', message.engramId, null) || {};
-            engramMessages = loaded.messages || [];
-        }
-
-        // Hardened memory injection
-        if (Array.isArray(memory) && memory.length > 0) {
-            // Insert all imprints except the first (bootrom) as memory messages
-            for (const imprint of memory.slice(1)) {
-                if (imprint && typeof imprint.text === 'string' && imprint.text.trim()) {
-                    engramMessages.unshift({ text: imprint.text, role: 'memory', timestamp: Date.now() });
-                }
-            }
-
-            // insert a json encoded servers list
-            engramMessages.unshift({ text: JSON.stringify(mcpServersIndex), role: 'memory', timestamp: Date.now() });
-
-
-            // Insert bootrom if it exists and has text
-            const bootrom = memory[0];
-            if (bootrom && typeof bootrom.text === 'string' && bootrom.text.trim()) {
-                engramMessages.unshift({ text: bootrom.text, role: 'memory', timestamp: Date.now() });
-            }
-        }
-
-        if (engramMessages.length === 1) {
-            if (connectedStringArg) toolArgs[connectedStringArg] = engramMessages[0].text;
-            if (connectedArrayArg) toolArgs[connectedArrayArg] = [];
-        } else if (engramMessages.length > 1) {
-            if (connectedStringArg) {
-                let template = toolArgs[connectedStringArg];
-                const latestMsg = engramMessages[engramMessages.length - 1].text;
-                if (typeof template === 'string' && template.includes('{{cbus_message}}')) {
-                    toolArgs[connectedStringArg] = template.replace(/{{cbus_message}}/g, latestMsg);
-                } else if (typeof template === 'string' && template.length > 0) {
-                    toolArgs[connectedStringArg] = template;
-                } else {
-                    toolArgs[connectedStringArg] = latestMsg;
-                }
-            }
-            if (connectedArrayArg) toolArgs[connectedArrayArg] = engramMessages.slice(0, -1).map(msg => msg.text);
-        }
-    }
-    debugLog({ source: 'ServiceWorker', type: 'log', level: 'DEBUG', message: '[handleToolCall] Calling tool', data: { tapConfig, toolArgs, requestId: message.requestId, source } });
-    let result;
-    try {
-        result = await wasmInstance.call_tool(
-            tapConfig.serverUrl,
-            tapConfig.toolName,
-            toolArgs
-        );
-    } catch (err) {
-        const errorMsg = {
-            type: 'tool_result',
-            error: err.message || String(err),
-            source,
-            engramId: message.engramId || null,
-            requestId: message.requestId || null
-        };
-        if (message.engramId && message.requestId) {
-            sendToEngramClient(message.engramId, errorMsg);
-        } else if (event?.source) {
-            event.source.postMessage(errorMsg);
-        } else {
-            broadcastToClients(errorMsg);
-        }
-        return;
-    }
-    let parsedResult;
-    try {
-        parsedResult = typeof result === 'string' ? JSON.parse(result) : result;
-    } catch (e) {
-        parsedResult = { text: '[Tool returned invalid JSON]' };
-    }
-    let toolText = extractToolResponseText(parsedResult);
-    // For tap/auto, also create a cbus_message and persist
-    if (source === 'tap' || source === 'extracted') {
-        const toolMsg = {
-            text: toolText,
-            role: 'tool',
-            timestamp: Date.now(),
-            engramId: message.engramId || null
-        };
-        // Only send cbus_message to the correct client/engram
-        if (message.engramId && message.requestId) {
-            sendToEngramClient(message.engramId, { type: 'cbus_message', message: toolMsg });
-        } else if (event?.source) {
-            event.source.postMessage({ type: 'cbus_message', message: toolMsg });
-        } else {
-            broadcastToClients({ type: 'cbus_message', message: toolMsg });
-        }
-        await persistEngramMessage(toolMsg);
-    }
-    // Route tool_result strictly
-    const resultMsg = {
-        type: 'tool_result',
-        result: parsedResult,
-        source,
-        engramId: message.engramId || null,
-        requestId: message.requestId || null
-    };
-    if (message.engramId && message.requestId) {
-        sendToEngramClient(message.engramId, resultMsg);
-    } else if (event?.source) {
-        event.source.postMessage(resultMsg);
-    } else {
-        broadcastToClients(resultMsg);
-    }
-
-    // --- Extract and dispatch tool calls from tool output ---
-    let extractedCalls = extractJsonRpcCalls(toolText);
-    if (Array.isArray(extractedCalls) && extractedCalls.length > 0) {
-        for (const call of extractedCalls) {
-            await maybeCallExtractedTool(call, message.engramId || null);
-        }
-    }
-}
-
-// --- Tool/Server Lookup Helper ---
-function findToolAndServerByMethod(method) {
-    for (const [url, server] of Object.entries(mcpServersIndex)) {
-        if (server.tools && Array.isArray(server.tools)) {
-            const tool = server.tools.find(t => t.name === method);
-            if (tool) {
-                return { serverUrl: url, tool };
-            }
-        }
-    }
-    return null;
-}
-
-// --- TapConfig Builder ---
-function buildTapConfigForTool(serverUrl, tool) {
-    return {
-        serverUrl,
-        toolName: tool.name,
-        // Optionally: add connectedStringArg, connectedArrayArg, etc.
-    };
+    if (source === 'tap') memory = await withRetrievedMemories(memory, message, tapConfig);
+    return runTapPipeline({ source, tapConfig, message, event, engramMessages, memory });
 }
@@ -0,0 +1,669 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{self, json};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::rc::Rc;
+use std::sync::{LazyLock, Mutex};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+
+use crate::breaker::{check_call, BreakerTrip};
+use crate::context::ContextItem;
+use crate::dialects::parse_tool_calls_in;
+use crate::pipeline::{
+    call_tap_tool, connected_arguments, connected_messages, context_items, outcome_text, protocol_error_text,
+    remember_result, template_error_text, EventTarget, Hooks, PipelineHooks, TapConfig, TapEvent,
+};
+use crate::policy::{await_extracted_call, gate_extracted_call, reject_pending, ExtractedGate, PolicyBlock};
+use crate::router::{resolve_route, route_call, tool_call_target, with_tool_arguments};
+use crate::simulate::is_simulated;
+use crate::tool_call::ToolCallOutcome;
+use crate::tool_call::delay;
+use crate::{error, info};
+
+/// What produces the model's turns.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub(crate) enum ModelProvider {
+    /// A tool that answers as the model, wired like a CBus tap: its
+    /// connected arguments receive the conversation.
+    Tap { tap_config: Box<TapConfig> },
+    /// The runner's `model` hook.
+    Js,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub(crate) struct AgentBudget {
+    /// Model turns and tool calls together.
+    pub max_steps: usize,
+    /// Wall-clock limit, not counting time spent paused.
+    pub max_duration_ms: f64,
+    pub max_tool_calls: Option<usize>,
+    /// Calls allowed per tool over the whole run.
+    pub tool_budgets: HashMap<String, usize>,
+}
+
+impl Default for AgentBudget {
+    fn default() -> Self {
+        AgentBudget { max_steps: 20, max_duration_ms: 300_000.0, max_tool_calls: None, tool_budgets: HashMap::new() }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct AgentRequest {
+    #[serde(default)]
+    pub engram_id: Option<String>,
+    pub model: ModelProvider,
+    #[serde(default)]
+    pub budget: AgentBudget,
+    /// A user message to start from; it is added to the engram.
+    #[serde(default)]
+    pub prompt: Option<String>,
+    /// Conversation so far, oldest first. Loaded from the engram when omitted.
+    #[serde(default)]
+    pub messages: Option<Vec<serde_json::Value>>,
+    /// Imprints, bootrom first.
+    #[serde(default)]
+    pub memory: Vec<serde_json::Value>,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum AgentStatus {
+    Running,
+    Paused,
+    Finished,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub(crate) enum StopReason {
+    /// The model answered without calling a tool.
+    Completed,
+    MaxSteps { steps: usize },
+    Timeout { elapsed_ms: f64 },
+    MaxToolCalls { calls: usize },
+    ToolBudget { tool: String, calls: usize },
+    Breaker { trip: BreakerTrip },
+    Aborted { message: Option<String> },
+    ModelError { message: String },
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub(crate) enum StepDetail {
+    Model {
+        reply: String,
+        /// Tools the reply called, in order.
+        tool_calls: Vec<String>,
+    },
+    Tool {
+        tool: String,
+        server: Option<String>,
+        arguments: serde_json::Value,
+        outcome: Option<serde_json::Value>,
+        /// What the model is shown: the result text or the error.
+        text: String,
+        failed: bool,
+    },
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub(crate) struct AgentStep {
+    pub index: usize,
+    pub started_at: f64,
+    pub elapsed_ms: f64,
+    #[serde(flatten)]
+    pub detail: StepDetail,
+}
+
+/// A run and everything it recorded.
+#[derive(Debug, Serialize, Clone)]
+pub(crate) struct AgentRun {
+    pub id: u64,
+    pub engram_id: Option<String>,
+    pub status: AgentStatus,
+    pub started_at: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<f64>,
+    pub paused_ms: f64,
+    pub budget: AgentBudget,
+    pub steps: Vec<AgentStep>,
+    /// Messages the run added, oldest first.
+    pub transcript: Vec<serde_json::Value>,
+    /// Calls made per tool.
+    pub tool_calls: BTreeMap<String, usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_reason: Option<StopReason>,
+    #[serde(skip)]
+    paused_at: Option<f64>,
+    /// Set by `abort_agent_run`; honoured at the next checkpoint.
+    #[serde(skip)]
+    abort: Option<Option<String>>,
+}
+
+impl AgentRun {
+    /// Running time so far, excluding pauses.
+    fn active_ms(&self, now: f64) -> f64 {
+        let paused = self.paused_ms + self.paused_at.map(|at| now - at).unwrap_or(0.0);
+        (self.finished_at.unwrap_or(now) - self.started_at - paused).max(0.0)
+    }
+
+    /// Reject the approvals the run's calls still wait on, so nothing it
+    /// started runs once it stops.
+    fn reject_pending_approvals(&self, reason: &str) {
+        reject_pending(
+            |item| {
+                item.engram_id == self.engram_id
+                    && item.created_at as f64 >= self.started_at.floor()
+                    && self.tool_calls.contains_key(&item.tool_name)
+            },
+            reason,
+        );
+    }
+
+    fn summary(&self) -> serde_json::Value {
+        json!({
+            "id": self.id,
+            "engram_id": self.engram_id,
+            "status": self.status,
+            "started_at": self.started_at,
+            "steps": self.steps.len(),
+            "stop_reason": self.stop_reason
+        })
+    }
+}
+
+struct AgentRegistry {
+    next_id: u64,
+    runs: BTreeMap<u64, AgentRun>,
+}
+
+static AGENT_RUNS: LazyLock<Mutex<AgentRegistry>> =
+    LazyLock::new(|| Mutex::new(AgentRegistry { next_id: 1, runs: BTreeMap::new() }));
+
+thread_local! {
+    // Resolvers that wake paused runs; JS handles are not `Send`.
+    static RESUME_WAKERS: RefCell<HashMap<u64, js_sys::Function>> = RefCell::new(HashMap::new());
+}
+
+fn wake(id: u64) {
+    if let Some(resolve) = RESUME_WAKERS.with(|wakers| wakers.borrow_mut().remove(&id)) {
+        let _ = resolve.call0(&JsValue::NULL);
+    }
+}
+
+fn with_run<T>(id: u64, f: impl FnOnce(&mut AgentRun) -> T) -> Option<T> {
+    AGENT_RUNS.lock().unwrap().runs.get_mut(&id).map(f)
+}
+
+/// Run `future` for at most `ms`. A future that loses keeps running in the
+/// background and its output is discarded; a losing tool call that is still
+/// waiting on approval is rejected when the run stops.
+async fn within<T: 'static>(ms: f64, future: impl Future<Output = T> + 'static) -> Option<T> {
+    let slot: Rc<RefCell<Option<T>>> = Rc::new(RefCell::new(None));
+    let writer = slot.clone();
+    let task = wasm_bindgen_futures::future_to_promise(async move {
+        *writer.borrow_mut() = Some(future.await);
+        Ok(JsValue::TRUE)
+    });
+    let timer = delay(ms.clamp(0.0, u32::MAX as f64) as u32);
+    let _ = JsFuture::from(js_sys::Promise::race(&js_sys::Array::of2(&task, &timer))).await;
+    let output = slot.borrow_mut().take();
+    output
+}
+
+struct Agent {
+    id: u64,
+    engram_id: Option<String>,
+    model: ModelProvider,
+    budget: AgentBudget,
+    memory: Vec<serde_json::Value>,
+    /// The conversation the model sees, oldest first.
+    messages: Vec<ContextItem>,
+    hooks: Rc<Hooks>,
+    model_hook: Option<js_sys::Function>,
+    events: usize,
+}
+
+impl Agent {
+    fn emit(&mut self, message: serde_json::Value) {
+        let target = if self.engram_id.is_some() { EventTarget::Engram } else { EventTarget::Broadcast };
+        let event = TapEvent { seq: self.events, target, engram_id: self.engram_id.clone(), message };
+        self.events += 1;
+        self.hooks.emit(&event);
+    }
+
+    fn emit_status(&mut self) {
+        if let Some(summary) = with_run(self.id, |run| run.summary()) {
+            self.emit(json!({ "type": "agent_run", "run": summary }));
+        }
+    }
+
+    /// Add a message to the conversation, the CBus and the engram.
+    async fn post(&mut self, role: &str, text: &str) {
+        let message = json!({
+            "text": text,
+            "role": role,
+            "timestamp": js_sys::Date::now() as u64,
+            "engramId": self.engram_id,
+            "agentRunId": self.id
+        });
+        self.messages.push(ContextItem {
+            id: None,
+            role: Some(role.to_string()),
+            text: text.to_string(),
+            timestamp: message.get("timestamp").cloned(),
+        });
+        with_run(self.id, |run| run.transcript.push(message.clone()));
+        self.emit(json!({ "type": "cbus_message", "message": message }));
+        if self.engram_id.is_some() {
+            self.hooks.persist(&message).await;
+        }
+    }
+
+    fn record(&mut self, started_at: f64, detail: StepDetail) {
+        let step = with_run(self.id, |run| {
+            let step = AgentStep {
+                index: run.steps.len(),
+                started_at,
+                elapsed_ms: js_sys::Date::now() - started_at,
+                detail,
+            };
+            run.steps.push(step.clone());
+            step
+        });
+        if let Some(step) = step {
+            self.emit(json!({ "type": "agent_step", "run_id": self.id, "step": step }));
+        }
+    }
+
+    /// Time left on the wall clock.
+    fn remaining_ms(&self) -> f64 {
+        let active = with_run(self.id, |run| run.active_ms(js_sys::Date::now())).unwrap_or(0.0);
+        self.budget.max_duration_ms - active
+    }
+
+    /// Wait out a pause, then report an abort or an exhausted budget.
+    async fn checkpoint(&mut self) -> Option<StopReason> {
+        loop {
+            // Checking and registering the waker happen without yielding, so
+            // a resume cannot slip in between
+            let pause = {
+                let state = with_run(self.id, |run| (run.abort.clone(), run.status))?;
+                match state {
+                    (Some(message), _) => return Some(StopReason::Aborted { message }),
+                    (None, AgentStatus::Paused) => {
+                        let id = self.id;
+                        Some(js_sys::Promise::new(&mut |resolve, _| {
+                            RESUME_WAKERS.with(|wakers| wakers.borrow_mut().insert(id, resolve));
+                        }))
+                    }
+                    _ => None,
+                }
+            };
+            match pause {
+                Some(promise) => {
+                    self.emit_status();
+                    let _ = JsFuture::from(promise).await;
+                    self.emit_status();
+                }
+                None => break,
+            }
+        }
+        let steps = with_run(self.id, |run| run.steps.len()).unwrap_or(0);
+        if steps >= self.budget.max_steps {
+            return Some(StopReason::MaxSteps { steps });
+        }
+        if self.remaining_ms() <= 0.0 {
+            return Some(self.timeout());
+        }
+        None
+    }
+
+    fn timeout(&self) -> StopReason {
+        let elapsed_ms = with_run(self.id, |run| run.active_ms(js_sys::Date::now())).unwrap_or(0.0);
+        StopReason::Timeout { elapsed_ms }
+    }
+
+    /// Ask the model for its next turn.
+    async fn model_turn(&self) -> Option<Result<String, String>> {
+        let remaining = self.remaining_ms();
+        match self.model.clone() {
+            ModelProvider::Tap { tap_config } => {
+                let messages = connected_messages(&tap_config, self.messages.clone(), &self.memory);
+                let args = connected_arguments(&tap_config, &messages, &self.memory, self.engram_id.as_deref())
+                    .map_err(|errors| format!("Tap template error: {}", template_error_text(&errors)));
+                let args = match args {
+                    Ok(args) => args,
+                    Err(e) => return Some(Err(e)),
+                };
+                let engram_id = self.engram_id.clone();
+                let outcome = within(remaining, async move { call_tap_tool(&tap_config, args, engram_id.as_deref(), false).await }).await?;
+                Some(outcome.and_then(|outcome| match protocol_error_text(&outcome) {
+                    Some(text) => Err(text),
+                    None => Ok(outcome_text(&outcome)),
+                }))
+            }
+            ModelProvider::Js => {
+                let Some(hook) = self.model_hook.clone() else {
+                    return Some(Err("No model hook registered".to_string()));
+                };
+                let input = json!({
+                    "run_id": self.id,
+                    "engram_id": self.engram_id,
+                    "messages": self.messages,
+                    "memory": self.memory
+                })
+                .to_string();
+                let reply = within(remaining, async move {
+                    let value = hook.call1(&JsValue::NULL, &JsValue::from_str(&input)).map_err(|e| format!("{:?}", e))?;
+                    let reply = JsFuture::from(js_sys::Promise::resolve(&value)).await.map_err(|e| format!("{:?}", e))?;
+                    reply.as_string().ok_or_else(|| "Model hook did not return a string".to_string())
+                })
+                .await?;
+                Some(reply)
+            }
+        }
+    }
+
+    /// Count a call against the run's budgets.
+    fn take_budget(&self, tool: &str) -> Result<(), StopReason> {
+        with_run(self.id, |run| {
+            let total: usize = run.tool_calls.values().sum();
+            if self.budget.max_tool_calls.is_some_and(|max| total >= max) {
+                return Err(StopReason::MaxToolCalls { calls: total });
+            }
+            let calls = run.tool_calls.get(tool).copied().unwrap_or(0);
+            if self.budget.tool_budgets.get(tool).is_some_and(|max| calls >= *max) {
+                return Err(StopReason::ToolBudget { tool: tool.to_string(), calls });
+            }
+            *run.tool_calls.entry(tool.to_string()).or_default() += 1;
+            Ok(())
+        })
+        .unwrap_or(Ok(()))
+    }
+
+    /// Run one call from a model reply. Failures are shown to the model as
+    /// the tool's answer; only budgets, the breaker and the clock stop the
+    /// run.
+    async fn tool_step(&mut self, call: serde_json::Value) -> Result<(), StopReason> {
+        let started_at = js_sys::Date::now();
+        let method = call["method"].as_str().unwrap_or_default().to_string();
+        let params = call.get("params").cloned().unwrap_or(serde_json::Value::Null);
+        let target = tool_call_target(&method, params.clone())
+            .and_then(|(tool_name, arguments)| resolve_route(&tool_name).map(|route| (route, arguments)));
+        let (route, arguments) = match target {
+            Ok(target) => target,
+            Err(e) => {
+                let text = format!("Error: {}", e);
+                self.record(started_at, StepDetail::Tool { tool: method, server: None, arguments: params, outcome: None, text: text.clone(), failed: true });
+                self.post("tool", &text).await;
+                return Ok(());
+            }
+        };
+        self.take_budget(&route.tool_name)?;
+        check_call(self.engram_id.as_deref(), &route.url, &route.tool_name, &arguments, started_at)
+            .map_err(|trip| StopReason::Breaker { trip })?;
+
+        // The model's calls pass the same gate as calls extracted from tap
+        // output; simulated calls are answered without being sent
+        let gate = match is_simulated(self.engram_id.as_deref()) {
+            true => Ok(ExtractedGate::Allowed),
+            false => gate_extracted_call(&route.url, &route.tool_name, &arguments, self.engram_id.as_deref()),
+        };
+
+        info(&format!("Agent run {} calling '{}'", self.id, method));
+        let engram_id = self.engram_id.clone();
+        let routed_method = method.clone();
+        let requested = arguments.clone();
+        let routed = within(self.remaining_ms(), async move {
+            let grant = match gate? {
+                ExtractedGate::Allowed => None,
+                ExtractedGate::Pending(item) => Some(await_extracted_call(&item).await?),
+            };
+            let (params, arguments) = match &grant {
+                Some(grant) => (with_tool_arguments(&routed_method, params, grant.arguments.clone()), grant.arguments.clone()),
+                None => (params, arguments),
+            };
+            let routed = route_call(&routed_method, params, engram_id.as_deref(), true).await;
+            Ok::<_, PolicyBlock>((arguments, routed))
+        })
+        .await
+        .ok_or_else(|| self.timeout())?;
+        let (arguments, routed) = match routed {
+            Ok(routed) => routed,
+            Err(block) => {
+                let outcome = serde_json::to_value(ToolCallOutcome::from(block)).unwrap_or_default();
+                let text = format!("Error: {}", protocol_error_text(&outcome).unwrap_or_default());
+                self.record(started_at, StepDetail::Tool { tool: method, server: None, arguments: requested, outcome: Some(outcome), text: text.clone(), failed: true });
+                self.post("tool", &text).await;
+                return Ok(());
+            }
+        };
+        let (outcome, server, text, failed) = match routed {
+            Ok(routed) => {
+                let outcome = serde_json::to_value(&routed.outcome).unwrap_or_default();
+                match protocol_error_text(&outcome) {
+                    Some(text) => (Some(outcome), routed.served_by, format!("Error: {}", text), true),
+                    None => {
+                        let text = outcome_text(&outcome);
+                        if let Some(engram_id) = &self.engram_id {
+                            remember_result(engram_id, &route.tool_name, &text, &outcome);
+                        }
+                        let failed = outcome["kind"] == "tool_error";
+                        (Some(outcome), routed.served_by, text, failed)
+                    }
+                }
+            }
+            Err(e) => (None, None, format!("Error: {}", e), true),
+        };
+        self.record(started_at, StepDetail::Tool { tool: method, server, arguments, outcome, text: text.clone(), failed });
+        self.post("tool", &text).await;
+        Ok(())
+    }
+
+    /// Alternate model turns and tool calls until something stops the run.
+    async fn run(&mut self, prompt: Option<String>) -> StopReason {
+        if let Some(prompt) = prompt.filter(|p| !p.trim().is_empty()) {
+            self.post("user", &prompt).await;
+        }
+        loop {
+            if let Some(stop) = self.checkpoint().await {
+                return stop;
+            }
+            let started_at = js_sys::Date::now();
+            let reply = match self.model_turn().await {
+                None => return self.timeout(),
+                Some(Err(message)) => return StopReason::ModelError { message },
+                Some(Ok(reply)) => reply,
+            };
+            let calls: Vec<serde_json::Value> = parse_tool_calls_in(&reply).calls.into_iter().map(|parsed| parsed.call).collect();
+            let tool_calls = calls.iter().map(|c| c["method"].as_str().unwrap_or_default().to_string()).collect();
+            self.record(started_at, StepDetail::Model { reply: reply.clone(), tool_calls });
+            self.post("assistant", &reply).await;
+            if calls.is_empty() {
+                return StopReason::Completed;
+            }
+            for call in calls {
+                if let Some(stop) = self.checkpoint().await {
+                    return stop;
+                }
+                if let Err(stop) = self.tool_step(call).await {
+                    return stop;
+                }
+            }
+        }
+    }
+}
+
+/// Drives agent runs: model turn, tool calls, model turn, ... until the model
+/// answers without calling a tool or a budget runs out. Runs are controlled
+/// by id with `pause_agent_run`, `resume_agent_run` and `abort_agent_run`.
+#[wasm_bindgen]
+pub struct AgentRunner {
+    hooks: Rc<Hooks>,
+    model: Option<js_sys::Function>,
+}
+
+#[wasm_bindgen]
+impl AgentRunner {
+    /// `emit`, `load` and `persist` are the `TapPipeline` hooks. `model`
+    /// serves `{"kind": "js"}` providers: it receives
+    /// `{ run_id, engram_id, messages, memory }` as JSON and returns (a
+    /// promise of) the reply text.
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        emit: js_sys::Function,
+        load: Option<js_sys::Function>,
+        persist: Option<js_sys::Function>,
+        model: Option<js_sys::Function>,
+    ) -> AgentRunner {
+        AgentRunner { hooks: Rc::new(Hooks::new(emit, load, persist)), model }
+    }
+
+    /// Start a run in the background and return its id. Takes
+    /// `{ engram_id?, model, budget?, prompt?, messages?, memory? }`; progress
+    /// is emitted as `agent_run`, `agent_step` and `cbus_message` events, the
+    /// last `agent_run` event carrying the stop reason.
+    pub fn start(&self, request_json: &str) -> Result<u64, JsValue> {
+        let request: AgentRequest = serde_json::from_str(request_json)
+            .map_err(|e| JsValue::from_str(&format!("Invalid agent request: {}", e)))?;
+        let id = {
+            let mut registry = AGENT_RUNS.lock().unwrap();
+            let id = registry.next_id;
+            registry.next_id += 1;
+            registry.runs.insert(
+                id,
+                AgentRun {
+                    id,
+                    engram_id: request.engram_id.clone(),
+                    status: AgentStatus::Running,
+                    started_at: js_sys::Date::now(),
+                    finished_at: None,
+                    paused_ms: 0.0,
+                    budget: request.budget.clone(),
+                    steps: Vec::new(),
+                    transcript: Vec::new(),
+                    tool_calls: BTreeMap::new(),
+                    stop_reason: None,
+                    paused_at: None,
+                    abort: None,
+                },
+            );
+            id
+        };
+        let mut agent = Agent {
+            id,
+            engram_id: request.engram_id,
+            model: request.model,
+            budget: request.budget,
+            memory: request.memory,
+            messages: request.messages.as_deref().map(context_items).unwrap_or_default(),
+            hooks: self.hooks.clone(),
+            model_hook: self.model.clone(),
+            events: 0,
+        };
+        let (load_history, prompt) = (request.messages.is_none(), request.prompt);
+        wasm_bindgen_futures::spawn_local(async move {
+            if let (true, Some(engram_id)) = (load_history, agent.engram_id.clone()) {
+                agent.messages = context_items(&agent.hooks.load(&engram_id).await);
+            }
+            agent.emit_status();
+            let stop = agent.run(prompt).await;
+            info(&format!("Agent run {} stopped: {:?}", id, stop));
+            let finished = with_run(id, |run| {
+                let now = js_sys::Date::now();
+                if let Some(paused_at) = run.paused_at.take() {
+                    run.paused_ms += now - paused_at;
+                }
+                run.status = AgentStatus::Finished;
+                run.finished_at = Some(now);
+                run.stop_reason = Some(stop);
+                run.clone()
+            });
+            if let Some(run) = finished {
+                run.reject_pending_approvals("The agent run stopped");
+            }
+            RESUME_WAKERS.with(|wakers| wakers.borrow_mut().remove(&id));
+            agent.emit_status();
+        });
+        Ok(id)
+    }
+}
+
+fn unknown_run(id: u64) -> JsValue {
+    JsValue::from_str(&format!("Unknown agent run: {}", id))
+}
+
+/// Pause a run at its next checkpoint (between steps).
+#[wasm_bindgen]
+pub fn pause_agent_run(id: u64) -> Result<(), JsValue> {
+    with_run(id, |run| match run.status {
+        AgentStatus::Running => {
+            run.status = AgentStatus::Paused;
+            run.paused_at = Some(js_sys::Date::now());
+            Ok(())
+        }
+        AgentStatus::Paused => Ok(()),
+        AgentStatus::Finished => Err(JsValue::from_str(&format!("Agent run {} has finished", id))),
+    })
+    .ok_or_else(|| unknown_run(id))?
+}
+
+#[wasm_bindgen]
+pub fn resume_agent_run(id: u64) -> Result<(), JsValue> {
+    with_run(id, |run| {
+        if run.status == AgentStatus::Paused {
+            run.status = AgentStatus::Running;
+            if let Some(paused_at) = run.paused_at.take() {
+                run.paused_ms += js_sys::Date::now() - paused_at;
+            }
+        }
+    })
+    .ok_or_else(|| unknown_run(id))?;
+    wake(id);
+    Ok(())
+}
+
+/// Stop a run at its next checkpoint. Approvals its calls wait on are
+/// rejected; a call already sent is left to finish but no further step
+/// starts.
+#[wasm_bindgen]
+pub fn abort_agent_run(id: u64, reason: Option<String>) -> Result<(), JsValue> {
+    let run = with_run(id, |run| {
+        if run.status != AgentStatus::Finished {
+            run.abort = Some(reason);
+        }
+        run.clone()
+    })
+    .ok_or_else(|| unknown_run(id))?;
+    run.reject_pending_approvals("The agent run was aborted");
+    wake(id);
+    Ok(())
+}
+
+/// The full record of a run: steps, transcript and stop reason.
+#[wasm_bindgen]
+pub fn get_agent_run(id: u64) -> Result<String, JsValue> {
+    let run = with_run(id, |run| run.clone()).ok_or_else(|| unknown_run(id))?;
+    serde_json::to_string(&run).map_err(|e| {
+        error(&format!("Failed to serialize agent run {}: {}", id, e));
+        JsValue::from_str(&e.to_string())
+    })
+}
+
+/// Summaries of every run: `[{ id, engram_id, status, started_at, steps, stop_reason }]`.
+#[wasm_bindgen]
+pub fn list_agent_runs() -> String {
+    let registry = AGENT_RUNS.lock().unwrap();
+    json!(registry.runs.values().map(AgentRun::summary).collect::<Vec<_>>()).to_string()
+}
+
+/// Drop finished runs from the registry.
+#[wasm_bindgen]
+pub fn clear_finished_agent_runs() {
+    AGENT_RUNS.lock().unwrap().runs.retain(|_, run| run.status != AgentStatus::Finished);
+}
@@ -0,0 +1,242 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{self, json};
+use std::sync::{LazyLock, Mutex};
+use wasm_bindgen::prelude::*;
+
+use crate::catalog::{catalog_entries, CatalogEntry};
+use crate::router::server_alias;
+use crate::{ToolParameter, BOOTROM_DATA, BOOTROM_VERSION, BUILD_DATETIME, BUILD_HASH, SERVER_REGISTRY, VERSION};
+
+/// One block of the composed bootrom. `template` may use the placeholders
+/// listed on `render_placeholders`; a section whose placeholders all render
+/// empty is left out.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct BootromSection {
+    pub id: String,
+    pub template: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn section(id: &str, template: &str) -> BootromSection {
+    BootromSection { id: id.to_string(), template: template.to_string(), enabled: true }
+}
+
+fn default_sections() -> Vec<BootromSection> {
+    vec![
+        section("version", "<!-- bootrom {{version}} -->"),
+        section("instructions", BOOTROM_DATA.trim()),
+        section("tools", "# Available Tools\n\n{{tool_catalog}}"),
+        section("server_instructions", "# Server Instructions\n\n{{server_instructions}}"),
+    ]
+}
+
+static BOOTROM_SECTIONS: LazyLock<Mutex<Vec<BootromSection>>> = LazyLock::new(|| Mutex::new(default_sections()));
+
+/// `bootrom <version>+<build hash> client <version> built <datetime>`.
+pub(crate) fn version_stamp() -> String {
+    format!(
+        "{}+{} client {} built {}",
+        BOOTROM_VERSION,
+        &BUILD_HASH[..BUILD_HASH.len().min(12)],
+        VERSION,
+        BUILD_DATETIME
+    )
+}
+
+/// Placeholder value for an argument in an example call.
+fn example_value(parameter: &ToolParameter) -> serde_json::Value {
+    if let Some(default) = &parameter.default {
+        return default.clone();
+    }
+    if let Some(first) = parameter.enum_values.as_ref().and_then(|values| values.first()) {
+        return first.clone();
+    }
+    match parameter.param_type.as_str() {
+        "integer" | "number" => json!(1),
+        "boolean" => json!(true),
+        "array" => json!(parameter.items.as_deref().map(example_value).into_iter().collect::<Vec<_>>()),
+        "object" => example_arguments(&parameter.properties),
+        _ => json!(format!("<{}>", parameter.name)),
+    }
+}
+
+/// Example arguments: every required parameter, or every parameter when
+/// none is required.
+fn example_arguments(parameters: &[ToolParameter]) -> serde_json::Value {
+    let required: Vec<&ToolParameter> = parameters.iter().filter(|p| p.required).collect();
+    let shown = if required.is_empty() { parameters.iter().collect() } else { required };
+    serde_json::Value::Object(shown.into_iter().map(|p| (p.name.clone(), example_value(p))).collect())
+}
+
+/// Table cells may not contain pipes or line breaks.
+fn table_cell(text: &str) -> String {
+    text.replace('|', "\\|").replace(['\n', '\r'], " ")
+}
+
+fn type_label(parameter: &ToolParameter) -> String {
+    let base = match (&parameter.param_type[..], &parameter.items) {
+        ("array", Some(items)) => format!("{}[]", items.param_type),
+        (other, _) => other.to_string(),
+    };
+    if parameter.nullable {
+        format!("{} \\| null", base)
+    } else {
+        base
+    }
+}
+
+fn argument_table(parameters: &[ToolParameter]) -> String {
+    let mut table = String::from("| Argument | Type | Required | Description |\n|---|---|---|---|\n");
+    for parameter in parameters {
+        let mut description = table_cell(&parameter.description);
+        if let Some(values) = &parameter.enum_values {
+            let values: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+            description.push_str(&format!(" One of: {}.", table_cell(&values.join(", "))));
+        }
+        if let Some(default) = &parameter.default {
+            description.push_str(&format!(" Default: {}.", table_cell(&default.to_string())));
+        }
+        table.push_str(&format!(
+            "| `{}` | {} | {} | {} |\n",
+            parameter.name,
+            type_label(parameter),
+            if parameter.required { "yes" } else { "no" },
+            description.trim()
+        ));
+    }
+    table
+}
+
+fn render_tool(entry: &CatalogEntry) -> String {
+    let mut text = format!("## {}", entry.original);
+    if let Some(title) = entry.tool.title.as_ref().filter(|t| !t.is_empty()) {
+        text.push_str(&format!(" ({})", title));
+    }
+    text.push('\n');
+    if !entry.description.is_empty() {
+        text.push_str(&format!("\n{}\n", entry.description.trim()));
+    }
+    if !entry.tool.parameters.is_empty() {
+        text.push_str(&format!("\n{}", argument_table(&entry.tool.parameters)));
+    }
+    let example = json!({
+        "jsonrpc": "2.0",
+        "method": entry.original,
+        "params": example_arguments(&entry.tool.parameters),
+        "id": 1
+    });
+    text.push_str(&format!(
+        "\nExample:\n```json\n{}\n```\n",
+        serde_json::to_string_pretty(&example).unwrap_or_default()
+    ));
+    text
+}
+
+/// Markdown catalog of every registered tool, named as the router
+/// resolves them.
+pub(crate) fn render_tool_catalog() -> String {
+    catalog_entries().iter().map(render_tool).collect::<Vec<_>>().join("\n")
+}
+
+fn render_server_instructions() -> String {
+    let registry = SERVER_REGISTRY.lock().unwrap();
+    let mut servers: Vec<_> = registry.servers.values().filter(|s| s.instructions.is_some()).collect();
+    servers.sort_by(|a, b| a.url.cmp(&b.url));
+    servers
+        .into_iter()
+        .map(|server| format!("## {}\n\n{}\n", server_alias(server), server.instructions.as_deref().unwrap_or("").trim()))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Values for the template placeholders: `version`, `bootrom_version`,
+/// `client_version`, `build_datetime`, `tool_catalog`,
+/// `server_instructions`, `server_count` and `tool_count`.
+fn render_placeholders(include_catalog: bool) -> Vec<(&'static str, String)> {
+    let (server_count, tool_count) = {
+        let registry = SERVER_REGISTRY.lock().unwrap();
+        (registry.servers.len(), registry.servers.values().map(|s| s.tools.len()).sum::<usize>())
+    };
+    vec![
+        ("version", version_stamp()),
+        ("bootrom_version", BOOTROM_VERSION.to_string()),
+        ("client_version", VERSION.to_string()),
+        ("build_datetime", BUILD_DATETIME.to_string()),
+        ("tool_catalog", if include_catalog { render_tool_catalog() } else { String::new() }),
+        ("server_instructions", render_server_instructions()),
+        ("server_count", server_count.to_string()),
+        ("tool_count", tool_count.to_string()),
+    ]
+}
+
+/// Fill a template. Returns `None` when it uses placeholders and every one
+/// of them is empty, so sections like "Server Instructions" drop out
+/// instead of rendering a bare heading.
+fn render_template(template: &str, values: &[(&str, String)]) -> Option<String> {
+    let mut text = template.to_string();
+    let mut used = 0;
+    let mut filled = 0;
+    for (name, value) in values {
+        let placeholder = format!("{{{{{}}}}}", name);
+        if text.contains(&placeholder) {
+            used += 1;
+            if !value.trim().is_empty() {
+                filled += 1;
+            }
+            text = text.replace(&placeholder, value.trim_end());
+        }
+    }
+    (used == 0 || filled > 0).then_some(text)
+}
+
+/// Compose the enabled sections against the current registry.
+pub(crate) fn compose_bootrom() -> String {
+    compose_bootrom_with(true)
+}
+
+/// Compose the bootrom, optionally leaving the tool catalog out so a caller
+/// can budget it separately. Sections that only carry the catalog drop out.
+pub(crate) fn compose_bootrom_with(include_catalog: bool) -> String {
+    let values = render_placeholders(include_catalog);
+    let sections = BOOTROM_SECTIONS.lock().unwrap().clone();
+    sections
+        .iter()
+        .filter(|section| section.enabled)
+        .filter_map(|section| render_template(&section.template, &values))
+        .map(|text| text.trim().to_string())
+        .filter(|text| !text.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Replace the bootrom sections. Takes a JSON array of
+/// `{ id, template, enabled? }`, composed in order.
+#[wasm_bindgen]
+pub fn set_bootrom_sections(sections_json: &str) -> Result<(), JsValue> {
+    let sections: Vec<BootromSection> = serde_json::from_str(sections_json)
+        .map_err(|e| JsValue::from_str(&format!("Invalid bootrom sections: {}", e)))?;
+    *BOOTROM_SECTIONS.lock().unwrap() = sections;
+    Ok(())
+}
+
+#[wasm_bindgen]
+pub fn get_bootrom_sections() -> String {
+    serde_json::to_string(&*BOOTROM_SECTIONS.lock().unwrap()).unwrap_or_else(|_| "[]".to_string())
+}
+
+/// Restore the built-in sections.
+#[wasm_bindgen]
+pub fn reset_bootrom_sections() {
+    *BOOTROM_SECTIONS.lock().unwrap() = default_sections();
+}
+
+/// Markdown tool catalog as it appears in the bootrom.
+#[wasm_bindgen]
+pub fn get_tool_catalog_text() -> String {
+    render_tool_catalog()
+}
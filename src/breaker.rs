@@ -0,0 +1,489 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{self, json};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use wasm_bindgen::prelude::*;
+
+use crate::catalog::short_hash;
+use crate::tool_call::delay;
+use crate::{debug, info};
+
+/// How long state changes are collected before they are written out.
+const PERSIST_DELAY_MS: u32 = 1_000;
+
+/// How often calls may be made within one rate key.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub(crate) enum RateLimit {
+    /// At most `max_calls` in any `window_ms`.
+    SlidingWindow { max_calls: usize, window_ms: f64 },
+    /// Bursts of up to `capacity` calls, refilled at `refill_per_sec`.
+    TokenBucket { capacity: f64, refill_per_sec: f64 },
+    Unlimited,
+}
+
+/// What a rate key is made of. Every scope includes the engram.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum RateScope {
+    Engram,
+    Server,
+    Tool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub(crate) struct BreakerConfig {
+    pub enabled: bool,
+    pub rate: RateLimit,
+    pub scope: RateScope,
+    /// Identical calls (same server, tool and arguments) allowed within
+    /// `loop_window_ms`; 0 turns the check off.
+    pub max_identical_calls: usize,
+    /// Longest cycle of calls looked for, e.g. 2 for A→B→A→B.
+    pub max_cycle_length: usize,
+    /// Times a cycle must repeat back to back to count as a loop; 0 turns
+    /// the check off.
+    pub cycle_repeats: usize,
+    /// Compare cycle steps by tool only, not by tool and arguments.
+    pub cycle_ignores_arguments: bool,
+    /// How far back loop detection looks.
+    pub loop_window_ms: f64,
+    /// Calls remembered per engram for loop detection.
+    pub history_size: usize,
+}
+
+impl Default for BreakerConfig {
+    fn default() -> Self {
+        BreakerConfig {
+            enabled: true,
+            rate: RateLimit::SlidingWindow { max_calls: 3, window_ms: 10_000.0 },
+            scope: RateScope::Engram,
+            max_identical_calls: 3,
+            max_cycle_length: 3,
+            cycle_repeats: 3,
+            cycle_ignores_arguments: false,
+            loop_window_ms: 60_000.0,
+            history_size: 32,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct Bucket {
+    tokens: f64,
+    updated: f64,
+}
+
+/// A call as loop detection remembers it.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct CallRecord {
+    server: String,
+    tool: String,
+    /// Hash of the arguments in canonical (key-sorted) form.
+    fingerprint: String,
+    at: f64,
+}
+
+impl CallRecord {
+    fn same_call(&self, other: &CallRecord) -> bool {
+        self.server == other.server && self.tool == other.tool && self.fingerprint == other.fingerprint
+    }
+}
+
+/// Everything the breaker remembers. Timestamps are wall-clock
+/// milliseconds, so a restored state stays meaningful after a restart.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub(crate) struct BreakerState {
+    windows: HashMap<String, Vec<f64>>,
+    buckets: HashMap<String, Bucket>,
+    history: HashMap<String, Vec<CallRecord>>,
+}
+
+/// Why a call was stopped.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub(crate) enum BreakerTrip {
+    RateLimited { key: String, retry_after_ms: f64 },
+    RepeatedCall { tool: String, count: usize },
+    /// `tools` is one turn of the cycle, oldest first.
+    Cycle { tools: Vec<String>, repeats: usize },
+}
+
+impl BreakerTrip {
+    pub(crate) fn message(&self) -> String {
+        match self {
+            BreakerTrip::RateLimited { retry_after_ms, .. } => format!(
+                "Circuit breaker: too many tool calls in a short period (retry in {} ms)",
+                retry_after_ms.ceil()
+            ),
+            BreakerTrip::RepeatedCall { tool, count } => {
+                format!("Loop detected: '{}' called {} times with the same arguments", tool, count)
+            }
+            BreakerTrip::Cycle { tools, repeats } => {
+                format!("Loop detected: {} repeated {} times", tools.join(" → "), repeats)
+            }
+        }
+    }
+}
+
+static BREAKER_CONFIG: LazyLock<Mutex<BreakerConfig>> = LazyLock::new(|| Mutex::new(BreakerConfig::default()));
+
+static BREAKER_STATE: LazyLock<Mutex<BreakerState>> = LazyLock::new(|| Mutex::new(BreakerState::default()));
+
+thread_local! {
+    // Called with the state as JSON shortly after it changes, and with the
+    // config whenever it is set; JS handles are not `Send`.
+    static PERSIST_HOOK: RefCell<Option<js_sys::Function>> = const { RefCell::new(None) };
+    static CONFIG_PERSIST_HOOK: RefCell<Option<js_sys::Function>> = const { RefCell::new(None) };
+    static PERSIST_SCHEDULED: Cell<bool> = const { Cell::new(false) };
+}
+
+fn rate_key(scope: RateScope, engram_id: &str, server: &str, tool: &str) -> String {
+    match scope {
+        RateScope::Engram => engram_id.to_string(),
+        RateScope::Server => format!("{}|{}", engram_id, server),
+        RateScope::Tool => format!("{}|{}|{}", engram_id, server, tool),
+    }
+}
+
+/// Whether `keys` ends in a cycle of `length` calls repeated `repeats`
+/// times, with at least two distinct calls per turn.
+fn trailing_cycle(keys: &[String], length: usize, repeats: usize) -> bool {
+    let span = length * repeats;
+    if length < 2 || repeats == 0 || keys.len() < span {
+        return false;
+    }
+    let tail = &keys[keys.len() - span..];
+    let periodic = tail.iter().enumerate().all(|(i, key)| *key == tail[i % length]);
+    periodic && tail[..length].iter().any(|key| *key != tail[0])
+}
+
+impl BreakerState {
+    fn detect_loop(&self, config: &BreakerConfig, engram_id: &str, call: &CallRecord) -> Option<BreakerTrip> {
+        let history = self.history.get(engram_id).map(Vec::as_slice).unwrap_or_default();
+        let recent: Vec<&CallRecord> = history.iter().filter(|r| call.at - r.at < config.loop_window_ms).collect();
+        if config.max_identical_calls > 0 {
+            let count = recent.iter().filter(|r| r.same_call(call)).count() + 1;
+            if count > config.max_identical_calls {
+                return Some(BreakerTrip::RepeatedCall { tool: call.tool.clone(), count });
+            }
+        }
+        let calls: Vec<&CallRecord> = recent.into_iter().chain(std::iter::once(call)).collect();
+        let keys: Vec<String> = calls
+            .iter()
+            .map(|r| {
+                if config.cycle_ignores_arguments {
+                    format!("{}|{}", r.server, r.tool)
+                } else {
+                    format!("{}|{}|{}", r.server, r.tool, r.fingerprint)
+                }
+            })
+            .collect();
+        let length = (2..=config.max_cycle_length).find(|&length| trailing_cycle(&keys, length, config.cycle_repeats))?;
+        let start = calls.len() - length * config.cycle_repeats;
+        Some(BreakerTrip::Cycle {
+            tools: calls[start..start + length].iter().map(|r| r.tool.clone()).collect(),
+            repeats: config.cycle_repeats,
+        })
+    }
+
+    /// Check the rate limit for `key`, consuming a call when it passes.
+    fn take_rate(&mut self, rate: &RateLimit, key: &str, now: f64) -> Result<(), BreakerTrip> {
+        match rate {
+            RateLimit::Unlimited => Ok(()),
+            RateLimit::SlidingWindow { max_calls, window_ms } => {
+                let calls = self.windows.entry(key.to_string()).or_default();
+                calls.retain(|ts| now - ts < *window_ms);
+                if calls.len() >= *max_calls {
+                    let oldest = calls.first().copied().unwrap_or(now);
+                    return Err(BreakerTrip::RateLimited { key: key.to_string(), retry_after_ms: oldest + window_ms - now });
+                }
+                calls.push(now);
+                Ok(())
+            }
+            RateLimit::TokenBucket { capacity, refill_per_sec } => {
+                let bucket = self.buckets.entry(key.to_string()).or_insert(Bucket { tokens: *capacity, updated: now });
+                let elapsed = (now - bucket.updated).max(0.0);
+                bucket.tokens = (bucket.tokens + elapsed * refill_per_sec / 1000.0).min(*capacity);
+                bucket.updated = now;
+                // Tolerate float drift in the refill
+                if bucket.tokens + 1e-9 < 1.0 {
+                    let retry_after_ms = if *refill_per_sec > 0.0 { (1.0 - bucket.tokens) / refill_per_sec * 1000.0 } else { f64::INFINITY };
+                    return Err(BreakerTrip::RateLimited { key: key.to_string(), retry_after_ms });
+                }
+                bucket.tokens -= 1.0;
+                Ok(())
+            }
+        }
+    }
+
+    /// Drop windows, buckets and histories that no longer affect any call:
+    /// expired windows, refilled buckets and histories past the loop window.
+    fn prune(&mut self, config: &BreakerConfig, now: f64) {
+        let window_ms = match config.rate {
+            RateLimit::SlidingWindow { window_ms, .. } => window_ms,
+            _ => 0.0,
+        };
+        self.windows.retain(|_, calls| calls.iter().any(|ts| now - ts < window_ms));
+        self.buckets.retain(|_, bucket| match config.rate {
+            RateLimit::TokenBucket { capacity, refill_per_sec } => {
+                bucket.tokens + (now - bucket.updated).max(0.0) * refill_per_sec / 1000.0 < capacity
+            }
+            _ => false,
+        });
+        self.history
+            .retain(|_, history| history.last().is_some_and(|last| now - last.at < config.loop_window_ms));
+    }
+
+    fn record(&mut self, config: &BreakerConfig, engram_id: &str, call: CallRecord) {
+        let history = self.history.entry(engram_id.to_string()).or_default();
+        history.push(call);
+        let excess = history.len().saturating_sub(config.history_size);
+        history.drain(..excess);
+    }
+}
+
+fn call_persist_hook(hook: &'static std::thread::LocalKey<RefCell<Option<js_sys::Function>>>, snapshot: String) {
+    hook.with(|hook| {
+        if let Some(hook) = hook.borrow().as_ref() {
+            if let Err(e) = hook.call1(&JsValue::NULL, &JsValue::from_str(&snapshot)) {
+                debug(&format!("Persisting breaker state failed: {:?}", e));
+            }
+        }
+    });
+}
+
+fn persist(state: &BreakerState) {
+    call_persist_hook(&PERSIST_HOOK, serde_json::to_string(state).unwrap_or_default());
+}
+
+/// Write the state out once changes stop arriving for a moment, rather
+/// than on every call.
+fn schedule_persist() {
+    let hooked = PERSIST_HOOK.with(|hook| hook.borrow().is_some());
+    if !hooked || PERSIST_SCHEDULED.with(|scheduled| scheduled.replace(true)) {
+        return;
+    }
+    wasm_bindgen_futures::spawn_local(async {
+        let _ = wasm_bindgen_futures::JsFuture::from(delay(PERSIST_DELAY_MS)).await;
+        PERSIST_SCHEDULED.with(|scheduled| scheduled.set(false));
+        persist(&BREAKER_STATE.lock().unwrap());
+    });
+}
+
+/// Admit or stop a call. Loop detection runs first, then the rate limit;
+/// only admitted calls are remembered. Calls without an engram are not
+/// limited.
+pub(crate) fn check_call(
+    engram_id: Option<&str>,
+    server: &str,
+    tool: &str,
+    arguments: &serde_json::Value,
+    now: f64,
+) -> Result<(), BreakerTrip> {
+    let config = BREAKER_CONFIG.lock().unwrap().clone();
+    let Some(engram_id) = engram_id.filter(|_| config.enabled) else {
+        return Ok(());
+    };
+    let call = CallRecord {
+        server: server.to_string(),
+        tool: tool.to_string(),
+        fingerprint: short_hash(&arguments.to_string()),
+        at: now,
+    };
+    let mut state = BREAKER_STATE.lock().unwrap();
+    state.prune(&config, now);
+    let checked = match state.detect_loop(&config, engram_id, &call) {
+        Some(trip) => Err(trip),
+        None => state.take_rate(&config.rate, &rate_key(config.scope, engram_id, server, tool), now),
+    };
+    match &checked {
+        Ok(()) => state.record(&config, engram_id, call),
+        Err(trip) => info(&format!("Breaker stopped '{}' for engram {}: {}", tool, engram_id, trip.message())),
+    }
+    drop(state);
+    schedule_persist();
+    checked
+}
+
+#[wasm_bindgen]
+pub fn set_breaker_config(config_json: &str) -> Result<(), JsValue> {
+    let config: BreakerConfig = serde_json::from_str(config_json)
+        .map_err(|e| JsValue::from_str(&format!("Invalid breaker config: {}", e)))?;
+    call_persist_hook(&CONFIG_PERSIST_HOOK, serde_json::to_string(&config).unwrap_or_default());
+    *BREAKER_CONFIG.lock().unwrap() = config;
+    Ok(())
+}
+
+#[wasm_bindgen]
+pub fn get_breaker_config() -> String {
+    serde_json::to_string(&*BREAKER_CONFIG.lock().unwrap()).unwrap_or_else(|_| "{}".to_string())
+}
+
+/// Check a call against the breaker, counting it when admitted. Returns
+/// `{ allowed, trip?, message? }`.
+#[wasm_bindgen]
+pub fn check_tool_call_breaker(
+    engram_id: Option<String>,
+    server: &str,
+    tool: &str,
+    args_json: &str,
+) -> Result<String, JsValue> {
+    let arguments: serde_json::Value = serde_json::from_str(args_json)
+        .map_err(|e| JsValue::from_str(&format!("Invalid args: {}", e)))?;
+    Ok(match check_call(engram_id.as_deref(), server, tool, &arguments, js_sys::Date::now()) {
+        Ok(()) => json!({ "allowed": true }),
+        Err(trip) => json!({ "allowed": false, "trip": trip, "message": trip.message() }),
+    }
+    .to_string())
+}
+
+/// The breaker's state as JSON, for storage.
+#[wasm_bindgen]
+pub fn get_breaker_state() -> String {
+    serde_json::to_string(&*BREAKER_STATE.lock().unwrap()).unwrap_or_else(|_| "{}".to_string())
+}
+
+/// Restore state saved from `get_breaker_state` or the persistence hook.
+#[wasm_bindgen]
+pub fn restore_breaker_state(state_json: &str) -> Result<(), JsValue> {
+    let state: BreakerState = serde_json::from_str(state_json)
+        .map_err(|e| JsValue::from_str(&format!("Invalid breaker state: {}", e)))?;
+    *BREAKER_STATE.lock().unwrap() = state;
+    Ok(())
+}
+
+/// Forget the state of one engram, or of all when `engram_id` is null.
+#[wasm_bindgen]
+pub fn reset_breaker(engram_id: Option<String>) {
+    let mut state = BREAKER_STATE.lock().unwrap();
+    match engram_id {
+        Some(engram_id) => {
+            let prefix = format!("{}|", engram_id);
+            state.windows.retain(|key, _| *key != engram_id && !key.starts_with(&prefix));
+            state.buckets.retain(|key, _| *key != engram_id && !key.starts_with(&prefix));
+            state.history.remove(&engram_id);
+        }
+        None => *state = BreakerState::default(),
+    }
+    persist(&state);
+}
+
+/// Register a function called with the state as JSON shortly after it
+/// changes; pass `null` to unregister.
+#[wasm_bindgen]
+pub fn set_breaker_persistence(hook: Option<js_sys::Function>) {
+    PERSIST_HOOK.with(|slot| *slot.borrow_mut() = hook);
+}
+
+/// Register a function called with the config as JSON whenever it is set;
+/// pass `null` to unregister. Restore a saved config with
+/// `set_breaker_config` before registering.
+#[wasm_bindgen]
+pub fn set_breaker_config_persistence(hook: Option<js_sys::Function>) {
+    CONFIG_PERSIST_HOOK.with(|slot| *slot.borrow_mut() = hook);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(tool: &str, at: f64) -> CallRecord {
+        CallRecord { server: "s".to_string(), tool: tool.to_string(), fingerprint: "{}".to_string(), at }
+    }
+
+    /// Feed `tools` one second apart, recording each call that passes, and
+    /// return the first trip with its position.
+    fn first_trip(config: &BreakerConfig, tools: &[&str]) -> Option<(usize, BreakerTrip)> {
+        let mut state = BreakerState::default();
+        for (i, tool) in tools.iter().enumerate() {
+            let record = call(tool, i as f64 * 1000.0);
+            if let Some(trip) = state.detect_loop(config, "e", &record) {
+                return Some((i, trip));
+            }
+            state.record(config, "e", record);
+        }
+        None
+    }
+
+    #[test]
+    fn trailing_cycle_needs_distinct_repeated_turns() {
+        let keys = |text: &str| text.chars().map(String::from).collect::<Vec<_>>();
+        assert!(trailing_cycle(&keys("xABABAB"), 2, 3));
+        assert!(!trailing_cycle(&keys("xABABAB"), 2, 4));
+        assert!(!trailing_cycle(&keys("ABABAC"), 2, 3));
+        assert!(!trailing_cycle(&keys("AAAAAA"), 2, 3));
+        assert!(trailing_cycle(&keys("ABCABC"), 3, 2));
+        assert!(!trailing_cycle(&keys("ABAB"), 1, 4));
+    }
+
+    #[test]
+    fn detects_cycles_and_repeated_calls() {
+        let config = BreakerConfig { max_identical_calls: 0, ..Default::default() };
+        assert_eq!(
+            first_trip(&config, &["a", "b", "a", "b", "a", "b"]),
+            Some((5, BreakerTrip::Cycle { tools: vec!["a".to_string(), "b".to_string()], repeats: 3 }))
+        );
+        assert_eq!(first_trip(&config, &["a", "b", "a", "c", "a", "b"]), None);
+
+        let config = BreakerConfig { cycle_repeats: 0, ..Default::default() };
+        assert_eq!(
+            first_trip(&config, &["a", "b", "a", "a", "a"]),
+            Some((4, BreakerTrip::RepeatedCall { tool: "a".to_string(), count: 4 }))
+        );
+        // Calls past the loop window are forgotten
+        let config = BreakerConfig { cycle_repeats: 0, loop_window_ms: 1_500.0, ..Default::default() };
+        assert_eq!(first_trip(&config, &["a"; 6]), None);
+    }
+
+    #[test]
+    fn sliding_window_limits_calls() {
+        let rate = RateLimit::SlidingWindow { max_calls: 2, window_ms: 1_000.0 };
+        let mut state = BreakerState::default();
+        assert!(state.take_rate(&rate, "k", 0.0).is_ok());
+        assert!(state.take_rate(&rate, "k", 400.0).is_ok());
+        assert_eq!(
+            state.take_rate(&rate, "k", 600.0),
+            Err(BreakerTrip::RateLimited { key: "k".to_string(), retry_after_ms: 400.0 })
+        );
+        assert!(state.take_rate(&rate, "other", 600.0).is_ok());
+        assert!(state.take_rate(&rate, "k", 1_000.0).is_ok());
+    }
+
+    #[test]
+    fn token_bucket_refills() {
+        let rate = RateLimit::TokenBucket { capacity: 2.0, refill_per_sec: 1.0 };
+        let mut state = BreakerState::default();
+        assert!(state.take_rate(&rate, "k", 0.0).is_ok());
+        assert!(state.take_rate(&rate, "k", 0.0).is_ok());
+        assert_eq!(
+            state.take_rate(&rate, "k", 250.0),
+            Err(BreakerTrip::RateLimited { key: "k".to_string(), retry_after_ms: 750.0 })
+        );
+        assert!(state.take_rate(&rate, "k", 1_000.0).is_ok());
+        assert!(state.take_rate(&rate, "k", 1_000.0).is_err());
+    }
+
+    #[test]
+    fn prune_keeps_only_live_state() {
+        let config = BreakerConfig { loop_window_ms: 5_000.0, ..Default::default() };
+        let mut state = BreakerState::default();
+        state.take_rate(&config.rate, "old", 0.0).unwrap();
+        state.take_rate(&config.rate, "new", 9_000.0).unwrap();
+        state.record(&config, "old", call("a", 0.0));
+        state.record(&config, "new", call("a", 9_000.0));
+        state.prune(&config, 12_000.0);
+        assert_eq!(state.windows.keys().collect::<Vec<_>>(), ["new"]);
+        assert_eq!(state.history.keys().collect::<Vec<_>>(), ["new"]);
+
+        let config = BreakerConfig { rate: RateLimit::TokenBucket { capacity: 2.0, refill_per_sec: 1.0 }, ..config };
+        state.take_rate(&config.rate, "drained", 10_000.0).unwrap();
+        state.take_rate(&config.rate, "full", 0.0).unwrap();
+        state.prune(&config, 10_500.0);
+        assert_eq!(state.buckets.keys().collect::<Vec<_>>(), ["drained"]);
+        assert!(state.windows.is_empty());
+    }
+}
@@ -1,3 +1,3 @@
 // Generated by generate-build-info.sh
-pub const BUILD_DATETIME: &str = "2025-06-17T07:51:09Z";
-pub const BUILD_HASH: &str = "8503720f4817edd800fe2c1334a39edfbc0488e97b723969705274fbde4698c4";
+pub const BUILD_DATETIME: &str = "2026-10-18T22:09:38Z";
+pub const BUILD_HASH: &str = "87da2eb2e4ce294ddbf9c848a2baeb6dc39d72a07f673f6e12ba755a116f6939";
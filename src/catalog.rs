@@ -0,0 +1,363 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{self, json};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use wasm_bindgen::prelude::*;
+
+use crate::router::{qualified_name, resolve_route, server_alias};
+use crate::schema::resolve_local_refs;
+use crate::{debug, McpTool, ToolParameter, SERVER_REGISTRY};
+
+/// Longest function name any of the supported APIs accepts.
+const MAX_NAME_LENGTH: usize = 64;
+
+/// Keywords Gemini's OpenAPI-subset `Schema` understands; anything else is
+/// dropped from exported parameters. `format` is left out as Gemini rejects
+/// most of the values JSON Schema allows.
+const GEMINI_SCHEMA_KEYS: [&str; 16] = [
+    "type", "title", "description", "nullable", "enum", "default", "properties", "required", "items",
+    "minItems", "maxItems", "minimum", "maximum", "minLength", "maxLength", "anyOf",
+];
+
+/// Function-calling API a catalog is rendered for.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum CatalogFormat {
+    OpenAi,
+    Anthropic,
+    Gemini,
+}
+
+impl CatalogFormat {
+    fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "openai" => Ok(CatalogFormat::OpenAi),
+            "anthropic" => Ok(CatalogFormat::Anthropic),
+            "gemini" => Ok(CatalogFormat::Gemini),
+            other => Err(format!("Unknown catalog format: {}", other)),
+        }
+    }
+
+    /// Gemini also allows `.` and requires a leading letter or underscore.
+    fn allows(&self, c: char) -> bool {
+        c.is_ascii_alphanumeric() || c == '_' || c == '-' || (*self == CatalogFormat::Gemini && c == '.')
+    }
+}
+
+/// Exported name to original (routable) tool name, per format. Rebuilt on
+/// every export so it always matches the catalog last handed to a model,
+/// and persisted so names in a model's reply resolve after a restart.
+static EXPORTED_NAMES: LazyLock<Mutex<HashMap<CatalogFormat, HashMap<String, String>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+thread_local! {
+    static PERSIST_HOOK: RefCell<Option<js_sys::Function>> = const { RefCell::new(None) };
+}
+
+/// A tool as it appears in an exported catalog.
+pub(crate) struct CatalogEntry {
+    /// Name `route_tool_call` accepts: plain for the precedence winner,
+    /// `alias/tool` for shadowed providers.
+    pub original: String,
+    pub description: String,
+    pub schema: serde_json::Value,
+    pub tool: McpTool,
+}
+
+pub(crate) fn short_hash(text: &str) -> String {
+    // FNV-1a; only needs to be stable and spread similar names apart.
+    let hash = text
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3));
+    format!("{:08x}", hash as u32)
+}
+
+/// Make `original` a valid function name for `format`. `-` escapes: the
+/// qualifier becomes `--` and any other character the API does not allow,
+/// `-` included, becomes `-` and its UTF-8 bytes in hex, so distinct names
+/// never encode alike. Gemini names must start with a letter or `_`, so
+/// other names get a leading `_`, their own leading `_` escaped. Over-long
+/// names are cut with a hash suffix.
+fn sanitize_name(original: &str, format: CatalogFormat) -> String {
+    let mut name = String::new();
+    for (index, c) in original.char_indices() {
+        let leading_underscore = index == 0 && c == '_' && format == CatalogFormat::Gemini;
+        match c {
+            '/' => name.push_str("--"),
+            c if c != '-' && format.allows(c) && !leading_underscore => name.push(c),
+            c => {
+                let mut bytes = [0; 4];
+                for byte in c.encode_utf8(&mut bytes).bytes() {
+                    name.push_str(&format!("-{:02x}", byte));
+                }
+            }
+        }
+    }
+    if format == CatalogFormat::Gemini && !original.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        name.insert(0, '_');
+    }
+    if name.is_empty() {
+        name.push('-');
+    }
+    if name.len() > MAX_NAME_LENGTH {
+        let hash = short_hash(original);
+        name.truncate(MAX_NAME_LENGTH - hash.len() - 1);
+        name = format!("{}_{}", name, hash);
+    }
+    name
+}
+
+/// Pick a name not already in `taken`, numbering duplicates within the
+/// length limit.
+fn unique_name(base: String, taken: &HashMap<String, String>) -> String {
+    if !taken.contains_key(&base) {
+        return base;
+    }
+    (2..)
+        .map(|n| {
+            let suffix = format!("_{}", n);
+            let keep = base.len().min(MAX_NAME_LENGTH - suffix.len());
+            format!("{}{}", &base[..keep], suffix)
+        })
+        .find(|candidate| !taken.contains_key(candidate))
+        .unwrap_or(base)
+}
+
+/// JSON Schema for a parameter, for tools cached without an `inputSchema`.
+fn parameter_schema(parameter: &ToolParameter) -> serde_json::Value {
+    let mut schema = json!({ "type": parameter.param_type });
+    if parameter.nullable {
+        schema["type"] = json!([parameter.param_type, "null"]);
+    }
+    if !parameter.description.is_empty() {
+        schema["description"] = json!(parameter.description);
+    }
+    if let Some(values) = &parameter.enum_values {
+        schema["enum"] = json!(values);
+    }
+    if let Some(default) = &parameter.default {
+        schema["default"] = default.clone();
+    }
+    if let Some(format) = &parameter.format {
+        schema["format"] = json!(format);
+    }
+    if !parameter.properties.is_empty() {
+        schema["properties"] = parameters_object(&parameter.properties)["properties"].take();
+        let required: Vec<&str> = parameter.properties.iter().filter(|p| p.required).map(|p| p.name.as_str()).collect();
+        schema["required"] = json!(required);
+    }
+    if let Some(items) = &parameter.items {
+        schema["items"] = parameter_schema(items);
+    }
+    schema
+}
+
+fn parameters_object(parameters: &[ToolParameter]) -> serde_json::Value {
+    let properties: serde_json::Map<String, serde_json::Value> =
+        parameters.iter().map(|p| (p.name.clone(), parameter_schema(p))).collect();
+    let required: Vec<&str> = parameters.iter().filter(|p| p.required).map(|p| p.name.as_str()).collect();
+    json!({ "type": "object", "properties": properties, "required": required })
+}
+
+/// The tool's input schema with local `$ref`s inlined, always an object
+/// schema as every API requires.
+fn input_schema(tool: &McpTool) -> serde_json::Value {
+    if !tool.input_schema.is_object() {
+        return parameters_object(&tool.parameters);
+    }
+    let mut schema = resolve_local_refs(&tool.input_schema);
+    if let Some(map) = schema.as_object_mut() {
+        map.remove("$schema");
+        map.insert("type".to_string(), json!("object"));
+        map.entry("properties").or_insert_with(|| json!({}));
+    }
+    schema
+}
+
+/// Convert a JSON Schema to Gemini's subset: upper-case single types with
+/// `nullable`, `const` as a one-value enum, unsupported keywords dropped.
+fn gemini_schema(schema: &serde_json::Value) -> serde_json::Value {
+    let Some(map) = schema.as_object() else {
+        return schema.clone();
+    };
+    let mut out = serde_json::Map::new();
+    for (key, value) in map {
+        match key.as_str() {
+            "type" => {
+                let names: Vec<&str> = match value {
+                    serde_json::Value::Array(names) => names.iter().filter_map(|n| n.as_str()).collect(),
+                    other => other.as_str().into_iter().collect(),
+                };
+                if names.contains(&"null") {
+                    out.insert("nullable".to_string(), json!(true));
+                }
+                if let Some(name) = names.iter().find(|n| **n != "null") {
+                    out.insert("type".to_string(), json!(name.to_uppercase()));
+                }
+            }
+            "const" => {
+                out.insert("enum".to_string(), json!([value]));
+            }
+            "properties" => {
+                let properties: serde_json::Map<String, serde_json::Value> = value
+                    .as_object()
+                    .map(|p| p.iter().map(|(name, prop)| (name.clone(), gemini_schema(prop))).collect())
+                    .unwrap_or_default();
+                out.insert(key.clone(), serde_json::Value::Object(properties));
+            }
+            "items" => {
+                out.insert(key.clone(), gemini_schema(value));
+            }
+            "anyOf" | "oneOf" => {
+                let alternatives: Vec<serde_json::Value> = value
+                    .as_array()
+                    .map(|a| a.iter().map(gemini_schema).collect())
+                    .unwrap_or_default();
+                out.insert("anyOf".to_string(), json!(alternatives));
+            }
+            _ if GEMINI_SCHEMA_KEYS.contains(&key.as_str()) => {
+                out.insert(key.clone(), value.clone());
+            }
+            _ => {}
+        }
+    }
+    // Gemini enums are string-only
+    if out.get("enum").is_some_and(|e| e.as_array().is_some_and(|v| v.iter().any(|x| !x.is_string()))) {
+        out.remove("enum");
+    }
+    serde_json::Value::Object(out)
+}
+
+/// Every cached tool once per provider. Tools several servers provide keep
+/// their plain name on the server routing would pick; the others are
+/// exported under their qualified name.
+pub(crate) fn catalog_entries() -> Vec<CatalogEntry> {
+    let mut tools: Vec<(String, String, String, McpTool)> = {
+        let registry = SERVER_REGISTRY.lock().unwrap();
+        registry
+            .servers
+            .values()
+            .flat_map(|server| {
+                server.tools.iter().map(move |tool| {
+                    (server.url.clone(), server_alias(server), qualified_name(server, &tool.name), tool.clone())
+                })
+            })
+            .collect()
+    };
+    tools.sort_by(|a, b| a.3.name.cmp(&b.3.name).then(a.0.cmp(&b.0)));
+    tools
+        .into_iter()
+        .map(|(url, alias, qualified, tool)| {
+            let is_winner = resolve_route(&tool.name).is_ok_and(|route| route.url == url);
+            let description = tool.description.clone();
+            CatalogEntry {
+                original: if is_winner { tool.name.clone() } else { qualified },
+                description: if is_winner { description } else { format!("[{}] {}", alias, description).trim_end().to_string() },
+                schema: input_schema(&tool),
+                tool,
+            }
+        })
+        .collect()
+}
+
+/// Render the catalog for `format` and record the name mapping.
+pub(crate) fn export_catalog(format: CatalogFormat) -> serde_json::Value {
+    let mut names: HashMap<String, String> = HashMap::new();
+    let mut rendered = Vec::new();
+    for entry in catalog_entries() {
+        let name = unique_name(sanitize_name(&entry.original, format), &names);
+        names.insert(name.clone(), entry.original.clone());
+        rendered.push(match format {
+            CatalogFormat::OpenAi => json!({
+                "type": "function",
+                "function": { "name": name, "description": entry.description, "parameters": entry.schema }
+            }),
+            CatalogFormat::Anthropic => json!({
+                "name": name, "description": entry.description, "input_schema": entry.schema
+            }),
+            CatalogFormat::Gemini => {
+                let mut declaration = json!({ "name": name, "description": entry.description });
+                // Gemini rejects an OBJECT schema without properties
+                if entry.schema["properties"].as_object().is_some_and(|p| !p.is_empty()) {
+                    declaration["parameters"] = gemini_schema(&entry.schema);
+                }
+                declaration
+            }
+        });
+    }
+    let mut exported = EXPORTED_NAMES.lock().unwrap();
+    if exported.get(&format) != Some(&names) {
+        exported.insert(format, names);
+        persist(&exported);
+    }
+    drop(exported);
+    match format {
+        CatalogFormat::Gemini => json!({ "functionDeclarations": rendered }),
+        _ => json!(rendered),
+    }
+}
+
+fn persist(names: &HashMap<CatalogFormat, HashMap<String, String>>) {
+    PERSIST_HOOK.with(|hook| {
+        if let Some(hook) = hook.borrow().as_ref() {
+            let snapshot = serde_json::to_string(names).unwrap_or_default();
+            if let Err(e) = hook.call1(&JsValue::NULL, &JsValue::from_str(&snapshot)) {
+                debug(&format!("Persisting exported tool names failed: {:?}", e));
+            }
+        }
+    });
+}
+
+/// Original tool name for a name handed out in an exported catalog,
+/// checking every format when none is given.
+pub(crate) fn original_tool_name(format: Option<CatalogFormat>, exported: &str) -> Option<String> {
+    let names = EXPORTED_NAMES.lock().unwrap();
+    match format {
+        Some(format) => names.get(&format)?.get(exported).cloned(),
+        None => names.values().find_map(|map| map.get(exported).cloned()),
+    }
+}
+
+/// Render every cached tool as a function-calling catalog. `format` is
+/// `openai` (a `tools` array), `anthropic` (a `tools` array) or `gemini`
+/// (`{ functionDeclarations }`). Names are sanitised for the target API;
+/// use `resolve_exported_tool_name` to map a model's choice back.
+#[wasm_bindgen]
+pub fn export_tool_catalog(format: &str) -> Result<String, JsValue> {
+    let format = CatalogFormat::parse(format).map_err(|e| JsValue::from_str(&e))?;
+    Ok(export_catalog(format).to_string())
+}
+
+/// Map a function name from a model response back to the tool name
+/// `route_tool_call` accepts. Unknown names are returned unchanged.
+#[wasm_bindgen]
+pub fn resolve_exported_tool_name(format: Option<String>, name: &str) -> Result<String, JsValue> {
+    let format = format.as_deref().map(CatalogFormat::parse).transpose().map_err(|e| JsValue::from_str(&e))?;
+    Ok(original_tool_name(format, name).unwrap_or_else(|| name.to_string()))
+}
+
+/// The exported-to-original name mapping for `format` from the last export.
+#[wasm_bindgen]
+pub fn get_exported_tool_names(format: &str) -> Result<String, JsValue> {
+    let format = CatalogFormat::parse(format).map_err(|e| JsValue::from_str(&e))?;
+    let names = EXPORTED_NAMES.lock().unwrap().get(&format).cloned().unwrap_or_default();
+    Ok(serde_json::to_string(&names).unwrap_or_else(|_| "{}".to_string()))
+}
+
+/// Replace the exported name mappings with a snapshot saved by the
+/// persistence hook, keyed by format.
+#[wasm_bindgen]
+pub fn restore_exported_tool_names(names_json: &str) -> Result<(), JsValue> {
+    let names: HashMap<CatalogFormat, HashMap<String, String>> = serde_json::from_str(names_json)
+        .map_err(|e| JsValue::from_str(&format!("Invalid exported tool names: {}", e)))?;
+    *EXPORTED_NAMES.lock().unwrap() = names;
+    Ok(())
+}
+
+/// Register a function called with every format's name mapping as JSON
+/// whenever an export changes one; pass `null` to unregister.
+#[wasm_bindgen]
+pub fn set_catalog_persistence(hook: Option<js_sys::Function>) {
+    PERSIST_HOOK.with(|slot| *slot.borrow_mut() = hook);
+}
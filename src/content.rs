@@ -0,0 +1,239 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{self, json};
+use wasm_bindgen::prelude::*;
+
+use crate::debug;
+
+/// Who a content block is intended for, per the MCP `annotations.audience` field.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Role {
+    User,
+    Assistant,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Annotations {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub audience: Option<Vec<Role>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
+}
+
+impl Annotations {
+    fn excludes(&self, role: &Role) -> bool {
+        self.audience.as_ref().is_some_and(|audience| !audience.contains(role))
+    }
+}
+
+/// Contents of an embedded `resource` block: either text or base64 `blob`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(untagged)]
+pub(crate) enum ResourceContents {
+    Text {
+        uri: String,
+        #[serde(rename = "mimeType", skip_serializing_if = "Option::is_none")]
+        mime_type: Option<String>,
+        text: String,
+    },
+    Blob {
+        uri: String,
+        #[serde(rename = "mimeType", skip_serializing_if = "Option::is_none")]
+        mime_type: Option<String>,
+        blob: String,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(crate) enum ContentBlock {
+    Text {
+        text: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        annotations: Option<Annotations>,
+    },
+    Image {
+        data: String,
+        #[serde(rename = "mimeType")]
+        mime_type: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        annotations: Option<Annotations>,
+    },
+    Audio {
+        data: String,
+        #[serde(rename = "mimeType")]
+        mime_type: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        annotations: Option<Annotations>,
+    },
+    ResourceLink {
+        uri: String,
+        name: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        description: Option<String>,
+        #[serde(rename = "mimeType", skip_serializing_if = "Option::is_none")]
+        mime_type: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        size: Option<u64>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        annotations: Option<Annotations>,
+    },
+    Resource {
+        resource: ResourceContents,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        annotations: Option<Annotations>,
+    },
+}
+
+impl ContentBlock {
+    pub(crate) fn annotations(&self) -> Option<&Annotations> {
+        match self {
+            ContentBlock::Text { annotations, .. }
+            | ContentBlock::Image { annotations, .. }
+            | ContentBlock::Audio { annotations, .. }
+            | ContentBlock::ResourceLink { annotations, .. }
+            | ContentBlock::Resource { annotations, .. } => annotations.as_ref(),
+        }
+    }
+
+    /// Render the block as text suitable for the CBus. Binary payloads are
+    /// summarised rather than inlined.
+    pub(crate) fn to_text(&self) -> String {
+        match self {
+            ContentBlock::Text { text, .. } => text.clone(),
+            ContentBlock::Image { data, mime_type, .. } => {
+                format!("[image: {}, {} bytes]", mime_type, base64_decoded_len(data))
+            }
+            ContentBlock::Audio { data, mime_type, .. } => {
+                format!("[audio: {}, {} bytes]", mime_type, base64_decoded_len(data))
+            }
+            ContentBlock::ResourceLink { uri, name, description, .. } => match description {
+                Some(description) => format!("[resource: {}]({}) - {}", name, uri, description),
+                None => format!("[resource: {}]({})", name, uri),
+            },
+            ContentBlock::Resource { resource, .. } => match resource {
+                ResourceContents::Text { text, .. } => text.clone(),
+                ResourceContents::Blob { uri, mime_type, blob } => format!(
+                    "[resource: {} ({}, {} bytes)]",
+                    uri,
+                    mime_type.as_deref().unwrap_or("application/octet-stream"),
+                    base64_decoded_len(blob)
+                ),
+            },
+        }
+    }
+
+    /// Data URL for blocks that carry base64 payloads, for display in the UI.
+    pub(crate) fn to_data_url(&self) -> Option<String> {
+        match self {
+            ContentBlock::Image { data, mime_type, .. } | ContentBlock::Audio { data, mime_type, .. } => {
+                Some(format!("data:{};base64,{}", mime_type, data))
+            }
+            ContentBlock::Resource { resource: ResourceContents::Blob { mime_type, blob, .. }, .. } => Some(format!(
+                "data:{};base64,{}",
+                mime_type.as_deref().unwrap_or("application/octet-stream"),
+                blob
+            )),
+            _ => None,
+        }
+    }
+}
+
+/// Decoded `tools/call` result.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ToolResult {
+    pub content: Vec<ContentBlock>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub structured_content: Option<serde_json::Value>,
+    #[serde(default)]
+    pub is_error: bool,
+}
+
+impl ToolResult {
+    /// Decode a result object, skipping content blocks of unknown type
+    /// instead of rejecting the whole result.
+    pub(crate) fn from_value(value: &serde_json::Value) -> Self {
+        let content = value
+            .get("content")
+            .and_then(|v| v.as_array())
+            .map(|blocks| {
+                blocks
+                    .iter()
+                    .filter_map(|block| match serde_json::from_value::<ContentBlock>(block.clone()) {
+                        Ok(block) => Some(block),
+                        Err(e) => {
+                            debug(&format!("Skipping undecodable content block: {} ({})", block, e));
+                            None
+                        }
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        ToolResult {
+            content,
+            structured_content: value.get("structuredContent").cloned(),
+            is_error: value.get("isError").and_then(|v| v.as_bool()).unwrap_or(false),
+        }
+    }
+
+    /// Text for the LLM: blocks addressed only to the user are left out, and
+    /// `structuredContent` is used when the tool returned no content blocks.
+    pub(crate) fn to_cbus_text(&self) -> String {
+        let parts: Vec<String> = self
+            .content
+            .iter()
+            .filter(|block| !block.annotations().is_some_and(|a| a.excludes(&Role::Assistant)))
+            .map(|block| block.to_text())
+            .collect();
+        if parts.is_empty() {
+            return match &self.structured_content {
+                Some(structured) => structured.to_string(),
+                None => "[No content]".to_string(),
+            };
+        }
+        parts.join("\n")
+    }
+}
+
+fn base64_decoded_len(data: &str) -> usize {
+    let trimmed = data.trim_end_matches('=');
+    trimmed.len() * 3 / 4
+}
+
+/// Decode a tool result into typed content blocks, each annotated with its
+/// rendered `text` and, where applicable, a `dataUrl` for the UI.
+#[wasm_bindgen]
+pub fn decode_tool_result(result_json: &str) -> Result<JsValue, JsValue> {
+    let value: serde_json::Value = serde_json::from_str(result_json)
+        .map_err(|e| JsValue::from_str(&format!("Invalid tool result: {}", e)))?;
+    let result = ToolResult::from_value(&value);
+    let blocks: Vec<serde_json::Value> = result
+        .content
+        .iter()
+        .map(|block| {
+            json!({
+                "block": block,
+                "text": block.to_text(),
+                "dataUrl": block.to_data_url()
+            })
+        })
+        .collect();
+    Ok(JsValue::from_str(&json!({
+        "result": result,
+        "blocks": blocks,
+        "text": result.to_cbus_text()
+    }).to_string()))
+}
+
+/// Render a tool result as CBus text.
+#[wasm_bindgen]
+pub fn render_tool_result_text(result_json: &str) -> String {
+    match serde_json::from_str::<serde_json::Value>(result_json) {
+        Ok(value) => ToolResult::from_value(&value).to_cbus_text(),
+        Err(_) => "[Tool returned invalid JSON]".to_string(),
+    }
+}
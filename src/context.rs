@@ -0,0 +1,566 @@
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::sync::{LazyLock, Mutex};
+use wasm_bindgen::prelude::*;
+
+use crate::bootrom_builder::{compose_bootrom_with, render_tool_catalog};
+use crate::debug;
+
+/// Marker appended to text cut to fit the budget.
+const TRUNCATION_MARKER: &str = " …[truncated]";
+
+/// How token counts are estimated. No tokenizer ships in the module, so
+/// these are heuristics; `js` defers to a function registered with
+/// `set_context_tokenizer`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub(crate) enum Tokenizer {
+    /// A fixed number of characters per token.
+    CharsPerToken { chars: f64 },
+    /// A fixed number of tokens per whitespace-separated word.
+    Words { tokens_per_word: f64 },
+    /// Word pieces of up to four ASCII characters, plus one token per
+    /// punctuation mark and per non-ASCII character.
+    Mixed,
+    Js,
+}
+
+thread_local! {
+    // JS tokenizer for `Tokenizer::Js`; JS handles are not `Send`.
+    static JS_TOKENIZER: RefCell<Option<js_sys::Function>> = const { RefCell::new(None) };
+}
+
+fn mixed_estimate(text: &str) -> usize {
+    let mut tokens = 0;
+    let mut word_length = 0;
+    let flush = |word_length: &mut usize, tokens: &mut usize| {
+        *tokens += word_length.div_ceil(4);
+        *word_length = 0;
+    };
+    for c in text.chars() {
+        if c.is_ascii_alphanumeric() {
+            word_length += 1;
+            continue;
+        }
+        flush(&mut word_length, &mut tokens);
+        if !c.is_whitespace() {
+            tokens += 1;
+        }
+    }
+    flush(&mut word_length, &mut tokens);
+    tokens
+}
+
+impl Tokenizer {
+    pub(crate) fn estimate(&self, text: &str) -> usize {
+        match self {
+            Tokenizer::CharsPerToken { chars } => (text.chars().count() as f64 / chars.max(0.1)).ceil() as usize,
+            Tokenizer::Words { tokens_per_word } => {
+                (text.split_whitespace().count() as f64 * tokens_per_word).ceil() as usize
+            }
+            Tokenizer::Mixed => mixed_estimate(text),
+            Tokenizer::Js => {
+                let counted = JS_TOKENIZER.with(|tokenizer| {
+                    let tokenizer = tokenizer.borrow();
+                    let result = tokenizer.as_ref()?.call1(&JsValue::NULL, &JsValue::from_str(text));
+                    match result {
+                        Ok(count) => count.as_f64().map(|n| n.max(0.0) as usize),
+                        Err(e) => {
+                            debug(&format!("JS tokenizer failed: {:?}", e));
+                            None
+                        }
+                    }
+                });
+                counted.unwrap_or_else(|| mixed_estimate(text))
+            }
+        }
+    }
+}
+
+/// How conversation history is cut when it does not fit.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Truncation {
+    /// Drop the oldest messages first.
+    OldestFirst,
+    /// Keep the start and the end of the conversation, dropping from the
+    /// middle.
+    MiddleOut,
+    /// Drop the oldest messages and put a short digest of them in their
+    /// place.
+    Summarize,
+}
+
+/// Higher goes first when the budget is handed out.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub(crate) struct ContextPriorities {
+    pub bootrom: i32,
+    pub tool_catalog: i32,
+    /// The `keep_latest` newest messages.
+    pub latest: i32,
+    pub imprints: i32,
+    /// Every other message.
+    pub history: i32,
+}
+
+impl Default for ContextPriorities {
+    fn default() -> Self {
+        ContextPriorities { bootrom: 100, tool_catalog: 90, latest: 80, imprints: 50, history: 10 }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub(crate) struct ContextBudget {
+    pub max_tokens: usize,
+    /// Held back for the model's reply.
+    pub reserve_tokens: usize,
+    /// Added to every message for role markers and separators.
+    pub message_overhead: usize,
+    pub tokenizer: Tokenizer,
+    pub truncation: Truncation,
+    pub priorities: ContextPriorities,
+    /// Newest messages budgeted at `priorities.latest` instead of with the
+    /// rest of the history.
+    pub keep_latest: usize,
+    /// Cap on the digest `summarize` puts in place of dropped messages.
+    pub summary_max_tokens: usize,
+    /// Text is only cut when at least this many tokens of it would remain;
+    /// otherwise it is dropped.
+    pub min_truncated_tokens: usize,
+}
+
+impl Default for ContextBudget {
+    fn default() -> Self {
+        ContextBudget {
+            max_tokens: 8000,
+            reserve_tokens: 0,
+            message_overhead: 4,
+            tokenizer: Tokenizer::Mixed,
+            truncation: Truncation::OldestFirst,
+            priorities: ContextPriorities::default(),
+            keep_latest: 1,
+            summary_max_tokens: 256,
+            min_truncated_tokens: 32,
+        }
+    }
+}
+
+pub(crate) static CONTEXT_BUDGET: LazyLock<Mutex<ContextBudget>> = LazyLock::new(|| Mutex::new(ContextBudget::default()));
+
+/// A piece of text offered to the assembler.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct ContextItem {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<String>,
+    #[serde(default)]
+    pub text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+#[serde(default)]
+pub(crate) struct ContextInput {
+    pub bootrom: Option<String>,
+    /// Compose the bootrom from the registry when `bootrom` is not given.
+    /// The tool catalog is then budgeted as its own segment.
+    pub compose_bootrom: bool,
+    pub tool_catalog: Option<String>,
+    pub imprints: Vec<ContextItem>,
+    /// Conversation, oldest first.
+    pub messages: Vec<ContextItem>,
+    /// Overrides the configured budget for this call.
+    pub budget: Option<ContextBudget>,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ContextSource {
+    Bootrom,
+    ToolCatalog,
+    Imprint,
+    Message,
+    Summary,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub(crate) struct ContextMessage {
+    pub source: ContextSource,
+    pub role: String,
+    pub text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<serde_json::Value>,
+    pub tokens: usize,
+}
+
+/// Something left out or cut down, and why.
+#[derive(Debug, Serialize, Clone)]
+pub(crate) struct ContextOmission {
+    pub source: ContextSource,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<serde_json::Value>,
+    /// Position among the input messages or imprints.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index: Option<usize>,
+    pub tokens: usize,
+    /// Tokens kept when the text was truncated rather than dropped.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kept_tokens: Option<usize>,
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize, Default)]
+pub(crate) struct AssembledContext {
+    pub messages: Vec<ContextMessage>,
+    pub total_tokens: usize,
+    pub budget_tokens: usize,
+    pub dropped: Vec<ContextOmission>,
+    pub truncated: Vec<ContextOmission>,
+}
+
+/// A candidate for the context with its estimated size.
+#[derive(Clone)]
+struct Candidate {
+    source: ContextSource,
+    index: Option<usize>,
+    item: ContextItem,
+    tokens: usize,
+}
+
+impl Candidate {
+    fn into_message(self) -> ContextMessage {
+        let role = self.item.role.unwrap_or_else(|| {
+            match self.source {
+                ContextSource::Message => "user",
+                _ => "memory",
+            }
+            .to_string()
+        });
+        ContextMessage {
+            source: self.source,
+            role,
+            text: self.item.text,
+            id: self.item.id,
+            timestamp: self.item.timestamp,
+            tokens: self.tokens,
+        }
+    }
+
+    fn omission(&self, kept_tokens: Option<usize>, reason: &str) -> ContextOmission {
+        ContextOmission {
+            source: self.source,
+            id: self.item.id.clone(),
+            index: self.index,
+            tokens: self.tokens,
+            kept_tokens,
+            reason: reason.to_string(),
+        }
+    }
+}
+
+struct Assembler<'a> {
+    budget: &'a ContextBudget,
+    remaining: usize,
+    report: AssembledContext,
+}
+
+impl Assembler<'_> {
+    fn cost(&self, text: &str) -> usize {
+        self.budget.tokenizer.estimate(text) + self.budget.message_overhead
+    }
+
+    fn candidate(&self, source: ContextSource, index: Option<usize>, item: ContextItem) -> Candidate {
+        let tokens = self.cost(&item.text);
+        Candidate { source, index, item, tokens }
+    }
+
+    /// Longest prefix of `text` that, with the marker, costs at most `max`.
+    fn truncate(&self, text: &str, max: usize) -> Option<String> {
+        let boundaries: Vec<usize> = text.char_indices().map(|(i, _)| i).collect();
+        let fits = |chars: usize| {
+            let end = boundaries.get(chars).copied().unwrap_or(text.len());
+            self.cost(&format!("{}{}", &text[..end], TRUNCATION_MARKER)) <= max
+        };
+        let (mut low, mut high) = (0usize, boundaries.len());
+        while low < high {
+            let middle = (low + high).div_ceil(2);
+            if fits(middle) {
+                low = middle;
+            } else {
+                high = middle - 1;
+            }
+        }
+        (low > 0 && fits(low)).then(|| {
+            let end = boundaries.get(low).copied().unwrap_or(text.len());
+            format!("{}{}", text[..end].trim_end(), TRUNCATION_MARKER)
+        })
+    }
+
+    /// Take the candidate whole, cut it down to the remaining budget, or
+    /// drop it.
+    fn place(&mut self, candidate: Candidate) -> Option<Candidate> {
+        if candidate.tokens <= self.remaining {
+            self.remaining -= candidate.tokens;
+            return Some(candidate);
+        }
+        if self.remaining >= self.budget.min_truncated_tokens.max(self.budget.message_overhead + 1) {
+            if let Some(text) = self.truncate(&candidate.item.text, self.remaining) {
+                let kept = self.cost(&text);
+                self.report.truncated.push(candidate.omission(Some(kept), "over budget"));
+                self.remaining -= kept;
+                let mut item = candidate.item;
+                item.text = text;
+                return Some(Candidate { item, tokens: kept, ..candidate });
+            }
+        }
+        self.report.dropped.push(candidate.omission(None, "over budget"));
+        None
+    }
+
+    /// Keep whole messages from the end of `history` while they fit.
+    fn keep_newest(&mut self, history: &[Candidate]) -> usize {
+        let mut kept = 0;
+        for candidate in history.iter().rev() {
+            if candidate.tokens > self.remaining {
+                break;
+            }
+            self.remaining -= candidate.tokens;
+            kept += 1;
+        }
+        kept
+    }
+
+    /// Budget the history with the configured strategy. Returns the kept
+    /// messages, with a digest in place of dropped ones for `summarize`.
+    fn fit_history(&mut self, history: Vec<Candidate>) -> Vec<Candidate> {
+        match self.budget.truncation {
+            Truncation::OldestFirst => {
+                let kept = self.keep_newest(&history);
+                let split = history.len() - kept;
+                for candidate in &history[..split] {
+                    self.report.dropped.push(candidate.omission(None, "oldest first"));
+                }
+                history[split..].to_vec()
+            }
+            Truncation::MiddleOut => {
+                // Alternate newest and oldest so both ends of the
+                // conversation survive; a side stops at its first misfit.
+                let (mut front, mut back) = (0usize, history.len());
+                let (mut front_open, mut back_open) = (true, true);
+                let mut take_back = true;
+                while front < back && (front_open || back_open) {
+                    if take_back && back_open {
+                        if history[back - 1].tokens <= self.remaining {
+                            self.remaining -= history[back - 1].tokens;
+                            back -= 1;
+                        } else {
+                            back_open = false;
+                        }
+                    } else if !take_back && front_open {
+                        if history[front].tokens <= self.remaining {
+                            self.remaining -= history[front].tokens;
+                            front += 1;
+                        } else {
+                            front_open = false;
+                        }
+                    }
+                    take_back = !take_back;
+                }
+                for candidate in &history[front..back] {
+                    self.report.dropped.push(candidate.omission(None, "middle out"));
+                }
+                history[..front].iter().chain(&history[back..]).cloned().collect()
+            }
+            Truncation::Summarize => {
+                // The digest may take at most half of what history has left
+                let reserved = self.budget.summary_max_tokens.min(self.remaining / 2);
+                self.remaining -= reserved;
+                let kept = self.keep_newest(&history);
+                self.remaining += reserved;
+                let split = history.len() - kept;
+                let mut result = Vec::new();
+                if split > 0 {
+                    for candidate in &history[..split] {
+                        self.report.dropped.push(candidate.omission(None, "summarized"));
+                    }
+                    let summary = self.summarize(&history[..split], reserved);
+                    if let Some(summary) = summary {
+                        self.remaining -= summary.tokens;
+                        result.push(summary);
+                    }
+                }
+                result.extend(history[split..].iter().cloned());
+                result
+            }
+        }
+    }
+
+    /// Digest of dropped messages: one line per message with its role and
+    /// opening words, as many as fit in `max` tokens.
+    fn summarize(&self, dropped: &[Candidate], max: usize) -> Option<Candidate> {
+        let header = format!("[Summary of {} earlier message(s)]", dropped.len());
+        let mut text = header.clone();
+        for (shown, candidate) in dropped.iter().enumerate() {
+            let opening: String = candidate.item.text.split_whitespace().collect::<Vec<_>>().join(" ");
+            let opening: String = opening.chars().take(100).collect();
+            let ellipsis = if candidate.item.text.chars().count() > opening.chars().count() { "…" } else { "" };
+            let role = candidate.item.role.as_deref().unwrap_or("user");
+            let line = format!("\n- {}: {}{}", role, opening, ellipsis);
+            let rest = dropped.len() - shown - 1;
+            let tail = if rest > 0 { format!("\n- … and {} more", rest) } else { String::new() };
+            if self.cost(&format!("{}{}{}", text, line, tail)) > max {
+                text.push_str(&format!("\n- … and {} more", dropped.len() - shown));
+                break;
+            }
+            text.push_str(&line);
+        }
+        let tokens = self.cost(&text);
+        (tokens <= max).then(|| Candidate {
+            source: ContextSource::Summary,
+            index: None,
+            item: ContextItem { id: None, role: Some("memory".to_string()), text, timestamp: None },
+            tokens,
+        })
+    }
+}
+
+/// Which part of the context a budgeting step covers.
+enum Segment {
+    Bootrom,
+    ToolCatalog,
+    Imprints,
+    Latest,
+    History,
+}
+
+/// Fit bootrom, tool catalog, imprints and conversation into the budget.
+/// Segments claim budget in priority order; the result is laid out as
+/// bootrom, catalog, imprints, then the conversation oldest first.
+pub(crate) fn assemble(input: ContextInput, budget: &ContextBudget) -> AssembledContext {
+    let budget_tokens = budget.max_tokens.saturating_sub(budget.reserve_tokens);
+    let mut assembler = Assembler {
+        budget,
+        remaining: budget_tokens,
+        report: AssembledContext { budget_tokens, ..Default::default() },
+    };
+
+    let (bootrom, tool_catalog) = match (input.bootrom, input.compose_bootrom) {
+        (Some(bootrom), _) => (Some(bootrom), input.tool_catalog),
+        (None, true) => (
+            Some(compose_bootrom_with(false)),
+            input.tool_catalog.or_else(|| Some(render_tool_catalog())),
+        ),
+        (None, false) => (None, input.tool_catalog),
+    };
+    let text_item = |text: String| ContextItem { id: None, role: None, text, timestamp: None };
+    let bootrom = bootrom
+        .filter(|t| !t.trim().is_empty())
+        .map(|t| assembler.candidate(ContextSource::Bootrom, None, text_item(t)));
+    let tool_catalog = tool_catalog
+        .filter(|t| !t.trim().is_empty())
+        .map(|t| assembler.candidate(ContextSource::ToolCatalog, None, text_item(t)));
+    let imprints: Vec<Candidate> = input
+        .imprints
+        .into_iter()
+        .enumerate()
+        .filter(|(_, item)| !item.text.trim().is_empty())
+        .map(|(index, item)| assembler.candidate(ContextSource::Imprint, Some(index), item))
+        .collect();
+    let mut history: Vec<Candidate> = input
+        .messages
+        .into_iter()
+        .enumerate()
+        .map(|(index, item)| assembler.candidate(ContextSource::Message, Some(index), item))
+        .collect();
+    let latest = history.split_off(history.len().saturating_sub(budget.keep_latest));
+
+    let priorities = &budget.priorities;
+    let mut order = [
+        (priorities.bootrom, Segment::Bootrom),
+        (priorities.tool_catalog, Segment::ToolCatalog),
+        (priorities.latest, Segment::Latest),
+        (priorities.imprints, Segment::Imprints),
+        (priorities.history, Segment::History),
+    ];
+    order.sort_by_key(|(priority, _)| std::cmp::Reverse(*priority));
+
+    let (mut kept_bootrom, mut kept_catalog) = (None, None);
+    let (mut kept_imprints, mut kept_latest, mut kept_history) = (Vec::new(), Vec::new(), Vec::new());
+    let (mut imprints, mut latest, mut history) = (Some(imprints), Some(latest), Some(history));
+    let (mut bootrom, mut tool_catalog) = (bootrom, tool_catalog);
+    for (_, segment) in order {
+        match segment {
+            Segment::Bootrom => kept_bootrom = bootrom.take().and_then(|c| assembler.place(c)),
+            Segment::ToolCatalog => kept_catalog = tool_catalog.take().and_then(|c| assembler.place(c)),
+            Segment::Imprints => {
+                kept_imprints = imprints.take().unwrap_or_default().into_iter().filter_map(|c| assembler.place(c)).collect();
+            }
+            Segment::Latest => {
+                // Newest first, so the latest message is the last to go
+                let mut placed: Vec<Candidate> =
+                    latest.take().unwrap_or_default().into_iter().rev().filter_map(|c| assembler.place(c)).collect();
+                placed.reverse();
+                kept_latest = placed;
+            }
+            Segment::History => kept_history = assembler.fit_history(history.take().unwrap_or_default()),
+        }
+    }
+
+    let messages: Vec<ContextMessage> = kept_bootrom
+        .into_iter()
+        .chain(kept_catalog)
+        .chain(kept_imprints)
+        .chain(kept_history)
+        .chain(kept_latest)
+        .map(Candidate::into_message)
+        .collect();
+    let mut report = assembler.report;
+    report.total_tokens = messages.iter().map(|m| m.tokens).sum();
+    report.messages = messages;
+    report
+}
+
+/// Assemble a context within the token budget. Takes
+/// `{ bootrom?, compose_bootrom?, tool_catalog?, imprints, messages, budget? }`
+/// and returns `{ messages, total_tokens, budget_tokens, dropped, truncated }`.
+#[wasm_bindgen]
+pub fn assemble_context(input_json: &str) -> Result<String, JsValue> {
+    let mut input: ContextInput = serde_json::from_str(input_json)
+        .map_err(|e| JsValue::from_str(&format!("Invalid context input: {}", e)))?;
+    let budget = input.budget.take().unwrap_or_else(|| CONTEXT_BUDGET.lock().unwrap().clone());
+    Ok(serde_json::to_string(&assemble(input, &budget)).unwrap_or_else(|_| "{}".to_string()))
+}
+
+/// Set the default budget used when `assemble_context` is given none.
+#[wasm_bindgen]
+pub fn set_context_budget(budget_json: &str) -> Result<(), JsValue> {
+    let budget: ContextBudget = serde_json::from_str(budget_json)
+        .map_err(|e| JsValue::from_str(&format!("Invalid context budget: {}", e)))?;
+    *CONTEXT_BUDGET.lock().unwrap() = budget;
+    Ok(())
+}
+
+#[wasm_bindgen]
+pub fn get_context_budget() -> String {
+    serde_json::to_string(&*CONTEXT_BUDGET.lock().unwrap()).unwrap_or_else(|_| "{}".to_string())
+}
+
+/// Register the function `{"kind": "js"}` tokenizers call. It receives the
+/// text and returns a token count; pass `null` to unregister.
+#[wasm_bindgen]
+pub fn set_context_tokenizer(tokenizer: Option<js_sys::Function>) {
+    JS_TOKENIZER.with(|slot| *slot.borrow_mut() = tokenizer);
+}
+
+/// Token estimate for `text` with the configured tokenizer.
+#[wasm_bindgen]
+pub fn estimate_tokens(text: &str) -> usize {
+    let tokenizer = CONTEXT_BUDGET.lock().unwrap().tokenizer.clone();
+    tokenizer.estimate(text)
+}
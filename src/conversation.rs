@@ -0,0 +1,698 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{self, json};
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::{LazyLock, Mutex};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{
+    IdbCursor, IdbCursorDirection, IdbCursorWithValue, IdbDatabase, IdbFactory, IdbKeyRange, IdbObjectStoreParameters,
+    IdbOpenDbRequest, IdbRequest, IdbTransaction, IdbTransactionMode, IdbVersionChangeEvent,
+};
+
+use crate::{pipeline, search};
+use crate::{debug, error, get_timestamp, info};
+
+const DB_NAME: &str = "chat_contexts";
+const CONVERSATIONS_STORE: &str = "conversations";
+const MESSAGES_STORE: &str = "messages";
+/// Worker state that must survive restarts, keyed by name.
+const STATE_STORE: &str = "state";
+/// Messages of an engram ordered by `[engramId, timestamp, id]`.
+const TIMELINE_INDEX: &str = "timeline";
+
+/// Tool details carried by `tool` messages.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ToolMetadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub structured_content: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_validation: Option<serde_json::Value>,
+}
+
+/// A message of an engram's conversation, as stored.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct EngramMessage {
+    /// UUIDv7, assigned on append when empty.
+    #[serde(default)]
+    pub id: String,
+    pub engram_id: String,
+    #[serde(default = "default_role")]
+    pub role: String,
+    #[serde(default)]
+    pub text: String,
+    /// Milliseconds since the epoch, assigned on append when zero.
+    #[serde(default)]
+    pub timestamp: u64,
+    #[serde(flatten)]
+    pub tool: ToolMetadata,
+}
+
+fn default_role() -> String {
+    "user".to_string()
+}
+
+impl EngramMessage {
+    /// Size counted against `max_bytes`: the message as JSON.
+    fn size(&self) -> usize {
+        serde_json::to_string(self).map(|json| json.len()).unwrap_or(0)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct ConversationMeta {
+    engram_id: String,
+    created: u64,
+    #[serde(default)]
+    updated: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ConversationRecord {
+    engram_id: String,
+    meta: ConversationMeta,
+}
+
+/// Limits applied oldest message first. Unset limits do not apply.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub(crate) struct PrunePolicy {
+    pub max_messages: Option<usize>,
+    pub max_age_ms: Option<u64>,
+    /// Total size of the messages as JSON.
+    pub max_bytes: Option<usize>,
+}
+
+impl PrunePolicy {
+    fn is_empty(&self) -> bool {
+        self.max_messages.is_none() && self.max_age_ms.is_none() && self.max_bytes.is_none()
+    }
+
+    /// Whether the newest `count` messages, `bytes` in total and the oldest
+    /// of them sent at `timestamp`, fit every limit.
+    fn admits(&self, count: usize, bytes: usize, timestamp: u64, now: u64) -> bool {
+        self.max_messages.is_none_or(|max| count <= max)
+            && self.max_bytes.is_none_or(|max| bytes <= max)
+            && self.max_age_ms.is_none_or(|age| timestamp >= now.saturating_sub(age))
+    }
+}
+
+/// Applied after every append.
+static PRUNE_POLICY: LazyLock<Mutex<PrunePolicy>> = LazyLock::new(|| Mutex::new(PrunePolicy::default()));
+
+/// Last UUIDv7 timestamp and the 12-bit counter drawn within it.
+static UUID_CLOCK: Mutex<(u64, u16)> = Mutex::new((0, 0));
+
+fn random_bytes<const N: usize>() -> [u8; N] {
+    let mut bytes = [0u8; N];
+    let array = js_sys::Uint8Array::new_with_length(N as u32);
+    let crypto = js_sys::Reflect::get(&js_sys::global(), &"crypto".into()).ok();
+    let filled = crypto
+        .and_then(|crypto| {
+            let get_random_values = js_sys::Reflect::get(&crypto, &"getRandomValues".into()).ok()?;
+            get_random_values.dyn_into::<js_sys::Function>().ok()?.call1(&crypto, &array).ok()
+        })
+        .is_some();
+    if filled {
+        array.copy_to(&mut bytes);
+    } else {
+        bytes.iter_mut().for_each(|byte| *byte = (js_sys::Math::random() * 256.0) as u8);
+    }
+    bytes
+}
+
+/// Lay out an RFC 9562 UUIDv7: 48 bits of Unix milliseconds, version 7,
+/// 12 bits of `rand_a`, variant `10` and 62 bits of `rand_b`.
+fn format_uuid_v7(unix_ms: u64, rand_a: u16, rand_b: [u8; 8]) -> String {
+    let mut bytes = [0u8; 16];
+    bytes[..6].copy_from_slice(&unix_ms.to_be_bytes()[2..]);
+    bytes[6] = 0x70 | ((rand_a >> 8) & 0x0f) as u8;
+    bytes[7] = rand_a as u8;
+    bytes[8..].copy_from_slice(&rand_b);
+    bytes[8] = 0x80 | (bytes[8] & 0x3f);
+    let hex: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..])
+}
+
+/// Next clock reading for a UUIDv7 minted at `now`: `rand_a` starts at a
+/// random value each millisecond and counts up within it (RFC 9562 method 1),
+/// borrowing the next millisecond when it runs out, so ids minted here sort
+/// in creation order.
+fn next_uuid_clock(last: (u64, u16), now: u64, seed: u16) -> (u64, u16) {
+    let (last_ms, counter) = last;
+    if now > last_ms {
+        // Keep headroom for the counter
+        return (now, seed & 0x07ff);
+    }
+    if counter < 0x0fff {
+        (last_ms, counter + 1)
+    } else {
+        (last_ms + 1, seed & 0x07ff)
+    }
+}
+
+/// A new RFC 9562 UUIDv7; ids minted in this worker are strictly
+/// increasing.
+#[wasm_bindgen]
+pub fn uuid_v7() -> String {
+    let random: [u8; 10] = random_bytes();
+    let (unix_ms, rand_a) = {
+        let mut clock = UUID_CLOCK.lock().unwrap();
+        *clock = next_uuid_clock(*clock, get_timestamp(), u16::from_be_bytes([random[0], random[1]]));
+        *clock
+    };
+    let mut rand_b = [0u8; 8];
+    rand_b.copy_from_slice(&random[2..]);
+    format_uuid_v7(unix_ms, rand_a, rand_b)
+}
+
+/// Milliseconds encoded in a UUIDv7, for records stored without a
+/// timestamp.
+fn uuid_v7_timestamp(id: &str) -> Option<u64> {
+    let hex: String = id.chars().filter(|c| *c != '-').take(12).collect();
+    (hex.len() == 12 && id.as_bytes().get(14) == Some(&b'7')).then(|| u64::from_str_radix(&hex, 16).ok()).flatten()
+}
+
+/// Resolve with a request's result once it succeeds.
+async fn wait(request: &IdbRequest) -> Result<JsValue, JsValue> {
+    let mut settle = None;
+    let promise = js_sys::Promise::new(&mut |resolve, reject| settle = Some((resolve, reject)));
+    let (resolve, reject) = settle.expect("promise executor runs synchronously");
+    let succeeded = request.clone();
+    let on_success = Closure::<dyn FnMut(JsValue)>::new(move |_| {
+        let _ = resolve.call1(&JsValue::NULL, &succeeded.result().unwrap_or(JsValue::UNDEFINED));
+    });
+    let failed = request.clone();
+    let on_error = Closure::<dyn FnMut(JsValue)>::new(move |_| {
+        let error = failed.error().ok().flatten().map(JsValue::from).unwrap_or(JsValue::UNDEFINED);
+        let _ = reject.call1(&JsValue::NULL, &error);
+    });
+    request.set_onsuccess(Some(on_success.as_ref().unchecked_ref()));
+    request.set_onerror(Some(on_error.as_ref().unchecked_ref()));
+    let result = JsFuture::from(promise).await;
+    request.set_onsuccess(None);
+    request.set_onerror(None);
+    result
+}
+
+/// Resolve once a transaction commits; reject when it fails or aborts.
+async fn committed(transaction: &IdbTransaction) -> Result<(), JsValue> {
+    let mut settle = None;
+    let promise = js_sys::Promise::new(&mut |resolve, reject| settle = Some((resolve, reject)));
+    let (resolve, reject) = settle.expect("promise executor runs synchronously");
+    let on_complete = Closure::<dyn FnMut(JsValue)>::new(move |_| {
+        let _ = resolve.call0(&JsValue::NULL);
+    });
+    let failed = transaction.clone();
+    let on_error = Closure::<dyn FnMut(JsValue)>::new(move |_| {
+        let error = failed.error().map(JsValue::from).unwrap_or_else(|| JsValue::from_str("Transaction aborted"));
+        let _ = reject.call1(&JsValue::NULL, &error);
+    });
+    transaction.set_oncomplete(Some(on_complete.as_ref().unchecked_ref()));
+    transaction.set_onerror(Some(on_error.as_ref().unchecked_ref()));
+    transaction.set_onabort(Some(on_error.as_ref().unchecked_ref()));
+    JsFuture::from(promise).await.map(|_| ())
+}
+
+/// Handlers a migration registers; they must outlive the upgrade
+/// transaction, so the open request keeps them.
+type UpgradeHandlers = Rc<RefCell<Vec<Closure<dyn FnMut(JsValue)>>>>;
+
+struct Upgrade<'a> {
+    db: &'a IdbDatabase,
+    transaction: &'a IdbTransaction,
+    handlers: &'a UpgradeHandlers,
+}
+
+type Migration = fn(&Upgrade) -> Result<(), JsValue>;
+
+/// Schema steps; the database version is how many have run. The first two
+/// match what the service worker created before the store moved here.
+const MIGRATIONS: [Migration; 3] = [create_conversation_stores, create_state_store, add_timeline_index];
+
+fn create_conversation_stores(upgrade: &Upgrade) -> Result<(), JsValue> {
+    let names = upgrade.db.object_store_names();
+    if !names.contains(CONVERSATIONS_STORE) {
+        let parameters = IdbObjectStoreParameters::new();
+        parameters.set_key_path(&"engramId".into());
+        upgrade.db.create_object_store_with_optional_parameters(CONVERSATIONS_STORE, &parameters)?;
+    }
+    if !names.contains(MESSAGES_STORE) {
+        let parameters = IdbObjectStoreParameters::new();
+        parameters.set_key_path(&"id".into());
+        let messages = upgrade.db.create_object_store_with_optional_parameters(MESSAGES_STORE, &parameters)?;
+        messages.create_index_with_str("engramId", "engramId")?;
+    }
+    Ok(())
+}
+
+fn create_state_store(upgrade: &Upgrade) -> Result<(), JsValue> {
+    if !upgrade.db.object_store_names().contains(STATE_STORE) {
+        upgrade.db.create_object_store(STATE_STORE)?;
+    }
+    Ok(())
+}
+
+/// Index messages by time within an engram for range and paged loads.
+/// Records without a numeric timestamp would be left out of it, so they get
+/// the one in their UUIDv7 id, or zero.
+fn add_timeline_index(upgrade: &Upgrade) -> Result<(), JsValue> {
+    let messages = upgrade.transaction.object_store(MESSAGES_STORE)?;
+    let key_path = js_sys::Array::of3(&"engramId".into(), &"timestamp".into(), &"id".into());
+    messages.create_index_with_str_sequence(TIMELINE_INDEX, &key_path)?;
+    let request = messages.open_cursor()?;
+    let cursor_request = request.clone();
+    let backfill = Closure::<dyn FnMut(JsValue)>::new(move |_| {
+        let Some(cursor) = cursor_request.result().ok().and_then(|c| c.dyn_into::<IdbCursorWithValue>().ok()) else {
+            return;
+        };
+        if let Ok(record) = cursor.value() {
+            let timestamp = js_sys::Reflect::get(&record, &"timestamp".into()).unwrap_or(JsValue::UNDEFINED);
+            if timestamp.as_f64().is_none() {
+                let id = js_sys::Reflect::get(&record, &"id".into()).ok().and_then(|id| id.as_string()).unwrap_or_default();
+                let timestamp = uuid_v7_timestamp(&id).unwrap_or(0);
+                let _ = js_sys::Reflect::set(&record, &"timestamp".into(), &JsValue::from(timestamp as f64));
+                let _ = cursor.update(&record);
+            }
+        }
+        let _ = cursor.continue_();
+    });
+    request.set_onsuccess(Some(backfill.as_ref().unchecked_ref()));
+    upgrade.handlers.borrow_mut().push(backfill);
+    Ok(())
+}
+
+fn run_migrations(request: &IdbOpenDbRequest, old_version: u32, handlers: &UpgradeHandlers) -> Result<(), JsValue> {
+    let db: IdbDatabase = request.result()?.dyn_into()?;
+    let transaction = request.transaction().ok_or_else(|| JsValue::from_str("No upgrade transaction"))?;
+    let upgrade = Upgrade { db: &db, transaction: &transaction, handlers };
+    for (step, migrate) in MIGRATIONS.iter().enumerate().skip(old_version as usize) {
+        migrate(&upgrade)?;
+        debug(&format!("Conversation store migrated to version {}", step + 1));
+    }
+    Ok(())
+}
+
+thread_local! {
+    static DATABASE: RefCell<Option<IdbDatabase>> = const { RefCell::new(None) };
+}
+
+/// The open database, opening and migrating it on first use.
+async fn database() -> Result<IdbDatabase, JsValue> {
+    if let Some(db) = DATABASE.with(|slot| slot.borrow().clone()) {
+        return Ok(db);
+    }
+    let factory: IdbFactory = js_sys::Reflect::get(&js_sys::global(), &"indexedDB".into())?
+        .dyn_into()
+        .map_err(|_| JsValue::from_str("IndexedDB is not available"))?;
+    let request = factory.open_with_u32(DB_NAME, MIGRATIONS.len() as u32)?;
+    let handlers: UpgradeHandlers = Rc::default();
+    let upgrading = request.clone();
+    let migration_handlers = handlers.clone();
+    let on_upgrade = Closure::<dyn FnMut(IdbVersionChangeEvent)>::new(move |event: IdbVersionChangeEvent| {
+        if let Err(e) = run_migrations(&upgrading, event.old_version() as u32, &migration_handlers) {
+            error(&format!("Conversation store migration failed: {:?}", e));
+            if let Some(transaction) = upgrading.transaction() {
+                let _ = transaction.abort();
+            }
+        }
+    });
+    request.set_onupgradeneeded(Some(on_upgrade.as_ref().unchecked_ref()));
+    let opened = wait(&request).await;
+    request.set_onupgradeneeded(None);
+    let db: IdbDatabase = opened?.dyn_into()?;
+    // Let a newer worker upgrade the schema; the next call reopens
+    let on_version_change = Closure::once_into_js(|| {
+        if let Some(db) = DATABASE.with(|slot| slot.borrow_mut().take()) {
+            db.close();
+        }
+    });
+    db.set_onversionchange(Some(on_version_change.unchecked_ref()));
+    DATABASE.with(|slot| *slot.borrow_mut() = Some(db.clone()));
+    Ok(db)
+}
+
+fn transaction(db: &IdbDatabase, stores: &[&str], mode: IdbTransactionMode) -> Result<IdbTransaction, JsValue> {
+    let names: js_sys::Array = stores.iter().map(|store| JsValue::from_str(store)).collect();
+    db.transaction_with_str_sequence_and_mode(&names, mode)
+}
+
+fn to_js<T: Serialize>(value: &T) -> Result<JsValue, JsValue> {
+    value
+        .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+        .map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// Messages from stored records, skipping any that no longer parse.
+fn messages_from(records: impl IntoIterator<Item = JsValue>) -> Vec<EngramMessage> {
+    records
+        .into_iter()
+        .filter_map(|record| match serde_wasm_bindgen::from_value(record) {
+            Ok(message) => Some(message),
+            Err(e) => {
+                debug(&format!("Skipping unreadable stored message: {}", e));
+                None
+            }
+        })
+        .collect()
+}
+
+/// Key range over an engram's timeline from `since` (inclusive) to `until`
+/// (exclusive).
+fn timeline_range(engram_id: &str, since: Option<u64>, until: Option<u64>) -> Result<IdbKeyRange, JsValue> {
+    let bound = |time: Option<u64>, open_end: f64| {
+        js_sys::Array::of2(&engram_id.into(), &time.map_or(open_end, |time| time as f64).into())
+    };
+    IdbKeyRange::bound(&bound(since, f64::NEG_INFINITY), &bound(until, f64::INFINITY))
+}
+
+/// Drop an engram's oldest messages until the rest fit `policy`. A cursor
+/// walks the timeline newest first, keeping messages while they fit and
+/// deleting every one from the first that does not. Only a byte limit
+/// needs the stored values; otherwise the index keys suffice.
+async fn prune(db: &IdbDatabase, engram_id: &str, policy: &PrunePolicy) -> Result<usize, JsValue> {
+    if policy.is_empty() {
+        return Ok(0);
+    }
+    let transaction = transaction(db, &[MESSAGES_STORE], IdbTransactionMode::Readwrite)?;
+    let messages = transaction.object_store(MESSAGES_STORE)?;
+    let index = messages.index(TIMELINE_INDEX)?;
+    let range = timeline_range(engram_id, None, None)?;
+    let request = match policy.max_bytes {
+        Some(_) => index.open_cursor_with_range_and_direction(&range, IdbCursorDirection::Prev)?,
+        None => index.open_key_cursor_with_range_and_direction(&range, IdbCursorDirection::Prev)?,
+    };
+    let now = get_timestamp();
+    let (mut count, mut bytes, mut keeping) = (0, 0, true);
+    let mut dropped = Vec::new();
+    while let Ok(cursor) = wait(&request).await?.dyn_into::<IdbCursor>() {
+        let id = cursor.primary_key()?;
+        if keeping {
+            // Timeline keys are `[engramId, timestamp, id]`
+            let timestamp = js_sys::Reflect::get_u32(&cursor.key()?, 1)?.as_f64().unwrap_or(0.0) as u64;
+            let size = match cursor.dyn_ref::<IdbCursorWithValue>() {
+                Some(cursor) => messages_from([cursor.value()?]).first().map_or(0, EngramMessage::size),
+                None => 0,
+            };
+            count += 1;
+            bytes += size;
+            keeping = policy.admits(count, bytes, timestamp, now);
+        }
+        if !keeping {
+            messages.delete(&id)?;
+            dropped.extend(id.as_string());
+        }
+        cursor.continue_()?;
+    }
+    committed(&transaction).await?;
+    dropped.iter().for_each(|id| search::remove_message(id));
+    if !dropped.is_empty() {
+        info(&format!("Pruned {} message(s) of {}", dropped.len(), engram_id));
+    }
+    Ok(dropped.len())
+}
+
+async fn append(mut message: EngramMessage) -> Result<EngramMessage, JsValue> {
+    if message.engram_id.is_empty() {
+        return Err(JsValue::from_str("Message has no engramId"));
+    }
+    if message.id.is_empty() {
+        message.id = uuid_v7();
+    }
+    if message.timestamp == 0 {
+        message.timestamp = get_timestamp();
+    }
+    let db = database().await?;
+    let transaction = transaction(&db, &[CONVERSATIONS_STORE, MESSAGES_STORE], IdbTransactionMode::Readwrite)?;
+    let conversations = transaction.object_store(CONVERSATIONS_STORE)?;
+    let existing = wait(&conversations.get(&JsValue::from_str(&message.engram_id))?).await?;
+    let mut record = serde_wasm_bindgen::from_value::<ConversationRecord>(existing).unwrap_or_else(|_| ConversationRecord {
+        engram_id: message.engram_id.clone(),
+        meta: ConversationMeta { engram_id: message.engram_id.clone(), created: message.timestamp, updated: 0 },
+    });
+    record.meta.updated = record.meta.updated.max(message.timestamp);
+    conversations.put(&to_js(&record)?)?;
+    transaction.object_store(MESSAGES_STORE)?.put(&to_js(&message)?)?;
+    committed(&transaction).await?;
+    search::index_engram_message(&message);
+    // The message is stored; a failed prune is retried on the next append
+    let policy = PRUNE_POLICY.lock().unwrap().clone();
+    if let Err(e) = prune(&db, &message.engram_id, &policy).await {
+        error(&format!("Pruning {} failed: {:?}", message.engram_id, e));
+    }
+    Ok(message)
+}
+
+/// Which messages `load_engram_messages` returns.
+#[derive(Debug, Deserialize, Default)]
+#[serde(default)]
+struct LoadOptions {
+    /// Inclusive lower bound on `timestamp`.
+    since: Option<u64>,
+    /// Exclusive upper bound on `timestamp`.
+    until: Option<u64>,
+    /// Page size; all matching messages when unset.
+    limit: Option<usize>,
+    /// The `next` of the previous page.
+    after: Option<PageCursor>,
+    newest_first: bool,
+}
+
+/// Position of the last message on a page.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct PageCursor {
+    timestamp: u64,
+    id: String,
+}
+
+async fn load(engram_id: &str, options: LoadOptions) -> Result<serde_json::Value, JsValue> {
+    let db = database().await?;
+    let transaction = transaction(&db, &[MESSAGES_STORE], IdbTransactionMode::Readonly)?;
+    let index = transaction.object_store(MESSAGES_STORE)?.index(TIMELINE_INDEX)?;
+    let range = match &options.after {
+        Some(after) => {
+            let position = js_sys::Array::of3(&engram_id.into(), &(after.timestamp as f64).into(), &after.id.as_str().into());
+            let range = timeline_range(engram_id, options.since, options.until)?;
+            if options.newest_first {
+                IdbKeyRange::bound_with_lower_open_and_upper_open(&range.lower()?, &position, false, true)?
+            } else {
+                IdbKeyRange::bound_with_lower_open_and_upper_open(&position, &range.upper()?, true, false)?
+            }
+        }
+        None => timeline_range(engram_id, options.since, options.until)?,
+    };
+    let direction = if options.newest_first { IdbCursorDirection::Prev } else { IdbCursorDirection::Next };
+    let request = index.open_cursor_with_range_and_direction(&range, direction)?;
+    let limit = options.limit.unwrap_or(usize::MAX);
+    let mut records = Vec::new();
+    let mut more = false;
+    // One past the page tells whether another follows
+    while let Ok(cursor) = wait(&request).await?.dyn_into::<IdbCursorWithValue>() {
+        if records.len() == limit {
+            more = true;
+            break;
+        }
+        records.push(cursor.value()?);
+        cursor.continue_()?;
+    }
+    let messages = messages_from(records);
+    let next = more
+        .then(|| messages.last())
+        .flatten()
+        .map(|last| PageCursor { timestamp: last.timestamp, id: last.id.clone() });
+    Ok(json!({ "engramId": engram_id, "messages": messages, "next": next }))
+}
+
+/// Open the store, migrating its schema; resolves to the schema version.
+#[wasm_bindgen]
+pub fn open_conversation_store() -> js_sys::Promise {
+    wasm_bindgen_futures::future_to_promise(async move {
+        database().await?;
+        Ok(JsValue::from(MIGRATIONS.len() as u32))
+    })
+}
+
+/// Append `{ engramId, role?, text, timestamp?, id?, toolName?, serverUrl?,
+/// structuredContent?, outputValidation? }` to its engram, creating the
+/// conversation on first use. Resolves to the stored message as JSON, with
+/// its id and timestamp filled in. The prune policy runs afterwards.
+#[wasm_bindgen]
+pub fn append_engram_message(message_json: &str) -> Result<js_sys::Promise, JsValue> {
+    let message: EngramMessage = serde_json::from_str(message_json)
+        .map_err(|e| JsValue::from_str(&format!("Invalid message: {}", e)))?;
+    Ok(wasm_bindgen_futures::future_to_promise(async move {
+        let stored = append(message).await?;
+        Ok(JsValue::from_str(&serde_json::to_string(&stored).unwrap_or_default()))
+    }))
+}
+
+/// Load an engram's messages, oldest first unless `newest_first`.
+/// `options_json` may give `{ since, until, limit, after, newest_first }`;
+/// resolves to `{ engramId, messages, next }` as JSON, where `next` is
+/// passed back as `after` for the following page and is null on the last.
+#[wasm_bindgen]
+pub fn load_engram_messages(engram_id: String, options_json: Option<String>) -> Result<js_sys::Promise, JsValue> {
+    let options: LoadOptions = match options_json {
+        Some(json) => serde_json::from_str(&json)
+            .map_err(|e| JsValue::from_str(&format!("Invalid load options: {}", e)))?,
+        None => LoadOptions::default(),
+    };
+    Ok(wasm_bindgen_futures::future_to_promise(async move {
+        let page = load(&engram_id, options).await?;
+        Ok(JsValue::from_str(&page.to_string()))
+    }))
+}
+
+/// Resolves to the conversations' `{ engramId, created, updated }` as a
+/// JSON array.
+#[wasm_bindgen]
+pub fn list_conversations() -> js_sys::Promise {
+    wasm_bindgen_futures::future_to_promise(async move {
+        let db = database().await?;
+        let transaction = transaction(&db, &[CONVERSATIONS_STORE], IdbTransactionMode::Readonly)?;
+        let records: js_sys::Array = wait(&transaction.object_store(CONVERSATIONS_STORE)?.get_all()?).await?.dyn_into()?;
+        let metas: Vec<ConversationMeta> = records
+            .iter()
+            .filter_map(|record| serde_wasm_bindgen::from_value::<ConversationRecord>(record).ok())
+            .map(|record| record.meta)
+            .collect();
+        Ok(JsValue::from_str(&serde_json::to_string(&metas).unwrap_or_default()))
+    })
+}
+
+/// Delete a conversation and its messages.
+#[wasm_bindgen]
+pub fn delete_conversation(engram_id: String) -> js_sys::Promise {
+    wasm_bindgen_futures::future_to_promise(async move {
+        let db = database().await?;
+        let transaction = transaction(&db, &[CONVERSATIONS_STORE, MESSAGES_STORE], IdbTransactionMode::Readwrite)?;
+        transaction.object_store(CONVERSATIONS_STORE)?.delete(&JsValue::from_str(&engram_id))?;
+        let messages = transaction.object_store(MESSAGES_STORE)?;
+        let range = timeline_range(&engram_id, None, None)?;
+        let keys: js_sys::Array = wait(&messages.index(TIMELINE_INDEX)?.get_all_keys_with_key(&range)?).await?.dyn_into()?;
+        for key in keys.iter() {
+            messages.delete(&key)?;
+        }
+        committed(&transaction).await?;
+        search::remove_engram_from_search_index(&engram_id);
+        pipeline::forget_result(&engram_id);
+        info(&format!("Deleted conversation {}", engram_id));
+        Ok(JsValue::from(keys.length()))
+    })
+}
+
+/// Prune an engram's messages with `{ max_messages, max_age_ms, max_bytes }`,
+/// or the configured policy when `policy_json` is null. Resolves to how many
+/// messages were removed.
+#[wasm_bindgen]
+pub fn prune_conversation(engram_id: String, policy_json: Option<String>) -> Result<js_sys::Promise, JsValue> {
+    let policy: PrunePolicy = match policy_json {
+        Some(json) => serde_json::from_str(&json)
+            .map_err(|e| JsValue::from_str(&format!("Invalid prune policy: {}", e)))?,
+        None => PRUNE_POLICY.lock().unwrap().clone(),
+    };
+    Ok(wasm_bindgen_futures::future_to_promise(async move {
+        let db = database().await?;
+        Ok(JsValue::from(prune(&db, &engram_id, &policy).await? as u32))
+    }))
+}
+
+/// Set the policy applied to an engram after each append:
+/// `{ max_messages?, max_age_ms?, max_bytes? }`.
+#[wasm_bindgen]
+pub fn set_conversation_prune_policy(policy_json: &str) -> Result<(), JsValue> {
+    let policy: PrunePolicy = serde_json::from_str(policy_json)
+        .map_err(|e| JsValue::from_str(&format!("Invalid prune policy: {}", e)))?;
+    *PRUNE_POLICY.lock().unwrap() = policy;
+    Ok(())
+}
+
+#[wasm_bindgen]
+pub fn get_conversation_prune_policy() -> String {
+    serde_json::to_string(&*PRUNE_POLICY.lock().unwrap()).unwrap_or_default()
+}
+
+/// Resolves to the value saved under `key`, or null.
+#[wasm_bindgen]
+pub fn load_state(key: String) -> js_sys::Promise {
+    wasm_bindgen_futures::future_to_promise(async move {
+        let db = database().await?;
+        let transaction = transaction(&db, &[STATE_STORE], IdbTransactionMode::Readonly)?;
+        let value = wait(&transaction.object_store(STATE_STORE)?.get(&JsValue::from_str(&key))?).await?;
+        Ok(if value.is_undefined() { JsValue::NULL } else { value })
+    })
+}
+
+/// Save `value` under `key`; resolves once written.
+#[wasm_bindgen]
+pub fn save_state(key: String, value: JsValue) -> js_sys::Promise {
+    wasm_bindgen_futures::future_to_promise(async move {
+        let db = database().await?;
+        let transaction = transaction(&db, &[STATE_STORE], IdbTransactionMode::Readwrite)?;
+        transaction.object_store(STATE_STORE)?.put_with_key(&value, &JsValue::from_str(&key))?;
+        committed(&transaction).await?;
+        Ok(JsValue::UNDEFINED)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uuid_v7_layout() {
+        let id = format_uuid_v7(0x0189_7f3a_1b2c, 0xfabc, [0xff; 8]);
+        assert_eq!(id, "01897f3a-1b2c-7abc-bfff-ffffffffffff");
+        let id = format_uuid_v7(1, 0, [0; 8]);
+        assert_eq!(id, "00000000-0001-7000-8000-000000000000");
+        // Version nibble is 7 and the variant bits are 10
+        assert_eq!(&id[14..15], "7");
+        assert_eq!(u8::from_str_radix(&id[19..20], 16).unwrap() >> 2, 0b10);
+        assert_eq!(uuid_v7_timestamp(&format_uuid_v7(1_700_000_000_123, 5, [7; 8])), Some(1_700_000_000_123));
+        assert_eq!(uuid_v7_timestamp("550e8400-e29b-41d4-a716-446655440000"), None);
+        assert_eq!(uuid_v7_timestamp("not-an-id"), None);
+    }
+
+    #[test]
+    fn uuid_clock_is_strictly_monotonic() {
+        assert_eq!(next_uuid_clock((0, 0), 100, 0xffff), (100, 0x07ff));
+        assert_eq!(next_uuid_clock((100, 7), 100, 0), (100, 8));
+        // A clock going backwards keeps counting from the last reading
+        assert_eq!(next_uuid_clock((100, 7), 90, 0), (100, 8));
+        assert_eq!(next_uuid_clock((100, 0x0fff), 100, 3), (101, 3));
+
+        let mut clock = (0, 0);
+        let mut ids = Vec::new();
+        for step in 0..10_000u64 {
+            let seed = (step.wrapping_mul(0x9e37_79b9) >> 7) as u16;
+            clock = next_uuid_clock(clock, 1_000 + step / 5_000, seed);
+            ids.push(format_uuid_v7(clock.0, clock.1, [0; 8]));
+        }
+        assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn prune_policy_limits() {
+        let unlimited = PrunePolicy::default();
+        assert!(unlimited.is_empty());
+        assert!(unlimited.admits(usize::MAX, usize::MAX, 0, u64::MAX));
+
+        let policy = PrunePolicy { max_messages: Some(3), max_age_ms: Some(1_000), max_bytes: Some(100) };
+        assert!(!policy.is_empty());
+        assert!(policy.admits(3, 100, 9_000, 10_000));
+        assert!(!policy.admits(4, 10, 9_500, 10_000));
+        assert!(!policy.admits(1, 101, 9_500, 10_000));
+        assert!(!policy.admits(1, 10, 8_999, 10_000));
+        // An age limit longer than the clock reading keeps everything
+        assert!(policy.admits(1, 10, 0, 500));
+    }
+}
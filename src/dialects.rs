@@ -0,0 +1,742 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{self, json};
+use std::cell::RefCell;
+use wasm_bindgen::prelude::*;
+
+use crate::catalog::original_tool_name;
+use crate::extract::{balanced_end, extract_calls, parse_lenient, utf16_offset, Repair};
+use crate::router::{resolve_route, server_alias, tool_call_target};
+use crate::{debug, SERVER_REGISTRY};
+
+/// A tool invocation recognised by a dialect, before validation. `start`
+/// and `end` are UTF-16 offsets into the scanned text.
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct RawToolCall {
+    pub name: String,
+    #[serde(default)]
+    pub arguments: serde_json::Value,
+    #[serde(default)]
+    pub id: Option<serde_json::Value>,
+    pub start: usize,
+    pub end: usize,
+    #[serde(skip)]
+    pub repairs: Vec<Repair>,
+}
+
+/// One way models write tool invocations in their output.
+pub(crate) trait ToolCallDialect {
+    fn name(&self) -> &str;
+    fn parse(&self, text: &str) -> Vec<RawToolCall>;
+}
+
+/// A validated call normalised to the JSON-RPC shape used by extraction.
+#[derive(Debug, Serialize, Clone)]
+pub(crate) struct ParsedToolCall {
+    pub dialect: String,
+    pub call: serde_json::Value,
+    pub server: String,
+    pub start: usize,
+    pub end: usize,
+    pub repairs: Vec<Repair>,
+}
+
+/// A recognised call that failed validation.
+#[derive(Debug, Serialize, Clone)]
+pub(crate) struct RejectedToolCall {
+    pub dialect: String,
+    pub name: String,
+    pub start: usize,
+    pub end: usize,
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize, Default)]
+pub(crate) struct ParseReport {
+    pub calls: Vec<ParsedToolCall>,
+    pub rejected: Vec<RejectedToolCall>,
+}
+
+/// Arguments given as a JSON string (OpenAI style) are parsed leniently.
+fn arguments_value(value: Option<&serde_json::Value>, repairs: &mut Vec<Repair>) -> serde_json::Value {
+    match value {
+        None | Some(serde_json::Value::Null) => json!({}),
+        Some(serde_json::Value::String(encoded)) if encoded.trim().is_empty() => json!({}),
+        Some(serde_json::Value::String(encoded)) => match parse_lenient(encoded) {
+            Some((parsed, more)) => {
+                repairs.extend(more);
+                parsed
+            }
+            None => serde_json::Value::String(encoded.clone()),
+        },
+        Some(other) => other.clone(),
+    }
+}
+
+/// Name, arguments and id from the object shapes seen inside tags:
+/// `{ name, arguments | parameters | input | args }`, an OpenAI
+/// `{ function: { name, arguments } }` item, or a JSON-RPC call.
+fn call_from_object(
+    value: &serde_json::Value,
+    repairs: &mut Vec<Repair>,
+) -> Option<(String, serde_json::Value, Option<serde_json::Value>)> {
+    let object = value.as_object()?;
+    let id = object.get("id").cloned();
+    if let Some(function) = object.get("function").filter(|f| f.is_object()) {
+        let (name, arguments, _) = call_from_object(function, repairs)?;
+        return Some((name, arguments, id));
+    }
+    if let Some(method) = object.get("method").and_then(|m| m.as_str()) {
+        let params = object.get("params").cloned().unwrap_or(serde_json::Value::Null);
+        let (name, arguments) = tool_call_target(method, params).ok()?;
+        return Some((name, arguments, id));
+    }
+    let name = object.get("name").and_then(|n| n.as_str())?.to_string();
+    let arguments = ["arguments", "parameters", "input", "args"]
+        .iter()
+        .find_map(|key| object.get(*key));
+    Some((name, arguments_value(arguments, repairs), id))
+}
+
+/// Every top-level JSON value in `text` with its byte span.
+fn json_candidates(text: &str) -> Vec<(usize, usize, serde_json::Value, Vec<Repair>)> {
+    let mut candidates = Vec::new();
+    let mut position = 0;
+    while let Some(found) = text[position..].find(['{', '[']) {
+        let start = position + found;
+        match balanced_end(text, start).and_then(|end| parse_lenient(&text[start..end]).map(|p| (end, p))) {
+            Some((end, (value, repairs))) => {
+                candidates.push((start, end, value, repairs));
+                position = end;
+            }
+            None => position = start + 1,
+        }
+    }
+    candidates
+}
+
+/// Visit `value` and every value nested inside it.
+fn walk<'a>(value: &'a serde_json::Value, visit: &mut dyn FnMut(&'a serde_json::Value)) {
+    visit(value);
+    match value {
+        serde_json::Value::Array(items) => items.iter().for_each(|item| walk(item, visit)),
+        serde_json::Value::Object(map) => map.values().for_each(|item| walk(item, visit)),
+        _ => {}
+    }
+}
+
+fn raw_call(
+    text: &str,
+    (start, end): (usize, usize),
+    (name, arguments, id): (String, serde_json::Value, Option<serde_json::Value>),
+    repairs: Vec<Repair>,
+) -> RawToolCall {
+    RawToolCall {
+        name,
+        arguments,
+        id,
+        start: utf16_offset(text, start),
+        end: utf16_offset(text, end),
+        repairs,
+    }
+}
+
+/// JSON-RPC objects anywhere in the text, as in `extract_json_rpc_calls`.
+/// `tools/call` requests are unwrapped to the tool they name.
+struct JsonRpcDialect;
+
+impl ToolCallDialect for JsonRpcDialect {
+    fn name(&self) -> &str {
+        "json_rpc"
+    }
+
+    fn parse(&self, text: &str) -> Vec<RawToolCall> {
+        extract_calls(text)
+            .into_iter()
+            .filter_map(|extracted| {
+                let method = extracted.call["method"].as_str()?;
+                let (name, arguments) = tool_call_target(method, extracted.call["params"].clone()).ok()?;
+                Some(RawToolCall {
+                    name,
+                    arguments,
+                    id: extracted.call.get("id").cloned(),
+                    start: extracted.start,
+                    end: extracted.end,
+                    repairs: extracted.repairs,
+                })
+            })
+            .collect()
+    }
+}
+
+/// `<tool_call>{"name": ..., "arguments": {...}}</tool_call>`, also under
+/// the `function_call` and `tool_use` tag names.
+struct XmlTagDialect;
+
+const XML_TAGS: [&str; 3] = ["tool_call", "function_call", "tool_use"];
+
+impl ToolCallDialect for XmlTagDialect {
+    fn name(&self) -> &str {
+        "xml_tag"
+    }
+
+    fn parse(&self, text: &str) -> Vec<RawToolCall> {
+        let mut calls = Vec::new();
+        for tag in XML_TAGS {
+            let (open, close) = (format!("<{}", tag), format!("</{}>", tag));
+            let mut position = 0;
+            while let Some(found) = text[position..].find(&open) {
+                let start = position + found;
+                position = start + open.len();
+                // Require `<tag>` or `<tag attr=...>`, not `<tag_other>`
+                let rest = &text[position..];
+                if !rest.starts_with('>') && !rest.starts_with(char::is_whitespace) {
+                    continue;
+                }
+                let Some(body_start) = rest.find('>').map(|i| position + i + 1) else {
+                    break;
+                };
+                let Some(body_end) = text[body_start..].find(&close).map(|i| body_start + i) else {
+                    break;
+                };
+                let end = body_end + close.len();
+                position = end;
+                let Some((value, mut repairs)) = parse_lenient(text[body_start..body_end].trim()) else {
+                    continue;
+                };
+                let values = match value {
+                    serde_json::Value::Array(items) => items,
+                    single => vec![single],
+                };
+                for value in values {
+                    if let Some(parts) = call_from_object(&value, &mut repairs) {
+                        calls.push(raw_call(text, (start, end), parts, repairs.clone()));
+                    }
+                }
+            }
+        }
+        calls
+    }
+}
+
+/// OpenAI `tool_calls` arrays and legacy `function_call` objects, either as
+/// a whole message or as the bare array.
+struct OpenAiDialect;
+
+impl ToolCallDialect for OpenAiDialect {
+    fn name(&self) -> &str {
+        "openai"
+    }
+
+    fn parse(&self, text: &str) -> Vec<RawToolCall> {
+        let mut calls = Vec::new();
+        for (start, end, value, repairs) in json_candidates(text) {
+            walk(&value, &mut |node| {
+                let mut items: Vec<&serde_json::Value> = Vec::new();
+                if let Some(tool_calls) = node.get("tool_calls").and_then(|t| t.as_array()) {
+                    items.extend(tool_calls);
+                }
+                if let Some(function_call) = node.get("function_call").filter(|f| f.get("name").is_some()) {
+                    items.push(function_call);
+                }
+                // A bare `tool_calls` array at the top level
+                if std::ptr::eq(node, &value) {
+                    if let Some(array) = node.as_array() {
+                        items.extend(array.iter().filter(|item| item.get("type").and_then(|t| t.as_str()) == Some("function")));
+                    }
+                }
+                for item in items {
+                    let mut repairs = repairs.clone();
+                    if let Some(parts) = call_from_object(item, &mut repairs) {
+                        calls.push(raw_call(text, (start, end), parts, repairs));
+                    }
+                }
+            });
+        }
+        calls
+    }
+}
+
+/// Anthropic `{"type": "tool_use", "id", "name", "input"}` content blocks.
+struct AnthropicDialect;
+
+impl ToolCallDialect for AnthropicDialect {
+    fn name(&self) -> &str {
+        "anthropic"
+    }
+
+    fn parse(&self, text: &str) -> Vec<RawToolCall> {
+        let mut calls = Vec::new();
+        for (start, end, value, repairs) in json_candidates(text) {
+            walk(&value, &mut |node| {
+                if node.get("type").and_then(|t| t.as_str()) != Some("tool_use") {
+                    return;
+                }
+                let Some(name) = node.get("name").and_then(|n| n.as_str()) else {
+                    return;
+                };
+                let mut repairs = repairs.clone();
+                let arguments = arguments_value(node.get("input"), &mut repairs);
+                let parts = (name.to_string(), arguments, node.get("id").cloned());
+                calls.push(raw_call(text, (start, end), parts, repairs));
+            });
+        }
+        calls
+    }
+}
+
+/// Python-style `tool_name(arg="x", n=2)` inside fenced code blocks. Only
+/// names of registered tools are matched, and prose outside the fences is
+/// never read, so a model describing a tool does not call it.
+/// Positional arguments take the tool's parameter names in order.
+struct FunctionSyntaxDialect;
+
+/// Byte spans of the bodies of ``` and ~~~ fenced blocks. A fence left
+/// open runs to the end of the text.
+fn fenced_blocks(text: &str) -> Vec<(usize, usize)> {
+    let mut blocks = Vec::new();
+    let mut open: Option<(&str, usize)> = None;
+    let mut line_start = 0;
+    for line in text.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        let fence = ["```", "~~~"].into_iter().find(|fence| trimmed.starts_with(fence));
+        match (open, fence) {
+            (None, Some(fence)) => open = Some((fence, line_start + line.len())),
+            (Some((current, body_start)), Some(fence)) if fence == current => {
+                blocks.push((body_start, line_start));
+                open = None;
+            }
+            _ => {}
+        }
+        line_start += line.len();
+    }
+    if let Some((_, body_start)) = open {
+        blocks.push((body_start, text.len()));
+    }
+    blocks
+}
+
+/// Byte offset just past the `)` closing the call whose `(` is at `open`.
+fn closing_paren(text: &str, open: usize) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut in_string: Option<char> = None;
+    let mut chars = text[open..].char_indices();
+    while let Some((offset, c)) = chars.next() {
+        if let Some(close) = in_string {
+            if c == '\\' {
+                chars.next();
+            } else if c == close {
+                in_string = None;
+            }
+            continue;
+        }
+        match c {
+            '"' | '\'' => in_string = Some(c),
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => {
+                depth = depth.checked_sub(1)?;
+                if depth == 0 {
+                    return (c == ')').then_some(open + offset + 1);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Split on commas that are not inside brackets or strings.
+fn split_top_level(args: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let (mut depth, mut part_start) = (0i32, 0usize);
+    let mut in_string: Option<char> = None;
+    let mut escaped = false;
+    for (offset, c) in args.char_indices() {
+        if let Some(close) = in_string {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == close {
+                in_string = None;
+            }
+            continue;
+        }
+        match c {
+            '"' | '\'' => in_string = Some(c),
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(&args[part_start..offset]);
+                part_start = offset + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&args[part_start..]);
+    parts.into_iter().filter(|p| !p.trim().is_empty()).collect()
+}
+
+/// Parse one argument value; anything that is not JSON-like is kept as a
+/// plain string.
+fn argument_literal(raw: &str, repairs: &mut Vec<Repair>) -> serde_json::Value {
+    match parse_lenient(raw) {
+        Some((value, more)) => {
+            repairs.extend(more);
+            value
+        }
+        None => serde_json::Value::String(raw.to_string()),
+    }
+}
+
+/// `key=value` or `key: value`, returning the key and the raw value.
+fn keyword_argument(part: &str) -> Option<(&str, &str)> {
+    let split = part.find(['=', ':'])?;
+    let key = part[..split].trim();
+    let is_identifier = !key.is_empty()
+        && key.chars().all(|c| c.is_alphanumeric() || c == '_')
+        && !key.starts_with(|c: char| c.is_ascii_digit());
+    is_identifier.then(|| (key, part[split + 1..].trim()))
+}
+
+impl ToolCallDialect for FunctionSyntaxDialect {
+    fn name(&self) -> &str {
+        "function_call"
+    }
+
+    fn parse(&self, text: &str) -> Vec<RawToolCall> {
+        let blocks = fenced_blocks(text);
+        if blocks.is_empty() {
+            return Vec::new();
+        }
+        // Known names, longest first so `search_files` wins over `search`
+        let mut known: Vec<(String, Vec<String>)> = {
+            let registry = SERVER_REGISTRY.lock().unwrap();
+            registry
+                .servers
+                .values()
+                .flat_map(|server| {
+                    let alias = server_alias(server);
+                    server.tools.iter().flat_map(move |tool| {
+                        let parameters: Vec<String> = tool.parameters.iter().map(|p| p.name.clone()).collect();
+                        [
+                            (tool.name.clone(), parameters.clone()),
+                            (format!("{}/{}", alias, tool.name), parameters),
+                        ]
+                    })
+                })
+                .collect()
+        };
+        known.sort_by(|a, b| b.0.len().cmp(&a.0.len()).then(a.0.cmp(&b.0)));
+        known.dedup_by(|a, b| a.0 == b.0);
+
+        let fenced = |start: usize, end: usize| blocks.iter().any(|&(s, e)| s <= start && end <= e);
+        let mut calls = Vec::new();
+        let mut claimed: Vec<(usize, usize)> = Vec::new();
+        for (name, parameters) in &known {
+            let pattern = format!("{}(", name);
+            let mut position = 0;
+            while let Some(found) = text[position..].find(&pattern) {
+                let start = position + found;
+                position = start + pattern.len();
+                let preceded_by_identifier = text[..start]
+                    .chars()
+                    .next_back()
+                    .is_some_and(|c| c.is_alphanumeric() || c == '_' || c == '/' || c == '.');
+                if preceded_by_identifier || claimed.iter().any(|&(s, e)| start < e && s < position) {
+                    continue;
+                }
+                let open = start + name.len();
+                let Some(end) = closing_paren(text, open).filter(|end| fenced(start, *end)) else {
+                    continue;
+                };
+                let mut repairs = Vec::new();
+                let mut arguments = serde_json::Map::new();
+                let mut positional = parameters.iter();
+                let mut valid = true;
+                for part in split_top_level(&text[open + 1..end - 1]) {
+                    match keyword_argument(part) {
+                        Some((key, raw)) => {
+                            arguments.insert(key.to_string(), argument_literal(raw, &mut repairs));
+                        }
+                        None => match positional.next() {
+                            Some(parameter) => {
+                                arguments.insert(parameter.clone(), argument_literal(part.trim(), &mut repairs));
+                            }
+                            None => valid = false,
+                        },
+                    }
+                }
+                if !valid {
+                    continue;
+                }
+                claimed.push((start, end));
+                position = end;
+                let parts = (name.clone(), serde_json::Value::Object(arguments), None);
+                calls.push(raw_call(text, (start, end), parts, repairs));
+            }
+        }
+        calls.sort_by_key(|call| call.start);
+        calls
+    }
+}
+
+/// A dialect implemented in JS. The parser is called with the text and
+/// returns an array of `{ name, arguments, start, end, id? }`.
+struct JsDialect {
+    name: String,
+    parser: js_sys::Function,
+}
+
+impl ToolCallDialect for JsDialect {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn parse(&self, text: &str) -> Vec<RawToolCall> {
+        let result = self
+            .parser
+            .call1(&JsValue::NULL, &JsValue::from_str(text))
+            .map_err(|e| format!("{:?}", e))
+            .and_then(|value| serde_wasm_bindgen::from_value::<Vec<RawToolCall>>(value).map_err(|e| e.to_string()));
+        match result {
+            Ok(calls) => calls,
+            Err(e) => {
+                debug(&format!("Tool call dialect '{}' failed: {}", self.name, e));
+                Vec::new()
+            }
+        }
+    }
+}
+
+fn builtin_dialects() -> Vec<Box<dyn ToolCallDialect>> {
+    vec![
+        Box::new(XmlTagDialect),
+        Box::new(OpenAiDialect),
+        Box::new(AnthropicDialect),
+        Box::new(JsonRpcDialect),
+        Box::new(FunctionSyntaxDialect),
+    ]
+}
+
+thread_local! {
+    // Dialects in the order they are tried. JS parsers are not `Send`, so
+    // the registry lives here rather than in a `Mutex` static.
+    static DIALECTS: RefCell<Vec<Box<dyn ToolCallDialect>>> = RefCell::new(builtin_dialects());
+}
+
+/// Run every registered dialect over `text`. A call whose span overlaps one
+/// already claimed by an earlier dialect is skipped, so the same invocation
+/// is not reported twice (e.g. a JSON-RPC object inside `<tool_call>`).
+pub(crate) fn parse_tool_calls_in(text: &str) -> ParseReport {
+    let mut report = ParseReport::default();
+    let mut claimed: Vec<(String, usize, usize)> = Vec::new();
+    DIALECTS.with(|dialects| {
+        for dialect in dialects.borrow().iter() {
+            let name = dialect.name().to_string();
+            for raw in dialect.parse(text) {
+                let overlaps = claimed
+                    .iter()
+                    .any(|(owner, start, end)| *owner != name && raw.start < *end && *start < raw.end);
+                if overlaps {
+                    continue;
+                }
+                claimed.push((name.clone(), raw.start, raw.end));
+                let reject = |reason: String| RejectedToolCall {
+                    dialect: name.clone(),
+                    name: raw.name.clone(),
+                    start: raw.start,
+                    end: raw.end,
+                    reason,
+                };
+                if !raw.arguments.is_object() {
+                    report.rejected.push(reject("Arguments must be an object".to_string()));
+                    continue;
+                }
+                // Names from an exported catalog map back to the tool
+                let method = original_tool_name(None, &raw.name)
+                    .filter(|_| resolve_route(&raw.name).is_err())
+                    .unwrap_or_else(|| raw.name.clone());
+                match resolve_route(&method) {
+                    Ok(route) => {
+                        let mut call = json!({ "jsonrpc": "2.0", "method": method, "params": raw.arguments });
+                        if let Some(id) = raw.id.filter(|id| id.is_string() || id.is_number()) {
+                            call["id"] = id;
+                        }
+                        report.calls.push(ParsedToolCall {
+                            dialect: name.clone(),
+                            call,
+                            server: route.url,
+                            start: raw.start,
+                            end: raw.end,
+                            repairs: raw.repairs,
+                        });
+                    }
+                    Err(e) => report.rejected.push(reject(e)),
+                }
+            }
+        }
+    });
+    report.calls.sort_by_key(|call| call.start);
+    report
+}
+
+/// Parse tool invocations in any registered dialect. Returns
+/// `{ calls, rejected }`: `calls` are JSON-RPC shaped and name a tool some
+/// registered server provides; `rejected` lists recognised calls that did
+/// not validate.
+#[wasm_bindgen]
+pub fn parse_tool_calls(text: &str) -> String {
+    serde_json::to_string(&parse_tool_calls_in(text)).unwrap_or_else(|_| "{}".to_string())
+}
+
+/// Register a JS tool-call parser, replacing any dialect with the same name.
+/// New dialects are tried after the existing ones.
+#[wasm_bindgen]
+pub fn register_tool_call_dialect(name: &str, parser: js_sys::Function) {
+    DIALECTS.with(|dialects| {
+        let mut dialects = dialects.borrow_mut();
+        let dialect = Box::new(JsDialect { name: name.to_string(), parser });
+        match dialects.iter().position(|d| d.name() == name) {
+            Some(index) => dialects[index] = dialect,
+            None => dialects.push(dialect),
+        }
+    });
+}
+
+/// Remove a dialect, built-in or registered. Returns whether it existed.
+#[wasm_bindgen]
+pub fn unregister_tool_call_dialect(name: &str) -> bool {
+    DIALECTS.with(|dialects| {
+        let mut dialects = dialects.borrow_mut();
+        let before = dialects.len();
+        dialects.retain(|d| d.name() != name);
+        dialects.len() != before
+    })
+}
+
+/// Names of the registered dialects, in the order they are tried.
+#[wasm_bindgen]
+pub fn list_tool_call_dialects() -> String {
+    let names: Vec<String> = DIALECTS.with(|dialects| dialects.borrow().iter().map(|d| d.name().to_string()).collect());
+    serde_json::to_string(&names).unwrap_or_else(|_| "[]".to_string())
+}
+
+/// Restore the built-in dialects, dropping any JS parsers.
+#[wasm_bindgen]
+pub fn reset_tool_call_dialects() {
+    DIALECTS.with(|dialects| *dialects.borrow_mut() = builtin_dialects());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{McpServer, McpTool, SERVER_STATUS_CONNECTED};
+
+    const URL: &str = "http://dialects-test.invalid/mcp";
+
+    fn register_tools() {
+        let tool = |name: &str, parameter: &str| {
+            McpTool::from_definition(
+                name,
+                &json!({ "inputSchema": { "type": "object", "properties": { parameter: { "type": "string" } } } }),
+            )
+        };
+        SERVER_REGISTRY.lock().unwrap().servers.insert(
+            URL.to_string(),
+            McpServer {
+                url: URL.to_string(),
+                name: "dialects-test".to_string(),
+                version: "1".to_string(),
+                status: SERVER_STATUS_CONNECTED.to_string(),
+                tools: vec![tool("dt_search", "query"), tool("dt_search_files", "pattern")],
+                last_health_check: 0,
+                session_id: None,
+                policy: Default::default(),
+                alias: Some("dt".to_string()),
+                priority: 0,
+                latency_ms: None,
+                instructions: None,
+            },
+        );
+    }
+
+    fn names(calls: &[RawToolCall]) -> Vec<&str> {
+        calls.iter().map(|call| call.name.as_str()).collect()
+    }
+
+    #[test]
+    fn xml_tags_hold_single_calls_and_lists() {
+        let text = r#"<tool_call>{"name": "a", "arguments": {"x": 1}}</tool_call>
+<function_call id="2">[{"name": "b", "parameters": {}}, {"function": {"name": "c", "arguments": "{\"y\": 2}"}}]</function_call>
+<tool_callback>{"name": "ignored"}</tool_callback>"#;
+        let calls = XmlTagDialect.parse(text);
+        assert_eq!(names(&calls), ["a", "b", "c"]);
+        assert_eq!(calls[0].arguments, json!({ "x": 1 }));
+        assert_eq!(calls[2].arguments, json!({ "y": 2 }));
+        assert_eq!(calls[0].start, 0);
+        assert_eq!(calls[0].end, text.find('\n').unwrap());
+    }
+
+    #[test]
+    fn openai_tool_calls_and_legacy_function_call() {
+        let message = r#"{"role": "assistant", "tool_calls": [{"id": "call_1", "type": "function", "function": {"name": "a", "arguments": "{\"q\": \"x\",}"}}]}"#;
+        let calls = OpenAiDialect.parse(message);
+        assert_eq!(names(&calls), ["a"]);
+        assert_eq!(calls[0].arguments, json!({ "q": "x" }));
+        assert_eq!(calls[0].id, Some(json!("call_1")));
+        assert_eq!(calls[0].repairs, [Repair::TrailingCommas]);
+
+        let bare = r#"[{"type": "function", "function": {"name": "b", "arguments": ""}}]"#;
+        assert_eq!(OpenAiDialect.parse(bare)[0].arguments, json!({}));
+        let legacy = r#"{"function_call": {"name": "c", "arguments": "{}"}}"#;
+        assert_eq!(names(&OpenAiDialect.parse(legacy)), ["c"]);
+    }
+
+    #[test]
+    fn anthropic_tool_use_blocks() {
+        let text = r#"{"content": [{"type": "text", "text": "hi"}, {"type": "tool_use", "id": "toolu_1", "name": "a", "input": {"n": 2}}]}"#;
+        let calls = AnthropicDialect.parse(text);
+        assert_eq!(names(&calls), ["a"]);
+        assert_eq!(calls[0].arguments, json!({ "n": 2 }));
+        assert_eq!(calls[0].id, Some(json!("toolu_1")));
+        assert_eq!((calls[0].start, calls[0].end), (0, text.len()));
+    }
+
+    #[test]
+    fn fenced_blocks_and_closing_paren() {
+        let text = "intro\n```py\nbody\n```\nprose\n~~~\nopen";
+        let blocks = fenced_blocks(text);
+        assert_eq!(blocks.iter().map(|&(s, e)| &text[s..e]).collect::<Vec<_>>(), ["body\n", "open"]);
+        // A ~~~ line does not close a ``` fence
+        assert_eq!(fenced_blocks("```\na\n~~~\nb\n```\n").len(), 1);
+
+        let call = r#"f(a=")", b=[1, (2)], c={"k": ")"}) tail"#;
+        assert_eq!(closing_paren(call, 1), Some(call.find(" tail").unwrap()));
+        assert_eq!(closing_paren("f(a=[1)", 1), None);
+        assert_eq!(closing_paren("f(a", 1), None);
+    }
+
+    #[test]
+    fn function_syntax_is_read_only_inside_fences() {
+        register_tools();
+        let prose = r#"You could call dt_search(query="x") here."#;
+        assert!(FunctionSyntaxDialect.parse(prose).is_empty());
+
+        let text = "```\ndt_search_files(\"*.rs\")\ndt/dt_search(query='rust', limit=3)\nmy_dt_search(\"no\")\n```";
+        let calls = FunctionSyntaxDialect.parse(text);
+        assert_eq!(names(&calls), ["dt_search_files", "dt/dt_search"]);
+        assert_eq!(calls[0].arguments, json!({ "pattern": "*.rs" }));
+        assert_eq!(calls[1].arguments, json!({ "query": "rust", "limit": 3 }));
+    }
+
+    #[test]
+    fn overlapping_calls_are_reported_once() {
+        register_tools();
+        let text = r#"<tool_call>{"jsonrpc": "2.0", "method": "dt_search", "params": {"query": "x"}}</tool_call>
+{"jsonrpc": "2.0", "method": "dt_missing"}"#;
+        let report = parse_tool_calls_in(text);
+        assert_eq!(report.calls.len(), 1);
+        assert_eq!((report.calls[0].dialect.as_str(), report.calls[0].server.as_str()), ("xml_tag", URL));
+        assert_eq!(report.rejected.iter().map(|r| r.name.as_str()).collect::<Vec<_>>(), ["dt_missing"]);
+    }
+}
@@ -0,0 +1,387 @@
+use serde::Serialize;
+use serde_json::{self, json};
+use wasm_bindgen::prelude::*;
+
+/// Fix-ups applied to make a candidate parse as JSON.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Repair {
+    EscapedQuotes,
+    SingleQuotes,
+    SmartQuotes,
+    Comments,
+    TrailingCommas,
+    UnquotedKeys,
+    PythonLiterals,
+}
+
+/// A JSON-RPC call found in free text. `start` and `end` are UTF-16 offsets
+/// into the input so they can be used with `String.prototype.slice`.
+#[derive(Debug, Serialize, Clone)]
+pub(crate) struct ExtractedCall {
+    pub call: serde_json::Value,
+    pub start: usize,
+    pub end: usize,
+    pub repairs: Vec<Repair>,
+    /// Position within a batch when the call came from a JSON array.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub batch_index: Option<usize>,
+}
+
+/// Closing delimiter for a character that opens a string, if it does.
+fn string_close(c: char) -> Option<char> {
+    match c {
+        '"' => Some('"'),
+        '\'' => Some('\''),
+        '\u{201C}' => Some('\u{201D}'),
+        '\u{2018}' => Some('\u{2019}'),
+        _ => None,
+    }
+}
+
+/// End (exclusive byte offset) of the balanced object or array starting at
+/// `start`, tolerating the same string and comment forms `normalize` repairs.
+pub(crate) fn balanced_end(text: &str, start: usize) -> Option<usize> {
+    let mut stack: Vec<char> = Vec::new();
+    let mut chars = text[start..].char_indices().peekable();
+    let mut in_string: Option<char> = None;
+    while let Some((offset, c)) = chars.next() {
+        if let Some(close) = in_string {
+            if c == '\\' {
+                // `\"` closes a string that was opened by `\"`
+                if chars.next().map(|(_, n)| n) == Some('"') && close == '\\' {
+                    in_string = None;
+                }
+            } else if c == close || (close == '\u{201D}' && c == '\u{201C}') {
+                in_string = None;
+            }
+            continue;
+        }
+        match c {
+            '{' => stack.push('}'),
+            '[' => stack.push(']'),
+            '}' | ']' => {
+                if stack.pop() != Some(c) {
+                    return None;
+                }
+                if stack.is_empty() {
+                    return Some(start + offset + c.len_utf8());
+                }
+            }
+            // Escaped JSON pasted as-is: `{\"key\": ...}`
+            '\\' if chars.peek().map(|(_, n)| *n) == Some('"') => {
+                chars.next();
+                in_string = Some('\\');
+            }
+            '/' if chars.peek().map(|(_, n)| *n) == Some('/') => {
+                for (_, n) in chars.by_ref() {
+                    if n == '\n' {
+                        break;
+                    }
+                }
+            }
+            '/' if chars.peek().map(|(_, n)| *n) == Some('*') => {
+                chars.next();
+                let mut previous = '\0';
+                for (_, n) in chars.by_ref() {
+                    if previous == '*' && n == '/' {
+                        break;
+                    }
+                    previous = n;
+                }
+            }
+            _ => {
+                if let Some(close) = string_close(c) {
+                    in_string = Some(close);
+                }
+            }
+        }
+    }
+    None
+}
+
+pub(crate) fn push_repair(repairs: &mut Vec<Repair>, repair: Repair) {
+    if !repairs.contains(&repair) {
+        repairs.push(repair);
+    }
+}
+
+/// Drop a trailing comma from `out` before a closing bracket.
+fn drop_trailing_comma(out: &mut String, repairs: &mut Vec<Repair>) {
+    let trimmed = out.trim_end().len();
+    if out[..trimmed].ends_with(',') {
+        out.replace_range(trimmed - 1..trimmed, "");
+        push_repair(repairs, Repair::TrailingCommas);
+    }
+}
+
+/// Rewrite lenient, LLM-style JSON into strict JSON: single and smart quoted
+/// strings, comments, trailing commas, bare keys and Python literals.
+fn normalize(source: &str, repairs: &mut Vec<Repair>) -> String {
+    let mut out = String::with_capacity(source.len());
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        if let Some(close) = string_close(c) {
+            match c {
+                '\'' => push_repair(repairs, Repair::SingleQuotes),
+                '"' => {}
+                _ => push_repair(repairs, Repair::SmartQuotes),
+            }
+            out.push('"');
+            while let Some(s) = chars.next() {
+                if s == '\\' {
+                    match chars.next() {
+                        // `\'` is not a JSON escape
+                        Some('\'') => out.push('\''),
+                        Some(escaped) => {
+                            out.push('\\');
+                            out.push(escaped);
+                        }
+                        None => out.push('\\'),
+                    }
+                } else if s == close || (close == '\u{201D}' && s == '\u{201C}') {
+                    break;
+                } else if s == '"' {
+                    out.push_str("\\\"");
+                } else if s == '\n' {
+                    out.push_str("\\n");
+                } else {
+                    out.push(s);
+                }
+            }
+            out.push('"');
+            continue;
+        }
+        match c {
+            '/' if chars.peek() == Some(&'/') => {
+                push_repair(repairs, Repair::Comments);
+                for n in chars.by_ref() {
+                    if n == '\n' {
+                        out.push('\n');
+                        break;
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                push_repair(repairs, Repair::Comments);
+                chars.next();
+                let mut previous = '\0';
+                for n in chars.by_ref() {
+                    if previous == '*' && n == '/' {
+                        break;
+                    }
+                    previous = n;
+                }
+            }
+            '}' | ']' => {
+                drop_trailing_comma(&mut out, repairs);
+                out.push(c);
+            }
+            c if c.is_alphabetic() || c == '_' || c == '$' => {
+                let mut word = String::from(c);
+                while let Some(&n) = chars.peek() {
+                    if n.is_alphanumeric() || n == '_' || n == '$' || n == '-' {
+                        word.push(n);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                let is_key = {
+                    let rest: String = chars.clone().take_while(|n| n.is_whitespace() || *n == ':').collect();
+                    rest.contains(':')
+                };
+                match word.as_str() {
+                    _ if is_key => {
+                        push_repair(repairs, Repair::UnquotedKeys);
+                        out.push_str(&serde_json::Value::String(word).to_string());
+                    }
+                    "true" | "false" | "null" => out.push_str(&word),
+                    "True" | "False" | "None" => {
+                        push_repair(repairs, Repair::PythonLiterals);
+                        out.push_str(match word.as_str() {
+                            "True" => "true",
+                            "False" => "false",
+                            _ => "null",
+                        });
+                    }
+                    _ => out.push_str(&word),
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Parse a candidate strictly, then with repairs, then with `\"` unescaped.
+pub(crate) fn parse_lenient(candidate: &str) -> Option<(serde_json::Value, Vec<Repair>)> {
+    if let Ok(value) = serde_json::from_str(candidate) {
+        return Some((value, Vec::new()));
+    }
+    let mut repairs = Vec::new();
+    if let Ok(value) = serde_json::from_str(&normalize(candidate, &mut repairs)) {
+        return Some((value, repairs));
+    }
+    if candidate.contains("\\\"") {
+        let mut repairs = vec![Repair::EscapedQuotes];
+        let unescaped = candidate.replace("\\\"", "\"");
+        if let Ok(value) = serde_json::from_str(&unescaped) {
+            return Some((value, repairs));
+        }
+        if let Ok(value) = serde_json::from_str(&normalize(&unescaped, &mut repairs)) {
+            return Some((value, repairs));
+        }
+    }
+    None
+}
+
+/// Normalise a value into a JSON-RPC request if it has the right shape:
+/// `"jsonrpc": "2.0"`, a non-empty string `method` and `params` absent or
+/// structured.
+pub(crate) fn as_json_rpc_call(value: &serde_json::Value) -> Option<serde_json::Value> {
+    let object = value.as_object()?;
+    object.get("jsonrpc").and_then(|v| v.as_str()).filter(|version| *version == "2.0")?;
+    let method = object.get("method")?.as_str().filter(|m| !m.trim().is_empty())?;
+    let params = match object.get("params") {
+        None | Some(serde_json::Value::Null) => json!({}),
+        Some(params @ (serde_json::Value::Object(_) | serde_json::Value::Array(_))) => params.clone(),
+        Some(_) => return None,
+    };
+    let mut call = json!({ "jsonrpc": "2.0", "method": method, "params": params });
+    if let Some(id) = object.get("id").filter(|id| id.is_string() || id.is_number()) {
+        call["id"] = id.clone();
+    }
+    Some(call)
+}
+
+pub(crate) fn utf16_offset(text: &str, byte_offset: usize) -> usize {
+    text[..byte_offset].encode_utf16().count()
+}
+
+/// Scan `text` for JSON-RPC calls: bare or fenced objects, several per
+/// block, and batches in arrays. Candidates that parse but are not calls,
+/// such as tool results echoed into the text, are skipped whole rather
+/// than searched for nested calls.
+pub(crate) fn extract_calls(text: &str) -> Vec<ExtractedCall> {
+    let mut calls = Vec::new();
+    let mut position = 0;
+    while let Some(found) = text[position..].find(['{', '[']) {
+        let start = position + found;
+        let end = match balanced_end(text, start) {
+            Some(end) => end,
+            None => {
+                position = start + 1;
+                continue;
+            }
+        };
+        let mut found_calls = Vec::new();
+        if let Some((value, repairs)) = parse_lenient(&text[start..end]) {
+            match &value {
+                serde_json::Value::Array(items) => {
+                    for (index, item) in items.iter().enumerate() {
+                        if let Some(call) = as_json_rpc_call(item) {
+                            found_calls.push((call, repairs.clone(), Some(index)));
+                        }
+                    }
+                }
+                _ => {
+                    if let Some(call) = as_json_rpc_call(&value) {
+                        found_calls.push((call, repairs, None));
+                    }
+                }
+            }
+            if found_calls.is_empty() {
+                position = end;
+                continue;
+            }
+        }
+        if found_calls.is_empty() {
+            position = start + 1;
+            continue;
+        }
+        let (start_utf16, end_utf16) = (utf16_offset(text, start), utf16_offset(text, end));
+        calls.extend(found_calls.into_iter().map(|(call, repairs, batch_index)| ExtractedCall {
+            call,
+            start: start_utf16,
+            end: end_utf16,
+            repairs,
+            batch_index,
+        }));
+        position = end;
+    }
+    calls
+}
+
+/// Extract JSON-RPC calls from model output. Returns a JSON array of
+/// `{ call, start, end, repairs, batch_index? }`.
+#[wasm_bindgen]
+pub fn extract_json_rpc_calls(text: &str) -> String {
+    serde_json::to_string(&extract_calls(text)).unwrap_or_else(|_| "[]".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn methods(text: &str) -> Vec<String> {
+        extract_calls(text).iter().map(|c| c.call["method"].as_str().unwrap_or_default().to_string()).collect()
+    }
+
+    #[test]
+    fn normalize_repairs_llm_json() {
+        let mut repairs = Vec::new();
+        let fixed = normalize("{method: 'go', /* why */ \"n\": [1, 2,], // done\n flag: True,}", &mut repairs);
+        assert_eq!(serde_json::from_str::<serde_json::Value>(&fixed).unwrap(), json!({ "method": "go", "n": [1, 2], "flag": true }));
+        assert_eq!(
+            repairs,
+            [Repair::UnquotedKeys, Repair::SingleQuotes, Repair::Comments, Repair::TrailingCommas, Repair::PythonLiterals]
+        );
+    }
+
+    #[test]
+    fn parse_lenient_reports_repairs() {
+        assert_eq!(parse_lenient(r#"{"a": 1}"#), Some((json!({ "a": 1 }), Vec::new())));
+        assert_eq!(
+            parse_lenient("{\u{201C}a\u{201D}: \u{201C}b\u{201D}}"),
+            Some((json!({ "a": "b" }), vec![Repair::SmartQuotes]))
+        );
+        assert_eq!(parse_lenient(r#"{\"a\": 1}"#), Some((json!({ "a": 1 }), vec![Repair::EscapedQuotes])));
+        assert_eq!(parse_lenient("{\"a\": [1,], }"), Some((json!({ "a": [1] }), vec![Repair::TrailingCommas])));
+        assert_eq!(parse_lenient("{ nope"), None);
+    }
+
+    #[test]
+    fn extracts_calls_and_batches() {
+        let text = r#"First {"jsonrpc": "2.0", "method": "a", "params": {"x": 1},} then
+[{"jsonrpc": "2.0", "method": "b"}, {"not": "a call"}, {"jsonrpc": "2.0", "method": "c", "id": 3}]"#;
+        let calls = extract_calls(text);
+        assert_eq!(methods(text), ["a", "b", "c"]);
+        assert_eq!(calls[0].repairs, [Repair::TrailingCommas]);
+        assert_eq!(calls[0].batch_index, None);
+        assert_eq!((calls[1].batch_index, calls[2].batch_index), (Some(0), Some(2)));
+        assert_eq!(calls[1].call["params"], json!({}));
+        assert_eq!(calls[2].call["id"], json!(3));
+        assert_eq!((calls[1].start, calls[1].end), (calls[2].start, calls[2].end));
+    }
+
+    #[test]
+    fn skips_non_calls_without_searching_inside() {
+        assert!(methods(r#"{"jsonrpc": "1.0", "method": "old"}"#).is_empty());
+        assert!(methods(r#"{"jsonrpc": "2.0", "method": "bad", "params": 5}"#).is_empty());
+        // A tool result echoing a call is not a call itself
+        assert!(methods(r#"{"result": {"echo": {"jsonrpc": "2.0", "method": "inner"}}}"#).is_empty());
+        assert_eq!(methods(r#"{ unbalanced {"jsonrpc": "2.0", "method": "later"}"#), ["later"]);
+    }
+
+    #[test]
+    fn spans_are_utf16_offsets() {
+        let prefix = "😀 é ";
+        let call = r#"{"jsonrpc": "2.0", "method": "m"}"#;
+        let text = format!("{}{} tail", prefix, call);
+        let calls = extract_calls(&text);
+        let start = prefix.encode_utf16().count();
+        assert_eq!((calls[0].start, calls[0].end), (start, start + call.encode_utf16().count()));
+        assert_ne!(calls[0].start, prefix.len());
+    }
+}
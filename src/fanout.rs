@@ -0,0 +1,290 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{self, json};
+use std::cell::RefCell;
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+
+use crate::content::ToolResult;
+use crate::policy::reject_pending;
+use crate::router::server_alias;
+use crate::tool_call::{delay, run_tool_call, ToolCallOutcome};
+use crate::{info, SERVER_REGISTRY};
+
+/// Shared timeout applied when the caller does not give one.
+const DEFAULT_FANOUT_TIMEOUT_MS: u32 = 15_000;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum MergeStrategy {
+    /// All successful results, each under a heading naming its server.
+    Concatenate,
+    /// The earliest successful result to arrive.
+    FirstSuccess,
+    /// The result more servers agree on than any other, compared by
+    /// rendered text. This is a plurality, not necessarily a majority;
+    /// `majority` is accepted as an alias.
+    #[serde(alias = "majority")]
+    Plurality,
+}
+
+impl MergeStrategy {
+    fn parse(name: Option<&str>) -> Result<Self, String> {
+        match name.unwrap_or("concatenate") {
+            "concatenate" => Ok(MergeStrategy::Concatenate),
+            "first_success" => Ok(MergeStrategy::FirstSuccess),
+            "plurality" | "majority" => Ok(MergeStrategy::Plurality),
+            other => Err(format!("Unknown merge strategy: {}", other)),
+        }
+    }
+}
+
+/// Outcome and elapsed time of a call that finished before the timeout.
+type CompletedCall = Option<(ToolCallOutcome, f64)>;
+
+/// Outcome of the call on one server.
+#[derive(Debug, Serialize, Clone)]
+pub(crate) struct FanoutEntry {
+    pub url: String,
+    pub alias: String,
+    pub outcome: ToolCallOutcome,
+    pub elapsed_ms: f64,
+    pub timed_out: bool,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub(crate) struct MergedResult {
+    pub result: serde_json::Value,
+    /// Servers whose results make up `result`.
+    pub sources: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub votes: Option<usize>,
+}
+
+fn success_result(entry: &FanoutEntry) -> Option<&serde_json::Value> {
+    match &entry.outcome {
+        ToolCallOutcome::Success { result, .. } => Some(result),
+        _ => None,
+    }
+}
+
+/// Combine per-server outcomes with `strategy`. Returns `None` when no
+/// server succeeded.
+pub(crate) fn merge_outcomes(entries: &[FanoutEntry], strategy: MergeStrategy) -> Option<MergedResult> {
+    let successes: Vec<(&FanoutEntry, &serde_json::Value)> =
+        entries.iter().filter_map(|e| success_result(e).map(|r| (e, r))).collect();
+    if successes.is_empty() {
+        return None;
+    }
+    match strategy {
+        MergeStrategy::Concatenate => {
+            let mut content = Vec::new();
+            let mut structured = serde_json::Map::new();
+            for (entry, result) in &successes {
+                content.push(json!({ "type": "text", "text": format!("### {}", entry.alias) }));
+                if let Some(blocks) = result.get("content").and_then(|c| c.as_array()) {
+                    content.extend(blocks.iter().cloned());
+                }
+                if let Some(value) = result.get("structuredContent") {
+                    structured.insert(entry.alias.clone(), value.clone());
+                }
+            }
+            let mut result = json!({ "content": content, "isError": false });
+            if !structured.is_empty() {
+                result["structuredContent"] = serde_json::Value::Object(structured);
+            }
+            Some(MergedResult {
+                result,
+                sources: successes.iter().map(|(e, _)| e.url.clone()).collect(),
+                votes: None,
+            })
+        }
+        MergeStrategy::FirstSuccess => {
+            let (entry, result) = successes
+                .iter()
+                .min_by(|(a, _), (b, _)| a.elapsed_ms.partial_cmp(&b.elapsed_ms).unwrap_or(std::cmp::Ordering::Equal))?;
+            Some(MergedResult {
+                result: (*result).clone(),
+                sources: vec![entry.url.clone()],
+                votes: None,
+            })
+        }
+        MergeStrategy::Plurality => {
+            // Group by rendered text so formatting-only differences in the
+            // raw JSON do not split the vote. Ties go to the earliest group.
+            let mut groups: Vec<(String, Vec<(&FanoutEntry, &serde_json::Value)>)> = Vec::new();
+            for (entry, result) in &successes {
+                let key = ToolResult::from_value(result).to_cbus_text();
+                match groups.iter_mut().find(|(k, _)| *k == key) {
+                    Some((_, members)) => members.push((entry, result)),
+                    None => groups.push((key, vec![(entry, result)])),
+                }
+            }
+            let best = groups.iter().max_by(|(_, a), (_, b)| a.len().cmp(&b.len()).then(std::cmp::Ordering::Greater))?;
+            let members = &best.1;
+            Some(MergedResult {
+                result: members[0].1.clone(),
+                sources: members.iter().map(|(e, _)| e.url.clone()).collect(),
+                votes: Some(members.len()),
+            })
+        }
+    }
+}
+
+/// Resolve the requested servers (URLs or aliases) or, when none are given,
+/// every registered provider of `tool_name`.
+fn fanout_targets(tool_name: &str, servers: &[String]) -> Result<Vec<(String, String)>, String> {
+    let registry = SERVER_REGISTRY.lock().unwrap();
+    if servers.is_empty() {
+        let mut targets: Vec<(String, String)> = registry
+            .servers
+            .values()
+            .filter(|server| server.tools.iter().any(|t| t.name == tool_name))
+            .map(|server| (server.url.clone(), server_alias(server)))
+            .collect();
+        targets.sort();
+        return Ok(targets);
+    }
+    servers
+        .iter()
+        .map(|wanted| {
+            if let Some(server) = registry.servers.get(wanted) {
+                return Ok((server.url.clone(), server_alias(server)));
+            }
+            let matching: Vec<_> = registry.servers.values().filter(|server| &server_alias(server) == wanted).collect();
+            match matching.as_slice() {
+                [server] => Ok((server.url.clone(), server_alias(server))),
+                [] => Err(format!("Unknown server: {}", wanted)),
+                _ => Err(format!("Ambiguous alias: {}", wanted)),
+            }
+        })
+        .collect()
+}
+
+/// Call `tool_name` on every target concurrently. Calls still running when
+/// the shared timeout expires are reported as timed out and their late
+/// results discarded; with `first_success`, calls still running when one
+/// succeeds are abandoned the same way. Time spent waiting for approval
+/// counts against the timeout, and approvals still pending for abandoned
+/// calls are rejected.
+pub(crate) async fn fanout(
+    tool_name: &str,
+    arguments: serde_json::Value,
+    targets: Vec<(String, String)>,
+    timeout_ms: u32,
+    engram_id: Option<String>,
+    strategy: MergeStrategy,
+) -> Vec<FanoutEntry> {
+    let started = js_sys::Date::now();
+    let slots: Rc<RefCell<Vec<CompletedCall>>> = Rc::new(RefCell::new(vec![None; targets.len()]));
+    let mut first_success = None;
+    let succeeded = js_sys::Promise::new(&mut |resolve, _reject| first_success = Some(resolve));
+    let calls = js_sys::Array::new();
+    for (index, (url, _)) in targets.iter().enumerate() {
+        let slots = slots.clone();
+        let first_success = first_success.clone().filter(|_| strategy == MergeStrategy::FirstSuccess);
+        let (url, tool_name, arguments, engram_id) = (url.clone(), tool_name.to_string(), arguments.clone(), engram_id.clone());
+        calls.push(&wasm_bindgen_futures::future_to_promise(async move {
+            let outcome = run_tool_call(&url, &tool_name, arguments, engram_id.as_deref(), None, true).await;
+            let success = matches!(outcome, ToolCallOutcome::Success { .. });
+            slots.borrow_mut()[index] = Some((outcome, js_sys::Date::now() - started));
+            if let Some(resolve) = first_success.filter(|_| success) {
+                let _ = resolve.call0(&JsValue::NULL);
+            }
+            Ok(JsValue::TRUE)
+        }));
+    }
+    let all = js_sys::Promise::all(&calls);
+    let _ = JsFuture::from(js_sys::Promise::race(&js_sys::Array::of3(&all, &succeeded, &delay(timeout_ms)))).await;
+    let elapsed_ms = js_sys::Date::now() - started;
+    let timed_out = elapsed_ms >= timeout_ms as f64;
+    let mut abandoned = Vec::new();
+    let entries: Vec<FanoutEntry> = {
+        let mut slots = slots.borrow_mut();
+        targets
+            .into_iter()
+            .enumerate()
+            .map(|(index, (url, alias))| match slots[index].take() {
+                Some((outcome, elapsed_ms)) => FanoutEntry { url, alias, outcome, elapsed_ms, timed_out: false },
+                None => {
+                    abandoned.push(url.clone());
+                    let message = match timed_out {
+                        true => format!("Timed out after {} ms", timeout_ms),
+                        false => "Abandoned after another server succeeded".to_string(),
+                    };
+                    FanoutEntry {
+                        url,
+                        alias,
+                        outcome: ToolCallOutcome::TransportError { message, status: None },
+                        elapsed_ms,
+                        timed_out,
+                    }
+                }
+            })
+            .collect()
+    };
+    if !abandoned.is_empty() {
+        reject_pending(
+            |item| {
+                abandoned.contains(&item.url)
+                    && item.tool_name == tool_name
+                    && item.engram_id == engram_id
+                    && item.created_at as f64 >= started.floor()
+            },
+            "The fan-out call stopped waiting",
+        );
+    }
+    entries
+}
+
+/// Call a tool on several servers at once and merge the results.
+///
+/// `servers` is an array of URLs or aliases; when empty or omitted every
+/// registered provider is used. `strategy` is `concatenate` (default),
+/// `first_success` or `plurality` (alias `majority`). Returns
+/// `{ tool_name, strategy, outcomes, merged }` where `merged` is null when
+/// no server succeeded.
+#[wasm_bindgen]
+pub async fn call_tool_fanout(
+    name: &str,
+    args: JsValue,
+    servers: JsValue,
+    strategy: Option<String>,
+    timeout_ms: Option<u32>,
+    engram_id: Option<String>,
+) -> Result<JsValue, JsValue> {
+    let strategy = MergeStrategy::parse(strategy.as_deref()).map_err(|e| JsValue::from_str(&e))?;
+    let arguments: serde_json::Value = serde_wasm_bindgen::from_value(args)
+        .map_err(|e| JsValue::from_str(&format!("Invalid args: {}", e)))?;
+    let servers: Vec<String> = if servers.is_undefined() || servers.is_null() {
+        Vec::new()
+    } else {
+        serde_wasm_bindgen::from_value(servers).map_err(|e| JsValue::from_str(&format!("Invalid servers: {}", e)))?
+    };
+    let targets = fanout_targets(name, &servers).map_err(|e| JsValue::from_str(&e))?;
+    if targets.is_empty() {
+        return Err(JsValue::from_str(&format!("No registered server provides tool '{}'", name)));
+    }
+    info(&format!("Fanning out '{}' to {} server(s)", name, targets.len()));
+    let entries = fanout(name, arguments, targets, timeout_ms.unwrap_or(DEFAULT_FANOUT_TIMEOUT_MS), engram_id, strategy).await;
+    let merged = merge_outcomes(&entries, strategy);
+    Ok(JsValue::from_str(&json!({
+        "tool_name": name,
+        "strategy": strategy,
+        "outcomes": entries,
+        "merged": merged
+    }).to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn majority_is_an_alias_of_plurality() {
+        assert_eq!(MergeStrategy::parse(Some("majority")), Ok(MergeStrategy::Plurality));
+        assert_eq!(MergeStrategy::parse(Some("plurality")), Ok(MergeStrategy::Plurality));
+        assert_eq!(MergeStrategy::parse(None), Ok(MergeStrategy::Concatenate));
+        assert!(MergeStrategy::parse(Some("unanimous")).is_err());
+    }
+}
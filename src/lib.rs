@@ -4,27 +4,101 @@ use std::sync::atomic::{AtomicU64, Ordering, AtomicBool};
 use wasm_bindgen::prelude::*;
 use serde::{Deserialize};
 use serde_json::{self, json};
-use js_sys::Date;
-use web_sys;
 use wasm_bindgen_futures::JsFuture;
 use wasm_bindgen::JsCast;
 use std::sync::LazyLock;
 use std::collections::HashMap;
-use serde_wasm_bindgen;
 
 include!("build_info.rs");
 include!("bootrom.rs");
 
+mod agent;
+mod bootrom_builder;
+mod breaker;
+mod catalog;
+mod content;
+mod context;
+mod conversation;
+mod dialects;
+mod extract;
+mod fanout;
+mod memory;
+mod pipeline;
+mod policy;
+mod router;
+mod schema;
+mod search;
+mod simulate;
+mod stem;
+mod template;
+mod tool_call;
+mod validate;
+mod vector;
+
+use tool_call::run_tool_call;
+
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 const METADATA_VERSION: &str = "1.0.0";
 const DEFAULT_SERVER_URL: &str = "http://localhost:8081";
+const SERVER_STATUS_CONNECTED: &str = "connected";
+const SERVER_STATUS_FAILED: &str = "failed";
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 struct McpTool {
     name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
     description: String,
     version: String,
     parameters: Vec<ToolParameter>,
+    #[serde(rename = "inputSchema", default)]
+    input_schema: serde_json::Value,
+    #[serde(rename = "outputSchema", default, skip_serializing_if = "Option::is_none")]
+    output_schema: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    annotations: Option<policy::ToolAnnotations>,
+}
+
+impl McpTool {
+    /// Build a tool from a `tools/list` entry, flattening its `inputSchema`
+    /// into parameters.
+    fn from_definition(name: &str, definition: &serde_json::Value) -> Self {
+        let input_schema = definition.get("inputSchema")
+            .cloned()
+            .unwrap_or_else(|| json!({ "type": "object" }));
+        McpTool {
+            name: name.to_string(),
+            title: definition.get("title")
+                .and_then(|v| v.as_str())
+                .map(|t| t.to_string()),
+            description: definition.get("description")
+                .and_then(|v| v.as_str())
+                .unwrap_or("")
+                .to_string(),
+            version: definition.get("version")
+                .and_then(|v| v.as_str())
+                .unwrap_or("unknown")
+                .to_string(),
+            parameters: schema::JsonSchema::from_value(&input_schema).to_parameters(),
+            input_schema,
+            output_schema: definition.get("outputSchema").cloned(),
+            annotations: definition.get("annotations")
+                .and_then(|v| serde_json::from_value(v.clone()).ok()),
+        }
+    }
+
+    /// Parse the `tools` array of a `tools/list` result, skipping unnamed entries.
+    fn list_from_result(result: &serde_json::Value) -> Vec<McpTool> {
+        result.get("tools")
+            .and_then(|v| v.as_array())
+            .map(|tools| tools.iter()
+                .filter_map(|tool| {
+                    let name = tool.get("name").and_then(|v| v.as_str())?;
+                    Some(McpTool::from_definition(name, tool))
+                })
+                .collect())
+            .unwrap_or_default()
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -34,6 +108,20 @@ struct ToolParameter {
     required: bool,
     #[serde(rename = "type")]
     param_type: String,
+    #[serde(default)]
+    nullable: bool,
+    #[serde(rename = "enum", default, skip_serializing_if = "Option::is_none")]
+    enum_values: Option<Vec<serde_json::Value>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    default: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    format: Option<String>,
+    /// Nested properties when `type` is `object`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    properties: Vec<ToolParameter>,
+    /// Element description when `type` is `array`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    items: Option<Box<ToolParameter>>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -45,6 +133,20 @@ struct McpServer {
     tools: Vec<McpTool>,
     last_health_check: u64,
     session_id: Option<String>,
+    #[serde(default)]
+    policy: policy::ServerToolPolicy,
+    /// Short name used in qualified tool names (`alias/tool`).
+    #[serde(default)]
+    alias: Option<String>,
+    /// Higher wins when several servers provide the same tool.
+    #[serde(default)]
+    priority: i32,
+    /// Smoothed round-trip time of recent tool calls.
+    #[serde(default)]
+    latency_ms: Option<f64>,
+    /// Usage guidance the server returned from `initialize`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    instructions: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -92,6 +194,7 @@ struct JsonRpcError {
 
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "UPPERCASE")]
+#[allow(clippy::upper_case_acronyms)]
 enum LogLevel {
     DEBUG,
     INFO,
@@ -117,16 +220,10 @@ impl LogEntry {
     }
 }
 
-#[derive(Serialize, Deserialize)]
-struct MemoryEvent {
-    timestamp: u64,
-    text: String,
-}
-
 #[derive(Serialize, Deserialize)]
 struct ModuleMetadata {
     version: String,
-    memory_events: Vec<MemoryEvent>,
+    memory_events: Vec<memory::MemoryEvent>,
     last_health_check: u64,
 }
 
@@ -141,7 +238,18 @@ extern "C" {
 
 #[wasm_bindgen]
 pub fn get_timestamp() -> u64 {
-    Date::now() as u64
+    now_ms() as u64
+}
+
+/// Milliseconds since the epoch. Native test builds have no JS `Date` and
+/// read the system clock instead.
+fn now_ms() -> f64 {
+    #[cfg(not(test))]
+    return js_sys::Date::now();
+    #[cfg(test)]
+    return std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0.0, |elapsed| elapsed.as_secs_f64() * 1000.0);
 }
 
 #[wasm_bindgen]
@@ -160,7 +268,10 @@ pub fn increment_uptime() {
 fn log_with_level(level: LogLevel, message: String) {
     let entry = LogEntry::new(level, message);
     if let Ok(json) = serde_json::to_string(&entry) {
-        log(&format!("[WASM] {}", json));
+        // Native test builds have no console to log to
+        if cfg!(not(test)) {
+            log(&format!("[WASM] {}", json));
+        }
     }
 }
 
@@ -361,78 +472,62 @@ pub async fn handle_message(message: &str) -> Result<JsValue, JsValue> {
 pub fn get_metadata() -> String {
     let metadata = ModuleMetadata {
         version: METADATA_VERSION.to_string(),
-        memory_events: Vec::new(),
+        memory_events: memory::live_events(),
         last_health_check: get_timestamp(),
     };
     
     serde_json::to_string(&metadata).unwrap_or_default()
 }
 
-// In-memory metadata for memory events
-static mut MODULE_METADATA: Option<ModuleMetadata> = None;
-
-#[wasm_bindgen]
-pub fn add_memory_event(text: &str) {
-    let event = MemoryEvent {
-        timestamp: get_timestamp(),
-        text: text.to_string(),
-    };
-    unsafe {
-        let meta = MODULE_METADATA.get_or_insert(ModuleMetadata {
-            version: METADATA_VERSION.to_string(),
-            memory_events: Vec::new(),
-            last_health_check: get_timestamp(),
-        });
-        meta.memory_events.push(event);
-    }
-}
-
-#[wasm_bindgen]
-pub fn clear_memory_events() -> Result<(), String> {
-    unsafe {
-        MODULE_METADATA = Some(ModuleMetadata {
-            version: METADATA_VERSION.to_string(),
-            memory_events: Vec::new(),
-            last_health_check: get_timestamp(),
-        });
-    }
-    Ok(())
-}
-
 #[wasm_bindgen]
 pub async fn initialize_mcp_server(url: &str) -> Result<JsValue, JsValue> {
     info(&format!("Initializing MCP server at {}", url));
     
-    let mut registry = SERVER_REGISTRY.lock().unwrap();
-    
-    // Create new server entry
-    let server = McpServer {
-        url: url.to_string(),
-        name: format!("MCP Server at {}", url),
-        version: "unknown".to_string(),
-        status: "initializing".to_string(),
-        tools: Vec::new(),
-        last_health_check: get_timestamp(),
-        session_id: None,
-    };
-    
-    // Insert or update the server entry
-    registry.servers.insert(url.to_string(), server);
-    
-    // If this is the first server, set it as default
-    if registry.default_server.is_none() {
-        registry.default_server = Some(url.to_string());
-        info(&format!("Set {} as default server", url));
+    {
+        let mut registry = SERVER_REGISTRY.lock().unwrap();
+        
+        // Keep access rules and routing settings across re-initialization
+        let (policy, alias, priority) = registry.servers.get(url)
+            .map(|existing| (existing.policy.clone(), existing.alias.clone(), existing.priority))
+            .unwrap_or_default();
+        
+        // Create new server entry
+        let server = McpServer {
+            url: url.to_string(),
+            name: format!("MCP Server at {}", url),
+            version: "unknown".to_string(),
+            status: "initializing".to_string(),
+            tools: Vec::new(),
+            last_health_check: get_timestamp(),
+            session_id: None,
+            policy,
+            alias,
+            priority,
+            latency_ms: None,
+            instructions: None,
+        };
+        
+        // Insert or update the server entry
+        registry.servers.insert(url.to_string(), server);
+        
+        // If this is the first server, set it as default
+        if registry.default_server.is_none() {
+            registry.default_server = Some(url.to_string());
+            info(&format!("Set {} as default server", url));
+        }
     }
     
-    // Perform initial handshake
-    match perform_server_handshake(url).await {
+    // Perform initial handshake without holding the registry lock across the await
+    let handshake = perform_server_handshake(url).await;
+    let mut registry = SERVER_REGISTRY.lock().unwrap();
+    match handshake {
         Ok(server_info) => {
             let server = registry.servers.get_mut(url).unwrap();
             server.version = server_info.version.clone();
             server.status = "connected".to_string();
             server.tools = server_info.tools.clone();
             server.session_id = server_info.session_id.clone();
+            server.instructions = server_info.instructions.clone();
             
             info(&format!("Successfully initialized MCP server at {}", url));
             Ok(JsValue::from_str(&json!({
@@ -475,7 +570,7 @@ async fn perform_server_handshake(url: &str) -> Result<McpServer, String> {
     });
     
     if DEBUG_MODE.load(Ordering::Relaxed) {
-        debug(&format!("Sending handshake request: {}", handshake_request.to_string()));
+        debug(&format!("Sending handshake request: {}", handshake_request));
     }
     
     let options = js_sys::Object::new();
@@ -525,36 +620,31 @@ async fn perform_server_handshake(url: &str) -> Result<McpServer, String> {
                                 .map(|n| n.to_string())
                                 .unwrap_or_else(|| "unknown".to_string());
 
-                            let tools = response.result.as_ref()
+                            // `capabilities.tools` only says tools exist; they are listed once initialized
+                            let has_tools = response.result.as_ref()
                                 .and_then(|v| v.get("capabilities"))
                                 .and_then(|v| v.get("tools"))
-                                .and_then(|v| v.as_object())
-                                .map(|tools| tools.iter()
-                                    .filter_map(|(name, info)| {
-                                        Some(McpTool {
-                                            name: name.clone(),
-                                            description: info.get("description")
-                                                .and_then(|v| v.as_str())
-                                                .unwrap_or("")
-                                                .to_string(),
-                                            version: info.get("version")
-                                                .and_then(|v| v.as_str())
-                                                .unwrap_or("unknown")
-                                                .to_string(),
-                                            parameters: Vec::new(),
-                                        })
-                                    })
-                                    .collect::<Vec<_>>())
-                                .unwrap_or_default();
-
-                            let server_info = McpServer {
+                                .is_some();
+
+                            let instructions = response.result.as_ref()
+                                .and_then(|v| v.get("instructions"))
+                                .and_then(|v| v.as_str())
+                                .filter(|i| !i.trim().is_empty())
+                                .map(|i| i.to_string());
+
+                            let mut server_info = McpServer {
                                 url: url.to_string(),
                                 name,
                                 version,
                                 status: "connected".to_string(),
-                                tools,
+                                tools: Vec::new(),
                                 last_health_check: get_timestamp(),
                                 session_id: session_id.clone(),
+                                policy: Default::default(),
+                                alias: None,
+                                priority: 0,
+                                latency_ms: None,
+                                instructions,
                             };
                             
                             // Send initialized notification
@@ -575,6 +665,13 @@ async fn perform_server_handshake(url: &str) -> Result<McpServer, String> {
                                 
                                 let _ = JsFuture::from(fetch(url, &options)).await;
                             }
+
+                            if has_tools {
+                                match fetch_tool_list(url, server_info.session_id.as_deref()).await {
+                                    Ok(tools) => server_info.tools = tools,
+                                    Err(e) => error(&format!("Failed to list tools of {}: {}", url, e)),
+                                }
+                            }
                             
                             Ok(server_info)
                         }
@@ -591,6 +688,58 @@ async fn perform_server_handshake(url: &str) -> Result<McpServer, String> {
     }
 }
 
+/// Every tool `url` lists, following `nextCursor` across pages.
+async fn fetch_tool_list(url: &str, session_id: Option<&str>) -> Result<Vec<McpTool>, String> {
+    let mut tools = Vec::new();
+    let mut cursor: Option<String> = None;
+    loop {
+        let mut params = json!({});
+        if let Some(cursor) = &cursor {
+            params["cursor"] = json!(cursor);
+        }
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": js_sys::Date::now() as u64,
+            "method": "tools/list",
+            "params": params
+        });
+        let options = js_sys::Object::new();
+        let headers = web_sys::Headers::new().unwrap();
+        headers.set("Content-Type", "application/json").unwrap();
+        headers.set("Accept", "application/json, text/event-stream").unwrap();
+        if let Some(session_id) = session_id {
+            headers.set("mcp-session-id", session_id).unwrap();
+        }
+        js_sys::Reflect::set(&options, &"headers".into(), &headers.into()).unwrap();
+        js_sys::Reflect::set(&options, &"body".into(), &JsValue::from_str(&request.to_string())).unwrap();
+        js_sys::Reflect::set(&options, &"method".into(), &"POST".into()).unwrap();
+
+        let response = JsFuture::from(fetch(url, &options))
+            .await
+            .map_err(|e| format!("Failed to connect to server: {:?}", e))?;
+        let resp = response.dyn_ref::<web_sys::Response>().cloned().ok_or("Failed to get response")?;
+        if !resp.ok() {
+            return Err(format!("Server returned error response (status {})", resp.status()));
+        }
+        let json = JsFuture::from(resp.json().map_err(|e| format!("{:?}", e))?)
+            .await
+            .map_err(|e| format!("Failed to parse JSON: {:?}", e))?;
+        let text = js_sys::JSON::stringify(&json).ok().and_then(|t| t.as_string()).unwrap_or_default();
+        let response = serde_json::from_str::<JsonRpcResponse>(&text)
+            .map_err(|e| format!("Failed to parse tools response: {}", e))?;
+        if let Some(error) = response.error {
+            return Err(format!("JSON-RPC error {}: {}", error.code, error.message));
+        }
+        let result = response.result.unwrap_or_default();
+        tools.extend(McpTool::list_from_result(&result));
+        // Stop on a repeated cursor rather than loop forever
+        match result.get("nextCursor").and_then(|c| c.as_str()) {
+            Some(next) if !next.is_empty() && cursor.as_deref() != Some(next) => cursor = Some(next.to_string()),
+            _ => return Ok(tools),
+        }
+    }
+}
+
 #[wasm_bindgen]
 pub fn get_server_info() -> Result<JsValue, JsValue> {
     let registry = SERVER_REGISTRY.lock().unwrap();
@@ -670,7 +819,7 @@ pub async fn list_tools(url: &str) -> Result<JsValue, JsValue> {
             }
         }
     });
-    debug(&format!("[list_tools] Sending tools list request: {}", tools_request.to_string()));
+    debug(&format!("[list_tools] Sending tools list request: {}", tools_request));
     let options = js_sys::Object::new();
     let headers = web_sys::Headers::new().unwrap();
     headers.set("Content-Type", "application/json").unwrap();
@@ -704,8 +853,13 @@ pub async fn list_tools(url: &str) -> Result<JsValue, JsValue> {
                             if let Ok(response) = serde_json::from_str::<JsonRpcResponse>(&json_str) {
                                 if let Some(result) = response.result {
                                     info("[list_tools] Successfully parsed tools result");
+                                    let tools = McpTool::list_from_result(&result);
+                                    if let Some(server) = SERVER_REGISTRY.lock().unwrap().servers.get_mut(url) {
+                                        server.tools = tools.clone();
+                                    }
                                     Ok(JsValue::from_str(&json!({
-                                        "result": result
+                                        "result": result,
+                                        "tools": tools
                                     }).to_string()))
                                 } else if let Some(error) = response.error {
                                     info("[list_tools] Error in tools response");
@@ -751,59 +905,20 @@ pub fn get_compiled_info() -> String {
     )
 }
 
+/// Call a tool and return a JSON-encoded `ToolCallOutcome`.
+///
+/// Arguments are coerced and validated against the tool's cached
+/// `inputSchema` first, then checked against the server's access rules for
+/// `engram_id` and the confirmation policy, which may hold the call until it
+/// is approved. The outcome's `kind` is one of `success`, `tool_error`,
+/// `rpc_error`, `transport_error`, `invalid_arguments`, `policy` or
+/// `rejected`; only undecodable `args` are reported as a rejected promise.
 #[wasm_bindgen]
-pub async fn call_tool(url: &str, tool_name: &str, args: JsValue) -> Result<JsValue, JsValue> {
+pub async fn call_tool(url: &str, tool_name: &str, args: JsValue, engram_id: Option<String>) -> Result<JsValue, JsValue> {
     info(&format!("Calling tool '{}' on {}", tool_name, url));
     let args_value: serde_json::Value = serde_wasm_bindgen::from_value(args).map_err(|e| JsValue::from_str(&format!("Invalid args: {}", e)))?;
-    let call_request = json!({
-        "jsonrpc": "2.0",
-        "id": js_sys::Date::now() as u64,
-        "method": "tools/call",
-        "params": {
-            "name": tool_name,
-            "arguments": args_value
-        }
-    });
-    let options = js_sys::Object::new();
-    let headers = web_sys::Headers::new().unwrap();
-    headers.set("Content-Type", "application/json").unwrap();
-    if let Some(server) = SERVER_REGISTRY.lock().unwrap().servers.get(url) {
-        if let Some(session_id) = &server.session_id {
-            headers.set("mcp-session-id", session_id).unwrap();
-        }
-    }
-    js_sys::Reflect::set(&options, &"headers".into(), &headers.into()).unwrap();
-    js_sys::Reflect::set(&options, &"body".into(), &JsValue::from_str(&call_request.to_string())).unwrap();
-    js_sys::Reflect::set(&options, &"method".into(), &"POST".into()).unwrap();
-    let promise = fetch(url, &options);
-    match JsFuture::from(promise).await {
-        Ok(response) => {
-            let resp: Option<web_sys::Response> = response.dyn_ref::<web_sys::Response>().cloned();
-            if let Some(resp) = resp {
-                if resp.ok() {
-                    let status = resp.status();
-                    let json = JsFuture::from(resp.json().unwrap()).await?;
-                    let text = js_sys::JSON::stringify(&json).unwrap().as_string().unwrap_or_default();
-                    if DEBUG_MODE.load(Ordering::Relaxed) {
-                        debug(&format!("Received response (status {}): {}", status, text));
-                    }
-                    match serde_json::from_str::<JsonRpcResponse>(&text) {
-                        Ok(response) => {
-                            let json_str = serde_json::to_string(&response.result).unwrap_or_default();
-                            debug(&format!("Received tool call response: {}", json_str));
-                            Ok(JsValue::from_str(&json_str))
-                        }
-                        Err(e) => Err(JsValue::from_str(&format!("Failed to parse response: {}", e)))
-                    }
-                } else {
-                    Err(JsValue::from_str("Server returned error response"))
-                }
-            } else {
-                Err(JsValue::from_str("Failed to get response"))
-            }
-        }
-        Err(e) => Err(JsValue::from_str(&format!("Failed to connect to server: {:?}", e)))
-    }
+    let outcome = run_tool_call(url, tool_name, args_value, engram_id.as_deref(), None, true).await;
+    Ok(JsValue::from_str(&outcome.to_json_string()))
 }
 
 #[wasm_bindgen]
@@ -812,12 +927,15 @@ pub fn set_debug_mode(enabled: bool) {
     log(&format!("set_debug_mode called: {}", enabled));
 }
 
+/// The bootrom imprint, composed from its sections against the current
+/// tool registry.
 #[wasm_bindgen]
 pub fn get_bootrom() -> String {
     let bootrom_event = serde_json::json!({
         "id": "bootrom",
         "name": "BOOTROM",
-        "text": BOOTROM_DATA,
+        "text": bootrom_builder::compose_bootrom(),
+        "version": bootrom_builder::version_stamp(),
         "timestamp": 0u64
     });
     bootrom_event.to_string()
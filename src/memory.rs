@@ -0,0 +1,263 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{self, json};
+use std::cell::RefCell;
+use std::sync::{LazyLock, Mutex};
+use wasm_bindgen::prelude::*;
+
+use crate::search;
+use crate::{debug, get_timestamp, info};
+
+/// Events kept before the oldest are dropped.
+const MAX_EVENTS: usize = 10_000;
+
+/// Where a memory came from.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum MemorySource {
+    #[default]
+    User,
+    Tool,
+    Resource,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct MemoryEvent {
+    pub id: u64,
+    pub timestamp: u64,
+    pub text: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub source: MemorySource,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<u64>,
+    /// Unscoped events belong to every engram.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub engram_id: Option<String>,
+}
+
+impl MemoryEvent {
+    fn is_live(&self, now: u64) -> bool {
+        self.expires_at.is_none_or(|expires_at| expires_at > now)
+    }
+}
+
+/// Optional fields for `add_memory_event`.
+#[derive(Debug, Deserialize, Default)]
+#[serde(default)]
+struct MemoryEventOptions {
+    tags: Vec<String>,
+    source: MemorySource,
+    /// Lifetime from now; `expires_at` wins when both are set.
+    ttl_ms: Option<u64>,
+    expires_at: Option<u64>,
+    engram_id: Option<String>,
+}
+
+/// Filters for `query_memory_events`. Every given filter must match.
+#[derive(Debug, Deserialize, Default)]
+#[serde(default)]
+pub(crate) struct MemoryQuery {
+    /// Inclusive lower bound on `timestamp`.
+    pub since: Option<u64>,
+    /// Exclusive upper bound on `timestamp`.
+    pub until: Option<u64>,
+    /// Events must carry all of these tags.
+    pub tags: Vec<String>,
+    /// Case-insensitive substring of the text.
+    pub text: Option<String>,
+    pub source: Option<MemorySource>,
+    /// This engram's events and unscoped ones.
+    pub engram_id: Option<String>,
+    pub limit: Option<usize>,
+    /// Order by time ascending; newest first by default.
+    pub oldest_first: bool,
+}
+
+impl MemoryQuery {
+    fn matches(&self, event: &MemoryEvent, needle: Option<&str>) -> bool {
+        self.since.is_none_or(|since| event.timestamp >= since)
+            && self.until.is_none_or(|until| event.timestamp < until)
+            && self.tags.iter().all(|tag| event.tags.contains(tag))
+            && needle.is_none_or(|needle| event.text.to_lowercase().contains(needle))
+            && self.source.is_none_or(|source| event.source == source)
+            && (self.engram_id.is_none() || event.engram_id.is_none() || event.engram_id == self.engram_id)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct MemoryStore {
+    next_id: u64,
+    /// Oldest first.
+    events: Vec<MemoryEvent>,
+}
+
+impl Default for MemoryStore {
+    fn default() -> Self {
+        MemoryStore { next_id: 1, events: Vec::new() }
+    }
+}
+
+impl MemoryStore {
+    /// Drop expired events, then the oldest beyond `MAX_EVENTS`.
+    fn prune(&mut self, now: u64) {
+        self.events.retain(|event| {
+            let live = event.is_live(now);
+            if !live {
+                search::remove_memory_event(event.id);
+            }
+            live
+        });
+        if self.events.len() > MAX_EVENTS {
+            let excess = self.events.len() - MAX_EVENTS;
+            self.events.drain(..excess).for_each(|event| search::remove_memory_event(event.id));
+        }
+    }
+
+    pub(crate) fn query(&self, query: &MemoryQuery, now: u64) -> Vec<MemoryEvent> {
+        let needle = query.text.as_deref().map(str::to_lowercase);
+        let matching = self
+            .events
+            .iter()
+            .filter(|event| event.is_live(now) && query.matches(event, needle.as_deref()));
+        let limit = query.limit.unwrap_or(usize::MAX);
+        if query.oldest_first {
+            matching.take(limit).cloned().collect()
+        } else {
+            matching.rev().take(limit).cloned().collect()
+        }
+    }
+}
+
+static MEMORY_STORE: LazyLock<Mutex<MemoryStore>> = LazyLock::new(|| Mutex::new(MemoryStore::default()));
+
+thread_local! {
+    static PERSIST_HOOK: RefCell<Option<js_sys::Function>> = const { RefCell::new(None) };
+}
+
+fn persist(store: &MemoryStore) {
+    PERSIST_HOOK.with(|hook| {
+        if let Some(hook) = hook.borrow().as_ref() {
+            let snapshot = serde_json::to_string(store).unwrap_or_default();
+            if let Err(e) = hook.call1(&JsValue::NULL, &JsValue::from_str(&snapshot)) {
+                debug(&format!("Persisting memory events failed: {:?}", e));
+            }
+        }
+    });
+}
+
+/// Events matching `query`, as `query_memory_events` returns them.
+pub(crate) fn query_events(query: &MemoryQuery) -> Vec<MemoryEvent> {
+    MEMORY_STORE.lock().unwrap().query(query, get_timestamp())
+}
+
+/// Unexpired events, oldest first.
+pub(crate) fn live_events() -> Vec<MemoryEvent> {
+    let now = get_timestamp();
+    let store = MEMORY_STORE.lock().unwrap();
+    store.events.iter().filter(|event| event.is_live(now)).cloned().collect()
+}
+
+pub(crate) fn live_event_ids() -> Vec<u64> {
+    let now = get_timestamp();
+    let store = MEMORY_STORE.lock().unwrap();
+    store.events.iter().filter(|event| event.is_live(now)).map(|event| event.id).collect()
+}
+
+/// Store an event and return its id. `options_json` may give
+/// `{ tags, source, ttl_ms, expires_at, engram_id }`; `source` is `user`,
+/// `tool` or `resource` and defaults to `user`.
+#[wasm_bindgen]
+pub fn add_memory_event(text: &str, options_json: Option<String>) -> Result<u64, JsValue> {
+    let options: MemoryEventOptions = match options_json {
+        Some(json) => serde_json::from_str(&json)
+            .map_err(|e| JsValue::from_str(&format!("Invalid memory event options: {}", e)))?,
+        None => MemoryEventOptions::default(),
+    };
+    let now = get_timestamp();
+    let mut store = MEMORY_STORE.lock().unwrap();
+    let id = store.next_id;
+    store.next_id += 1;
+    let event = MemoryEvent {
+        id,
+        timestamp: now,
+        text: text.to_string(),
+        tags: options.tags,
+        source: options.source,
+        expires_at: options.expires_at.or(options.ttl_ms.map(|ttl| now + ttl)),
+        engram_id: options.engram_id,
+    };
+    search::index_memory_event(&event);
+    store.events.push(event);
+    store.prune(now);
+    persist(&store);
+    Ok(id)
+}
+
+/// Events matching `{ since, until, tags, text, source, engram_id, limit,
+/// oldest_first }`, as `{ events: [...] }`.
+#[wasm_bindgen]
+pub fn query_memory_events(query_json: &str) -> Result<String, JsValue> {
+    let query: MemoryQuery = serde_json::from_str(query_json)
+        .map_err(|e| JsValue::from_str(&format!("Invalid memory query: {}", e)))?;
+    let events = MEMORY_STORE.lock().unwrap().query(&query, get_timestamp());
+    Ok(json!({ "events": events }).to_string())
+}
+
+#[wasm_bindgen]
+pub fn remove_memory_event(id: u64) -> bool {
+    let mut store = MEMORY_STORE.lock().unwrap();
+    let before = store.events.len();
+    store.events.retain(|event| event.id != id);
+    let removed = store.events.len() != before;
+    search::remove_memory_event(id);
+    if removed {
+        persist(&store);
+    }
+    removed
+}
+
+/// Forget the events scoped to one engram, or every event when `engram_id`
+/// is null.
+#[wasm_bindgen]
+pub fn clear_memory_events(engram_id: Option<String>) -> Result<(), String> {
+    let mut store = MEMORY_STORE.lock().unwrap();
+    match &engram_id {
+        Some(engram_id) => store.events.retain(|event| event.engram_id.as_ref() != Some(engram_id)),
+        None => store.events.clear(),
+    }
+    search::remove_memory_events(engram_id.as_deref());
+    info(&format!("Cleared memory events{}", engram_id.map(|id| format!(" of {}", id)).unwrap_or_default()));
+    persist(&store);
+    Ok(())
+}
+
+/// The store as JSON, for storage.
+#[wasm_bindgen]
+pub fn export_memory_events() -> String {
+    let mut store = MEMORY_STORE.lock().unwrap();
+    store.prune(get_timestamp());
+    serde_json::to_string(&*store).unwrap_or_else(|_| "{}".to_string())
+}
+
+/// Restore a store saved from `export_memory_events` or the persistence
+/// hook. Expired events are dropped.
+#[wasm_bindgen]
+pub fn import_memory_events(store_json: &str) -> Result<(), JsValue> {
+    let mut store: MemoryStore = serde_json::from_str(store_json)
+        .map_err(|e| JsValue::from_str(&format!("Invalid memory store: {}", e)))?;
+    store.next_id = store.next_id.max(store.events.iter().map(|event| event.id).max().unwrap_or(0) + 1);
+    store.events.sort_by_key(|event| event.timestamp);
+    search::remove_memory_events(None);
+    store.events.iter().for_each(search::index_memory_event);
+    store.prune(get_timestamp());
+    *MEMORY_STORE.lock().unwrap() = store;
+    Ok(())
+}
+
+/// Register a function called with the store as JSON whenever it changes;
+/// pass `null` to unregister.
+#[wasm_bindgen]
+pub fn set_memory_persistence(hook: Option<js_sys::Function>) {
+    PERSIST_HOOK.with(|slot| *slot.borrow_mut() = hook);
+}
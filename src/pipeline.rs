@@ -0,0 +1,921 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{self, json};
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::{LazyLock, Mutex};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+
+use crate::breaker::check_call;
+use crate::content::ToolResult;
+use crate::context::{assemble, ContextBudget, ContextInput, ContextItem, CONTEXT_BUDGET};
+use crate::dialects::parse_tool_calls_in;
+use crate::policy::{await_extracted_call, gate_extracted_call, ApprovalGrant, ExtractedGate};
+use crate::router::{resolve_route, route_call, tool_call_target, with_tool_arguments};
+use crate::simulate::is_simulated;
+use crate::template::{render_arguments, ArgumentError, TemplateContext};
+use crate::tool_call::{run_tool_call, ToolCallOutcome};
+use crate::{debug, error, now_ms};
+
+/// Outcome kinds reported to the user instead of the CBus. Tool errors
+/// (`isError: true`) are not among them; the LLM is meant to see those.
+const PROTOCOL_FAILURES: [&str; 5] = ["rpc_error", "transport_error", "invalid_arguments", "policy", "rejected"];
+
+/// A tap as the client configures it. Field names follow the client's
+/// camelCase `tapConfig`.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct TapConfig {
+    pub server_url: Option<String>,
+    pub tool_name: String,
+    /// Fixed arguments; strings may be templates.
+    pub args: serde_json::Map<String, serde_json::Value>,
+    /// Receives the latest message unless it already holds a template.
+    pub connected_string_arg: Option<String>,
+    /// Receives the text of every earlier message.
+    pub connected_array_arg: Option<String>,
+    /// Resolve `tool_name` through the router, with failover, instead of
+    /// calling `server_url` directly.
+    pub use_router: bool,
+    /// Overrides the configured context budget for this tap.
+    pub context_budget: Option<ContextBudget>,
+}
+
+impl TapConfig {
+    fn connected_string_arg(&self) -> Option<&str> {
+        self.connected_string_arg.as_deref().filter(|name| !name.is_empty())
+    }
+
+    fn connected_array_arg(&self) -> Option<&str> {
+        self.connected_array_arg.as_deref().filter(|name| !name.is_empty())
+    }
+
+    fn is_connected(&self) -> bool {
+        self.connected_string_arg().is_some() || self.connected_array_arg().is_some()
+    }
+}
+
+/// What started a tool call. Tap and extracted results are also posted to
+/// the CBus and persisted; console results only go back to the caller.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum TapSource {
+    #[default]
+    Tap,
+    Console,
+    Extracted,
+}
+
+/// Input to `TapPipeline.run`.
+#[derive(Debug, Deserialize, Default)]
+#[serde(default)]
+pub(crate) struct TapRequest {
+    pub source: TapSource,
+    pub tap_config: TapConfig,
+    pub engram_id: Option<String>,
+    pub request_id: Option<serde_json::Value>,
+    /// The requesting client can be answered directly.
+    pub reply: bool,
+    /// Engram history, oldest first. Loaded through the `load` hook when
+    /// omitted and the tap has connected arguments.
+    pub messages: Option<Vec<serde_json::Value>>,
+    /// Imprints, bootrom first.
+    pub memory: Vec<serde_json::Value>,
+    /// A JSON-RPC call to dispatch as an extracted call instead of the tap.
+    pub call: Option<serde_json::Value>,
+}
+
+/// Who an event is for, by the service worker's routing rules.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum EventTarget {
+    /// The client that owns the engram.
+    Engram,
+    /// The client that sent the request.
+    Reply,
+    Broadcast,
+}
+
+/// A message for the service worker to post, in emission order.
+#[derive(Debug, Serialize)]
+pub(crate) struct TapEvent {
+    pub seq: usize,
+    pub target: EventTarget,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub engram_id: Option<String>,
+    /// `{ type: "cbus_message" | "tool_result", ... }`, posted as is.
+    pub message: serde_json::Value,
+}
+
+#[derive(Debug, Serialize, Default)]
+pub(crate) struct RunSummary {
+    /// Tool calls made, the tap included.
+    pub calls: usize,
+    pub events: usize,
+    /// Extracted calls not made: unknown tools, malformed calls, the
+    /// circuit breaker or the approval queue.
+    pub skipped: usize,
+}
+
+/// Engrams whose last result is kept; the least recently updated goes first.
+const LAST_RESULTS_CAPACITY: usize = 256;
+
+/// The last tool result per engram, for `previous_result` in templates.
+static LAST_RESULTS: LazyLock<Mutex<HashMap<String, serde_json::Value>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+fn event_target(engram_id: Option<&str>, request_id: Option<&serde_json::Value>, reply: bool) -> EventTarget {
+    if engram_id.is_some() && request_id.is_some() {
+        EventTarget::Engram
+    } else if reply {
+        EventTarget::Reply
+    } else {
+        EventTarget::Broadcast
+    }
+}
+
+/// Stored messages whose `text` is a string; anything else is skipped.
+pub(crate) fn context_items(values: &[serde_json::Value]) -> Vec<ContextItem> {
+    values
+        .iter()
+        .filter(|value| value.get("text").is_some_and(|text| text.is_string()))
+        .filter_map(|value| serde_json::from_value(value.clone()).ok())
+        .collect()
+}
+
+/// Wire the conversation into the connected arguments: the string argument
+/// gets the latest message unless the tap configures it (as a template),
+/// the array argument the text of every earlier message.
+fn connect_arguments(args: &mut serde_json::Map<String, serde_json::Value>, config: &TapConfig, messages: &[ContextItem]) {
+    if let (Some(name), Some(latest)) = (config.connected_string_arg(), messages.last()) {
+        let templated = config.args.get(name).and_then(|v| v.as_str()).is_some_and(|t| !t.is_empty());
+        if !templated {
+            args.insert(name.to_string(), json!(latest.text));
+        }
+    }
+    if let Some(name) = config.connected_array_arg() {
+        let earlier = &messages[..messages.len().saturating_sub(1)];
+        args.insert(name.to_string(), json!(earlier.iter().map(|m| m.text.clone()).collect::<Vec<_>>()));
+    }
+}
+
+/// Text for outcomes that go to the user rather than the CBus.
+pub(crate) fn protocol_error_text(outcome: &serde_json::Value) -> Option<String> {
+    let kind = outcome.get("kind").and_then(|k| k.as_str())?;
+    if !PROTOCOL_FAILURES.contains(&kind) {
+        return None;
+    }
+    let field = |name: &str| outcome.get(name).and_then(|v| v.as_str()).unwrap_or_default().to_string();
+    Some(match kind {
+        "rpc_error" => format!("JSON-RPC error {}: {}", outcome["code"], field("message")),
+        "invalid_arguments" => {
+            let errors: Vec<String> = outcome["errors"]
+                .as_array()
+                .map(Vec::as_slice)
+                .unwrap_or_default()
+                .iter()
+                .map(|e| {
+                    let path = e["path"].as_str().filter(|p| !p.is_empty()).unwrap_or("/");
+                    format!("{}: {}", path, e["message"].as_str().unwrap_or_default())
+                })
+                .collect();
+            format!("Invalid arguments: {}", errors.join("; "))
+        }
+        "policy" => format!("{} (rule: {})", field("message"), field("rule")),
+        "rejected" => {
+            let reason = outcome["reason"].as_str().map(|r| format!(": {}", r)).unwrap_or_default();
+            format!("Tool call #{} rejected{}", outcome["approval_id"], reason)
+        }
+        _ => field("message"),
+    })
+}
+
+/// Fit the conversation into the tap's context budget. Returns the messages
+/// as templates and connected arguments see them; taps without connected
+/// arguments get the messages unchanged.
+pub(crate) fn connected_messages(
+    config: &TapConfig,
+    messages: Vec<ContextItem>,
+    memory: &[serde_json::Value],
+) -> Vec<ContextItem> {
+    if !config.is_connected() {
+        return messages;
+    }
+    // Fit bootrom, tool catalog, imprints and history into the budget
+    let budget = config.context_budget.clone().unwrap_or_else(|| CONTEXT_BUDGET.lock().unwrap().clone());
+    let assembled = assemble(
+        ContextInput {
+            compose_bootrom: !memory.is_empty(),
+            imprints: context_items(memory.get(1..).unwrap_or_default()),
+            messages,
+            ..Default::default()
+        },
+        &budget,
+    );
+    if !assembled.dropped.is_empty() || !assembled.truncated.is_empty() {
+        debug(&format!(
+            "Tap context trimmed to {} of {} tokens ({} dropped, {} truncated)",
+            assembled.total_tokens,
+            assembled.budget_tokens,
+            assembled.dropped.len(),
+            assembled.truncated.len()
+        ));
+    }
+    assembled
+        .messages
+        .into_iter()
+        .map(|m| ContextItem { id: m.id, role: Some(m.role), text: m.text, timestamp: m.timestamp })
+        .collect()
+}
+
+/// Render the tap's templated arguments ({{ latest.text }}, {% if %},
+/// filters, ...), then wire the conversation into the connected ones.
+/// Only the tap's own configuration is template source; message text
+/// reaches templates as variables and arguments as plain values.
+pub(crate) fn connected_arguments(
+    config: &TapConfig,
+    messages: &[ContextItem],
+    memory: &[serde_json::Value],
+    engram_id: Option<&str>,
+) -> Result<serde_json::Value, Vec<ArgumentError>> {
+    let context = TemplateContext {
+        engram_id: engram_id.map(str::to_string),
+        messages: messages
+            .iter()
+            .map(|m| json!({ "role": m.role, "text": m.text, "timestamp": m.timestamp }))
+            .collect(),
+        memory: memory.get(1..).unwrap_or_default().to_vec(),
+        previous_result: engram_id.and_then(|id| LAST_RESULTS.lock().unwrap().get(id).cloned()),
+        server_url: config.server_url.clone(),
+        tool_name: Some(config.tool_name.clone()),
+    };
+    let mut errors = Vec::new();
+    let rendered = render_arguments(&serde_json::Value::Object(config.args.clone()), &context, "", &mut errors);
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    let mut args = match rendered {
+        serde_json::Value::Object(args) => args,
+        _ => serde_json::Map::new(),
+    };
+    connect_arguments(&mut args, config, messages);
+    Ok(serde_json::Value::Object(args))
+}
+
+/// One `<pointer> line L, column C: message` per error.
+pub(crate) fn template_error_text(errors: &[ArgumentError]) -> String {
+    errors
+        .iter()
+        .map(|e| {
+            let path = if e.path.is_empty() { "/" } else { &e.path };
+            format!("{} line {}, column {}: {}", path, e.error.line, e.error.column, e.error.message)
+        })
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Call the tap's tool, through the router when `use_router` is set. The
+/// outcome is returned as JSON, with `served_by` and `attempts` when routed.
+/// `simulate` is false for the tap itself, which stands in for the model.
+pub(crate) async fn call_tap_tool(
+    config: &TapConfig,
+    args: serde_json::Value,
+    engram_id: Option<&str>,
+    simulate: bool,
+) -> Result<serde_json::Value, String> {
+    if config.use_router {
+        let routed = route_call(&config.tool_name, args, engram_id, simulate).await?;
+        let mut outcome = serde_json::to_value(&routed.outcome).unwrap_or_default();
+        outcome["served_by"] = json!(routed.served_by);
+        outcome["attempts"] = json!(routed.attempts);
+        return Ok(outcome);
+    }
+    let url = config.server_url.as_deref().filter(|url| !url.is_empty()).ok_or("Tap has no serverUrl")?;
+    Ok(serde_json::to_value(run_tool_call(url, &config.tool_name, args, engram_id, None, simulate).await).unwrap_or_default())
+}
+
+/// CBus text of an outcome's result.
+pub(crate) fn outcome_text(outcome: &serde_json::Value) -> String {
+    ToolResult::from_value(outcome.get("result").unwrap_or(outcome)).to_cbus_text()
+}
+
+/// Keep a result for `previous_result` in the engram's next templates.
+pub(crate) fn remember_result(engram_id: &str, tool_name: &str, text: &str, outcome: &serde_json::Value) {
+    let previous = json!({
+        "tool": tool_name,
+        "text": text,
+        "result": outcome.get("result"),
+        "timestamp": now_ms()
+    });
+    let mut results = LAST_RESULTS.lock().unwrap();
+    results.insert(engram_id.to_string(), previous);
+    if results.len() > LAST_RESULTS_CAPACITY {
+        let oldest = results
+            .iter()
+            .filter(|(id, _)| id.as_str() != engram_id)
+            .min_by(|a, b| a.1["timestamp"].as_f64().unwrap_or(0.0).total_cmp(&b.1["timestamp"].as_f64().unwrap_or(0.0)))
+            .map(|(id, _)| id.clone());
+        if let Some(id) = oldest {
+            results.remove(&id);
+        }
+    }
+}
+
+/// Drop an engram's last result, once its conversation is gone.
+pub(crate) fn forget_result(engram_id: &str) {
+    LAST_RESULTS.lock().unwrap().remove(engram_id);
+}
+
+/// One tool call in a run: the tap itself or a call extracted from output.
+struct Step {
+    source: TapSource,
+    config: TapConfig,
+    engram_id: Option<String>,
+    request_id: Option<serde_json::Value>,
+    reply: bool,
+    messages: Option<Vec<serde_json::Value>>,
+    memory: Vec<serde_json::Value>,
+    /// A user's approval of an extracted call, held until the call is done.
+    grant: Option<ApprovalGrant>,
+}
+
+impl Step {
+    fn target(&self) -> EventTarget {
+        event_target(self.engram_id.as_deref(), self.request_id.as_ref(), self.reply)
+    }
+}
+
+enum Work {
+    Tap(Box<Step>),
+    Extracted { call: serde_json::Value, engram_id: Option<String> },
+}
+
+/// What a run needs from its host: event delivery, engram storage and the
+/// tool call itself.
+pub(crate) trait PipelineHooks {
+    fn emit(&self, event: &TapEvent);
+
+    /// Stored messages of an engram.
+    async fn load(&self, engram_id: &str) -> Vec<serde_json::Value>;
+
+    async fn persist(&self, message: &serde_json::Value);
+
+    async fn call_tool(
+        &self,
+        config: &TapConfig,
+        args: serde_json::Value,
+        engram_id: Option<&str>,
+        simulate: bool,
+    ) -> Result<serde_json::Value, String> {
+        call_tap_tool(config, args, engram_id, simulate).await
+    }
+}
+
+/// Callbacks into the service worker. Each receives a string; `load` and
+/// `persist` may return promises, which are awaited.
+pub(crate) struct Hooks {
+    emit: js_sys::Function,
+    load: Option<js_sys::Function>,
+    persist: Option<js_sys::Function>,
+}
+
+async fn call_hook(hook: &js_sys::Function, argument: &str) -> Result<JsValue, JsValue> {
+    let value = hook.call1(&JsValue::NULL, &JsValue::from_str(argument))?;
+    JsFuture::from(js_sys::Promise::resolve(&value)).await
+}
+
+impl Hooks {
+    pub(crate) fn new(emit: js_sys::Function, load: Option<js_sys::Function>, persist: Option<js_sys::Function>) -> Self {
+        Hooks { emit, load, persist }
+    }
+}
+
+impl PipelineHooks for Hooks {
+    fn emit(&self, event: &TapEvent) {
+        let payload = serde_json::to_string(event).unwrap_or_default();
+        if let Err(e) = self.emit.call1(&JsValue::NULL, &JsValue::from_str(&payload)) {
+            error(&format!("Emitting event failed: {:?}", e));
+        }
+    }
+
+    /// Empty without a `load` hook.
+    async fn load(&self, engram_id: &str) -> Vec<serde_json::Value> {
+        let Some(load) = &self.load else {
+            return Vec::new();
+        };
+        match call_hook(load, engram_id).await {
+            Ok(value) => value.as_string().and_then(|json| serde_json::from_str(&json).ok()).unwrap_or_default(),
+            Err(e) => {
+                error(&format!("Loading engram {} failed: {:?}", engram_id, e));
+                Vec::new()
+            }
+        }
+    }
+
+    async fn persist(&self, message: &serde_json::Value) {
+        if let Some(persist) = &self.persist {
+            if let Err(e) = call_hook(persist, &message.to_string()).await {
+                error(&format!("Persisting message failed: {:?}", e));
+            }
+        }
+    }
+}
+
+struct Run<H: PipelineHooks> {
+    hooks: Rc<H>,
+    summary: RunSummary,
+}
+
+impl<H: PipelineHooks> Run<H> {
+    fn emit(&mut self, target: EventTarget, engram_id: Option<&str>, message: serde_json::Value) {
+        let event = TapEvent { seq: self.summary.events, target, engram_id: engram_id.map(str::to_string), message };
+        self.summary.events += 1;
+        self.hooks.emit(&event);
+    }
+
+    /// Report a failure as a `tool_result` error.
+    fn fail(&mut self, step: &Step, text: String, extra: serde_json::Value) {
+        let mut message = json!({
+            "type": "tool_result",
+            "error": text,
+            "source": step.source,
+            "engramId": step.engram_id,
+            "requestId": step.request_id
+        });
+        if let (Some(message), serde_json::Value::Object(extra)) = (message.as_object_mut(), extra) {
+            message.extend(extra);
+        }
+        self.emit(step.target(), step.engram_id.as_deref(), message);
+    }
+
+    /// Turn an extracted JSON-RPC call into a routed step, or report why it
+    /// is not made. Failures here are broadcast, as no client asked for them.
+    /// Calls needing a user's approval are announced by the policy's
+    /// approval listener and run once approved.
+    async fn extracted(&mut self, call: serde_json::Value, engram_id: Option<String>) -> Option<Step> {
+        let skip = |run: &mut Run<H>, text: String, extra: serde_json::Value| {
+            run.summary.skipped += 1;
+            let mut message = json!({ "type": "tool_result", "error": text, "engramId": engram_id, "source": TapSource::Extracted });
+            if let (Some(message), serde_json::Value::Object(extra)) = (message.as_object_mut(), extra) {
+                message.extend(extra);
+            }
+            run.emit(EventTarget::Broadcast, engram_id.as_deref(), message);
+        };
+        let method = call.get("method").and_then(|m| m.as_str()).filter(|_| call["jsonrpc"] == "2.0");
+        let Some(method) = method.map(str::to_string) else {
+            debug(&format!("Ignoring extracted call that is not JSON-RPC 2.0: {}", call));
+            self.summary.skipped += 1;
+            return None;
+        };
+        let params = call.get("params").cloned().unwrap_or(serde_json::Value::Null);
+        let target = tool_call_target(&method, params.clone())
+            .and_then(|(tool_name, arguments)| resolve_route(&tool_name).map(|route| (route, arguments)));
+        let (route, arguments) = match target {
+            Ok(target) => target,
+            Err(e) => {
+                error(&format!("No tool for extracted call '{}': {}", method, e));
+                skip(self, format!("Tool not found: {}", method), json!({}));
+                return None;
+            }
+        };
+        // Rate limits and loop detection, keyed by engram, server and tool
+        if let Err(trip) = check_call(engram_id.as_deref(), &route.url, &route.tool_name, &arguments, now_ms()) {
+            skip(self, trip.message(), json!({ "trip": trip }));
+            return None;
+        }
+        // Simulated calls are answered without being sent and need no approval
+        let gate = match is_simulated(engram_id.as_deref()) {
+            true => Ok(ExtractedGate::Allowed),
+            false => gate_extracted_call(&route.url, &route.tool_name, &arguments, engram_id.as_deref()),
+        };
+        let decision = match gate {
+            Ok(ExtractedGate::Allowed) => Ok(None),
+            Ok(ExtractedGate::Pending(item)) => await_extracted_call(&item).await.map(Some),
+            Err(block) => Err(block),
+        };
+        let grant = match decision {
+            Ok(grant) => grant,
+            Err(block) => {
+                let outcome = serde_json::to_value(ToolCallOutcome::from(block)).unwrap_or_default();
+                skip(self, protocol_error_text(&outcome).unwrap_or_default(), json!({ "outcome": outcome }));
+                return None;
+            }
+        };
+        let args = match &grant {
+            Some(grant) if grant.arguments != arguments => with_tool_arguments(&method, params, grant.arguments.clone()),
+            _ => params,
+        };
+        let args = match args {
+            serde_json::Value::Object(args) => args,
+            _ => serde_json::Map::new(),
+        };
+        Some(Step {
+            source: TapSource::Extracted,
+            config: TapConfig {
+                server_url: Some(route.url),
+                // Routed by the method as written, so an alias-qualified
+                // name keeps its server and `tools/call` is unwrapped again
+                tool_name: method,
+                args,
+                use_router: true,
+                ..Default::default()
+            },
+            engram_id,
+            request_id: None,
+            reply: false,
+            messages: None,
+            memory: Vec::new(),
+            grant,
+        })
+    }
+
+    /// Run one tool call and return the calls extracted from its output, in
+    /// order of appearance.
+    async fn tap(&mut self, mut step: Step) -> Vec<Work> {
+        let mut messages: Vec<ContextItem> = step.messages.as_deref().map(context_items).unwrap_or_default();
+        if let (true, None, Some(engram_id)) = (step.config.is_connected(), &step.messages, &step.engram_id) {
+            messages = context_items(&self.hooks.load(engram_id).await);
+        }
+        let messages = connected_messages(&step.config, messages, &step.memory);
+        let args = match connected_arguments(&step.config, &messages, &step.memory, step.engram_id.as_deref()) {
+            Ok(args) => args,
+            Err(errors) => {
+                let text = format!("Tap template error: {}", template_error_text(&errors));
+                self.fail(&step, text, json!({ "templateErrors": errors }));
+                return Vec::new();
+            }
+        };
+
+        let engram_id = step.engram_id.clone();
+        debug(&format!("Tap pipeline calling '{}' ({:?}): {}", step.config.tool_name, step.source, args));
+        self.summary.calls += 1;
+        // The tap answers as the model, so it runs even when simulating
+        let simulate = step.source != TapSource::Tap;
+        let outcome = self.hooks.call_tool(&step.config, args, engram_id.as_deref(), simulate).await;
+        // An approval covers this call only, not calls extracted from its result
+        drop(step.grant.take());
+        let outcome = match outcome {
+            Ok(outcome) => outcome,
+            Err(e) => {
+                self.fail(&step, e, json!({}));
+                return Vec::new();
+            }
+        };
+        if let Some(text) = protocol_error_text(&outcome) {
+            self.fail(&step, text, json!({ "outcome": outcome }));
+            return Vec::new();
+        }
+
+        let tool_text = outcome_text(&outcome);
+        let structured_content = outcome.get("result").and_then(|r| r.get("structuredContent")).cloned();
+        let output_validation = outcome.get("output_validation").cloned();
+        if let Some(engram_id) = &engram_id {
+            remember_result(engram_id, &step.config.tool_name, &tool_text, &outcome);
+        }
+
+        // Tap and extracted results go on the CBus and into the engram
+        let target = step.target();
+        if step.source != TapSource::Console {
+            let mut tool_message = json!({
+                "text": tool_text,
+                "role": "tool",
+                "timestamp": now_ms() as u64,
+                "engramId": engram_id,
+                "toolName": step.config.tool_name
+            });
+            if let Some(server_url) = &step.config.server_url {
+                tool_message["serverUrl"] = json!(server_url);
+            }
+            if let Some(structured_content) = &structured_content {
+                tool_message["structuredContent"] = structured_content.clone();
+                tool_message["outputValidation"] = output_validation.clone().unwrap_or(serde_json::Value::Null);
+            }
+            self.emit(target, engram_id.as_deref(), json!({ "type": "cbus_message", "message": tool_message }));
+            if engram_id.is_some() {
+                self.hooks.persist(&tool_message).await;
+            }
+        }
+        let result_message = json!({
+            "type": "tool_result",
+            "result": outcome,
+            "structuredContent": structured_content,
+            "outputValidation": output_validation,
+            "source": step.source,
+            "engramId": engram_id,
+            "requestId": step.request_id.take()
+        });
+        self.emit(target, engram_id.as_deref(), result_message);
+
+        parse_tool_calls_in(&tool_text)
+            .calls
+            .into_iter()
+            .map(|parsed| Work::Extracted { call: parsed.call, engram_id: engram_id.clone() })
+            .collect()
+    }
+
+    /// Work through a request depth first: calls extracted from a result run,
+    /// with their own extracted calls, before the next call from the same
+    /// result.
+    async fn run(mut self, request: TapRequest) -> RunSummary {
+        let first = match request.call {
+            Some(call) => Work::Extracted { call, engram_id: request.engram_id },
+            None => Work::Tap(Box::new(Step {
+                source: request.source,
+                config: request.tap_config,
+                engram_id: request.engram_id,
+                request_id: request.request_id,
+                reply: request.reply,
+                messages: request.messages,
+                memory: request.memory,
+                grant: None,
+            })),
+        };
+        let mut pending = vec![first];
+        while let Some(work) = pending.pop() {
+            let step = match work {
+                Work::Tap(step) => Some(*step),
+                Work::Extracted { call, engram_id } => self.extracted(call, engram_id).await,
+            };
+            if let Some(step) = step {
+                let extracted = self.tap(step).await;
+                pending.extend(extracted.into_iter().rev());
+            }
+        }
+        self.summary
+    }
+}
+
+/// Runs taps end to end: context assembly, connected arguments, templates,
+/// the tool call, CBus posting and persistence, and dispatch of tool calls
+/// found in the output. The service worker supplies the hooks and forwards
+/// the emitted events to clients.
+#[wasm_bindgen]
+pub struct TapPipeline {
+    hooks: Rc<Hooks>,
+}
+
+#[wasm_bindgen]
+impl TapPipeline {
+    /// `emit` receives each event as JSON `{ seq, target, engram_id?, message }`
+    /// where `target` is `engram`, `reply` or `broadcast`. `load` receives an
+    /// engram id and returns (a promise of) its messages as a JSON array;
+    /// `persist` receives a CBus message as JSON and may return a promise.
+    #[wasm_bindgen(constructor)]
+    pub fn new(emit: js_sys::Function, load: Option<js_sys::Function>, persist: Option<js_sys::Function>) -> TapPipeline {
+        TapPipeline { hooks: Rc::new(Hooks::new(emit, load, persist)) }
+    }
+
+    /// Run a tap, or with `call` set dispatch a JSON-RPC call as if it had
+    /// been extracted from tool output. Takes
+    /// `{ source, tap_config, engram_id?, request_id?, reply?, messages?, memory?, call? }`
+    /// and resolves to `{ calls, events, skipped }` once every extracted
+    /// call has run.
+    pub fn run(&self, request_json: &str) -> Result<js_sys::Promise, JsValue> {
+        let request: TapRequest = serde_json::from_str(request_json)
+            .map_err(|e| JsValue::from_str(&format!("Invalid tap request: {}", e)))?;
+        let run = Run { hooks: self.hooks.clone(), summary: RunSummary::default() };
+        Ok(wasm_bindgen_futures::future_to_promise(async move {
+            let summary = run.run(request).await;
+            Ok(JsValue::from_str(&serde_json::to_string(&summary).unwrap_or_default()))
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::future::Future;
+    use std::pin::pin;
+    use std::task::{Context, Poll, Waker};
+
+    use crate::{McpServer, McpTool, SERVER_REGISTRY, SERVER_STATUS_CONNECTED};
+
+    fn item(role: &str, text: &str) -> ContextItem {
+        ContextItem { id: None, role: Some(role.to_string()), text: text.to_string(), timestamp: None }
+    }
+
+    fn connected_tap(args: serde_json::Value) -> TapConfig {
+        TapConfig {
+            tool_name: "chat".to_string(),
+            args: args.as_object().cloned().unwrap_or_default(),
+            connected_string_arg: Some("prompt".to_string()),
+            connected_array_arg: Some("history".to_string()),
+            ..Default::default()
+        }
+    }
+
+    /// Poll a future that never waits on anything outside the test.
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = pin!(future);
+        match future.as_mut().poll(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("future is waiting on something the test does not drive"),
+        }
+    }
+
+    #[test]
+    fn event_target_follows_routing_rules() {
+        let request_id = json!("r1");
+        assert_eq!(event_target(Some("e1"), Some(&request_id), true), EventTarget::Engram);
+        assert_eq!(event_target(Some("e1"), None, true), EventTarget::Reply);
+        assert_eq!(event_target(None, Some(&request_id), true), EventTarget::Reply);
+        assert_eq!(event_target(Some("e1"), None, false), EventTarget::Broadcast);
+    }
+
+    #[test]
+    fn context_items_skip_messages_without_text() {
+        let items = context_items(&[
+            json!({ "role": "user", "text": "hello" }),
+            json!({ "role": "tool", "text": { "structured": true } }),
+            json!({ "role": "user" }),
+            json!({ "role": "assistant", "text": "hi" }),
+        ]);
+        let texts: Vec<&str> = items.iter().map(|m| m.text.as_str()).collect();
+        assert_eq!(texts, ["hello", "hi"]);
+    }
+
+    #[test]
+    fn connect_arguments_wires_latest_and_earlier_messages() {
+        let config = connected_tap(json!({}));
+        let mut args = serde_json::Map::new();
+        connect_arguments(&mut args, &config, &[item("user", "one"), item("assistant", "two"), item("user", "three")]);
+        assert_eq!(serde_json::Value::Object(args), json!({ "prompt": "three", "history": ["one", "two"] }));
+    }
+
+    #[test]
+    fn connect_arguments_keeps_a_configured_string_argument() {
+        let config = connected_tap(json!({ "prompt": "Summarise: {{ latest.text }}" }));
+        let mut args = serde_json::Map::new();
+        args.insert("prompt".to_string(), json!("Summarise: three"));
+        connect_arguments(&mut args, &config, &[item("user", "three")]);
+        assert_eq!(args["prompt"], json!("Summarise: three"));
+        assert_eq!(args["history"], json!([]));
+    }
+
+    #[test]
+    fn connected_arguments_renders_the_tap_config_only() {
+        let config = connected_tap(json!({ "prompt": "Q: {{ latest.text }}", "mode": "fast" }));
+        let messages = [item("user", "{{ engram_id }}"), item("user", "{% if true %}x{% endif %}")];
+        let args = connected_arguments(&config, &messages, &[], Some("e1")).unwrap();
+        assert_eq!(args["prompt"], json!("Q: {% if true %}x{% endif %}"));
+        assert_eq!(args["history"], json!(["{{ engram_id }}"]));
+        assert_eq!(args["mode"], json!("fast"));
+
+        // An unconfigured string argument receives message text verbatim
+        let config = connected_tap(json!({}));
+        let args = connected_arguments(&config, &messages, &[], Some("e1")).unwrap();
+        assert_eq!(args["prompt"], json!("{% if true %}x{% endif %}"));
+    }
+
+    #[test]
+    fn template_errors_name_the_argument() {
+        let config = connected_tap(json!({ "prompt": "ok", "nested": { "bad": "{{ latest.text" } }));
+        let errors = connected_arguments(&config, &[item("user", "hi")], &[], None).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        let text = template_error_text(&errors);
+        assert!(text.starts_with("/nested/bad line 1, column "), "{}", text);
+    }
+
+    #[test]
+    fn protocol_error_text_covers_protocol_failures_only() {
+        let text = |outcome: serde_json::Value| protocol_error_text(&outcome);
+        assert_eq!(
+            text(json!({ "kind": "rpc_error", "code": -32601, "message": "Method not found" })).as_deref(),
+            Some("JSON-RPC error -32601: Method not found")
+        );
+        assert_eq!(
+            text(json!({ "kind": "invalid_arguments", "errors": [{ "path": "", "message": "missing q" }, { "path": "/n", "message": "not an integer" }] })).as_deref(),
+            Some("Invalid arguments: /: missing q; /n: not an integer")
+        );
+        assert_eq!(
+            text(json!({ "kind": "policy", "rule": "global:deny:rm", "message": "Tool 'rm' is denied" })).as_deref(),
+            Some("Tool 'rm' is denied (rule: global:deny:rm)")
+        );
+        assert_eq!(
+            text(json!({ "kind": "rejected", "approval_id": 7, "reason": "no" })).as_deref(),
+            Some("Tool call #7 rejected: no")
+        );
+        assert_eq!(text(json!({ "kind": "rejected", "approval_id": 7, "reason": null })).as_deref(), Some("Tool call #7 rejected"));
+        assert_eq!(text(json!({ "kind": "transport_error", "message": "offline" })).as_deref(), Some("offline"));
+        assert_eq!(text(json!({ "kind": "success", "result": {} })), None);
+        assert_eq!(text(json!({ "kind": "tool_error", "result": {} })), None);
+        assert_eq!(text(json!({ "result": {} })), None);
+    }
+
+    /// Answers each tool with canned text and records what the run did.
+    #[derive(Default)]
+    struct MockHooks {
+        outputs: HashMap<String, String>,
+        calls: RefCell<Vec<String>>,
+        events: RefCell<Vec<serde_json::Value>>,
+        persisted: RefCell<Vec<serde_json::Value>>,
+    }
+
+    impl PipelineHooks for MockHooks {
+        fn emit(&self, event: &TapEvent) {
+            self.events.borrow_mut().push(serde_json::to_value(event).unwrap());
+        }
+
+        async fn load(&self, _engram_id: &str) -> Vec<serde_json::Value> {
+            Vec::new()
+        }
+
+        async fn persist(&self, message: &serde_json::Value) {
+            self.persisted.borrow_mut().push(message.clone());
+        }
+
+        async fn call_tool(
+            &self,
+            config: &TapConfig,
+            _args: serde_json::Value,
+            _engram_id: Option<&str>,
+            _simulate: bool,
+        ) -> Result<serde_json::Value, String> {
+            self.calls.borrow_mut().push(config.tool_name.clone());
+            let text = self.outputs.get(&config.tool_name).cloned().unwrap_or_default();
+            Ok(json!({ "kind": "success", "result": { "content": [{ "type": "text", "text": text }], "isError": false } }))
+        }
+    }
+
+    fn call_text(method: &str) -> String {
+        json!({ "jsonrpc": "2.0", "method": method, "params": {} }).to_string()
+    }
+
+    #[test]
+    fn run_dispatches_extracted_calls_depth_first() {
+        let url = "http://pipeline-test.invalid/mcp";
+        let tools = ["pt_root", "pt_a", "pt_b", "pt_c"]
+            .map(|name| McpTool::from_definition(name, &json!({ "annotations": { "readOnlyHint": true } })));
+        SERVER_REGISTRY.lock().unwrap().servers.insert(
+            url.to_string(),
+            McpServer {
+                url: url.to_string(),
+                name: "pipeline-test".to_string(),
+                version: "1".to_string(),
+                status: SERVER_STATUS_CONNECTED.to_string(),
+                tools: tools.to_vec(),
+                last_health_check: 0,
+                session_id: None,
+                policy: Default::default(),
+                alias: None,
+                priority: 0,
+                latency_ms: None,
+                instructions: None,
+            },
+        );
+        crate::policy::set_extracted_call_policy(r#"{"low":"auto_allow","medium":"auto_allow","destructive":"auto_allow","unknown":"auto_allow"}"#).unwrap();
+
+        // pt_root extracts pt_a then pt_b; pt_a extracts pt_c, which must
+        // run before pt_b
+        let hooks = Rc::new(MockHooks {
+            outputs: HashMap::from([
+                ("pt_root".to_string(), format!("First {} then {}", call_text("pt_a"), call_text("pt_b"))),
+                ("pt_a".to_string(), format!("Also {}", call_text("pt_c"))),
+                ("pt_b".to_string(), "done".to_string()),
+                ("pt_c".to_string(), "done".to_string()),
+            ]),
+            ..Default::default()
+        });
+        let run = Run { hooks: hooks.clone(), summary: RunSummary::default() };
+        let request = TapRequest {
+            tap_config: TapConfig { server_url: Some(url.to_string()), tool_name: "pt_root".to_string(), ..Default::default() },
+            engram_id: Some("pipeline-test".to_string()),
+            request_id: Some(json!("r1")),
+            ..Default::default()
+        };
+        let summary = block_on(run.run(request));
+
+        assert_eq!(*hooks.calls.borrow(), ["pt_root", "pt_a", "pt_c", "pt_b"]);
+        assert_eq!((summary.calls, summary.skipped), (4, 0));
+
+        let events = hooks.events.borrow();
+        assert_eq!(summary.events, events.len());
+        let seqs: Vec<u64> = events.iter().map(|event| event["seq"].as_u64().unwrap()).collect();
+        assert!(seqs.windows(2).all(|pair| pair[0] < pair[1]), "{:?}", seqs);
+        // Each call posts its CBus message, then its result
+        let results: Vec<&str> = events
+            .iter()
+            .filter(|event| event["message"]["type"] == "cbus_message")
+            .filter_map(|event| event["message"]["message"]["toolName"].as_str())
+            .collect();
+        assert_eq!(results, ["pt_root", "pt_a", "pt_c", "pt_b"]);
+        assert_eq!(events[0]["target"], "engram");
+        assert_eq!(events[2]["target"], "broadcast");
+        assert_eq!(hooks.persisted.borrow().len(), 4);
+    }
+
+    #[test]
+    fn last_results_are_bounded_and_forgotten() {
+        for n in 0..LAST_RESULTS_CAPACITY + 8 {
+            remember_result(&format!("lr-test-{n}"), "t", "text", &json!({ "result": n }));
+        }
+        let newest = format!("lr-test-{}", LAST_RESULTS_CAPACITY + 7);
+        {
+            let results = LAST_RESULTS.lock().unwrap();
+            assert!(results.len() <= LAST_RESULTS_CAPACITY);
+            assert_eq!(results[&newest]["result"], json!(LAST_RESULTS_CAPACITY + 7));
+        }
+        forget_result(&newest);
+        assert!(!LAST_RESULTS.lock().unwrap().contains_key(&newest));
+    }
+}
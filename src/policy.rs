@@ -0,0 +1,1058 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{self, json};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::LazyLock;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+
+use crate::validate::{json_equal, pattern_matches, prepare_arguments};
+use crate::{debug, get_timestamp, info, SERVER_REGISTRY};
+
+/// Behavioural hints a server attaches to a tool (MCP `ToolAnnotations`).
+/// Absent hints take the protocol defaults described on each accessor.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ToolAnnotations {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub read_only_hint: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub destructive_hint: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idempotent_hint: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub open_world_hint: Option<bool>,
+}
+
+impl ToolAnnotations {
+    /// Defaults to `false`.
+    pub(crate) fn read_only(&self) -> bool {
+        self.read_only_hint.unwrap_or(false)
+    }
+
+    /// Defaults to `true`; meaningless for read-only tools.
+    pub(crate) fn destructive(&self) -> bool {
+        !self.read_only() && self.destructive_hint.unwrap_or(true)
+    }
+
+    /// Defaults to `true`.
+    pub(crate) fn open_world(&self) -> bool {
+        self.open_world_hint.unwrap_or(true)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum PolicyDecision {
+    AutoAllow,
+    RequireApproval,
+    Deny,
+}
+
+/// Maps tool annotations to a decision. Tools without annotations are
+/// governed by `unannotated` rather than the protocol defaults, which would
+/// otherwise treat every legacy tool as destructive.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub(crate) struct ConfirmationPolicy {
+    pub read_only: PolicyDecision,
+    pub destructive: PolicyDecision,
+    pub non_destructive: PolicyDecision,
+    /// Applied on top of the above for tools that reach outside the client,
+    /// when stricter.
+    pub open_world: PolicyDecision,
+    pub unannotated: PolicyDecision,
+    /// How long a call waits for a user before it is rejected; 0 waits
+    /// indefinitely.
+    pub approval_timeout_ms: u64,
+}
+
+impl Default for ConfirmationPolicy {
+    fn default() -> Self {
+        ConfirmationPolicy {
+            read_only: PolicyDecision::AutoAllow,
+            destructive: PolicyDecision::RequireApproval,
+            non_destructive: PolicyDecision::AutoAllow,
+            open_world: PolicyDecision::AutoAllow,
+            unannotated: PolicyDecision::AutoAllow,
+            approval_timeout_ms: 120_000,
+        }
+    }
+}
+
+fn stricter(a: PolicyDecision, b: PolicyDecision) -> PolicyDecision {
+    let rank = |d: PolicyDecision| match d {
+        PolicyDecision::AutoAllow => 0,
+        PolicyDecision::RequireApproval => 1,
+        PolicyDecision::Deny => 2,
+    };
+    if rank(b) > rank(a) { b } else { a }
+}
+
+impl ConfirmationPolicy {
+    /// Decide how to handle a call, returning the decision and the rule that
+    /// produced it.
+    pub(crate) fn evaluate(&self, annotations: Option<&ToolAnnotations>) -> (PolicyDecision, &'static str) {
+        let annotations = match annotations {
+            Some(annotations) => annotations,
+            None => return (self.unannotated, "annotations.unannotated"),
+        };
+        let (base, rule) = if annotations.read_only() {
+            (self.read_only, "annotations.read_only")
+        } else if annotations.destructive() {
+            (self.destructive, "annotations.destructive")
+        } else {
+            (self.non_destructive, "annotations.non_destructive")
+        };
+        if annotations.open_world() && stricter(base, self.open_world) != base {
+            return (self.open_world, "annotations.open_world");
+        }
+        (base, rule)
+    }
+}
+
+/// Restriction on one argument of the tools matching `tool`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct ArgumentConstraint {
+    /// Glob over tool names (`*` and `?`).
+    pub tool: String,
+    pub argument: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_values: Option<Vec<serde_json::Value>>,
+    /// ECMA-262 regex the argument's string form must match.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_length: Option<usize>,
+}
+
+/// Tool access rules for one scope. An empty `allow` list allows every tool
+/// not matched by `deny`.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub(crate) struct ToolAccessRules {
+    pub allow: Vec<String>,
+    pub deny: Vec<String>,
+    pub constraints: Vec<ArgumentConstraint>,
+}
+
+/// Access policy attached to a registered server: rules applied to every
+/// call plus rules for individual engrams, which apply in addition.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub(crate) struct ServerToolPolicy {
+    pub global: ToolAccessRules,
+    pub engrams: HashMap<String, ToolAccessRules>,
+}
+
+/// Glob match supporting `*` (any run) and `?` (one character); `\`
+/// makes the next character literal.
+pub(crate) fn glob_match(pattern: &str, text: &str) -> bool {
+    // (character, is a wildcard)
+    let mut tokens: Vec<(char, bool)> = Vec::new();
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => tokens.push((chars.next().unwrap_or('\\'), false)),
+            '*' | '?' => tokens.push((c, true)),
+            _ => tokens.push((c, false)),
+        }
+    }
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < tokens.len() && (tokens[p] == ('?', true) || (!tokens[p].1 && tokens[p].0 == text[t])) {
+            p += 1;
+            t += 1;
+        } else if p < tokens.len() && tokens[p] == ('*', true) {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+    tokens[p..].iter().all(|token| *token == ('*', true))
+}
+
+/// Pattern matching `text` exactly under [`glob_match`].
+pub(crate) fn glob_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '*' | '?' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+impl ToolAccessRules {
+    /// Check a call against these rules. `scope` prefixes the returned rule
+    /// name so callers can tell global and engram rules apart.
+    fn check(&self, scope: &str, tool_name: &str, arguments: &serde_json::Value) -> Result<(), PolicyBlock> {
+        if let Some(pattern) = self.deny.iter().find(|p| glob_match(p, tool_name)) {
+            return Err(PolicyBlock::Denied {
+                rule: format!("{}:deny:{}", scope, pattern),
+                message: format!("Tool '{}' is denied", tool_name),
+            });
+        }
+        if !self.allow.is_empty() && !self.allow.iter().any(|p| glob_match(p, tool_name)) {
+            return Err(PolicyBlock::Denied {
+                rule: format!("{}:allow", scope),
+                message: format!("Tool '{}' is not in the allow list", tool_name),
+            });
+        }
+        for constraint in self.constraints.iter().filter(|c| glob_match(&c.tool, tool_name)) {
+            let value = match arguments.get(&constraint.argument) {
+                Some(value) => value,
+                None => continue,
+            };
+            let rule = |check: &str| format!("{}:constraint:{}.{}:{}", scope, constraint.tool, constraint.argument, check);
+            if let Some(allowed) = &constraint.allowed_values {
+                if !allowed.iter().any(|allowed| json_equal(allowed, value)) {
+                    return Err(PolicyBlock::Denied {
+                        rule: rule("allowed_values"),
+                        message: format!("Argument '{}' has a value that is not allowed", constraint.argument),
+                    });
+                }
+            }
+            let text = match value {
+                serde_json::Value::String(text) => text.clone(),
+                other => other.to_string(),
+            };
+            if let Some(max) = constraint.max_length.filter(|max| text.chars().count() > *max) {
+                return Err(PolicyBlock::Denied {
+                    rule: rule("max_length"),
+                    message: format!("Argument '{}' exceeds {} characters", constraint.argument, max),
+                });
+            }
+            if let Some(pattern) = &constraint.pattern {
+                if !pattern_matches(pattern, &text).unwrap_or(false) {
+                    return Err(PolicyBlock::Denied {
+                        rule: rule("pattern"),
+                        message: format!("Argument '{}' does not match {}", constraint.argument, pattern),
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ServerToolPolicy {
+    pub(crate) fn check(
+        &self,
+        tool_name: &str,
+        arguments: &serde_json::Value,
+        engram_id: Option<&str>,
+    ) -> Result<(), PolicyBlock> {
+        self.global.check("global", tool_name, arguments)?;
+        if let Some((engram_id, rules)) = engram_id.and_then(|id| self.engrams.get_key_value(id)) {
+            rules.check(&format!("engram:{}", engram_id), tool_name, arguments)?;
+        }
+        Ok(())
+    }
+}
+
+/// How much harm a call could do, judged from the tool's annotations.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum RiskLevel {
+    /// Read-only, or non-destructive and closed-world.
+    Low,
+    /// Non-destructive but reaches outside the client.
+    Medium,
+    Destructive,
+    /// The server does not annotate the tool.
+    Unknown,
+}
+
+pub(crate) fn classify_risk(annotations: Option<&ToolAnnotations>) -> RiskLevel {
+    match annotations {
+        None => RiskLevel::Unknown,
+        Some(annotations) if annotations.read_only() => RiskLevel::Low,
+        Some(annotations) if annotations.destructive() => RiskLevel::Destructive,
+        Some(annotations) if annotations.open_world() => RiskLevel::Medium,
+        Some(_) => RiskLevel::Low,
+    }
+}
+
+/// Decisions for tool calls extracted from model output, by risk. Low and
+/// medium risk calls run; destructive and unannotated ones wait for a user
+/// unless configured otherwise or matched by a remembered rule.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub(crate) struct ExtractedCallPolicy {
+    pub low: PolicyDecision,
+    pub medium: PolicyDecision,
+    pub destructive: PolicyDecision,
+    pub unknown: PolicyDecision,
+    /// How long a queued call waits for a decision before it is rejected;
+    /// 0 waits indefinitely.
+    pub approval_timeout_ms: u64,
+}
+
+impl Default for ExtractedCallPolicy {
+    fn default() -> Self {
+        ExtractedCallPolicy {
+            low: PolicyDecision::AutoAllow,
+            medium: PolicyDecision::AutoAllow,
+            destructive: PolicyDecision::RequireApproval,
+            unknown: PolicyDecision::RequireApproval,
+            approval_timeout_ms: 120_000,
+        }
+    }
+}
+
+impl ExtractedCallPolicy {
+    fn decision(&self, risk: RiskLevel) -> PolicyDecision {
+        match risk {
+            RiskLevel::Low => self.low,
+            RiskLevel::Medium => self.medium,
+            RiskLevel::Destructive => self.destructive,
+            RiskLevel::Unknown => self.unknown,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum RuleDecision {
+    Allow,
+    Deny,
+}
+
+/// A remembered decision, such as "always allow tool X on server Y". Deny
+/// rules win over allow rules.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct ApprovalRule {
+    #[serde(default)]
+    pub id: u64,
+    /// Glob over server URLs.
+    pub server: String,
+    /// Glob over tool names.
+    pub tool: String,
+    /// Limits the rule to one engram.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub engram_id: Option<String>,
+    pub decision: RuleDecision,
+    #[serde(default)]
+    pub created_at: u64,
+}
+
+impl ApprovalRule {
+    fn matches(&self, url: &str, tool_name: &str, engram_id: Option<&str>) -> bool {
+        glob_match(&self.server, url)
+            && glob_match(&self.tool, tool_name)
+            && self.engram_id.as_deref().is_none_or(|id| Some(id) == engram_id)
+    }
+}
+
+/// What a decision on a pending call is remembered for.
+#[derive(Debug, Deserialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+enum RememberScope {
+    /// This tool on this server.
+    Tool,
+    /// Every tool on this server.
+    Server,
+}
+
+struct ApprovalRules {
+    next_id: u64,
+    rules: Vec<ApprovalRule>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ApprovalSource {
+    /// Required by the confirmation policy.
+    Policy,
+    /// A call extracted from model output.
+    Extracted,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ApprovalStatus {
+    Pending,
+    Approved,
+    Rejected,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub(crate) struct PendingApproval {
+    pub id: u64,
+    pub source: ApprovalSource,
+    pub engram_id: Option<String>,
+    pub url: String,
+    pub tool_name: String,
+    pub arguments: serde_json::Value,
+    /// Whether a user changed `arguments` since the call was queued.
+    pub edited: bool,
+    pub annotations: Option<ToolAnnotations>,
+    pub risk: RiskLevel,
+    pub rule: String,
+    pub status: ApprovalStatus,
+    pub created_at: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+struct ApprovalQueue {
+    next_id: u64,
+    items: Vec<PendingApproval>,
+}
+
+static TOOL_POLICY: LazyLock<std::sync::Mutex<ConfirmationPolicy>> =
+    LazyLock::new(|| std::sync::Mutex::new(ConfirmationPolicy::default()));
+
+static EXTRACTED_CALL_POLICY: LazyLock<std::sync::Mutex<ExtractedCallPolicy>> =
+    LazyLock::new(|| std::sync::Mutex::new(ExtractedCallPolicy::default()));
+
+static APPROVAL_QUEUE: LazyLock<std::sync::Mutex<ApprovalQueue>> = LazyLock::new(|| {
+    std::sync::Mutex::new(ApprovalQueue {
+        next_id: 1,
+        items: Vec::new(),
+    })
+});
+
+static APPROVAL_RULES: LazyLock<std::sync::Mutex<ApprovalRules>> =
+    LazyLock::new(|| std::sync::Mutex::new(ApprovalRules { next_id: 1, rules: Vec::new() }));
+
+/// An approved extracted call: the approval it came from and the server,
+/// tool, engram and arguments it was given for.
+struct Grant {
+    approval_id: u64,
+    url: String,
+    tool_name: String,
+    engram_id: Option<String>,
+    arguments: serde_json::Value,
+}
+
+/// One-shot passes through the confirmation policy for approved extracted
+/// calls, so a user is not asked twice.
+static APPROVAL_GRANTS: LazyLock<std::sync::Mutex<Vec<Grant>>> =
+    LazyLock::new(|| std::sync::Mutex::new(Vec::new()));
+
+thread_local! {
+    // Resolvers for calls suspended on approval. JS handles are not `Send`,
+    // so they live outside the registry-style statics.
+    static APPROVAL_RESOLVERS: RefCell<HashMap<u64, js_sys::Function>> = RefCell::new(HashMap::new());
+    static RULES_PERSIST_HOOK: RefCell<Option<js_sys::Function>> = const { RefCell::new(None) };
+    static APPROVAL_LISTENER: RefCell<Option<js_sys::Function>> = const { RefCell::new(None) };
+}
+
+/// Cached annotations for a tool, if the tool is known.
+pub(crate) fn tool_annotations(url: &str, tool_name: &str) -> Option<ToolAnnotations> {
+    let registry = SERVER_REGISTRY.lock().unwrap();
+    registry
+        .servers
+        .get(url)
+        .and_then(|server| server.tools.iter().find(|t| t.name == tool_name))
+        .and_then(|tool| tool.annotations.clone())
+}
+
+/// Why a call was not executed.
+#[derive(Debug, Clone)]
+pub(crate) enum PolicyBlock {
+    Denied { rule: String, message: String },
+    Rejected { approval_id: u64, reason: Option<String> },
+}
+
+/// Apply the server's access policy to a call.
+pub(crate) fn check_access(
+    url: &str,
+    tool_name: &str,
+    arguments: &serde_json::Value,
+    engram_id: Option<&str>,
+) -> Result<(), PolicyBlock> {
+    let access_policy = SERVER_REGISTRY.lock().unwrap().servers.get(url).map(|server| server.policy.clone());
+    if let Some(access_policy) = access_policy {
+        access_policy.check(tool_name, arguments, engram_id).inspect_err(|block| {
+            if let PolicyBlock::Denied { rule, .. } = block {
+                info(&format!("Tool '{}' on {} blocked by {}", tool_name, url, rule));
+            }
+        })?;
+    }
+    Ok(())
+}
+
+/// The remembered decision for a call, if a rule matches.
+fn remembered_decision(url: &str, tool_name: &str, engram_id: Option<&str>) -> Option<(RuleDecision, u64)> {
+    let rules = APPROVAL_RULES.lock().unwrap();
+    let mut matching = rules.rules.iter().filter(|rule| rule.matches(url, tool_name, engram_id));
+    let first = matching.next()?;
+    let deny = std::iter::once(first)
+        .chain(matching)
+        .find(|rule| rule.decision == RuleDecision::Deny);
+    Some(deny.map_or((first.decision, first.id), |rule| (rule.decision, rule.id)))
+}
+
+fn remembered_block(id: u64, url: &str, tool_name: &str) -> PolicyBlock {
+    PolicyBlock::Denied {
+        rule: format!("rule:{}", id),
+        message: format!("Tool '{}' on {} is denied by a remembered decision", tool_name, url),
+    }
+}
+
+/// Consume the grant for exactly this call, if a user approved it.
+/// `arguments` are compared as prepared for the server.
+fn take_grant(url: &str, tool_name: &str, arguments: &serde_json::Value, engram_id: Option<&str>) -> bool {
+    let mut grants = APPROVAL_GRANTS.lock().unwrap();
+    let position = grants.iter().position(|grant| {
+        grant.url == url
+            && grant.tool_name == tool_name
+            && grant.engram_id.as_deref() == engram_id
+            && grant.arguments == *arguments
+    });
+    match position {
+        Some(position) => {
+            let grant = grants.remove(position);
+            debug(&format!("Tool '{}' on {} runs on approval #{}", tool_name, url, grant.approval_id));
+            true
+        }
+        None => false,
+    }
+}
+
+/// A user's approval of an extracted call. It lets the call with the
+/// approved arguments past the confirmation policy once; dropping it
+/// withdraws the grant, so it cannot outlive the call whatever the outcome.
+pub(crate) struct ApprovalGrant {
+    approval_id: u64,
+    /// The arguments to call with, which a user may have edited.
+    pub arguments: serde_json::Value,
+}
+
+impl Drop for ApprovalGrant {
+    fn drop(&mut self) {
+        APPROVAL_GRANTS.lock().unwrap().retain(|grant| grant.approval_id != self.approval_id);
+    }
+}
+
+fn enqueue(
+    source: ApprovalSource,
+    url: &str,
+    tool_name: &str,
+    arguments: &serde_json::Value,
+    engram_id: Option<&str>,
+    annotations: Option<ToolAnnotations>,
+    rule: &str,
+) -> PendingApproval {
+    let mut queue = APPROVAL_QUEUE.lock().unwrap();
+    let id = queue.next_id;
+    queue.next_id += 1;
+    let item = PendingApproval {
+        id,
+        source,
+        engram_id: engram_id.map(str::to_string),
+        url: url.to_string(),
+        tool_name: tool_name.to_string(),
+        arguments: arguments.clone(),
+        edited: false,
+        risk: classify_risk(annotations.as_ref()),
+        annotations,
+        rule: rule.to_string(),
+        status: ApprovalStatus::Pending,
+        created_at: get_timestamp(),
+        reason: None,
+    };
+    queue.items.push(item.clone());
+    drop(queue);
+    info(&format!("Tool '{}' on {} awaiting approval (#{})", tool_name, url, id));
+    notify_approval(json!({ "type": "tool_approval_pending", "approval": item }));
+    item
+}
+
+/// Tell clients about a queued call or its outcome, so approvals raised
+/// outside a pipeline run reach the UI too.
+fn notify_approval(event: serde_json::Value) {
+    APPROVAL_LISTENER.with(|listener| {
+        if let Some(listener) = listener.borrow().as_ref() {
+            if let Err(e) = listener.call1(&JsValue::NULL, &JsValue::from_str(&event.to_string())) {
+                debug(&format!("Approval listener failed: {:?}", e));
+            }
+        }
+    });
+}
+
+fn notify_resolved(item: &PendingApproval) {
+    notify_approval(json!({
+        "type": "tool_approval_resolved",
+        "id": item.id,
+        "status": item.status,
+        "reason": item.reason,
+    }));
+}
+
+/// Apply the server's access policy, remembered decisions and then the
+/// confirmation policy to a call, suspending until a user decides when
+/// approval is required.
+pub(crate) async fn authorize_tool_call(
+    url: &str,
+    tool_name: &str,
+    arguments: &serde_json::Value,
+    engram_id: Option<&str>,
+) -> Result<(), PolicyBlock> {
+    check_access(url, tool_name, arguments, engram_id)?;
+    match remembered_decision(url, tool_name, engram_id) {
+        Some((RuleDecision::Allow, _)) => return Ok(()),
+        Some((RuleDecision::Deny, id)) => return Err(remembered_block(id, url, tool_name)),
+        None => {}
+    }
+    if take_grant(url, tool_name, arguments, engram_id) {
+        return Ok(());
+    }
+    let annotations = tool_annotations(url, tool_name);
+    let (decision, rule) = TOOL_POLICY.lock().unwrap().evaluate(annotations.as_ref());
+    match decision {
+        PolicyDecision::AutoAllow => Ok(()),
+        PolicyDecision::Deny => Err(PolicyBlock::Denied {
+            rule: rule.to_string(),
+            message: format!("Tool '{}' on {} is denied by policy", tool_name, url),
+        }),
+        PolicyDecision::RequireApproval => {
+            let timeout_ms = TOOL_POLICY.lock().unwrap().approval_timeout_ms;
+            let item = enqueue(ApprovalSource::Policy, url, tool_name, arguments, engram_id, annotations, rule);
+            wait_for_decision(item.id, timeout_ms).await.map(|_| ())
+        }
+    }
+}
+
+/// Whether the tool on `url` gets a stricter confirmation decision, and
+/// whether it gets a stricter extracted-call decision, than on
+/// `authorized_url`, judged by each server's annotations.
+fn riskier_provider(authorized_url: &str, url: &str, tool_name: &str) -> (bool, bool) {
+    let authorized = tool_annotations(authorized_url, tool_name);
+    let candidate = tool_annotations(url, tool_name);
+    let raises = |from: PolicyDecision, to: PolicyDecision| stricter(from, to) != from;
+    let confirmation = {
+        let policy = TOOL_POLICY.lock().unwrap();
+        raises(policy.evaluate(authorized.as_ref()).0, policy.evaluate(candidate.as_ref()).0)
+    };
+    let extracted = {
+        let policy = EXTRACTED_CALL_POLICY.lock().unwrap();
+        raises(
+            policy.decision(classify_risk(authorized.as_ref())),
+            policy.decision(classify_risk(candidate.as_ref())),
+        )
+    };
+    (confirmation, extracted)
+}
+
+/// Authorize a failover attempt of a call already authorized on
+/// `authorized_url`, another provider of the same tool. The server's access
+/// policy and remembered denials apply. When this provider's annotations
+/// make the call riskier it goes through the confirmation policy again, or,
+/// when only its extracted-call risk rises, is not failed over at all.
+pub(crate) async fn authorize_failover(
+    authorized_url: &str,
+    url: &str,
+    tool_name: &str,
+    arguments: &serde_json::Value,
+    engram_id: Option<&str>,
+) -> Result<(), PolicyBlock> {
+    check_access(url, tool_name, arguments, engram_id)?;
+    if let Some((RuleDecision::Deny, id)) = remembered_decision(url, tool_name, engram_id) {
+        return Err(remembered_block(id, url, tool_name));
+    }
+    match riskier_provider(authorized_url, url, tool_name) {
+        (true, _) => {
+            info(&format!("Tool '{}' is riskier on {} than on {}, authorizing again", tool_name, url, authorized_url));
+            authorize_tool_call(url, tool_name, arguments, engram_id).await
+        }
+        (false, true) => Err(PolicyBlock::Denied {
+            rule: "failover.riskier".to_string(),
+            message: format!("Tool '{}' is riskier on {} than on {}; not failing over", tool_name, url, authorized_url),
+        }),
+        (false, false) => Ok(()),
+    }
+}
+
+/// How an extracted call may proceed.
+pub(crate) enum ExtractedGate {
+    Allowed,
+    /// Queued; settle with `await_extracted_call`.
+    Pending(Box<PendingApproval>),
+}
+
+/// Decide whether a call extracted from model output may run, queueing it
+/// for a user unless a remembered rule or the extracted-call policy
+/// settles it.
+pub(crate) fn gate_extracted_call(
+    url: &str,
+    tool_name: &str,
+    arguments: &serde_json::Value,
+    engram_id: Option<&str>,
+) -> Result<ExtractedGate, PolicyBlock> {
+    check_access(url, tool_name, arguments, engram_id)?;
+    match remembered_decision(url, tool_name, engram_id) {
+        Some((RuleDecision::Allow, _)) => return Ok(ExtractedGate::Allowed),
+        Some((RuleDecision::Deny, id)) => return Err(remembered_block(id, url, tool_name)),
+        None => {}
+    }
+    let annotations = tool_annotations(url, tool_name);
+    let risk = classify_risk(annotations.as_ref());
+    match EXTRACTED_CALL_POLICY.lock().unwrap().decision(risk) {
+        PolicyDecision::AutoAllow => return Ok(ExtractedGate::Allowed),
+        PolicyDecision::Deny => {
+            return Err(PolicyBlock::Denied {
+                rule: format!("extracted.{:?}", risk).to_lowercase(),
+                message: format!("Extracted call to '{}' on {} is denied by policy", tool_name, url),
+            })
+        }
+        PolicyDecision::RequireApproval => {}
+    }
+    let item = enqueue(ApprovalSource::Extracted, url, tool_name, arguments, engram_id, annotations, "extracted");
+    Ok(ExtractedGate::Pending(Box::new(item)))
+}
+
+/// Wait for a user's decision on a queued extracted call. The approved
+/// call then skips the confirmation policy once, so the user is not asked
+/// twice; hold the grant until the call is done.
+pub(crate) async fn await_extracted_call(item: &PendingApproval) -> Result<ApprovalGrant, PolicyBlock> {
+    let timeout_ms = EXTRACTED_CALL_POLICY.lock().unwrap().approval_timeout_ms;
+    let arguments = wait_for_decision(item.id, timeout_ms).await?;
+    // The call is checked with its arguments as prepared for the server
+    let prepared = prepare_arguments(&item.url, &item.tool_name, arguments.clone()).unwrap_or_else(|_| arguments.clone());
+    APPROVAL_GRANTS.lock().unwrap().push(Grant {
+        approval_id: item.id,
+        url: item.url.clone(),
+        tool_name: item.tool_name.clone(),
+        engram_id: item.engram_id.clone(),
+        arguments: prepared,
+    });
+    Ok(ApprovalGrant { approval_id: item.id, arguments })
+}
+
+/// Suspend until a user decides on a queued call, rejecting it once
+/// `timeout_ms` passes without a decision. Resolves to the arguments to
+/// call with, which a user may have edited.
+async fn wait_for_decision(id: u64, timeout_ms: u64) -> Result<serde_json::Value, PolicyBlock> {
+    let promise = js_sys::Promise::new(&mut |resolve, _reject| {
+        APPROVAL_RESOLVERS.with(|resolvers| resolvers.borrow_mut().insert(id, resolve));
+    });
+    let promise = match timeout_ms {
+        0 => promise,
+        ms => {
+            let timeout = crate::tool_call::delay(ms.min(u32::MAX as u64) as u32);
+            js_sys::Promise::race(&js_sys::Array::of2(&promise, &timeout))
+        }
+    };
+    let _ = JsFuture::from(promise).await;
+    APPROVAL_RESOLVERS.with(|resolvers| resolvers.borrow_mut().remove(&id));
+    let timed_out = {
+        let mut queue = APPROVAL_QUEUE.lock().unwrap();
+        let pending = queue
+            .items
+            .iter_mut()
+            .find(|item| item.id == id && item.status == ApprovalStatus::Pending);
+        pending.map(|item| {
+            item.status = ApprovalStatus::Rejected;
+            item.reason = Some(format!("No decision within {} s", timeout_ms / 1000));
+            item.clone()
+        })
+    };
+    if let Some(item) = timed_out {
+        info(&format!("Pending tool call #{} timed out", id));
+        notify_resolved(&item);
+    }
+    let mut queue = APPROVAL_QUEUE.lock().unwrap();
+    let position = queue.items.iter().position(|item| item.id == id);
+    let item = position.map(|position| queue.items.remove(position));
+    match item {
+        Some(item) if item.status == ApprovalStatus::Approved => Ok(item.arguments),
+        Some(item) => Err(PolicyBlock::Rejected {
+            approval_id: id,
+            reason: item.reason,
+        }),
+        None => Err(PolicyBlock::Rejected {
+            approval_id: id,
+            reason: Some("Approval request was discarded".to_string()),
+        }),
+    }
+}
+
+fn persist_rules(rules: &ApprovalRules) {
+    RULES_PERSIST_HOOK.with(|hook| {
+        if let Some(hook) = hook.borrow().as_ref() {
+            let snapshot = serde_json::to_string(&rules.rules).unwrap_or_default();
+            if let Err(e) = hook.call1(&JsValue::NULL, &JsValue::from_str(&snapshot)) {
+                debug(&format!("Persisting approval rules failed: {:?}", e));
+            }
+        }
+    });
+}
+
+fn add_rule(mut rule: ApprovalRule) -> u64 {
+    let mut rules = APPROVAL_RULES.lock().unwrap();
+    rule.id = rules.next_id;
+    rules.next_id += 1;
+    if rule.created_at == 0 {
+        rule.created_at = get_timestamp();
+    }
+    info(&format!("Remembering {:?} for '{}' on {}", rule.decision, rule.tool, rule.server));
+    let id = rule.id;
+    rules.rules.push(rule);
+    persist_rules(&rules);
+    id
+}
+
+fn parse_remember(remember: Option<String>) -> Result<Option<RememberScope>, JsValue> {
+    remember
+        .map(|scope| {
+            serde_json::from_value(serde_json::Value::String(scope))
+                .map_err(|e| JsValue::from_str(&format!("Invalid remember scope: {}", e)))
+        })
+        .transpose()
+}
+
+fn decide(id: u64, status: ApprovalStatus, reason: Option<String>, remember: Option<RememberScope>) -> Result<(), JsValue> {
+    let item = {
+        let mut queue = APPROVAL_QUEUE.lock().unwrap();
+        let item = queue
+            .items
+            .iter_mut()
+            .find(|item| item.id == id && item.status == ApprovalStatus::Pending)
+            .ok_or_else(|| JsValue::from_str(&format!("No pending approval with id {}", id)))?;
+        item.status = status;
+        item.reason = reason;
+        item.clone()
+    };
+    notify_resolved(&item);
+    if let Some(scope) = remember {
+        add_rule(ApprovalRule {
+            id: 0,
+            server: glob_escape(&item.url),
+            tool: match scope {
+                RememberScope::Tool => glob_escape(&item.tool_name),
+                RememberScope::Server => "*".to_string(),
+            },
+            engram_id: None,
+            decision: if item.status == ApprovalStatus::Approved { RuleDecision::Allow } else { RuleDecision::Deny },
+            created_at: 0,
+        });
+    }
+    let resolver = APPROVAL_RESOLVERS.with(|resolvers| resolvers.borrow_mut().remove(&id));
+    if let Some(resolve) = resolver {
+        resolve.call0(&JsValue::NULL)?;
+    }
+    Ok(())
+}
+
+/// Reject every pending call matching `filter`, for callers that stop
+/// waiting on calls they started. Returns how many were rejected.
+pub(crate) fn reject_pending(filter: impl Fn(&PendingApproval) -> bool, reason: &str) -> usize {
+    let ids: Vec<u64> = APPROVAL_QUEUE
+        .lock()
+        .unwrap()
+        .items
+        .iter()
+        .filter(|item| item.status == ApprovalStatus::Pending && filter(item))
+        .map(|item| item.id)
+        .collect();
+    ids.iter()
+        .filter(|id| decide(**id, ApprovalStatus::Rejected, Some(reason.to_string()), None).is_ok())
+        .count()
+}
+
+#[wasm_bindgen]
+pub fn set_tool_policy(policy_json: &str) -> Result<(), JsValue> {
+    let policy: ConfirmationPolicy = serde_json::from_str(policy_json)
+        .map_err(|e| JsValue::from_str(&format!("Invalid tool policy: {}", e)))?;
+    *TOOL_POLICY.lock().unwrap() = policy;
+    info("Updated tool confirmation policy");
+    Ok(())
+}
+
+#[wasm_bindgen]
+pub fn get_tool_policy() -> String {
+    serde_json::to_string(&*TOOL_POLICY.lock().unwrap()).unwrap_or_default()
+}
+
+/// Attach tool access rules to a registered server. `policy_json` has the
+/// shape `{ global: { allow, deny, constraints }, engrams: { <id>: {...} } }`.
+#[wasm_bindgen]
+pub fn set_server_tool_policy(url: &str, policy_json: &str) -> Result<(), JsValue> {
+    let policy: ServerToolPolicy = serde_json::from_str(policy_json)
+        .map_err(|e| JsValue::from_str(&format!("Invalid server tool policy: {}", e)))?;
+    let mut registry = SERVER_REGISTRY.lock().unwrap();
+    let server = registry
+        .servers
+        .get_mut(url)
+        .ok_or_else(|| JsValue::from_str(&format!("Unknown server: {}", url)))?;
+    server.policy = policy;
+    info(&format!("Updated tool access policy for {}", url));
+    Ok(())
+}
+
+#[wasm_bindgen]
+pub fn get_server_tool_policy(url: &str) -> Result<JsValue, JsValue> {
+    let registry = SERVER_REGISTRY.lock().unwrap();
+    let server = registry
+        .servers
+        .get(url)
+        .ok_or_else(|| JsValue::from_str(&format!("Unknown server: {}", url)))?;
+    Ok(JsValue::from_str(&serde_json::to_string(&server.policy).unwrap_or_default()))
+}
+
+#[wasm_bindgen]
+pub fn list_pending_approvals() -> String {
+    let queue = APPROVAL_QUEUE.lock().unwrap();
+    let pending: Vec<&PendingApproval> = queue
+        .items
+        .iter()
+        .filter(|item| item.status == ApprovalStatus::Pending)
+        .collect();
+    json!({ "pending": pending }).to_string()
+}
+
+/// Approve a pending call. `remember` is `tool` to always allow this tool
+/// on this server, or `server` to allow every tool on it.
+#[wasm_bindgen]
+pub fn approve_tool_call(id: u64, remember: Option<String>) -> Result<(), JsValue> {
+    let remember = parse_remember(remember)?;
+    info(&format!("Approved pending tool call #{}", id));
+    decide(id, ApprovalStatus::Approved, None, remember)
+}
+
+/// Reject a pending call; `remember` as for `approve_tool_call`, denying.
+#[wasm_bindgen]
+pub fn reject_tool_call(id: u64, reason: Option<String>, remember: Option<String>) -> Result<(), JsValue> {
+    let remember = parse_remember(remember)?;
+    info(&format!("Rejected pending tool call #{}", id));
+    decide(id, ApprovalStatus::Rejected, reason, remember)
+}
+
+/// Replace the arguments of a pending extracted call before approving it.
+/// Calls queued by the confirmation policy were already validated and
+/// cannot be edited.
+#[wasm_bindgen]
+pub fn edit_pending_arguments(id: u64, arguments_json: &str) -> Result<(), JsValue> {
+    let arguments: serde_json::Value = serde_json::from_str(arguments_json)
+        .map_err(|e| JsValue::from_str(&format!("Invalid arguments: {}", e)))?;
+    if !arguments.is_object() {
+        return Err(JsValue::from_str("Arguments must be an object"));
+    }
+    let mut queue = APPROVAL_QUEUE.lock().unwrap();
+    let item = queue
+        .items
+        .iter_mut()
+        .find(|item| item.id == id && item.status == ApprovalStatus::Pending)
+        .ok_or_else(|| JsValue::from_str(&format!("No pending approval with id {}", id)))?;
+    if item.source != ApprovalSource::Extracted {
+        return Err(JsValue::from_str(&format!("Approval {} is not an extracted call", id)));
+    }
+    item.arguments = arguments;
+    item.edited = true;
+    Ok(())
+}
+
+#[wasm_bindgen]
+pub fn set_extracted_call_policy(policy_json: &str) -> Result<(), JsValue> {
+    let policy: ExtractedCallPolicy = serde_json::from_str(policy_json)
+        .map_err(|e| JsValue::from_str(&format!("Invalid extracted call policy: {}", e)))?;
+    *EXTRACTED_CALL_POLICY.lock().unwrap() = policy;
+    info("Updated extracted call policy");
+    Ok(())
+}
+
+#[wasm_bindgen]
+pub fn get_extracted_call_policy() -> String {
+    serde_json::to_string(&*EXTRACTED_CALL_POLICY.lock().unwrap()).unwrap_or_default()
+}
+
+/// Remember a decision. Takes `{ server, tool, engram_id?, decision }` with
+/// globs for `server` and `tool`; returns the rule's id.
+#[wasm_bindgen]
+pub fn add_approval_rule(rule_json: &str) -> Result<u64, JsValue> {
+    let rule: ApprovalRule = serde_json::from_str(rule_json)
+        .map_err(|e| JsValue::from_str(&format!("Invalid approval rule: {}", e)))?;
+    Ok(add_rule(rule))
+}
+
+#[wasm_bindgen]
+pub fn remove_approval_rule(id: u64) -> bool {
+    let mut rules = APPROVAL_RULES.lock().unwrap();
+    let before = rules.rules.len();
+    rules.rules.retain(|rule| rule.id != id);
+    let removed = rules.rules.len() != before;
+    if removed {
+        persist_rules(&rules);
+    }
+    removed
+}
+
+#[wasm_bindgen]
+pub fn list_approval_rules() -> String {
+    json!({ "rules": APPROVAL_RULES.lock().unwrap().rules }).to_string()
+}
+
+/// Replace the remembered rules with a JSON array saved by the persistence
+/// hook.
+#[wasm_bindgen]
+pub fn restore_approval_rules(rules_json: &str) -> Result<(), JsValue> {
+    let restored: Vec<ApprovalRule> = serde_json::from_str(rules_json)
+        .map_err(|e| JsValue::from_str(&format!("Invalid approval rules: {}", e)))?;
+    let mut rules = APPROVAL_RULES.lock().unwrap();
+    rules.next_id = restored.iter().map(|rule| rule.id).max().unwrap_or(0) + 1;
+    rules.rules = restored;
+    Ok(())
+}
+
+/// Register a function called with the rules as a JSON array whenever they
+/// change; pass `null` to unregister.
+#[wasm_bindgen]
+pub fn set_approval_rule_persistence(hook: Option<js_sys::Function>) {
+    RULES_PERSIST_HOOK.with(|slot| *slot.borrow_mut() = hook);
+}
+
+/// Register a function called with a JSON event whenever a call is queued
+/// for approval (`tool_approval_pending`) or settled, by a user or by the
+/// approval timeout (`tool_approval_resolved`); pass `null` to unregister.
+#[wasm_bindgen]
+pub fn set_approval_listener(listener: Option<js_sys::Function>) {
+    APPROVAL_LISTENER.with(|slot| *slot.borrow_mut() = listener);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn remembered_servers_match_only_themselves() {
+        let url = "http://mcp.example/rpc?tenant=*";
+        let pattern = glob_escape(url);
+        assert!(glob_match(&pattern, url));
+        assert!(!glob_match(&pattern, "http://mcp.example/rpc?tenant=other"));
+        assert!(!glob_match(&glob_escape("http://a?b"), "http://aXb"));
+        assert!(glob_match(&glob_escape(r"tool\name"), r"tool\name"));
+        assert!(glob_match("http://*.example/*", "http://mcp.example/rpc"));
+        assert!(glob_match("fs_???", "fs_cat"));
+    }
+
+    #[test]
+    fn allowed_values_compare_numbers_by_value() {
+        let rules = ToolAccessRules {
+            constraints: vec![ArgumentConstraint {
+                tool: "resize".to_string(),
+                argument: "scale".to_string(),
+                allowed_values: Some(vec![json!(1), json!(2)]),
+                pattern: None,
+                max_length: None,
+            }],
+            ..Default::default()
+        };
+        assert!(rules.check("global", "resize", &json!({ "scale": 1.0 })).is_ok());
+        assert!(rules.check("global", "resize", &json!({ "scale": 2 })).is_ok());
+        assert!(rules.check("global", "resize", &json!({ "scale": 1.5 })).is_err());
+    }
+}
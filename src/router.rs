@@ -0,0 +1,515 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{self, json};
+use std::cmp::Ordering as CmpOrdering;
+use std::sync::LazyLock;
+use wasm_bindgen::prelude::*;
+
+use crate::simulate::is_simulated;
+use crate::tool_call::{run_failover_call, run_tool_call, ToolCallOutcome};
+use crate::{info, McpServer, McpServerRegistry, SERVER_REGISTRY, SERVER_STATUS_CONNECTED, SERVER_STATUS_FAILED};
+
+/// Weight of the newest sample in the smoothed latency.
+const LATENCY_SMOOTHING: f64 = 0.3;
+
+/// Separator between server alias and tool name in qualified names.
+const QUALIFIER: char = '/';
+
+/// Tie-breakers applied, in order, when several servers provide a tool.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Precedence {
+    DefaultServer,
+    Priority,
+    LowestLatency,
+}
+
+impl Precedence {
+    fn compare(&self, a: &McpServer, b: &McpServer, default_server: Option<&str>) -> CmpOrdering {
+        match self {
+            Precedence::DefaultServer => {
+                let is_default = |s: &McpServer| default_server == Some(s.url.as_str());
+                is_default(b).cmp(&is_default(a))
+            }
+            Precedence::Priority => b.priority.cmp(&a.priority),
+            Precedence::LowestLatency => match (a.latency_ms, b.latency_ms) {
+                (Some(a), Some(b)) => a.partial_cmp(&b).unwrap_or(CmpOrdering::Equal),
+                (Some(_), None) => CmpOrdering::Less,
+                (None, Some(_)) => CmpOrdering::Greater,
+                (None, None) => CmpOrdering::Equal,
+            },
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Precedence::DefaultServer => "default_server",
+            Precedence::Priority => "priority",
+            Precedence::LowestLatency => "lowest_latency",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub(crate) struct RouterConfig {
+    pub precedence: Vec<Precedence>,
+    /// Retry unqualified calls on the next healthy provider when the chosen
+    /// one fails at the transport level or times out.
+    pub failover: bool,
+    /// Per-attempt request timeout for routed calls.
+    pub call_timeout_ms: Option<u32>,
+}
+
+impl Default for RouterConfig {
+    fn default() -> Self {
+        RouterConfig {
+            precedence: vec![Precedence::DefaultServer, Precedence::Priority, Precedence::LowestLatency],
+            failover: false,
+            call_timeout_ms: None,
+        }
+    }
+}
+
+pub(crate) static ROUTER_CONFIG: LazyLock<std::sync::Mutex<RouterConfig>> =
+    LazyLock::new(|| std::sync::Mutex::new(RouterConfig::default()));
+
+/// Where a tool call will be sent and why.
+#[derive(Debug, Serialize, Clone)]
+pub(crate) struct ToolRoute {
+    pub url: String,
+    pub alias: String,
+    pub tool_name: String,
+    pub qualified_name: String,
+    /// `qualified`, `unique`, the precedence rule that picked this server,
+    /// or `order` when no rule distinguished the candidates.
+    pub rule: String,
+    /// Other providers of the same tool, as qualified names.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub shadowed: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub(crate) struct ToolConflict {
+    pub tool_name: String,
+    pub providers: Vec<String>,
+}
+
+fn slugify(text: &str) -> String {
+    let slug: String = text
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '_' })
+        .collect();
+    let slug = slug.trim_matches('_').to_string();
+    let mut collapsed = String::with_capacity(slug.len());
+    for c in slug.chars() {
+        if !(c == '_' && collapsed.ends_with('_')) {
+            collapsed.push(c);
+        }
+    }
+    collapsed
+}
+
+/// Explicit alias, else a slug of the server name, else of the URL's host.
+pub(crate) fn server_alias(server: &McpServer) -> String {
+    if let Some(alias) = server.alias.as_ref().filter(|a| !a.is_empty()) {
+        return alias.clone();
+    }
+    if !server.name.is_empty() && server.name != "Unknown" && !server.name.starts_with("MCP Server at ") {
+        return slugify(&server.name);
+    }
+    let host = server
+        .url
+        .split("://")
+        .nth(1)
+        .unwrap_or(&server.url)
+        .split('/')
+        .next()
+        .unwrap_or_default();
+    slugify(host)
+}
+
+pub(crate) fn qualified_name(server: &McpServer, tool_name: &str) -> String {
+    format!("{}{}{}", server_alias(server), QUALIFIER, tool_name)
+}
+
+/// Fold a call's round-trip time into the server's smoothed latency.
+pub(crate) fn record_latency(url: &str, elapsed_ms: f64) {
+    if let Some(server) = SERVER_REGISTRY.lock().unwrap().servers.get_mut(url) {
+        server.latency_ms = Some(match server.latency_ms {
+            Some(previous) => previous + LATENCY_SMOOTHING * (elapsed_ms - previous),
+            None => elapsed_ms,
+        });
+    }
+}
+
+/// Providers of `tool_name`, best first according to `config`, together with
+/// the rule that separated the first from the second.
+pub(crate) fn ranked_providers<'a>(
+    registry: &'a McpServerRegistry,
+    tool_name: &str,
+    config: &RouterConfig,
+) -> (Vec<&'a McpServer>, String) {
+    let mut providers: Vec<&McpServer> = registry
+        .servers
+        .values()
+        .filter(|server| server.tools.iter().any(|t| t.name == tool_name))
+        .collect();
+    let default_server = registry.default_server.as_deref();
+    let compare = |a: &&McpServer, b: &&McpServer| {
+        config
+            .precedence
+            .iter()
+            .map(|rule| rule.compare(a, b, default_server))
+            .find(|ordering| *ordering != CmpOrdering::Equal)
+            .unwrap_or_else(|| a.url.cmp(&b.url))
+    };
+    providers.sort_by(compare);
+    let rule = match providers.as_slice() {
+        [] => "none".to_string(),
+        [_] => "unique".to_string(),
+        [first, second, ..] => config
+            .precedence
+            .iter()
+            .find(|rule| rule.compare(first, second, default_server) != CmpOrdering::Equal)
+            .map(|rule| rule.name().to_string())
+            .unwrap_or_else(|| "order".to_string()),
+    };
+    (providers, rule)
+}
+
+/// Split `alias/tool` when the prefix names a registered server. An alias
+/// shared by several servers is an error rather than a guess.
+fn split_qualified<'a>(
+    registry: &'a McpServerRegistry,
+    method: &str,
+) -> Result<Option<(&'a McpServer, String)>, String> {
+    let Some((alias, tool_name)) = method.split_once(QUALIFIER) else {
+        return Ok(None);
+    };
+    let mut matching: Vec<&McpServer> = registry.servers.values().filter(|server| server_alias(server) == alias).collect();
+    match matching.len() {
+        0 => Ok(None),
+        1 => Ok(matching.pop().map(|server| (server, tool_name.to_string()))),
+        _ => {
+            let mut urls: Vec<&str> = matching.iter().map(|server| server.url.as_str()).collect();
+            urls.sort();
+            Err(format!("Ambiguous alias '{}': shared by {}", alias, urls.join(", ")))
+        }
+    }
+}
+
+/// Resolve a method name, qualified or not, to a single provider.
+pub(crate) fn resolve_route(method: &str) -> Result<ToolRoute, String> {
+    let registry = SERVER_REGISTRY.lock().unwrap();
+    if let Some((server, tool_name)) = split_qualified(&registry, method)? {
+        if !server.tools.iter().any(|t| t.name == tool_name) {
+            return Err(format!("Tool '{}' not found on server '{}'", tool_name, server_alias(server)));
+        }
+        return Ok(ToolRoute {
+            url: server.url.clone(),
+            alias: server_alias(server),
+            qualified_name: qualified_name(server, &tool_name),
+            tool_name,
+            rule: "qualified".to_string(),
+            shadowed: Vec::new(),
+        });
+    }
+    let config = ROUTER_CONFIG.lock().unwrap().clone();
+    let (providers, rule) = ranked_providers(&registry, method, &config);
+    let chosen = providers.first().ok_or_else(|| format!("Tool not found: {}", method))?;
+    Ok(ToolRoute {
+        url: chosen.url.clone(),
+        alias: server_alias(chosen),
+        tool_name: method.to_string(),
+        qualified_name: qualified_name(chosen, method),
+        rule,
+        shadowed: providers[1..].iter().map(|s| qualified_name(s, method)).collect(),
+    })
+}
+
+/// Servers to try, in order, for a resolved route. Qualified routes and
+/// routes without failover have a single candidate; otherwise healthy
+/// providers come first, keeping their precedence order, and servers marked
+/// failed are tried last.
+pub(crate) fn failover_candidates(route: &ToolRoute, config: &RouterConfig) -> Vec<String> {
+    if route.rule == "qualified" || !config.failover {
+        return vec![route.url.clone()];
+    }
+    let registry = SERVER_REGISTRY.lock().unwrap();
+    let (providers, _) = ranked_providers(&registry, &route.tool_name, config);
+    let (healthy, failed): (Vec<&McpServer>, Vec<&McpServer>) =
+        providers.into_iter().partition(|server| server.status != SERVER_STATUS_FAILED);
+    healthy.into_iter().chain(failed).map(|server| server.url.clone()).collect()
+}
+
+fn set_server_status(url: &str, status: &str) {
+    if let Some(server) = SERVER_REGISTRY.lock().unwrap().servers.get_mut(url) {
+        server.status = status.to_string();
+    }
+}
+
+/// One attempt made while serving a routed call.
+#[derive(Debug, Serialize, Clone)]
+pub(crate) struct RouteAttempt {
+    pub url: String,
+    pub kind: String,
+    pub elapsed_ms: f64,
+}
+
+/// Execute a routed call, failing over between providers when enabled.
+/// Returns the final outcome, the server that produced it and every attempt.
+/// The call is authorized on the first provider; later providers apply
+/// their own argument schema and access rules, and authorize it again when
+/// their annotations make it riskier.
+pub(crate) async fn call_with_failover(
+    route: &ToolRoute,
+    arguments: serde_json::Value,
+    engram_id: Option<&str>,
+    simulate: bool,
+) -> (ToolCallOutcome, String, Vec<RouteAttempt>) {
+    let config = ROUTER_CONFIG.lock().unwrap().clone();
+    let candidates = failover_candidates(route, &config);
+    let failover = config.failover && route.rule != "qualified";
+    let mut attempts = Vec::new();
+    let mut last: Option<(ToolCallOutcome, String)> = None;
+    let authorized_url = candidates.first().cloned().unwrap_or_default();
+    for url in candidates {
+        let started = js_sys::Date::now();
+        let simulated = simulate && is_simulated(engram_id);
+        let outcome = match last {
+            None => run_tool_call(&url, &route.tool_name, arguments.clone(), engram_id, config.call_timeout_ms, simulate).await,
+            Some(_) => {
+                run_failover_call(&authorized_url, &url, &route.tool_name, arguments.clone(), engram_id, config.call_timeout_ms)
+                    .await
+            }
+        };
+        attempts.push(RouteAttempt {
+            url: url.clone(),
+            kind: outcome_kind(&outcome),
+            elapsed_ms: js_sys::Date::now() - started,
+        });
+        // Only transport failures and timeouts move on to the next provider;
+        // protocol, validation and policy results are final. Only answers
+        // the server itself gave say anything about its health, and a
+        // failure only demotes a server when there is another to prefer.
+        match outcome {
+            ToolCallOutcome::TransportError { .. } => {
+                if failover {
+                    set_server_status(&url, SERVER_STATUS_FAILED);
+                    info(&format!("Tool '{}' failed on {}, trying next provider", route.tool_name, url));
+                }
+                last = Some((outcome, url));
+            }
+            ToolCallOutcome::Success { .. } | ToolCallOutcome::ToolError { .. } | ToolCallOutcome::RpcError { .. } => {
+                if !simulated {
+                    set_server_status(&url, SERVER_STATUS_CONNECTED);
+                }
+                return (outcome, url, attempts);
+            }
+            _ => return (outcome, url, attempts),
+        }
+    }
+    let (outcome, url) = last.unwrap_or_else(|| {
+        (
+            ToolCallOutcome::RpcError { code: -32601, message: format!("Tool not found: {}", route.tool_name), data: None },
+            route.url.clone(),
+        )
+    });
+    (outcome, url, attempts)
+}
+
+fn outcome_kind(outcome: &ToolCallOutcome) -> String {
+    serde_json::to_value(outcome)
+        .ok()
+        .and_then(|v| v.get("kind").and_then(|k| k.as_str()).map(|k| k.to_string()))
+        .unwrap_or_default()
+}
+
+/// Tools provided by more than one server, and aliases shared by servers.
+pub(crate) fn find_conflicts(registry: &McpServerRegistry) -> (Vec<ToolConflict>, Vec<ToolConflict>) {
+    let mut by_tool: std::collections::BTreeMap<&str, Vec<String>> = std::collections::BTreeMap::new();
+    let mut by_alias: std::collections::BTreeMap<String, Vec<String>> = std::collections::BTreeMap::new();
+    for server in registry.servers.values() {
+        by_alias.entry(server_alias(server)).or_default().push(server.url.clone());
+        for tool in &server.tools {
+            by_tool.entry(tool.name.as_str()).or_default().push(qualified_name(server, &tool.name));
+        }
+    }
+    let collect = |entries: Vec<(String, Vec<String>)>| {
+        entries
+            .into_iter()
+            .filter(|(_, providers)| providers.len() > 1)
+            .map(|(tool_name, mut providers)| {
+                providers.sort();
+                ToolConflict { tool_name, providers }
+            })
+            .collect::<Vec<_>>()
+    };
+    (
+        collect(by_tool.into_iter().map(|(k, v)| (k.to_string(), v)).collect()),
+        collect(by_alias.into_iter().collect()),
+    )
+}
+
+/// Split a JSON-RPC call into a tool name and arguments. Both the direct
+/// form (`method` is the tool) and `tools/call` with `{ name, arguments }`
+/// are accepted.
+pub(crate) fn tool_call_target(method: &str, params: serde_json::Value) -> Result<(String, serde_json::Value), String> {
+    if method == "tools/call" {
+        let name = params
+            .get("name")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| "tools/call requires params.name".to_string())?
+            .to_string();
+        let arguments = params.get("arguments").cloned().unwrap_or_else(|| json!({}));
+        return Ok((name, arguments));
+    }
+    let arguments = if params.is_null() { json!({}) } else { params };
+    Ok((method.to_string(), arguments))
+}
+
+/// The inverse of `tool_call_target`: `params` with its tool arguments
+/// replaced by `arguments`.
+pub(crate) fn with_tool_arguments(method: &str, params: serde_json::Value, arguments: serde_json::Value) -> serde_json::Value {
+    match params {
+        mut params if method == "tools/call" => {
+            params["arguments"] = arguments;
+            params
+        }
+        _ => arguments,
+    }
+}
+
+/// A routed call: where it went, what came back and every attempt made.
+/// `route` and `served_by` are null when no server provides the tool.
+#[derive(Debug, Serialize)]
+pub(crate) struct RoutedCall {
+    pub route: Option<ToolRoute>,
+    pub outcome: ToolCallOutcome,
+    pub served_by: Option<String>,
+    pub attempts: Vec<RouteAttempt>,
+}
+
+/// Resolve a JSON-RPC call to a provider and execute it with failover.
+/// Unknown tools yield a `-32601` outcome; only a malformed `tools/call`
+/// is an error. `simulate` is passed on to `run_tool_call`.
+pub(crate) async fn route_call(
+    method: &str,
+    params: serde_json::Value,
+    engram_id: Option<&str>,
+    simulate: bool,
+) -> Result<RoutedCall, String> {
+    let (tool_name, arguments) = tool_call_target(method, params)?;
+    let route = match resolve_route(&tool_name) {
+        Ok(route) => route,
+        Err(message) => {
+            return Ok(RoutedCall {
+                route: None,
+                outcome: ToolCallOutcome::RpcError { code: -32601, message, data: None },
+                served_by: None,
+                attempts: Vec::new(),
+            });
+        }
+    };
+    info(&format!("Routing '{}' to {} ({})", tool_name, route.url, route.rule));
+    let (outcome, served_by, attempts) = call_with_failover(&route, arguments, engram_id, simulate).await;
+    Ok(RoutedCall { route: Some(route), outcome, served_by: Some(served_by), attempts })
+}
+
+/// Route a JSON-RPC tool call to a registered server and execute it.
+/// Returns `{ route, outcome, served_by, attempts }`; unknown tools yield a
+/// `-32601` outcome.
+#[wasm_bindgen]
+pub async fn route_tool_call(method: &str, params: JsValue, engram_id: Option<String>) -> Result<JsValue, JsValue> {
+    let params: serde_json::Value = if params.is_undefined() || params.is_null() {
+        serde_json::Value::Null
+    } else {
+        serde_wasm_bindgen::from_value(params).map_err(|e| JsValue::from_str(&format!("Invalid params: {}", e)))?
+    };
+    let routed = route_call(method, params, engram_id.as_deref(), true).await.map_err(|e| JsValue::from_str(&e))?;
+    Ok(JsValue::from_str(&serde_json::to_string(&routed).unwrap_or_default()))
+}
+
+/// Resolve a tool name (optionally `alias/tool`) without calling it.
+#[wasm_bindgen]
+pub fn resolve_tool_route(method: &str) -> Result<JsValue, JsValue> {
+    resolve_route(method)
+        .map(|route| JsValue::from_str(&serde_json::to_string(&route).unwrap_or_default()))
+        .map_err(|e| JsValue::from_str(&e))
+}
+
+#[wasm_bindgen]
+pub fn list_tool_conflicts() -> String {
+    let registry = SERVER_REGISTRY.lock().unwrap();
+    let (tools, aliases) = find_conflicts(&registry);
+    json!({ "tools": tools, "aliases": aliases }).to_string()
+}
+
+#[wasm_bindgen]
+pub fn set_router_config(config_json: &str) -> Result<(), JsValue> {
+    let config: RouterConfig = serde_json::from_str(config_json)
+        .map_err(|e| JsValue::from_str(&format!("Invalid router config: {}", e)))?;
+    *ROUTER_CONFIG.lock().unwrap() = config;
+    Ok(())
+}
+
+#[wasm_bindgen]
+pub fn get_router_config() -> String {
+    serde_json::to_string(&*ROUTER_CONFIG.lock().unwrap()).unwrap_or_default()
+}
+
+/// Set the alias and priority used when routing to a registered server.
+#[wasm_bindgen]
+pub fn set_server_routing(url: &str, alias: Option<String>, priority: i32) -> Result<(), JsValue> {
+    if alias.as_deref().is_some_and(|a| a.contains(QUALIFIER)) {
+        return Err(JsValue::from_str(&format!("Alias may not contain '{}'", QUALIFIER)));
+    }
+    let mut registry = SERVER_REGISTRY.lock().unwrap();
+    let server = registry
+        .servers
+        .get_mut(url)
+        .ok_or_else(|| JsValue::from_str(&format!("Unknown server: {}", url)))?;
+    server.alias = alias;
+    server.priority = priority;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server(url: &str, alias: &str) -> McpServer {
+        McpServer {
+            url: url.to_string(),
+            name: alias.to_string(),
+            version: "1".to_string(),
+            status: SERVER_STATUS_CONNECTED.to_string(),
+            tools: Vec::new(),
+            last_health_check: 0,
+            session_id: None,
+            policy: Default::default(),
+            alias: Some(alias.to_string()),
+            priority: 0,
+            latency_ms: None,
+            instructions: None,
+        }
+    }
+
+    #[test]
+    fn shared_aliases_are_ambiguous() {
+        let mut registry = McpServerRegistry { servers: Default::default(), default_server: None };
+        for (url, alias) in [("http://a.invalid/mcp", "fs"), ("http://b.invalid/mcp", "web")] {
+            registry.servers.insert(url.to_string(), server(url, alias));
+        }
+        let (found, tool_name) = split_qualified(&registry, "fs/read").unwrap().unwrap();
+        assert_eq!((found.url.as_str(), tool_name.as_str()), ("http://a.invalid/mcp", "read"));
+        assert!(split_qualified(&registry, "db/read").unwrap().is_none());
+        assert!(split_qualified(&registry, "read").unwrap().is_none());
+
+        registry.servers.insert("http://c.invalid/mcp".to_string(), server("http://c.invalid/mcp", "fs"));
+        assert_eq!(
+            split_qualified(&registry, "fs/read").err().as_deref(),
+            Some("Ambiguous alias 'fs': shared by http://a.invalid/mcp, http://c.invalid/mcp")
+        );
+    }
+}
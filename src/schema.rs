@@ -0,0 +1,451 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{self, json};
+use std::collections::BTreeMap;
+use wasm_bindgen::prelude::*;
+
+use crate::{debug, ToolParameter, SERVER_REGISTRY};
+
+/// Maximum `$ref` nesting followed while resolving a schema.
+const MAX_REF_DEPTH: usize = 32;
+
+/// `type` may be a single type name or a list of them.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(untagged)]
+pub(crate) enum SchemaTypes {
+    Single(String),
+    Multiple(Vec<String>),
+}
+
+impl SchemaTypes {
+    pub(crate) fn names(&self) -> Vec<&str> {
+        match self {
+            SchemaTypes::Single(name) => vec![name.as_str()],
+            SchemaTypes::Multiple(names) => names.iter().map(|n| n.as_str()).collect(),
+        }
+    }
+}
+
+/// `additionalProperties` is either a boolean or a schema for extra keys.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(untagged)]
+pub(crate) enum AdditionalProperties {
+    Allowed(bool),
+    Schema(Box<JsonSchema>),
+}
+
+/// The subset of JSON Schema used by MCP tool `inputSchema` and `outputSchema`.
+/// Deserializing is lenient: see `JsonSchema::parse`.
+#[derive(Debug, Serialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct JsonSchema {
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub schema_type: Option<SchemaTypes>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default: Option<serde_json::Value>,
+    #[serde(rename = "enum", skip_serializing_if = "Option::is_none")]
+    pub enum_values: Option<Vec<serde_json::Value>>,
+    #[serde(rename = "const", skip_serializing_if = "Option::is_none")]
+    pub const_value: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub properties: Option<BTreeMap<String, JsonSchema>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub required: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub additional_properties: Option<AdditionalProperties>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub items: Option<Box<JsonSchema>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub minimum: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub maximum: Option<f64>,
+    /// A number in current drafts, a boolean modifier in draft-04.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exclusive_minimum: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exclusive_maximum: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_length: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_length: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_items: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_items: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub any_of: Option<Vec<JsonSchema>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub one_of: Option<Vec<JsonSchema>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub all_of: Option<Vec<JsonSchema>>,
+    #[serde(rename = "$ref", skip_serializing_if = "Option::is_none")]
+    pub reference: Option<String>,
+}
+
+impl JsonSchema {
+    /// Parse a schema after inlining its local `$ref`s.
+    pub(crate) fn from_value(value: &serde_json::Value) -> Self {
+        JsonSchema::parse(&resolve_local_refs(value))
+    }
+
+    /// Read a schema keyword by keyword, so one keyword outside the
+    /// supported subset drops only itself. Boolean schemas become the empty
+    /// schema (`true`) or one no value matches (`false`), tuple `items` the
+    /// alternatives of their positions, and draft-03 `required: true` on a
+    /// property makes it required in its parent.
+    pub(crate) fn parse(value: &serde_json::Value) -> Self {
+        let map = match value {
+            serde_json::Value::Object(map) => map,
+            serde_json::Value::Bool(false) => return JsonSchema { enum_values: Some(Vec::new()), ..JsonSchema::default() },
+            _ => return JsonSchema::default(),
+        };
+        fn keyword<T: serde::de::DeserializeOwned>(map: &serde_json::Map<String, serde_json::Value>, key: &str) -> Option<T> {
+            let value = map.get(key)?;
+            serde_json::from_value(value.clone())
+                .map_err(|e| debug(&format!("Ignoring schema keyword '{}': {}", key, e)))
+                .ok()
+        }
+        let schemas = |key: &str| -> Option<Vec<JsonSchema>> {
+            Some(map.get(key)?.as_array()?.iter().map(JsonSchema::parse).collect())
+        };
+        let properties = map.get("properties").and_then(|p| p.as_object()).map(|properties| {
+            properties.iter().map(|(name, schema)| (name.clone(), JsonSchema::parse(schema))).collect::<BTreeMap<_, _>>()
+        });
+        let mut required: Vec<String> = match map.get("required") {
+            Some(serde_json::Value::Array(names)) => names.iter().filter_map(|n| n.as_str().map(String::from)).collect(),
+            _ => Vec::new(),
+        };
+        if let Some(properties) = map.get("properties").and_then(|p| p.as_object()) {
+            let draft3 = properties.iter().filter(|(_, schema)| schema.get("required") == Some(&serde_json::Value::Bool(true)));
+            required.extend(draft3.map(|(name, _)| name.clone()).filter(|name| !required.contains(name)).collect::<Vec<_>>());
+        }
+        let items = match map.get("items") {
+            Some(serde_json::Value::Array(positions)) => Some(Box::new(JsonSchema {
+                any_of: Some(positions.iter().map(JsonSchema::parse).collect()),
+                ..JsonSchema::default()
+            })),
+            Some(schema) => Some(Box::new(JsonSchema::parse(schema))),
+            None => None,
+        };
+        let additional_properties = match map.get("additionalProperties") {
+            Some(serde_json::Value::Bool(allowed)) => Some(AdditionalProperties::Allowed(*allowed)),
+            Some(schema @ serde_json::Value::Object(_)) => Some(AdditionalProperties::Schema(Box::new(JsonSchema::parse(schema)))),
+            _ => None,
+        };
+        JsonSchema {
+            schema_type: keyword(map, "type"),
+            title: keyword(map, "title"),
+            description: keyword(map, "description"),
+            default: map.get("default").cloned(),
+            enum_values: keyword(map, "enum"),
+            const_value: map.get("const").cloned(),
+            properties,
+            required,
+            additional_properties,
+            items,
+            minimum: keyword(map, "minimum"),
+            maximum: keyword(map, "maximum"),
+            exclusive_minimum: map.get("exclusiveMinimum").cloned(),
+            exclusive_maximum: map.get("exclusiveMaximum").cloned(),
+            min_length: keyword(map, "minLength"),
+            max_length: keyword(map, "maxLength"),
+            min_items: keyword(map, "minItems"),
+            max_items: keyword(map, "maxItems"),
+            pattern: keyword(map, "pattern"),
+            format: keyword(map, "format"),
+            any_of: schemas("anyOf"),
+            one_of: schemas("oneOf"),
+            all_of: schemas("allOf"),
+            reference: keyword(map, "$ref"),
+        }
+    }
+
+    /// Type names declared directly or implied by the schema's keywords.
+    pub(crate) fn type_names(&self) -> Vec<&str> {
+        if let Some(types) = &self.schema_type {
+            return types.names();
+        }
+        if self.properties.is_some() {
+            vec!["object"]
+        } else if self.items.is_some() {
+            vec!["array"]
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// `anyOf`/`oneOf` alternatives, if any.
+    pub(crate) fn alternatives(&self) -> Option<&Vec<JsonSchema>> {
+        self.any_of.as_ref().or(self.one_of.as_ref())
+    }
+
+    /// Single non-null type used for display, e.g. `string` for
+    /// `["string", "null"]` or `anyOf: [{type: string}, {type: null}]`.
+    pub(crate) fn primary_type(&self) -> String {
+        if let Some(name) = self.type_names().into_iter().find(|t| *t != "null") {
+            return name.to_string();
+        }
+        if let Some(alternatives) = self.alternatives() {
+            let non_null: Vec<String> = alternatives
+                .iter()
+                .map(|alt| alt.primary_type())
+                .filter(|t| t != "null" && t != "any")
+                .collect();
+            if non_null.len() == 1 {
+                return non_null[0].clone();
+            }
+        }
+        if self.enum_values.is_some() || self.const_value.is_some() {
+            return "enum".to_string();
+        }
+        "any".to_string()
+    }
+
+    /// True when `null` is an accepted value.
+    pub(crate) fn is_nullable(&self) -> bool {
+        self.type_names().contains(&"null")
+            || self
+                .alternatives()
+                .is_some_and(|alts| alts.iter().any(|alt| alt.type_names().contains(&"null")))
+    }
+
+    /// The alternative carrying the schema's real shape when it is only
+    /// `anyOf`/`oneOf` with a null branch.
+    fn non_null_alternative(&self) -> Option<&JsonSchema> {
+        if self.schema_type.is_some() {
+            return None;
+        }
+        let alternatives = self.alternatives()?;
+        let mut non_null = alternatives.iter().filter(|alt| !alt.type_names().contains(&"null"));
+        match (non_null.next(), non_null.next()) {
+            (Some(only), None) => Some(only),
+            _ => None,
+        }
+    }
+
+    /// A value satisfying the schema's common keywords: `const`, `default`
+    /// or the first `enum` value when given, otherwise a placeholder of the
+    /// primary type. Patterns are not honoured, and recursion stops at
+    /// `MAX_REF_DEPTH` with `null`.
+    pub(crate) fn example(&self) -> serde_json::Value {
+        self.example_at(0)
+    }
+
+    fn example_at(&self, depth: usize) -> serde_json::Value {
+        if depth > MAX_REF_DEPTH {
+            return serde_json::Value::Null;
+        }
+        if let Some(value) = self.const_value.as_ref().or(self.default.as_ref()) {
+            return value.clone();
+        }
+        if let Some(first) = self.enum_values.as_ref().and_then(|values| values.first()) {
+            return first.clone();
+        }
+        if let Some(all_of) = self.all_of.as_ref().filter(|_| self.schema_type.is_none()) {
+            let mut merged = serde_json::Map::new();
+            for part in all_of {
+                if let serde_json::Value::Object(part) = part.example_at(depth + 1) {
+                    merged.extend(part);
+                }
+            }
+            return serde_json::Value::Object(merged);
+        }
+        if self.schema_type.is_none() && self.properties.is_none() && self.items.is_none() {
+            if let Some(alternatives) = self.alternatives() {
+                let shape = alternatives.iter().find(|alt| !alt.type_names().contains(&"null"));
+                return shape.map_or(serde_json::Value::Null, |alt| alt.example_at(depth + 1));
+            }
+        }
+        match self.primary_type().as_str() {
+            "object" => serde_json::Value::Object(
+                self.properties
+                    .iter()
+                    .flatten()
+                    .map(|(name, prop)| (name.clone(), prop.example_at(depth + 1)))
+                    .collect(),
+            ),
+            "array" => {
+                let item = self.items.as_ref().map_or(serde_json::Value::Null, |items| items.example_at(depth + 1));
+                json!(vec![item; self.min_items.unwrap_or(1).max(1) as usize])
+            }
+            "string" => {
+                let text = match self.format.as_deref() {
+                    Some("date-time") => "1970-01-01T00:00:00Z",
+                    Some("date") => "1970-01-01",
+                    Some("time") => "00:00:00",
+                    Some("email") => "user@example.com",
+                    Some("uri") | Some("url") => "https://example.com",
+                    Some("uuid") => "00000000-0000-0000-0000-000000000000",
+                    _ => "string",
+                };
+                let min = self.min_length.unwrap_or(0) as usize;
+                let max = self.max_length.map_or(usize::MAX, |max| max as usize);
+                let padded = format!("{}{}", text, "x".repeat(min.saturating_sub(text.len())));
+                json!(padded.chars().take(max).collect::<String>())
+            }
+            "integer" | "number" => {
+                let mut value = self.minimum.unwrap_or(0.0);
+                if let Some(max) = self.maximum {
+                    value = value.min(max);
+                }
+                if self.primary_type() == "integer" {
+                    json!(value.ceil() as i64)
+                } else {
+                    json!(value)
+                }
+            }
+            "boolean" => json!(false),
+            _ => serde_json::Value::Null,
+        }
+    }
+
+    /// Flatten the top-level properties into `ToolParameter` entries.
+    pub(crate) fn to_parameters(&self) -> Vec<ToolParameter> {
+        self.properties
+            .as_ref()
+            .map(|properties| {
+                properties
+                    .iter()
+                    .map(|(name, prop)| ToolParameter::from_schema(name, prop, self.required.contains(name)))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+impl ToolParameter {
+    pub(crate) fn from_schema(name: &str, schema: &JsonSchema, required: bool) -> Self {
+        let shape = schema.non_null_alternative().unwrap_or(schema);
+        ToolParameter {
+            name: name.to_string(),
+            description: schema
+                .description
+                .clone()
+                .or_else(|| shape.description.clone())
+                .or_else(|| schema.title.clone())
+                .unwrap_or_default(),
+            required,
+            param_type: schema.primary_type(),
+            nullable: schema.is_nullable(),
+            enum_values: shape
+                .enum_values
+                .clone()
+                .or_else(|| shape.const_value.clone().map(|c| vec![c])),
+            default: schema.default.clone().or_else(|| shape.default.clone()),
+            format: shape.format.clone(),
+            properties: shape.to_parameters(),
+            items: shape
+                .items
+                .as_ref()
+                .map(|items| Box::new(ToolParameter::from_schema("items", items, false))),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for JsonSchema {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(JsonSchema::parse(&serde_json::Value::deserialize(deserializer)?))
+    }
+}
+
+/// Inline local `$ref`s (`#/$defs/...`, `#/definitions/...` or any other
+/// JSON pointer into the same document). Sibling keywords next to a `$ref`
+/// override the referenced schema's. Recursive references are left in
+/// place once they would re-enter a definition already being expanded; the
+/// root's definitions are then kept so they still resolve.
+pub(crate) fn resolve_local_refs(root: &serde_json::Value) -> serde_json::Value {
+    fn resolve(node: &serde_json::Value, root: &serde_json::Value, stack: &mut Vec<String>, dangling: &mut bool) -> serde_json::Value {
+        match node {
+            serde_json::Value::Object(map) => {
+                if let Some(reference) = map.get("$ref").and_then(|r| r.as_str()) {
+                    let target = reference
+                        .strip_prefix('#')
+                        .filter(|_| !stack.iter().any(|r| r == reference) && stack.len() < MAX_REF_DEPTH)
+                        .and_then(|pointer| root.pointer(pointer));
+                    let Some(target) = target else {
+                        *dangling |= reference.starts_with('#');
+                        return serde_json::Value::Object(
+                            map.iter().map(|(key, value)| (key.clone(), resolve(value, root, stack, dangling))).collect(),
+                        );
+                    };
+                    stack.push(reference.to_string());
+                    let mut merged = match resolve(target, root, stack, dangling) {
+                        serde_json::Value::Object(target) => target,
+                        other => {
+                            stack.pop();
+                            return other;
+                        }
+                    };
+                    stack.pop();
+                    for (key, value) in map {
+                        if key != "$ref" {
+                            merged.insert(key.clone(), resolve(value, root, stack, dangling));
+                        }
+                    }
+                    return serde_json::Value::Object(merged);
+                }
+                // The root's definitions are copied as they are and dropped
+                // below once inlined; nested keys may be property names.
+                let is_root = std::ptr::eq(node, root);
+                serde_json::Value::Object(
+                    map.iter()
+                        .map(|(key, value)| match is_root && matches!(key.as_str(), "$defs" | "definitions") {
+                            true => (key.clone(), value.clone()),
+                            false => (key.clone(), resolve(value, root, stack, dangling)),
+                        })
+                        .collect(),
+                )
+            }
+            serde_json::Value::Array(items) => {
+                serde_json::Value::Array(items.iter().map(|item| resolve(item, root, stack, dangling)).collect())
+            }
+            other => other.clone(),
+        }
+    }
+    let mut dangling = false;
+    let mut resolved = resolve(root, root, &mut Vec::new(), &mut dangling);
+    if let (false, Some(map)) = (dangling, resolved.as_object_mut()) {
+        map.remove("$defs");
+        map.remove("definitions");
+    }
+    resolved
+}
+
+/// Flatten a raw `inputSchema` into `ToolParameter` entries.
+#[wasm_bindgen]
+pub fn flatten_input_schema(schema_json: &str) -> Result<JsValue, JsValue> {
+    let value: serde_json::Value = serde_json::from_str(schema_json)
+        .map_err(|e| JsValue::from_str(&format!("Invalid schema: {}", e)))?;
+    let schema = JsonSchema::from_value(&value);
+    Ok(JsValue::from_str(&json!({
+        "schema": schema,
+        "parameters": schema.to_parameters()
+    }).to_string()))
+}
+
+/// Parameters and resolved `inputSchema` for a tool cached in the registry.
+#[wasm_bindgen]
+pub fn get_tool_parameters(url: &str, tool_name: &str) -> Result<JsValue, JsValue> {
+    let registry = SERVER_REGISTRY.lock().unwrap();
+    let server = registry
+        .servers
+        .get(url)
+        .ok_or_else(|| JsValue::from_str(&format!("Unknown server: {}", url)))?;
+    let tool = server
+        .tools
+        .iter()
+        .find(|t| t.name == tool_name)
+        .ok_or_else(|| JsValue::from_str(&format!("Unknown tool '{}' on {}", tool_name, url)))?;
+    Ok(JsValue::from_str(&json!({
+        "name": tool.name,
+        "parameters": tool.parameters,
+        "inputSchema": resolve_local_refs(&tool.input_schema)
+    }).to_string()))
+}
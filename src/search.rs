@@ -0,0 +1,459 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{self, json};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{LazyLock, Mutex};
+use wasm_bindgen::prelude::*;
+
+use crate::get_timestamp;
+use crate::conversation::EngramMessage;
+use crate::memory::MemoryEvent;
+use crate::stem::stem;
+
+/// BM25 term-frequency saturation.
+const K1: f64 = 1.2;
+/// BM25 length normalisation.
+const B: f64 = 0.75;
+/// Hits returned when a query gives no `limit`.
+const DEFAULT_LIMIT: usize = 10;
+
+/// What an indexed document is.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub(crate) enum DocKey {
+    Memory { id: u64 },
+    Message { message_id: String },
+}
+
+struct Doc {
+    key: DocKey,
+    engram_id: Option<String>,
+    /// `memory` for memory events.
+    role: String,
+    timestamp: u64,
+    expires_at: Option<u64>,
+    text: String,
+    length: usize,
+}
+
+/// Lowercased words, stemmed. Positions are indexes into the result.
+pub(crate) fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| stem(&word.to_lowercase()))
+        .collect()
+}
+
+/// Inverted index over memory events and conversation messages.
+#[derive(Default)]
+pub(crate) struct SearchIndex {
+    next_doc: u32,
+    docs: HashMap<u32, Doc>,
+    keys: HashMap<DocKey, u32>,
+    /// Term to positions per document. Ordered for prefix scans.
+    postings: BTreeMap<String, HashMap<u32, Vec<u32>>>,
+    total_length: usize,
+}
+
+/// One part of a parsed query.
+#[derive(Debug, PartialEq)]
+enum Clause {
+    Term(String),
+    /// Matches stems starting with the text, or with its own stem.
+    Prefix(String),
+    /// Consecutive stems; documents must contain every phrase.
+    Phrase(Vec<String>),
+}
+
+/// Split a query into clauses: `"quoted phrases"`, `prefix*` and plain
+/// words.
+fn parse_query(query: &str) -> Vec<Clause> {
+    let mut clauses = Vec::new();
+    for (i, part) in query.split('"').enumerate() {
+        if i % 2 == 1 {
+            let mut terms = tokenize(part);
+            match terms.len() {
+                0 => {}
+                1 => clauses.push(Clause::Term(terms.remove(0))),
+                _ => clauses.push(Clause::Phrase(terms)),
+            }
+            continue;
+        }
+        for word in part.split_whitespace() {
+            let mut terms = tokenize(word);
+            if word.ends_with('*') {
+                if let Some(prefix) = word.trim_end_matches('*').split(|c: char| !c.is_alphanumeric()).next_back() {
+                    terms.pop();
+                    if !prefix.is_empty() {
+                        clauses.push(Clause::Prefix(prefix.to_lowercase()));
+                    }
+                }
+            }
+            clauses.extend(terms.into_iter().map(Clause::Term));
+        }
+    }
+    clauses
+}
+
+/// Filters and paging for `search_context`.
+#[derive(Debug, Deserialize, Default)]
+#[serde(default)]
+pub(crate) struct SearchQuery {
+    pub query: String,
+    /// This engram's messages and memory events, plus unscoped memory.
+    pub engram_id: Option<String>,
+    /// Roles to include; memory events have the role `memory`.
+    pub roles: Vec<String>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct SearchHit {
+    #[serde(flatten)]
+    pub key: DocKey,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub engram_id: Option<String>,
+    pub role: String,
+    pub timestamp: u64,
+    pub score: f64,
+    pub text: String,
+}
+
+impl SearchIndex {
+    /// Index a document, replacing any earlier version with the same key.
+    fn upsert(&mut self, key: DocKey, engram_id: Option<String>, role: String, timestamp: u64, expires_at: Option<u64>, text: &str) {
+        self.remove(&key);
+        let terms = tokenize(text);
+        let id = self.next_doc;
+        self.next_doc += 1;
+        for (position, term) in terms.iter().enumerate() {
+            self.postings.entry(term.clone()).or_default().entry(id).or_default().push(position as u32);
+        }
+        self.total_length += terms.len();
+        self.keys.insert(key.clone(), id);
+        self.docs.insert(id, Doc { key, engram_id, role, timestamp, expires_at, text: text.to_string(), length: terms.len() });
+    }
+
+    fn remove(&mut self, key: &DocKey) {
+        let Some(id) = self.keys.remove(key) else {
+            return;
+        };
+        let Some(doc) = self.docs.remove(&id) else {
+            return;
+        };
+        self.total_length -= doc.length;
+        let terms: HashSet<String> = tokenize(&doc.text).into_iter().collect();
+        for term in terms {
+            if let Some(postings) = self.postings.get_mut(&term) {
+                postings.remove(&id);
+                if postings.is_empty() {
+                    self.postings.remove(&term);
+                }
+            }
+        }
+    }
+
+    fn remove_where(&mut self, predicate: impl Fn(&Doc) -> bool) {
+        let keys: Vec<DocKey> = self.docs.values().filter(|doc| predicate(doc)).map(|doc| doc.key.clone()).collect();
+        for key in keys {
+            self.remove(&key);
+        }
+    }
+
+    fn bm25(&self, doc: &Doc, frequency: usize, document_frequency: usize) -> f64 {
+        let n = self.docs.len() as f64;
+        let df = document_frequency as f64;
+        let idf = (1.0 + (n - df + 0.5) / (df + 0.5)).ln();
+        let average = self.total_length as f64 / n.max(1.0);
+        let tf = frequency as f64;
+        idf * tf * (K1 + 1.0) / (tf + K1 * (1.0 - B + B * doc.length as f64 / average.max(1.0)))
+    }
+
+    /// Documents containing `phrase`, with the number of occurrences.
+    fn phrase_matches(&self, phrase: &[String]) -> HashMap<u32, usize> {
+        let lists: Option<Vec<&HashMap<u32, Vec<u32>>>> = phrase.iter().map(|term| self.postings.get(term)).collect();
+        let Some(lists) = lists else {
+            return HashMap::new();
+        };
+        let mut matches = HashMap::new();
+        for (doc, starts) in lists[0] {
+            let count = starts
+                .iter()
+                .filter(|start| {
+                    lists[1..].iter().enumerate().all(|(offset, list)| {
+                        list.get(doc).is_some_and(|positions| positions.contains(&(**start + offset as u32 + 1)))
+                    })
+                })
+                .count();
+            if count > 0 {
+                matches.insert(*doc, count);
+            }
+        }
+        matches
+    }
+
+    /// Per-document frequencies of the terms a clause stands for.
+    fn clause_terms(&self, clause: &Clause) -> Vec<HashMap<u32, usize>> {
+        let frequencies = |postings: &HashMap<u32, Vec<u32>>| postings.iter().map(|(doc, p)| (*doc, p.len())).collect();
+        match clause {
+            Clause::Term(term) => self.postings.get(term).map(frequencies).into_iter().collect(),
+            Clause::Prefix(prefix) => {
+                let stemmed = stem(prefix);
+                let mut terms: Vec<&String> = Vec::new();
+                for start in [prefix, &stemmed] {
+                    let range = self.postings.range::<String, _>(start.clone()..);
+                    terms.extend(range.take_while(|(term, _)| term.starts_with(start.as_str())).map(|(term, _)| term));
+                }
+                terms.sort();
+                terms.dedup();
+                terms.into_iter().map(|term| frequencies(&self.postings[term])).collect()
+            }
+            Clause::Phrase(phrase) => vec![self.phrase_matches(phrase)],
+        }
+    }
+
+    pub(crate) fn search(&self, query: &SearchQuery, now: u64) -> Vec<SearchHit> {
+        let admits = |doc: &Doc| {
+            doc.expires_at.is_none_or(|expires_at| expires_at > now)
+                && (query.roles.is_empty() || query.roles.contains(&doc.role))
+                && match (&query.engram_id, &doc.engram_id) {
+                    (None, _) => true,
+                    (Some(wanted), Some(engram_id)) => wanted == engram_id,
+                    (Some(_), None) => doc.role == "memory",
+                }
+        };
+        let clauses = parse_query(&query.query);
+        let mut scores: HashMap<u32, f64> = HashMap::new();
+        let mut required: Option<HashSet<u32>> = None;
+        for clause in &clauses {
+            // A prefix scores as its best expansion in each document
+            let mut clause_scores: HashMap<u32, f64> = HashMap::new();
+            for term in self.clause_terms(clause) {
+                for (id, frequency) in &term {
+                    let Some(doc) = self.docs.get(id).filter(|doc| admits(doc)) else {
+                        continue;
+                    };
+                    let score = self.bm25(doc, *frequency, term.len());
+                    let best = clause_scores.entry(*id).or_default();
+                    *best = best.max(score);
+                }
+            }
+            if matches!(clause, Clause::Phrase(_)) {
+                let matched: HashSet<u32> = clause_scores.keys().copied().collect();
+                required = Some(match required {
+                    Some(required) => required.intersection(&matched).copied().collect(),
+                    None => matched,
+                });
+            }
+            for (id, score) in clause_scores {
+                *scores.entry(id).or_default() += score;
+            }
+        }
+        let mut hits: Vec<SearchHit> = scores
+            .into_iter()
+            .filter(|(id, _)| required.as_ref().is_none_or(|required| required.contains(id)))
+            .filter_map(|(id, score)| {
+                let doc = self.docs.get(&id)?;
+                Some(SearchHit {
+                    key: doc.key.clone(),
+                    engram_id: doc.engram_id.clone(),
+                    role: doc.role.clone(),
+                    timestamp: doc.timestamp,
+                    score,
+                    text: doc.text.clone(),
+                })
+            })
+            .collect();
+        hits.sort_by(|a, b| b.score.total_cmp(&a.score).then(b.timestamp.cmp(&a.timestamp)));
+        hits.truncate(query.limit.unwrap_or(DEFAULT_LIMIT));
+        hits
+    }
+}
+
+static SEARCH_INDEX: LazyLock<Mutex<SearchIndex>> = LazyLock::new(|| Mutex::new(SearchIndex::default()));
+
+/// Add or replace a memory event in the index.
+pub(crate) fn index_memory_event(event: &MemoryEvent) {
+    SEARCH_INDEX.lock().unwrap().upsert(
+        DocKey::Memory { id: event.id },
+        event.engram_id.clone(),
+        "memory".to_string(),
+        event.timestamp,
+        event.expires_at,
+        &event.text,
+    );
+}
+
+pub(crate) fn remove_memory_event(id: u64) {
+    SEARCH_INDEX.lock().unwrap().remove(&DocKey::Memory { id });
+}
+
+/// Drop indexed memory events of one engram, or all of them.
+pub(crate) fn remove_memory_events(engram_id: Option<&str>) {
+    SEARCH_INDEX.lock().unwrap().remove_where(|doc| {
+        matches!(doc.key, DocKey::Memory { .. }) && engram_id.is_none_or(|id| doc.engram_id.as_deref() == Some(id))
+    });
+}
+
+/// Add or replace a stored conversation message in the index.
+pub(crate) fn index_engram_message(message: &EngramMessage) {
+    SEARCH_INDEX.lock().unwrap().upsert(
+        DocKey::Message { message_id: message.id.clone() },
+        Some(message.engram_id.clone()),
+        message.role.clone(),
+        message.timestamp,
+        None,
+        &message.text,
+    );
+}
+
+pub(crate) fn remove_message(message_id: &str) {
+    SEARCH_INDEX.lock().unwrap().remove(&DocKey::Message { message_id: message_id.to_string() });
+}
+
+/// A stored conversation message, as the service worker persists it.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StoredMessage {
+    id: String,
+    engram_id: String,
+    #[serde(default)]
+    role: Option<String>,
+    #[serde(default)]
+    text: serde_json::Value,
+    #[serde(default)]
+    timestamp: u64,
+}
+
+fn index_stored(index: &mut SearchIndex, message: StoredMessage) -> bool {
+    let serde_json::Value::String(text) = message.text else {
+        return false;
+    };
+    let role = message.role.unwrap_or_else(|| "user".to_string());
+    index.upsert(DocKey::Message { message_id: message.id }, Some(message.engram_id), role, message.timestamp, None, &text);
+    true
+}
+
+/// Index a stored message `{ id, engramId, role, text, timestamp }`,
+/// replacing the message with the same id. Messages without string text
+/// are skipped.
+#[wasm_bindgen]
+pub fn index_message(message_json: &str) -> Result<bool, JsValue> {
+    let message: StoredMessage = serde_json::from_str(message_json)
+        .map_err(|e| JsValue::from_str(&format!("Invalid message: {}", e)))?;
+    Ok(index_stored(&mut SEARCH_INDEX.lock().unwrap(), message))
+}
+
+/// Index a JSON array of stored messages; returns how many were indexed.
+#[wasm_bindgen]
+pub fn index_messages(messages_json: &str) -> Result<usize, JsValue> {
+    let messages: Vec<StoredMessage> = serde_json::from_str(messages_json)
+        .map_err(|e| JsValue::from_str(&format!("Invalid messages: {}", e)))?;
+    let mut index = SEARCH_INDEX.lock().unwrap();
+    Ok(messages.into_iter().map(|message| index_stored(&mut index, message)).filter(|indexed| *indexed).count())
+}
+
+/// Drop the indexed messages of an engram.
+#[wasm_bindgen]
+pub fn remove_engram_from_search_index(engram_id: &str) {
+    SEARCH_INDEX
+        .lock()
+        .unwrap()
+        .remove_where(|doc| matches!(doc.key, DocKey::Message { .. }) && doc.engram_id.as_deref() == Some(engram_id));
+}
+
+/// Rank memory events and messages against
+/// `{ query, engram_id?, roles?, limit? }`. Queries combine words,
+/// `"quoted phrases"` (required) and `prefix*` terms. Returns
+/// `{ hits: [{ kind, id | message_id, engram_id?, role, timestamp, score, text }] }`,
+/// best first.
+#[wasm_bindgen]
+pub fn search_context(query_json: &str) -> Result<String, JsValue> {
+    let query: SearchQuery = serde_json::from_str(query_json)
+        .map_err(|e| JsValue::from_str(&format!("Invalid search query: {}", e)))?;
+    let hits = SEARCH_INDEX.lock().unwrap().search(&query, get_timestamp());
+    Ok(json!({ "hits": hits }).to_string())
+}
+
+#[wasm_bindgen]
+pub fn get_search_index_stats() -> String {
+    let index = SEARCH_INDEX.lock().unwrap();
+    json!({
+        "documents": index.docs.len(),
+        "terms": index.postings.len(),
+        "average_length": index.total_length as f64 / index.docs.len().max(1) as f64
+    })
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index(texts: &[&str]) -> SearchIndex {
+        let mut index = SearchIndex::default();
+        for (id, text) in texts.iter().enumerate() {
+            index.upsert(DocKey::Memory { id: id as u64 }, None, "memory".to_string(), id as u64, None, text);
+        }
+        index
+    }
+
+    fn ids(index: &SearchIndex, query: &str) -> Vec<u64> {
+        let query = SearchQuery { query: query.to_string(), ..Default::default() };
+        index
+            .search(&query, 0)
+            .into_iter()
+            .map(|hit| match hit.key {
+                DocKey::Memory { id } => id,
+                DocKey::Message { .. } => unreachable!(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parse_query_splits_phrases_prefixes_and_terms() {
+        assert_eq!(
+            parse_query(r#"Running "open files" conf* "servers""#),
+            [
+                Clause::Term("run".to_string()),
+                Clause::Phrase(vec!["open".to_string(), "file".to_string()]),
+                Clause::Prefix("conf".to_string()),
+                Clause::Term("server".to_string()),
+            ]
+        );
+        assert_eq!(parse_query(r#"x-ray* "" *"#), [Clause::Prefix("ray".to_string()), Clause::Term("x".to_string())]);
+    }
+
+    #[test]
+    fn phrases_need_consecutive_terms() {
+        let index = index(&["open the files", "open files, open files", "files open"]);
+        assert_eq!(index.phrase_matches(&["open".to_string(), "file".to_string()]), HashMap::from([(1, 2)]));
+        assert_eq!(ids(&index, r#""open files""#), [1]);
+        assert!(ids(&index, r#""open missing""#).is_empty());
+    }
+
+    #[test]
+    fn bm25_prefers_frequent_terms_in_short_documents() {
+        let index = index(&[
+            "rust rust rust",
+            "rust and a great many other words about something else entirely",
+            "rust",
+            "nothing relevant",
+        ]);
+        assert_eq!(ids(&index, "rust"), [0, 2, 1]);
+        // The rarer term carries more weight
+        assert_eq!(ids(&index, "rust relevant"), [3, 0, 2, 1]);
+        assert_eq!(ids(&index, "rel*"), [3]);
+    }
+
+    #[test]
+    fn removed_and_expired_documents_are_not_found() {
+        let mut index = index(&["alpha", "alpha beta"]);
+        index.remove(&DocKey::Memory { id: 0 });
+        assert_eq!(ids(&index, "alpha"), [1]);
+        assert_eq!(index.total_length, 2);
+        index.upsert(DocKey::Memory { id: 2 }, None, "memory".to_string(), 0, Some(5), "alpha");
+        let query = SearchQuery { query: "alpha".to_string(), ..Default::default() };
+        assert_eq!(index.search(&query, 4).len(), 2);
+        assert_eq!(index.search(&query, 5).len(), 1);
+    }
+}
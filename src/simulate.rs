@@ -0,0 +1,299 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{self, json};
+use std::collections::VecDeque;
+use std::sync::{LazyLock, Mutex};
+use wasm_bindgen::prelude::*;
+
+use crate::policy::PolicyBlock;
+use crate::schema::JsonSchema;
+use crate::tool_call::ToolCallOutcome;
+use crate::{get_timestamp, info, SERVER_REGISTRY};
+
+/// Simulated calls kept for `get_simulation_log`.
+const LOG_CAPACITY: usize = 200;
+
+/// Which calls are simulated instead of sent, and whether real results are
+/// recorded as fixtures. Tap and agent model tools always run, so the calls
+/// the model would make can be seen.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub(crate) struct SimulationConfig {
+    /// Simulate every call.
+    pub enabled: bool,
+    /// Engrams simulated when `enabled` is off.
+    pub engrams: Vec<String>,
+    /// Keep the result of each real call as a fixture for its tool and
+    /// arguments.
+    pub record: bool,
+}
+
+impl SimulationConfig {
+    fn covers(&self, engram_id: Option<&str>) -> bool {
+        self.enabled || engram_id.is_some_and(|id| self.engrams.iter().any(|e| e == id))
+    }
+}
+
+/// A canned result. Fixtures without `server` or `arguments` match any;
+/// the most specific match wins, later fixtures before earlier ones.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct Fixture {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server: Option<String>,
+    pub tool: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arguments: Option<serde_json::Value>,
+    /// A `tools/call` result, `isError` included.
+    pub result: serde_json::Value,
+}
+
+impl Fixture {
+    fn specificity(&self, url: &str, tool_name: &str, arguments: &serde_json::Value) -> Option<usize> {
+        let server = match &self.server {
+            Some(server) if server != url => return None,
+            server => server.is_some() as usize,
+        };
+        let args = match &self.arguments {
+            Some(expected) if expected != arguments => return None,
+            expected => expected.is_some() as usize,
+        };
+        (self.tool == tool_name).then_some(2 * args + server)
+    }
+}
+
+/// Where a simulated result came from.
+#[derive(Debug, Serialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ResultSource {
+    Fixture,
+    /// Generated from the tool's `outputSchema`.
+    Schema,
+    /// The tool has neither; the result only echoes the request.
+    Echo,
+    /// The tool is not known, so the call would fail.
+    NotFound,
+    /// The server's access policy blocks the call, so it would not be sent.
+    Blocked,
+}
+
+/// A call that was simulated: the request that would have been sent and
+/// the result returned instead.
+#[derive(Debug, Serialize, Clone)]
+pub(crate) struct SimulatedCall {
+    pub at: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub engram_id: Option<String>,
+    pub server: String,
+    pub tool: String,
+    /// The JSON-RPC request body.
+    pub request: serde_json::Value,
+    pub source: ResultSource,
+    pub result: serde_json::Value,
+}
+
+static SIMULATION: LazyLock<Mutex<SimulationConfig>> = LazyLock::new(|| Mutex::new(SimulationConfig::default()));
+
+static FIXTURES: LazyLock<Mutex<Vec<Fixture>>> = LazyLock::new(|| Mutex::new(Vec::new()));
+
+static SIMULATION_LOG: LazyLock<Mutex<VecDeque<SimulatedCall>>> = LazyLock::new(|| Mutex::new(VecDeque::new()));
+
+/// Whether calls for this engram are simulated.
+pub(crate) fn is_simulated(engram_id: Option<&str>) -> bool {
+    SIMULATION.lock().unwrap().covers(engram_id)
+}
+
+fn store_fixture(fixture: Fixture) {
+    let mut fixtures = FIXTURES.lock().unwrap();
+    fixtures.retain(|f| !(f.server == fixture.server && f.tool == fixture.tool && f.arguments == fixture.arguments));
+    fixtures.push(fixture);
+}
+
+/// Keep the result of a real call as a fixture when recording.
+pub(crate) fn record_result(url: &str, tool_name: &str, arguments: &serde_json::Value, outcome: &ToolCallOutcome) {
+    if !SIMULATION.lock().unwrap().record {
+        return;
+    }
+    let result = match outcome {
+        ToolCallOutcome::Success { result, .. } | ToolCallOutcome::ToolError { result } => result.clone(),
+        _ => return,
+    };
+    store_fixture(Fixture {
+        server: Some(url.to_string()),
+        tool: tool_name.to_string(),
+        arguments: Some(arguments.clone()),
+        result,
+    });
+}
+
+fn synthetic_result(url: &str, tool_name: &str, arguments: &serde_json::Value) -> (ResultSource, serde_json::Value) {
+    let fixture = FIXTURES
+        .lock()
+        .unwrap()
+        .iter()
+        .filter_map(|f| f.specificity(url, tool_name, arguments).map(|score| (score, f)))
+        .max_by_key(|(score, _)| *score)
+        .map(|(_, f)| f.result.clone());
+    if let Some(result) = fixture {
+        return (ResultSource::Fixture, result);
+    }
+    let tool = {
+        let registry = SERVER_REGISTRY.lock().unwrap();
+        registry
+            .servers
+            .get(url)
+            .and_then(|server| server.tools.iter().find(|t| t.name == tool_name))
+            .map(|tool| tool.output_schema.clone())
+    };
+    match tool {
+        None => (
+            ResultSource::NotFound,
+            json!({
+                "content": [{ "type": "text", "text": format!("Tool '{}' is not available on {}", tool_name, url) }],
+                "isError": true
+            }),
+        ),
+        Some(Some(output_schema)) => {
+            let structured = JsonSchema::from_value(&output_schema).example();
+            (
+                ResultSource::Schema,
+                json!({
+                    "content": [{ "type": "text", "text": structured.to_string() }],
+                    "structuredContent": structured
+                }),
+            )
+        }
+        Some(None) => (
+            ResultSource::Echo,
+            json!({
+                "content": [{ "type": "text", "text": format!("Simulated call to '{}' with {}", tool_name, arguments) }]
+            }),
+        ),
+    }
+}
+
+/// Answer a validated call without sending it: log the request that would
+/// have gone out and return a fixture, a schema example or an echo. Results
+/// carry `_meta.simulated` so they can be told apart from real ones. A call
+/// the access policy blocks is logged and answered with the block.
+pub(crate) fn simulate_call(
+    url: &str,
+    tool_name: &str,
+    arguments: &serde_json::Value,
+    engram_id: Option<&str>,
+    block: Option<PolicyBlock>,
+) -> ToolCallOutcome {
+    let request = json!({
+        "jsonrpc": "2.0",
+        "method": "tools/call",
+        "params": { "name": tool_name, "arguments": arguments }
+    });
+    let blocked = block.map(ToolCallOutcome::from);
+    let (source, result) = match &blocked {
+        Some(outcome) => {
+            info(&format!("[simulated] Call to '{}' on {} would be blocked: {}", tool_name, url, request));
+            (ResultSource::Blocked, serde_json::to_value(outcome).unwrap_or_default())
+        }
+        None => {
+            info(&format!("[simulated] Would call '{}' on {}: {}", tool_name, url, request));
+            let (source, mut result) = synthetic_result(url, tool_name, arguments);
+            if let Some(result) = result.as_object_mut() {
+                let meta = result.entry("_meta").or_insert_with(|| json!({}));
+                if let Some(meta) = meta.as_object_mut() {
+                    meta.insert("simulated".to_string(), json!({ "source": source }));
+                }
+            }
+            (source, result)
+        }
+    };
+
+    {
+        let mut log = SIMULATION_LOG.lock().unwrap();
+        if log.len() == LOG_CAPACITY {
+            log.pop_front();
+        }
+        log.push_back(SimulatedCall {
+            at: get_timestamp(),
+            engram_id: engram_id.map(str::to_string),
+            server: url.to_string(),
+            tool: tool_name.to_string(),
+            request,
+            source,
+            result: result.clone(),
+        });
+    }
+
+    if let Some(outcome) = blocked {
+        return outcome;
+    }
+    if result.get("isError").and_then(|v| v.as_bool()).unwrap_or(false) {
+        ToolCallOutcome::ToolError { result }
+    } else {
+        ToolCallOutcome::Success { result, output_validation: None }.with_output_validation(url, tool_name)
+    }
+}
+
+/// Takes `{ enabled, engrams, record }`.
+#[wasm_bindgen]
+pub fn set_simulation_config(config_json: &str) -> Result<(), JsValue> {
+    let config: SimulationConfig = serde_json::from_str(config_json)
+        .map_err(|e| JsValue::from_str(&format!("Invalid simulation config: {}", e)))?;
+    info(&format!(
+        "Simulation {} ({} engram(s), recording {})",
+        if config.enabled { "on" } else { "off" },
+        config.engrams.len(),
+        if config.record { "on" } else { "off" }
+    ));
+    *SIMULATION.lock().unwrap() = config;
+    Ok(())
+}
+
+#[wasm_bindgen]
+pub fn get_simulation_config() -> String {
+    serde_json::to_string(&*SIMULATION.lock().unwrap()).unwrap_or_default()
+}
+
+/// Turn simulation on or off for one engram.
+#[wasm_bindgen]
+pub fn set_engram_simulation(engram_id: &str, enabled: bool) {
+    let mut config = SIMULATION.lock().unwrap();
+    config.engrams.retain(|id| id != engram_id);
+    if enabled {
+        config.engrams.push(engram_id.to_string());
+    }
+}
+
+/// Add fixtures from a JSON array of `{ server?, tool, arguments?, result }`.
+/// A fixture replaces one with the same server, tool and arguments.
+#[wasm_bindgen]
+pub fn add_simulation_fixtures(fixtures_json: &str) -> Result<usize, JsValue> {
+    let fixtures: Vec<Fixture> = serde_json::from_str(fixtures_json)
+        .map_err(|e| JsValue::from_str(&format!("Invalid fixtures: {}", e)))?;
+    let count = fixtures.len();
+    fixtures.into_iter().for_each(store_fixture);
+    Ok(count)
+}
+
+#[wasm_bindgen]
+pub fn list_simulation_fixtures() -> String {
+    json!({ "fixtures": *FIXTURES.lock().unwrap() }).to_string()
+}
+
+/// Drop the fixtures of one tool, or all when `tool` is null.
+#[wasm_bindgen]
+pub fn clear_simulation_fixtures(tool: Option<String>) {
+    let mut fixtures = FIXTURES.lock().unwrap();
+    match tool {
+        Some(tool) => fixtures.retain(|f| f.tool != tool),
+        None => fixtures.clear(),
+    }
+}
+
+/// Simulated calls, oldest first, as `{ calls: [...] }`.
+#[wasm_bindgen]
+pub fn get_simulation_log() -> String {
+    json!({ "calls": *SIMULATION_LOG.lock().unwrap() }).to_string()
+}
+
+#[wasm_bindgen]
+pub fn clear_simulation_log() {
+    SIMULATION_LOG.lock().unwrap().clear();
+}
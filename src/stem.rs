@@ -0,0 +1,216 @@
+fn is_consonant(word: &[u8], i: usize) -> bool {
+    match word[i] {
+        b'a' | b'e' | b'i' | b'o' | b'u' => false,
+        b'y' => i == 0 || !is_consonant(word, i - 1),
+        _ => true,
+    }
+}
+
+/// Porter's *m*: the number of vowel-consonant sequences in `word`.
+fn measure(word: &[u8]) -> usize {
+    let n = word.len();
+    let mut i = 0;
+    let mut m = 0;
+    while i < n && is_consonant(word, i) {
+        i += 1;
+    }
+    loop {
+        while i < n && !is_consonant(word, i) {
+            i += 1;
+        }
+        if i >= n {
+            return m;
+        }
+        while i < n && is_consonant(word, i) {
+            i += 1;
+        }
+        m += 1;
+    }
+}
+
+fn has_vowel(word: &[u8]) -> bool {
+    (0..word.len()).any(|i| !is_consonant(word, i))
+}
+
+fn ends_double_consonant(word: &[u8]) -> bool {
+    let n = word.len();
+    n >= 2 && word[n - 1] == word[n - 2] && is_consonant(word, n - 1)
+}
+
+/// Consonant-vowel-consonant at the end, the last not `w`, `x` or `y`.
+fn ends_cvc(word: &[u8]) -> bool {
+    let n = word.len();
+    n >= 3
+        && is_consonant(word, n - 1)
+        && !is_consonant(word, n - 2)
+        && is_consonant(word, n - 3)
+        && !matches!(word[n - 1], b'w' | b'x' | b'y')
+}
+
+/// Replace the first suffix of `rules` that `word` ends with, when what
+/// precedes it satisfies `condition`. Later rules are not tried once one
+/// suffix matched.
+fn replace_suffix(word: &mut Vec<u8>, rules: &[(&str, &str)], condition: impl Fn(&[u8]) -> bool) {
+    if let Some((suffix, replacement)) = rules.iter().find(|(suffix, _)| word.ends_with(suffix.as_bytes())) {
+        let stem = word.len() - suffix.len();
+        if condition(&word[..stem]) {
+            word.truncate(stem);
+            word.extend_from_slice(replacement.as_bytes());
+        }
+    }
+}
+
+fn step1ab(word: &mut Vec<u8>) {
+    if word.ends_with(b"sses") || word.ends_with(b"ies") {
+        word.truncate(word.len() - 2);
+    } else if word.ends_with(b"s") && !word.ends_with(b"ss") {
+        word.pop();
+    }
+
+    if word.ends_with(b"eed") {
+        if measure(&word[..word.len() - 3]) > 0 {
+            word.pop();
+        }
+        return;
+    }
+    let removed = [&b"ed"[..], b"ing"]
+        .into_iter()
+        .find(|suffix| word.ends_with(suffix) && has_vowel(&word[..word.len() - suffix.len()]));
+    let Some(suffix) = removed else {
+        return;
+    };
+    word.truncate(word.len() - suffix.len());
+    if word.ends_with(b"at") || word.ends_with(b"bl") || word.ends_with(b"iz") {
+        word.push(b'e');
+    } else if ends_double_consonant(word) && !matches!(word[word.len() - 1], b'l' | b's' | b'z') {
+        word.pop();
+    } else if measure(word) == 1 && ends_cvc(word) {
+        word.push(b'e');
+    }
+}
+
+fn step1c(word: &mut [u8]) {
+    let n = word.len();
+    if word.ends_with(b"y") && has_vowel(&word[..n - 1]) {
+        word[n - 1] = b'i';
+    }
+}
+
+fn step2(word: &mut Vec<u8>) {
+    const RULES: [(&str, &str); 20] = [
+        ("ational", "ate"),
+        ("tional", "tion"),
+        ("enci", "ence"),
+        ("anci", "ance"),
+        ("izer", "ize"),
+        ("abli", "able"),
+        ("alli", "al"),
+        ("entli", "ent"),
+        ("eli", "e"),
+        ("ousli", "ous"),
+        ("ization", "ize"),
+        ("ation", "ate"),
+        ("ator", "ate"),
+        ("alism", "al"),
+        ("iveness", "ive"),
+        ("fulness", "ful"),
+        ("ousness", "ous"),
+        ("aliti", "al"),
+        ("iviti", "ive"),
+        ("biliti", "ble"),
+    ];
+    replace_suffix(word, &RULES, |stem| measure(stem) > 0);
+}
+
+fn step3(word: &mut Vec<u8>) {
+    const RULES: [(&str, &str); 7] = [
+        ("icate", "ic"),
+        ("ative", ""),
+        ("alize", "al"),
+        ("iciti", "ic"),
+        ("ical", "ic"),
+        ("ful", ""),
+        ("ness", ""),
+    ];
+    replace_suffix(word, &RULES, |stem| measure(stem) > 0);
+}
+
+fn step4(word: &mut Vec<u8>) {
+    const SUFFIXES: [&str; 19] = [
+        "al", "ance", "ence", "er", "ic", "able", "ible", "ant", "ement", "ment", "ent", "ion", "ou", "ism", "ate",
+        "iti", "ous", "ive", "ize",
+    ];
+    let rules: Vec<(&str, &str)> = SUFFIXES.iter().map(|suffix| (*suffix, "")).collect();
+    // `ion` only goes after `s` or `t`
+    let ion = word.ends_with(b"ion");
+    replace_suffix(word, &rules, |stem| measure(stem) > 1 && (!ion || matches!(stem.last(), Some(b's' | b't'))));
+}
+
+fn step5(word: &mut Vec<u8>) {
+    if word.ends_with(b"e") {
+        let stem = &word[..word.len() - 1];
+        let m = measure(stem);
+        if m > 1 || (m == 1 && !ends_cvc(stem)) {
+            word.pop();
+        }
+    }
+    if measure(word) > 1 && word.ends_with(b"ll") {
+        word.pop();
+    }
+}
+
+/// Reduce an English word to its stem with the Porter algorithm as
+/// published in 1980. Words that are not lowercase ASCII letters are
+/// returned unchanged.
+pub(crate) fn stem(word: &str) -> String {
+    if word.len() <= 2 || !word.bytes().all(|b| b.is_ascii_lowercase()) {
+        return word.to_string();
+    }
+    let mut word = word.as_bytes().to_vec();
+    step1ab(&mut word);
+    step1c(&mut word);
+    step2(&mut word);
+    step3(&mut word);
+    step4(&mut word);
+    step5(&mut word);
+    String::from_utf8(word).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_porter_reference_stems() {
+        let pairs = [
+            ("caresses", "caress"), ("ponies", "poni"), ("ties", "ti"), ("caress", "caress"), ("cats", "cat"),
+            ("feed", "feed"), ("agreed", "agre"), ("plastered", "plaster"), ("bled", "bled"), ("motoring", "motor"),
+            ("sing", "sing"), ("conflated", "conflat"), ("troubled", "troubl"), ("sized", "size"), ("hopping", "hop"),
+            ("tanned", "tan"), ("falling", "fall"), ("hissing", "hiss"), ("fizzed", "fizz"), ("failing", "fail"),
+            ("filing", "file"), ("happy", "happi"), ("sky", "sky"), ("relational", "relat"), ("conditional", "condit"),
+            ("rational", "ration"), ("valenci", "valenc"), ("digitizer", "digit"), ("conformabli", "conform"),
+            ("radicalli", "radic"), ("differentli", "differ"), ("vileli", "vile"), ("analogousli", "analog"),
+            ("vietnamization", "vietnam"), ("predication", "predic"), ("operator", "oper"), ("feudalism", "feudal"),
+            ("decisiveness", "decis"), ("hopefulness", "hope"), ("callousness", "callous"), ("formaliti", "formal"),
+            ("sensitiviti", "sensit"), ("sensibiliti", "sensibl"), ("triplicate", "triplic"), ("formative", "form"),
+            ("formalize", "formal"), ("electriciti", "electr"), ("electrical", "electr"), ("hopeful", "hope"),
+            ("goodness", "good"), ("revival", "reviv"), ("allowance", "allow"), ("inference", "infer"),
+            ("airliner", "airlin"), ("gyroscopic", "gyroscop"), ("adjustable", "adjust"), ("defensible", "defens"),
+            ("irritant", "irrit"), ("replacement", "replac"), ("adjustment", "adjust"), ("dependent", "depend"),
+            ("adoption", "adopt"), ("homologou", "homolog"), ("communism", "commun"), ("activate", "activ"),
+            ("angulariti", "angular"), ("homologous", "homolog"), ("effective", "effect"), ("bowdlerize", "bowdler"),
+            ("probate", "probat"), ("rate", "rate"), ("cease", "ceas"), ("controll", "control"), ("roll", "roll"),
+            ("generalizations", "gener"), ("oscillators", "oscil"),
+        ];
+        let wrong: Vec<_> = pairs.iter().filter(|(word, stemmed)| stem(word) != *stemmed).map(|(word, _)| (*word, stem(word))).collect();
+        assert!(wrong.is_empty(), "{:?}", wrong);
+    }
+
+    #[test]
+    fn leaves_short_and_non_ascii_words_alone() {
+        assert_eq!(stem("is"), "is");
+        assert_eq!(stem("Running"), "Running");
+        assert_eq!(stem("café"), "café");
+        assert_eq!(stem("mp3s"), "mp3s");
+    }
+}
@@ -0,0 +1,910 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{self, json};
+use wasm_bindgen::prelude::*;
+
+use crate::content::ToolResult;
+use crate::router::server_alias;
+use crate::SERVER_REGISTRY;
+
+/// Variables a tap template may reference.
+const VARIABLES: [&str; 10] = [
+    "cbus_message", "latest", "messages", "engram_id", "memory", "previous_result", "server", "tool", "now", "now_ms",
+];
+
+/// Filters with their minimum and maximum argument counts.
+const FILTERS: [(&str, usize, usize); 10] = [
+    ("json", 0, 1),
+    ("truncate", 1, 2),
+    ("join", 0, 1),
+    ("escape", 0, 1),
+    ("last", 0, 1),
+    ("first", 0, 1),
+    ("role", 1, usize::MAX),
+    ("text", 0, 0),
+    ("default", 1, 1),
+    ("length", 0, 0),
+];
+
+/// A template problem, located in the template source. `line` and `column`
+/// are 1-based; `column` counts characters.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub(crate) struct TemplateError {
+    pub message: String,
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+    /// The template line the error is on.
+    pub source_line: String,
+}
+
+impl TemplateError {
+    fn at(source: &str, offset: usize, message: impl Into<String>) -> Self {
+        let offset = offset.min(source.len());
+        let before = &source[..offset];
+        let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+        let line_end = source[offset..].find('\n').map(|i| offset + i).unwrap_or(source.len());
+        TemplateError {
+            message: message.into(),
+            offset,
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+            source_line: source[line_start..line_end].to_string(),
+        }
+    }
+}
+
+/// Error before it is located against the template source.
+struct Located {
+    offset: usize,
+    message: String,
+}
+
+type Parsed<T> = Result<T, Located>;
+
+fn fail<T>(offset: usize, message: impl Into<String>) -> Parsed<T> {
+    Err(Located { offset, message: message.into() })
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Literal(serde_json::Value),
+    Path(Vec<String>),
+    Filter { input: Box<Expr>, name: String, args: Vec<Expr>, offset: usize },
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Compare { op: &'static str, left: Box<Expr>, right: Box<Expr>, offset: usize },
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Text(String),
+    Output(Expr),
+    If { branches: Vec<(Expr, Vec<Node>)>, otherwise: Vec<Node> },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Num(f64),
+    Op(&'static str),
+    Punct(char),
+}
+
+/// Split an expression into tokens with their offsets in the template.
+fn tokenize(source: &str, base: usize) -> Parsed<Vec<(Token, usize)>> {
+    let mut tokens = Vec::new();
+    let mut chars = source.char_indices().peekable();
+    while let Some(&(i, c)) = chars.peek() {
+        let offset = base + i;
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_alphabetic() || c == '_' {
+            let mut ident = String::new();
+            while let Some(&(_, n)) = chars.peek() {
+                if n.is_alphanumeric() || n == '_' {
+                    ident.push(n);
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            tokens.push((Token::Ident(ident), offset));
+        } else if c.is_ascii_digit() || (c == '-' && source[i + 1..].starts_with(|d: char| d.is_ascii_digit())) {
+            let mut number = String::from(c);
+            chars.next();
+            while let Some(&(j, n)) = chars.peek() {
+                // A dot only continues the number when a digit follows, so
+                // `memory.0.text` is a path
+                let decimal = n == '.' && source[j + 1..].starts_with(|d: char| d.is_ascii_digit());
+                if n.is_ascii_digit() || decimal {
+                    number.push(n);
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            match number.parse() {
+                Ok(value) => tokens.push((Token::Num(value), offset)),
+                Err(_) => return fail(offset, format!("Invalid number '{}'", number)),
+            }
+        } else if c == '"' || c == '\'' {
+            chars.next();
+            let mut text = String::new();
+            let mut closed = false;
+            while let Some((_, n)) = chars.next() {
+                match n {
+                    '\\' => match chars.next() {
+                        Some((_, 'n')) => text.push('\n'),
+                        Some((_, 't')) => text.push('\t'),
+                        Some((_, escaped)) => text.push(escaped),
+                        None => break,
+                    },
+                    _ if n == c => {
+                        closed = true;
+                        break;
+                    }
+                    _ => text.push(n),
+                }
+            }
+            if !closed {
+                return fail(offset, "Unterminated string");
+            }
+            tokens.push((Token::Str(text), offset));
+        } else {
+            chars.next();
+            let next = chars.peek().map(|&(_, n)| n);
+            let op = match (c, next) {
+                ('=', Some('=')) => Some("=="),
+                ('!', Some('=')) => Some("!="),
+                ('<', Some('=')) => Some("<="),
+                ('>', Some('=')) => Some(">="),
+                ('<', _) => Some("<"),
+                ('>', _) => Some(">"),
+                _ => None,
+            };
+            match op {
+                Some(op) => {
+                    if op.len() == 2 {
+                        chars.next();
+                    }
+                    tokens.push((Token::Op(op), offset));
+                }
+                None if "|.,()".contains(c) => tokens.push((Token::Punct(c), offset)),
+                None => return fail(offset, format!("Unexpected character '{}'", c)),
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+struct ExprParser {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+    /// Offset just past the expression, for "expected ... at end" errors.
+    end: usize,
+}
+
+impl ExprParser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(t, _)| t)
+    }
+
+    fn offset(&self) -> usize {
+        self.tokens.get(self.position).map(|(_, o)| *o).unwrap_or(self.end)
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        if self.peek() == Some(&Token::Ident(keyword.to_string())) {
+            self.position += 1;
+            return true;
+        }
+        false
+    }
+
+    fn eat_punct(&mut self, punct: char) -> bool {
+        if self.peek() == Some(&Token::Punct(punct)) {
+            self.position += 1;
+            return true;
+        }
+        false
+    }
+
+    fn expect_punct(&mut self, punct: char) -> Parsed<()> {
+        if self.eat_punct(punct) {
+            return Ok(());
+        }
+        fail(self.offset(), format!("Expected '{}'", punct))
+    }
+
+    fn parse_or(&mut self) -> Parsed<Expr> {
+        let mut left = self.parse_and()?;
+        while self.eat_keyword("or") {
+            left = Expr::Or(Box::new(left), Box::new(self.parse_and()?));
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Parsed<Expr> {
+        let mut left = self.parse_not()?;
+        while self.eat_keyword("and") {
+            left = Expr::And(Box::new(left), Box::new(self.parse_not()?));
+        }
+        Ok(left)
+    }
+
+    fn parse_not(&mut self) -> Parsed<Expr> {
+        if self.eat_keyword("not") {
+            return Ok(Expr::Not(Box::new(self.parse_not()?)));
+        }
+        self.parse_compare()
+    }
+
+    fn parse_compare(&mut self) -> Parsed<Expr> {
+        let left = self.parse_pipe()?;
+        if let Some(Token::Op(op)) = self.peek().cloned() {
+            let offset = self.offset();
+            self.position += 1;
+            let right = self.parse_pipe()?;
+            return Ok(Expr::Compare { op, left: Box::new(left), right: Box::new(right), offset });
+        }
+        Ok(left)
+    }
+
+    fn parse_pipe(&mut self) -> Parsed<Expr> {
+        let mut input = self.parse_primary()?;
+        while self.eat_punct('|') {
+            let offset = self.offset();
+            let name = match self.peek().cloned() {
+                Some(Token::Ident(name)) => name,
+                _ => return fail(offset, "Expected a filter name after '|'"),
+            };
+            self.position += 1;
+            let mut args = Vec::new();
+            if self.eat_punct('(') && !self.eat_punct(')') {
+                loop {
+                    args.push(self.parse_or()?);
+                    if self.eat_punct(')') {
+                        break;
+                    }
+                    self.expect_punct(',')?;
+                }
+            }
+            let Some((_, min, max)) = FILTERS.iter().find(|(known, _, _)| *known == name) else {
+                let known: Vec<&str> = FILTERS.iter().map(|(n, _, _)| *n).collect();
+                return fail(offset, format!("Unknown filter '{}'; expected one of {}", name, known.join(", ")));
+            };
+            if args.len() < *min || args.len() > *max {
+                let expected = match (*min, *max) {
+                    (min, max) if min == max => format!("{}", min),
+                    (min, usize::MAX) => format!("at least {}", min),
+                    (min, max) => format!("{} to {}", min, max),
+                };
+                return fail(offset, format!("Filter '{}' takes {} argument(s), got {}", name, expected, args.len()));
+            }
+            input = Expr::Filter { input: Box::new(input), name, args, offset };
+        }
+        Ok(input)
+    }
+
+    fn parse_primary(&mut self) -> Parsed<Expr> {
+        let offset = self.offset();
+        let Some(token) = self.peek().cloned() else {
+            return fail(offset, "Expected an expression");
+        };
+        self.position += 1;
+        match token {
+            Token::Str(text) => Ok(Expr::Literal(json!(text))),
+            Token::Num(number) => Ok(Expr::Literal(json!(number))),
+            Token::Punct('(') => {
+                let inner = self.parse_or()?;
+                self.expect_punct(')')?;
+                Ok(inner)
+            }
+            Token::Ident(ident) => match ident.as_str() {
+                "true" => Ok(Expr::Literal(json!(true))),
+                "false" => Ok(Expr::Literal(json!(false))),
+                "null" | "none" => Ok(Expr::Literal(serde_json::Value::Null)),
+                _ => {
+                    if !VARIABLES.contains(&ident.as_str()) {
+                        return fail(offset, format!("Unknown variable '{}'; expected one of {}", ident, VARIABLES.join(", ")));
+                    }
+                    let mut segments = vec![ident];
+                    while self.eat_punct('.') {
+                        match self.peek().cloned() {
+                            Some(Token::Ident(field)) => segments.push(field),
+                            Some(Token::Num(index)) if index >= 0.0 && index.fract() == 0.0 => {
+                                segments.push((index as usize).to_string())
+                            }
+                            _ => return fail(self.offset(), "Expected a field name or index after '.'"),
+                        }
+                        self.position += 1;
+                    }
+                    Ok(Expr::Path(segments))
+                }
+            },
+            _ => fail(offset, "Expected an expression"),
+        }
+    }
+}
+
+fn parse_expression(source: &str, base: usize) -> Parsed<Expr> {
+    let tokens = tokenize(source, base)?;
+    let mut parser = ExprParser { tokens, position: 0, end: base + source.len() };
+    let expr = parser.parse_or()?;
+    if parser.position < parser.tokens.len() {
+        return fail(parser.offset(), "Unexpected input after expression");
+    }
+    Ok(expr)
+}
+
+/// A lexed `{% ... %}` tag.
+enum Tag {
+    If(Expr),
+    Elif(Expr),
+    Else,
+    EndIf,
+}
+
+enum Piece {
+    Text(String),
+    Output(Expr),
+    Tag(Tag, usize),
+}
+
+/// Split the template into text, `{{ }}` outputs and `{% %}` tags.
+/// `{# #}` is a comment; a `-` just inside a delimiter trims whitespace
+/// on that side.
+fn lex(template: &str) -> Parsed<Vec<Piece>> {
+    let mut pieces = Vec::new();
+    let mut text_start = 0;
+    let mut search = 0;
+    let mut trim_next = false;
+    let push_text = |pieces: &mut Vec<Piece>, mut text: &str, trim_start: bool, trim_end: bool| {
+        if trim_start {
+            text = text.trim_start();
+        }
+        if trim_end {
+            text = text.trim_end();
+        }
+        if !text.is_empty() {
+            pieces.push(Piece::Text(text.to_string()));
+        }
+    };
+    while let Some(found) = template[search..].find('{') {
+        let start = search + found;
+        let close = match template.get(start..start + 2) {
+            Some("{{") => "}}",
+            Some("{%") => "%}",
+            Some("{#") => "#}",
+            _ => {
+                search = start + 1;
+                continue;
+            }
+        };
+        let Some(end) = template[start + 2..].find(close).map(|i| start + 2 + i) else {
+            return fail(start, format!("Unclosed '{}'; expected '{}'", &template[start..start + 2], close));
+        };
+        let (mut inner_start, mut inner_end) = (start + 2, end);
+        let trim_before = template[inner_start..inner_end].starts_with('-');
+        if trim_before {
+            inner_start += 1;
+        }
+        let trim_after = inner_end > inner_start && template[inner_start..inner_end].ends_with('-');
+        if trim_after {
+            inner_end -= 1;
+        }
+        push_text(&mut pieces, &template[text_start..start], trim_next, trim_before);
+        trim_next = trim_after;
+        let inner = &template[inner_start..inner_end];
+        match close {
+            "}}" => pieces.push(Piece::Output(parse_expression(inner, inner_start)?)),
+            "%}" => pieces.push(Piece::Tag(parse_tag(inner, inner_start)?, start)),
+            _ => {}
+        }
+        text_start = end + 2;
+        search = text_start;
+    }
+    push_text(&mut pieces, &template[text_start..], trim_next, false);
+    Ok(pieces)
+}
+
+/// Byte offset of `part`, a subslice of `whole`, within it.
+fn offset_within(whole: &str, part: &str) -> usize {
+    part.as_ptr() as usize - whole.as_ptr() as usize
+}
+
+fn parse_tag(inner: &str, base: usize) -> Parsed<Tag> {
+    let trimmed = inner.trim();
+    let at = |part: &str| base + offset_within(inner, part);
+    let (keyword, rest) = trimmed.split_once(char::is_whitespace).unwrap_or((trimmed, ""));
+    let rest = rest.trim();
+    match keyword {
+        "if" | "elif" if rest.is_empty() => fail(at(keyword), format!("'{}' needs a condition", keyword)),
+        "if" => Ok(Tag::If(parse_expression(rest, at(rest))?)),
+        "elif" => Ok(Tag::Elif(parse_expression(rest, at(rest))?)),
+        "else" | "endif" if !rest.is_empty() => fail(at(rest), format!("Unexpected input after '{}'", keyword)),
+        "else" => Ok(Tag::Else),
+        "endif" => Ok(Tag::EndIf),
+        "" => fail(base, "Empty tag"),
+        other => fail(at(other), format!("Unknown tag '{}'; expected if, elif, else or endif", other)),
+    }
+}
+
+/// Assemble pieces into a tree, checking that `if` blocks are balanced.
+fn build(pieces: Vec<Piece>) -> Parsed<Vec<Node>> {
+    // Each open `if`: finished branches, the current condition and body,
+    // the else body once seen, and where the block started.
+    struct Open {
+        branches: Vec<(Expr, Vec<Node>)>,
+        condition: Option<Expr>,
+        otherwise: Option<Vec<Node>>,
+        offset: usize,
+    }
+    let mut root = Vec::new();
+    let mut open: Vec<(Open, Vec<Node>)> = Vec::new();
+    fn target<'a, T>(root: &'a mut Vec<Node>, open: &'a mut [(T, Vec<Node>)]) -> &'a mut Vec<Node> {
+        match open.last_mut() {
+            Some((_, body)) => body,
+            None => root,
+        }
+    }
+    for piece in pieces {
+        match piece {
+            Piece::Text(text) => target(&mut root, &mut open).push(Node::Text(text)),
+            Piece::Output(expr) => target(&mut root, &mut open).push(Node::Output(expr)),
+            Piece::Tag(Tag::If(condition), offset) => open.push((
+                Open { branches: Vec::new(), condition: Some(condition), otherwise: None, offset },
+                Vec::new(),
+            )),
+            Piece::Tag(Tag::Elif(condition), offset) => {
+                let Some((block, body)) = open.last_mut() else {
+                    return fail(offset, "'elif' without a matching 'if'");
+                };
+                if block.otherwise.is_some() {
+                    return fail(offset, "'elif' after 'else'");
+                }
+                let finished = std::mem::take(body);
+                if let Some(previous) = block.condition.replace(condition) {
+                    block.branches.push((previous, finished));
+                }
+            }
+            Piece::Tag(Tag::Else, offset) => {
+                let Some((block, body)) = open.last_mut() else {
+                    return fail(offset, "'else' without a matching 'if'");
+                };
+                if block.otherwise.is_some() {
+                    return fail(offset, "Duplicate 'else'");
+                }
+                let finished = std::mem::take(body);
+                if let Some(previous) = block.condition.take() {
+                    block.branches.push((previous, finished));
+                }
+                block.otherwise = Some(Vec::new());
+            }
+            Piece::Tag(Tag::EndIf, offset) => {
+                let Some((mut block, body)) = open.pop() else {
+                    return fail(offset, "'endif' without a matching 'if'");
+                };
+                let otherwise = match block.condition.take() {
+                    Some(condition) => {
+                        block.branches.push((condition, body));
+                        Vec::new()
+                    }
+                    None => body,
+                };
+                target(&mut root, &mut open).push(Node::If { branches: block.branches, otherwise });
+            }
+        }
+    }
+    if let Some((block, _)) = open.last() {
+        return fail(block.offset, "'if' is never closed with 'endif'");
+    }
+    Ok(root)
+}
+
+/// A parsed template, ready to render any number of times.
+pub(crate) struct Template {
+    source: String,
+    nodes: Vec<Node>,
+}
+
+/// What a tap template renders against.
+#[derive(Debug, Deserialize, Default, Clone)]
+#[serde(default)]
+pub(crate) struct TemplateContext {
+    pub engram_id: Option<String>,
+    /// Conversation, oldest first: `{ role, text, timestamp }`.
+    pub messages: Vec<serde_json::Value>,
+    /// Memory events and imprints.
+    pub memory: Vec<serde_json::Value>,
+    /// The last tool result for this engram: `{ tool, text, result }`.
+    pub previous_result: Option<serde_json::Value>,
+    pub server_url: Option<String>,
+    pub tool_name: Option<String>,
+}
+
+impl TemplateContext {
+    fn variable(&self, name: &str) -> serde_json::Value {
+        match name {
+            "cbus_message" => self.messages.last().and_then(|m| m.get("text")).cloned().unwrap_or_else(|| json!("")),
+            "latest" => self.messages.last().cloned().unwrap_or(serde_json::Value::Null),
+            "messages" => json!(self.messages),
+            "engram_id" => json!(self.engram_id),
+            "memory" => json!(self.memory),
+            "previous_result" => self.previous_result_value(),
+            "server" => self.server_info(),
+            "tool" => json!(self.tool_name),
+            "now" => json!(js_sys::Date::new_0().to_iso_string().as_string()),
+            "now_ms" => json!(js_sys::Date::now()),
+            _ => serde_json::Value::Null,
+        }
+    }
+
+    /// The previous result with `text` filled in from its content blocks
+    /// when the caller did not render it.
+    fn previous_result_value(&self) -> serde_json::Value {
+        let Some(previous) = self.previous_result.clone() else {
+            return serde_json::Value::Null;
+        };
+        let mut previous = match previous {
+            serde_json::Value::String(text) => json!({ "text": text }),
+            other => other,
+        };
+        if previous.get("text").is_none() {
+            if let Some(result) = previous.get("result") {
+                previous["text"] = json!(ToolResult::from_value(result).to_cbus_text());
+            }
+        }
+        previous
+    }
+
+    fn server_info(&self) -> serde_json::Value {
+        let Some(url) = &self.server_url else {
+            return serde_json::Value::Null;
+        };
+        let registry = SERVER_REGISTRY.lock().unwrap();
+        match registry.servers.get(url) {
+            Some(server) => json!({
+                "url": server.url,
+                "name": server.name,
+                "alias": server_alias(server),
+                "version": server.version,
+                "status": server.status,
+                "instructions": server.instructions,
+                "tools": server.tools.iter().map(|t| t.name.clone()).collect::<Vec<_>>()
+            }),
+            None => json!({ "url": url }),
+        }
+    }
+}
+
+fn truthy(value: &serde_json::Value) -> bool {
+    match value {
+        serde_json::Value::Null => false,
+        serde_json::Value::Bool(b) => *b,
+        serde_json::Value::Number(n) => n.as_f64().is_some_and(|n| n != 0.0),
+        serde_json::Value::String(s) => !s.is_empty(),
+        serde_json::Value::Array(a) => !a.is_empty(),
+        serde_json::Value::Object(o) => !o.is_empty(),
+    }
+}
+
+/// Text for output: strings as-is, null as nothing, anything else as JSON.
+fn display(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::Null => String::new(),
+        serde_json::Value::String(s) => s.clone(),
+        serde_json::Value::Number(n) => match n.as_f64() {
+            Some(f) if f.fract() == 0.0 && f.abs() < 1e15 => format!("{}", f as i64),
+            _ => n.to_string(),
+        },
+        other => other.to_string(),
+    }
+}
+
+/// Text of a message-like item: its `text` field if it has one.
+fn item_text(value: &serde_json::Value) -> String {
+    match value.get("text").and_then(|t| t.as_str()) {
+        Some(text) => text.to_string(),
+        None => display(value),
+    }
+}
+
+fn type_name(value: &serde_json::Value) -> &'static str {
+    match value {
+        serde_json::Value::Null => "null",
+        serde_json::Value::Bool(_) => "boolean",
+        serde_json::Value::Number(_) => "number",
+        serde_json::Value::String(_) => "string",
+        serde_json::Value::Array(_) => "list",
+        serde_json::Value::Object(_) => "object",
+    }
+}
+
+fn escape(text: &str, kind: &str) -> Result<String, String> {
+    match kind {
+        "json" => {
+            let quoted = serde_json::Value::String(text.to_string()).to_string();
+            Ok(quoted[1..quoted.len() - 1].to_string())
+        }
+        "html" => Ok(text
+            .replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;")
+            .replace('\'', "&#39;")),
+        "shell" => Ok(format!("'{}'", text.replace('\'', "'\\''"))),
+        other => Err(format!("Unknown escape '{}'; expected json, html or shell", other)),
+    }
+}
+
+fn count_argument(value: Option<&serde_json::Value>, filter: &str) -> Result<Option<usize>, String> {
+    match value {
+        None => Ok(None),
+        Some(v) => match v.as_f64() {
+            Some(n) if n >= 0.0 && n.fract() == 0.0 => Ok(Some(n as usize)),
+            _ => Err(format!("'{}' expects a non-negative whole number, got {}", filter, display(v))),
+        },
+    }
+}
+
+fn apply_filter(name: &str, input: serde_json::Value, args: &[serde_json::Value]) -> Result<serde_json::Value, String> {
+    let list = |input: &serde_json::Value| -> Result<Vec<serde_json::Value>, String> {
+        input
+            .as_array()
+            .cloned()
+            .ok_or_else(|| format!("'{}' expects a list, got {}", name, type_name(input)))
+    };
+    match name {
+        "json" => {
+            let pretty = args.first().is_some_and(truthy);
+            let rendered = if pretty { serde_json::to_string_pretty(&input) } else { serde_json::to_string(&input) };
+            Ok(json!(rendered.unwrap_or_default()))
+        }
+        "truncate" => {
+            let limit = count_argument(args.first(), name)?.unwrap_or(0);
+            let suffix = args.get(1).map(display).unwrap_or_else(|| "…".to_string());
+            let text = display(&input);
+            if text.chars().count() <= limit {
+                return Ok(json!(text));
+            }
+            Ok(json!(format!("{}{}", text.chars().take(limit).collect::<String>(), suffix)))
+        }
+        "join" => {
+            let separator = args.first().map(display).unwrap_or_else(|| ", ".to_string());
+            Ok(json!(list(&input)?.iter().map(item_text).collect::<Vec<_>>().join(&separator)))
+        }
+        "escape" => {
+            let kind = args.first().map(display).unwrap_or_else(|| "json".to_string());
+            Ok(json!(escape(&display(&input), &kind)?))
+        }
+        "last" | "first" => {
+            let items = list(&input)?;
+            let count = count_argument(args.first(), name)?;
+            let slice = |n: usize| -> Vec<serde_json::Value> {
+                if name == "last" {
+                    items[items.len().saturating_sub(n)..].to_vec()
+                } else {
+                    items[..n.min(items.len())].to_vec()
+                }
+            };
+            Ok(match count {
+                Some(n) => json!(slice(n)),
+                None => slice(1).into_iter().next().unwrap_or(serde_json::Value::Null),
+            })
+        }
+        "role" => {
+            let roles: Vec<String> = args.iter().map(display).collect();
+            let matching: Vec<serde_json::Value> = list(&input)?
+                .into_iter()
+                .filter(|item| item.get("role").and_then(|r| r.as_str()).is_some_and(|r| roles.iter().any(|w| w == r)))
+                .collect();
+            Ok(json!(matching))
+        }
+        "text" => Ok(match &input {
+            serde_json::Value::Array(items) => json!(items.iter().map(item_text).collect::<Vec<_>>()),
+            serde_json::Value::Null => serde_json::Value::Null,
+            other => json!(item_text(other)),
+        }),
+        "default" => Ok(if truthy(&input) { input } else { args[0].clone() }),
+        "length" => match &input {
+            serde_json::Value::Array(items) => Ok(json!(items.len())),
+            serde_json::Value::String(text) => Ok(json!(text.chars().count())),
+            serde_json::Value::Object(map) => Ok(json!(map.len())),
+            serde_json::Value::Null => Ok(json!(0)),
+            other => Err(format!("'length' expects a list, string or object, got {}", type_name(other))),
+        },
+        other => Err(format!("Unknown filter '{}'", other)),
+    }
+}
+
+fn compare(op: &str, left: &serde_json::Value, right: &serde_json::Value) -> Result<bool, String> {
+    if let (Some(a), Some(b)) = (left.as_f64(), right.as_f64()) {
+        return Ok(match op {
+            "==" => a == b,
+            "!=" => a != b,
+            "<" => a < b,
+            ">" => a > b,
+            "<=" => a <= b,
+            _ => a >= b,
+        });
+    }
+    match op {
+        "==" => Ok(left == right),
+        "!=" => Ok(left != right),
+        _ => match (left.as_str(), right.as_str()) {
+            (Some(a), Some(b)) => Ok(match op {
+                "<" => a < b,
+                ">" => a > b,
+                "<=" => a <= b,
+                _ => a >= b,
+            }),
+            _ => Err(format!("Cannot compare {} {} {}", type_name(left), op, type_name(right))),
+        },
+    }
+}
+
+impl Template {
+    pub(crate) fn parse(source: &str) -> Result<Self, TemplateError> {
+        let nodes = lex(source)
+            .and_then(build)
+            .map_err(|e| TemplateError::at(source, e.offset, e.message))?;
+        Ok(Template { source: source.to_string(), nodes })
+    }
+
+    fn eval(&self, expr: &Expr, context: &TemplateContext) -> Result<serde_json::Value, TemplateError> {
+        match expr {
+            Expr::Literal(value) => Ok(value.clone()),
+            Expr::Path(segments) => {
+                let mut value = context.variable(&segments[0]);
+                for segment in &segments[1..] {
+                    value = match (&value, segment.parse::<usize>()) {
+                        (serde_json::Value::Array(items), Ok(index)) => items.get(index).cloned(),
+                        (serde_json::Value::Object(map), _) => map.get(segment).cloned(),
+                        _ => None,
+                    }
+                    .unwrap_or(serde_json::Value::Null);
+                }
+                Ok(value)
+            }
+            Expr::Filter { input, name, args, offset } => {
+                let input = self.eval(input, context)?;
+                let args = args.iter().map(|a| self.eval(a, context)).collect::<Result<Vec<_>, _>>()?;
+                apply_filter(name, input, &args).map_err(|e| TemplateError::at(&self.source, *offset, e))
+            }
+            Expr::Not(inner) => Ok(json!(!truthy(&self.eval(inner, context)?))),
+            Expr::And(left, right) => {
+                Ok(json!(truthy(&self.eval(left, context)?) && truthy(&self.eval(right, context)?)))
+            }
+            Expr::Or(left, right) => Ok(json!(truthy(&self.eval(left, context)?) || truthy(&self.eval(right, context)?))),
+            Expr::Compare { op, left, right, offset } => {
+                let (left, right) = (self.eval(left, context)?, self.eval(right, context)?);
+                compare(op, &left, &right)
+                    .map(|result| json!(result))
+                    .map_err(|e| TemplateError::at(&self.source, *offset, e))
+            }
+        }
+    }
+
+    fn render_nodes(&self, nodes: &[Node], context: &TemplateContext, out: &mut String) -> Result<(), TemplateError> {
+        for node in nodes {
+            match node {
+                Node::Text(text) => out.push_str(text),
+                Node::Output(expr) => out.push_str(&display(&self.eval(expr, context)?)),
+                Node::If { branches, otherwise } => {
+                    let mut chosen = otherwise;
+                    for (condition, body) in branches {
+                        if truthy(&self.eval(condition, context)?) {
+                            chosen = body;
+                            break;
+                        }
+                    }
+                    self.render_nodes(chosen, context, out)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn render(&self, context: &TemplateContext) -> Result<String, TemplateError> {
+        let mut out = String::new();
+        self.render_nodes(&self.nodes, context, &mut out)?;
+        Ok(out)
+    }
+
+    /// Render, keeping the value's type when the template is a single
+    /// `{{ expression }}` (so `{{ messages | last(5) }}` yields a list).
+    pub(crate) fn render_value(&self, context: &TemplateContext) -> Result<serde_json::Value, TemplateError> {
+        match self.nodes.as_slice() {
+            [Node::Output(expr)] => self.eval(expr, context),
+            _ => self.render(context).map(|text| json!(text)),
+        }
+    }
+}
+
+/// A template error tied to the argument it came from.
+#[derive(Debug, Serialize)]
+pub(crate) struct ArgumentError {
+    /// JSON Pointer to the argument.
+    pub path: String,
+    #[serde(flatten)]
+    pub error: TemplateError,
+}
+
+/// Render every string in `args` that contains template syntax.
+pub(crate) fn render_arguments(
+    args: &serde_json::Value,
+    context: &TemplateContext,
+    path: &str,
+    errors: &mut Vec<ArgumentError>,
+) -> serde_json::Value {
+    match args {
+        serde_json::Value::String(text) if text.contains("{{") || text.contains("{%") => {
+            match Template::parse(text).and_then(|template| template.render_value(context)) {
+                Ok(value) => value,
+                Err(error) => {
+                    errors.push(ArgumentError { path: path.to_string(), error });
+                    args.clone()
+                }
+            }
+        }
+        serde_json::Value::Object(map) => serde_json::Value::Object(
+            map.iter()
+                .map(|(key, value)| {
+                    let pointer = format!("{}/{}", path, key.replace('~', "~0").replace('/', "~1"));
+                    (key.clone(), render_arguments(value, context, &pointer, errors))
+                })
+                .collect(),
+        ),
+        serde_json::Value::Array(items) => serde_json::Value::Array(
+            items
+                .iter()
+                .enumerate()
+                .map(|(index, value)| render_arguments(value, context, &format!("{}/{}", path, index), errors))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+fn parse_context(context_json: &str) -> Result<TemplateContext, JsValue> {
+    serde_json::from_str(context_json).map_err(|e| JsValue::from_str(&format!("Invalid template context: {}", e)))
+}
+
+/// Render a tap template. Returns `{ output }` or `{ error }` where the
+/// error carries `message`, `offset`, `line`, `column` and `source_line`.
+#[wasm_bindgen]
+pub fn render_tap_template(template: &str, context_json: &str) -> Result<String, JsValue> {
+    let context = parse_context(context_json)?;
+    let rendered = Template::parse(template).and_then(|t| t.render(&context));
+    Ok(match rendered {
+        Ok(output) => json!({ "output": output }),
+        Err(error) => json!({ "error": error }),
+    }
+    .to_string())
+}
+
+/// Render every templated string in a tap's `args`. Returns
+/// `{ args, errors }`; each error names the argument by JSON Pointer.
+#[wasm_bindgen]
+pub fn render_tap_args(args_json: &str, context_json: &str) -> Result<String, JsValue> {
+    let args: serde_json::Value = serde_json::from_str(args_json)
+        .map_err(|e| JsValue::from_str(&format!("Invalid args: {}", e)))?;
+    let context = parse_context(context_json)?;
+    let mut errors = Vec::new();
+    let rendered = render_arguments(&args, &context, "", &mut errors);
+    Ok(json!({ "args": rendered, "errors": errors }).to_string())
+}
+
+/// Check a template's syntax without rendering it. Returns `{ ok: true }`
+/// or `{ ok: false, error }`.
+#[wasm_bindgen]
+pub fn check_tap_template(template: &str) -> String {
+    match Template::parse(template) {
+        Ok(_) => json!({ "ok": true }),
+        Err(error) => json!({ "ok": false, "error": error }),
+    }
+    .to_string()
+}
@@ -0,0 +1,286 @@
+use serde::Serialize;
+use serde_json::{self, json};
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::atomic::Ordering;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+
+use crate::policy::{authorize_failover, authorize_tool_call, check_access, PolicyBlock};
+use crate::router::record_latency;
+use crate::simulate::{is_simulated, record_result, simulate_call};
+use crate::validate::{prepare_arguments, validate_structured_content, OutputValidation, ValidationError};
+use crate::{debug, error, fetch, JsonRpcResponse, DEBUG_MODE, SERVER_REGISTRY};
+
+/// Outcome of a `tools/call` request.
+///
+/// Transport and JSON-RPC failures are protocol problems that should be
+/// surfaced to the user. Tool errors (`isError: true`) are results the tool
+/// produced on purpose and are meant to be shown to the LLM on the CBus.
+#[derive(Debug, Serialize, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub(crate) enum ToolCallOutcome {
+    Success {
+        result: serde_json::Value,
+        /// Present when the tool declares an `outputSchema`.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        output_validation: Option<OutputValidation>,
+    },
+    ToolError {
+        result: serde_json::Value,
+    },
+    RpcError {
+        code: i32,
+        message: String,
+        data: Option<serde_json::Value>,
+    },
+    TransportError {
+        message: String,
+        status: Option<u16>,
+    },
+    /// Arguments failed client-side validation; nothing was sent.
+    InvalidArguments {
+        errors: Vec<ValidationError>,
+    },
+    /// The call was blocked by policy; `rule` names the rule that matched.
+    Policy {
+        rule: String,
+        message: String,
+    },
+    /// The call required approval and the user rejected it.
+    Rejected {
+        approval_id: u64,
+        reason: Option<String>,
+    },
+}
+
+impl From<PolicyBlock> for ToolCallOutcome {
+    fn from(block: PolicyBlock) -> Self {
+        match block {
+            PolicyBlock::Denied { rule, message } => ToolCallOutcome::Policy { rule, message },
+            PolicyBlock::Rejected { approval_id, reason } => ToolCallOutcome::Rejected { approval_id, reason },
+        }
+    }
+}
+
+impl ToolCallOutcome {
+    fn transport(message: String, status: Option<u16>) -> Self {
+        ToolCallOutcome::TransportError { message, status }
+    }
+
+    /// Classify a decoded JSON-RPC response.
+    pub(crate) fn from_response(response: JsonRpcResponse) -> Self {
+        if let Some(err) = response.error {
+            return ToolCallOutcome::RpcError {
+                code: err.code,
+                message: err.message,
+                data: err.data,
+            };
+        }
+        match response.result {
+            Some(result) => {
+                let is_error = result
+                    .get("isError")
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(false);
+                if is_error {
+                    ToolCallOutcome::ToolError { result }
+                } else {
+                    ToolCallOutcome::Success { result, output_validation: None }
+                }
+            }
+            None => ToolCallOutcome::RpcError {
+                code: -32603,
+                message: "Response contained neither result nor error".to_string(),
+                data: None,
+            },
+        }
+    }
+
+    /// Check `structuredContent` of a successful result against the tool's
+    /// cached `outputSchema`.
+    pub(crate) fn with_output_validation(self, url: &str, tool_name: &str) -> Self {
+        match self {
+            ToolCallOutcome::Success { result, .. } => {
+                let output_validation = validate_structured_content(url, tool_name, &result);
+                if let Some(check) = output_validation.as_ref().filter(|check| !check.valid) {
+                    error(&format!(
+                        "Tool '{}' returned structuredContent violating its outputSchema: {} error(s)",
+                        tool_name,
+                        check.errors.len()
+                    ));
+                }
+                ToolCallOutcome::Success { result, output_validation }
+            }
+            other => other,
+        }
+    }
+
+    pub(crate) fn to_json_string(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+/// Validate, authorize and execute a tool call, or answer it without sending
+/// it in simulation mode when `simulate` allows. Calls that stand in for the
+/// model pass `false`, so a simulated engram still sees what the model would
+/// call. `timeout_ms` bounds only the request itself, not time spent
+/// waiting for approval.
+pub(crate) async fn run_tool_call(
+    url: &str,
+    tool_name: &str,
+    args: serde_json::Value,
+    engram_id: Option<&str>,
+    timeout_ms: Option<u32>,
+    simulate: bool,
+) -> ToolCallOutcome {
+    let arguments = match prepare_arguments(url, tool_name, args) {
+        Ok(arguments) => arguments,
+        Err(errors) => {
+            error(&format!("Rejected call to '{}': {} invalid argument(s)", tool_name, errors.len()));
+            return ToolCallOutcome::InvalidArguments { errors };
+        }
+    };
+    // Nothing is sent, so there is nothing to approve, but a call the
+    // server's access policy blocks is reported as blocked
+    if simulate && is_simulated(engram_id) {
+        let block = check_access(url, tool_name, &arguments, engram_id).err();
+        return simulate_call(url, tool_name, &arguments, engram_id, block);
+    }
+    if let Err(block) = authorize_tool_call(url, tool_name, &arguments, engram_id).await {
+        return ToolCallOutcome::from(block);
+    }
+    send_tool_call(url, tool_name, arguments, timeout_ms).await
+}
+
+/// Run a call already authorized on `authorized_url`, another provider of
+/// the same tool. Arguments are validated against this server's schema and
+/// its access rules still apply; a user is asked again only when the tool
+/// is riskier here.
+pub(crate) async fn run_failover_call(
+    authorized_url: &str,
+    url: &str,
+    tool_name: &str,
+    args: serde_json::Value,
+    engram_id: Option<&str>,
+    timeout_ms: Option<u32>,
+) -> ToolCallOutcome {
+    let arguments = match prepare_arguments(url, tool_name, args) {
+        Ok(arguments) => arguments,
+        Err(errors) => return ToolCallOutcome::InvalidArguments { errors },
+    };
+    if let Err(block) = authorize_failover(authorized_url, url, tool_name, &arguments, engram_id).await {
+        return ToolCallOutcome::from(block);
+    }
+    send_tool_call(url, tool_name, arguments, timeout_ms).await
+}
+
+async fn send_tool_call(url: &str, tool_name: &str, arguments: serde_json::Value, timeout_ms: Option<u32>) -> ToolCallOutcome {
+    let started = js_sys::Date::now();
+    let outcome = match timeout_ms {
+        Some(timeout_ms) => execute_with_timeout(url, tool_name, arguments.clone(), timeout_ms).await,
+        None => execute_tool_call(url, tool_name, arguments.clone()).await,
+    };
+    if !matches!(outcome, ToolCallOutcome::TransportError { .. }) {
+        record_latency(url, js_sys::Date::now() - started);
+    }
+    record_result(url, tool_name, &arguments, &outcome);
+    outcome.with_output_validation(url, tool_name)
+}
+
+/// Promise that resolves after `ms` milliseconds.
+pub(crate) fn delay(ms: u32) -> js_sys::Promise {
+    js_sys::Promise::new(&mut |resolve, reject| {
+        let set_timeout = js_sys::Reflect::get(&js_sys::global(), &"setTimeout".into())
+            .ok()
+            .and_then(|f| f.dyn_into::<js_sys::Function>().ok());
+        match set_timeout {
+            Some(set_timeout) => {
+                let _ = set_timeout.call2(&JsValue::NULL, &resolve, &JsValue::from(ms));
+            }
+            None => {
+                let _ = reject.call1(&JsValue::NULL, &JsValue::from_str("setTimeout is not available"));
+            }
+        }
+    })
+}
+
+/// Race a request against a timer. A request that loses the race keeps
+/// running in the background; its result is discarded.
+async fn execute_with_timeout(url: &str, tool_name: &str, arguments: serde_json::Value, timeout_ms: u32) -> ToolCallOutcome {
+    let slot: Rc<RefCell<Option<ToolCallOutcome>>> = Rc::new(RefCell::new(None));
+    let writer = slot.clone();
+    let (url_owned, tool_owned) = (url.to_string(), tool_name.to_string());
+    let request = wasm_bindgen_futures::future_to_promise(async move {
+        let outcome = execute_tool_call(&url_owned, &tool_owned, arguments).await;
+        *writer.borrow_mut() = Some(outcome);
+        Ok(JsValue::TRUE)
+    });
+    let _ = JsFuture::from(js_sys::Promise::race(&js_sys::Array::of2(&request, &delay(timeout_ms)))).await;
+    let outcome = slot.borrow_mut().take();
+    outcome.unwrap_or_else(|| {
+        error(&format!("Tool '{}' on {} timed out after {} ms", tool_name, url, timeout_ms));
+        ToolCallOutcome::transport(format!("Timed out after {} ms", timeout_ms), None)
+    })
+}
+
+/// Send a `tools/call` request to `url` and classify the response.
+pub(crate) async fn execute_tool_call(url: &str, tool_name: &str, args: serde_json::Value) -> ToolCallOutcome {
+    let call_request = json!({
+        "jsonrpc": "2.0",
+        "id": js_sys::Date::now() as u64,
+        "method": "tools/call",
+        "params": {
+            "name": tool_name,
+            "arguments": args
+        }
+    });
+    let options = js_sys::Object::new();
+    let headers = web_sys::Headers::new().unwrap();
+    headers.set("Content-Type", "application/json").unwrap();
+    if let Some(server) = SERVER_REGISTRY.lock().unwrap().servers.get(url) {
+        if let Some(session_id) = &server.session_id {
+            headers.set("mcp-session-id", session_id).unwrap();
+        }
+    }
+    js_sys::Reflect::set(&options, &"headers".into(), &headers.into()).unwrap();
+    js_sys::Reflect::set(&options, &"body".into(), &JsValue::from_str(&call_request.to_string())).unwrap();
+    js_sys::Reflect::set(&options, &"method".into(), &"POST".into()).unwrap();
+
+    let response = match JsFuture::from(fetch(url, &options)).await {
+        Ok(response) => response,
+        Err(e) => return ToolCallOutcome::transport(format!("Failed to connect to server: {:?}", e), None),
+    };
+    let resp = match response.dyn_ref::<web_sys::Response>().cloned() {
+        Some(resp) => resp,
+        None => return ToolCallOutcome::transport("Failed to get response".to_string(), None),
+    };
+    let status = resp.status();
+    let text = match resp.text() {
+        Ok(promise) => match JsFuture::from(promise).await {
+            Ok(body) => body.as_string().unwrap_or_default(),
+            Err(e) => return ToolCallOutcome::transport(format!("Failed to read response body: {:?}", e), Some(status)),
+        },
+        Err(e) => return ToolCallOutcome::transport(format!("Failed to read response body: {:?}", e), Some(status)),
+    };
+    if DEBUG_MODE.load(Ordering::Relaxed) {
+        debug(&format!("Received response (status {}): {}", status, text));
+    }
+
+    // Servers may report JSON-RPC errors with a non-2xx status, so try to
+    // decode the body before falling back to a transport error.
+    match serde_json::from_str::<JsonRpcResponse>(&text) {
+        Ok(response) if resp.ok() || response.error.is_some() => {
+            let outcome = ToolCallOutcome::from_response(response);
+            debug(&format!("Received tool call response: {}", outcome.to_json_string()));
+            outcome
+        }
+        Ok(_) => ToolCallOutcome::transport("Server returned error response".to_string(), Some(status)),
+        Err(e) if resp.ok() => {
+            error(&format!("Failed to parse tool call response: {}", e));
+            ToolCallOutcome::transport(format!("Failed to parse response: {}", e), Some(status))
+        }
+        Err(_) => ToolCallOutcome::transport(format!("Server returned error response (status {})", status), Some(status)),
+    }
+}
@@ -0,0 +1,424 @@
+use serde::Serialize;
+use serde_json::{self, json};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+use crate::schema::{AdditionalProperties, JsonSchema};
+use crate::SERVER_REGISTRY;
+
+/// A single schema violation, addressed by JSON Pointer (`""` is the root).
+#[derive(Debug, Serialize, Clone)]
+pub(crate) struct ValidationError {
+    pub path: String,
+    pub keyword: String,
+    pub message: String,
+}
+
+impl ValidationError {
+    fn new(path: &str, keyword: &str, message: String) -> Self {
+        ValidationError {
+            path: path.to_string(),
+            keyword: keyword.to_string(),
+            message,
+        }
+    }
+}
+
+fn child_path(path: &str, key: &str) -> String {
+    format!("{}/{}", path, key.replace('~', "~0").replace('/', "~1"))
+}
+
+fn type_matches(name: &str, value: &serde_json::Value) -> bool {
+    match name {
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.as_f64().is_some_and(|n| n.fract() == 0.0),
+        "boolean" => value.is_boolean(),
+        "array" => value.is_array(),
+        "object" => value.is_object(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+fn type_name_of(value: &serde_json::Value) -> &'static str {
+    match value {
+        serde_json::Value::Null => "null",
+        serde_json::Value::Bool(_) => "boolean",
+        serde_json::Value::Number(n) if n.is_i64() || n.is_u64() => "integer",
+        serde_json::Value::Number(_) => "number",
+        serde_json::Value::String(_) => "string",
+        serde_json::Value::Array(_) => "array",
+        serde_json::Value::Object(_) => "object",
+    }
+}
+
+/// Lower bound and whether it is exclusive, across draft-04 and later forms.
+fn exclusive_bound(limit: Option<f64>, exclusive: Option<&serde_json::Value>) -> Option<(f64, bool)> {
+    match exclusive {
+        Some(serde_json::Value::Number(n)) => n.as_f64().map(|n| (n, true)),
+        Some(serde_json::Value::Bool(true)) => limit.map(|l| (l, true)),
+        _ => limit.map(|l| (l, false)),
+    }
+}
+
+pub(crate) fn pattern_matches(pattern: &str, text: &str) -> Result<bool, String> {
+    // JSON Schema patterns are ECMA-262 regexes, so use the host engine.
+    // Construct via Reflect so an invalid pattern surfaces as an error
+    // instead of an uncaught exception.
+    let constructor: js_sys::Function = js_sys::Reflect::get(&js_sys::global(), &"RegExp".into())
+        .ok()
+        .and_then(|ctor| ctor.dyn_into().ok())
+        .ok_or_else(|| "RegExp is not available".to_string())?;
+    let regex = ["u", ""]
+        .iter()
+        .find_map(|flags| {
+            js_sys::Reflect::construct(&constructor, &js_sys::Array::of2(&pattern.into(), &(*flags).into())).ok()
+        })
+        .ok_or_else(|| format!("Invalid pattern: {}", pattern))?;
+    Ok(regex.unchecked_into::<js_sys::RegExp>().test(text))
+}
+
+/// Validate `value` against `schema`, collecting every violation.
+pub(crate) fn validate(schema: &JsonSchema, value: &serde_json::Value) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+    validate_at(schema, value, "", &mut errors);
+    errors
+}
+
+fn validate_at(schema: &JsonSchema, value: &serde_json::Value, path: &str, errors: &mut Vec<ValidationError>) {
+    let types = schema.type_names();
+    if !types.is_empty() && !types.iter().any(|t| type_matches(t, value)) {
+        errors.push(ValidationError::new(
+            path,
+            "type",
+            format!("Expected {}, got {}", types.join(" or "), type_name_of(value)),
+        ));
+        return;
+    }
+
+    if let Some(allowed) = &schema.enum_values {
+        if !allowed.iter().any(|option| json_equal(option, value)) {
+            let options: Vec<String> = allowed.iter().map(|v| v.to_string()).collect();
+            errors.push(ValidationError::new(path, "enum", format!("Must be one of: {}", options.join(", "))));
+        }
+    }
+    if let Some(expected) = &schema.const_value {
+        if !json_equal(expected, value) {
+            errors.push(ValidationError::new(path, "const", format!("Must equal {}", expected)));
+        }
+    }
+
+    match value {
+        serde_json::Value::String(text) => {
+            let length = text.chars().count() as u64;
+            if let Some(min) = schema.min_length.filter(|min| length < *min) {
+                errors.push(ValidationError::new(path, "minLength", format!("Must be at least {} characters", min)));
+            }
+            if let Some(max) = schema.max_length.filter(|max| length > *max) {
+                errors.push(ValidationError::new(path, "maxLength", format!("Must be at most {} characters", max)));
+            }
+            if let Some(pattern) = &schema.pattern {
+                match pattern_matches(pattern, text) {
+                    Ok(true) => {}
+                    Ok(false) => errors.push(ValidationError::new(path, "pattern", format!("Must match pattern {}", pattern))),
+                    Err(e) => errors.push(ValidationError::new(path, "pattern", e)),
+                }
+            }
+        }
+        serde_json::Value::Number(number) => {
+            let n = number.as_f64().unwrap_or_default();
+            if let Some((min, exclusive)) = exclusive_bound(schema.minimum, schema.exclusive_minimum.as_ref()) {
+                if n < min || (exclusive && n == min) {
+                    let relation = if exclusive { "greater than" } else { "at least" };
+                    errors.push(ValidationError::new(path, "minimum", format!("Must be {} {}", relation, min)));
+                }
+            }
+            if let Some((max, exclusive)) = exclusive_bound(schema.maximum, schema.exclusive_maximum.as_ref()) {
+                if n > max || (exclusive && n == max) {
+                    let relation = if exclusive { "less than" } else { "at most" };
+                    errors.push(ValidationError::new(path, "maximum", format!("Must be {} {}", relation, max)));
+                }
+            }
+        }
+        serde_json::Value::Array(items) => {
+            let count = items.len() as u64;
+            if let Some(min) = schema.min_items.filter(|min| count < *min) {
+                errors.push(ValidationError::new(path, "minItems", format!("Must have at least {} items", min)));
+            }
+            if let Some(max) = schema.max_items.filter(|max| count > *max) {
+                errors.push(ValidationError::new(path, "maxItems", format!("Must have at most {} items", max)));
+            }
+            if let Some(item_schema) = &schema.items {
+                for (index, item) in items.iter().enumerate() {
+                    validate_at(item_schema, item, &child_path(path, &index.to_string()), errors);
+                }
+            }
+        }
+        serde_json::Value::Object(map) => {
+            for name in &schema.required {
+                if !map.contains_key(name) {
+                    errors.push(ValidationError::new(
+                        &child_path(path, name),
+                        "required",
+                        format!("Missing required property '{}'", name),
+                    ));
+                }
+            }
+            for (key, item) in map {
+                let item_path = child_path(path, key);
+                match schema.properties.as_ref().and_then(|props| props.get(key)) {
+                    Some(prop_schema) => validate_at(prop_schema, item, &item_path, errors),
+                    None => match &schema.additional_properties {
+                        Some(AdditionalProperties::Allowed(false)) => errors.push(ValidationError::new(
+                            &item_path,
+                            "additionalProperties",
+                            format!("Unexpected property '{}'", key),
+                        )),
+                        Some(AdditionalProperties::Schema(extra)) => validate_at(extra, item, &item_path, errors),
+                        _ => {}
+                    },
+                }
+            }
+        }
+        _ => {}
+    }
+
+    if let Some(all) = &schema.all_of {
+        for sub in all {
+            validate_at(sub, value, path, errors);
+        }
+    }
+    if let Some(any) = &schema.any_of {
+        if !any.iter().any(|sub| validate(sub, value).is_empty()) {
+            errors.push(ValidationError::new(path, "anyOf", "Does not match any allowed schema".to_string()));
+        }
+    }
+    if let Some(one) = &schema.one_of {
+        let matching = one.iter().filter(|sub| validate(sub, value).is_empty()).count();
+        if matching != 1 {
+            errors.push(ValidationError::new(
+                path,
+                "oneOf",
+                format!("Must match exactly one schema, matched {}", matching),
+            ));
+        }
+    }
+}
+
+/// Convert string input (as produced by UI form fields) to the schema's
+/// declared type where that is unambiguous, and fill in defaults for absent
+/// properties. Values that cannot be coerced are left for validation to
+/// report.
+pub(crate) fn coerce_and_fill(schema: &JsonSchema, value: serde_json::Value) -> serde_json::Value {
+    let value = coerce(schema, value);
+    match value {
+        serde_json::Value::Object(mut map) => {
+            if let Some(properties) = &schema.properties {
+                for (name, prop_schema) in properties {
+                    match map.remove(name) {
+                        // Optional non-string fields left empty in a form mean "not set".
+                        Some(serde_json::Value::String(text))
+                            if text.is_empty()
+                                && !schema.required.contains(name)
+                                && !prop_schema.type_names().contains(&"string") =>
+                        {
+                            if let Some(default) = &prop_schema.default {
+                                map.insert(name.clone(), default.clone());
+                            }
+                        }
+                        Some(item) => {
+                            map.insert(name.clone(), coerce_and_fill(prop_schema, item));
+                        }
+                        None => {
+                            if let Some(default) = &prop_schema.default {
+                                map.insert(name.clone(), default.clone());
+                            }
+                        }
+                    }
+                }
+            }
+            serde_json::Value::Object(map)
+        }
+        serde_json::Value::Array(items) => match &schema.items {
+            Some(item_schema) => serde_json::Value::Array(
+                items.into_iter().map(|item| coerce_and_fill(item_schema, item)).collect(),
+            ),
+            None => serde_json::Value::Array(items),
+        },
+        other => other,
+    }
+}
+
+/// JSON equality with numbers compared by value, so `1.0` equals `1` as
+/// JSON Schema requires.
+pub(crate) fn json_equal(a: &serde_json::Value, b: &serde_json::Value) -> bool {
+    use serde_json::Value;
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => match (x.as_i64(), y.as_i64(), x.as_u64(), y.as_u64()) {
+            (Some(x), Some(y), _, _) => x == y,
+            (_, _, Some(x), Some(y)) => x == y,
+            _ => x.as_f64() == y.as_f64(),
+        },
+        (Value::Array(x), Value::Array(y)) => x.len() == y.len() && x.iter().zip(y).all(|(x, y)| json_equal(x, y)),
+        (Value::Object(x), Value::Object(y)) => {
+            x.len() == y.len() && x.iter().all(|(key, x)| y.get(key).is_some_and(|y| json_equal(x, y)))
+        }
+        _ => a == b,
+    }
+}
+
+fn coerce(schema: &JsonSchema, value: serde_json::Value) -> serde_json::Value {
+    let text = match &value {
+        serde_json::Value::String(text) => text.trim().to_string(),
+        _ => return value,
+    };
+    let types = schema.type_names();
+    if types.is_empty() {
+        return coerce_alternatives(schema, value);
+    }
+    if types.contains(&"string") {
+        return value;
+    }
+    for name in types {
+        let coerced = match name {
+            "integer" => text.parse::<i64>().ok().map(serde_json::Value::from),
+            "number" => text.parse::<i64>().ok().map(serde_json::Value::from).or_else(|| {
+                text.parse::<f64>().ok().and_then(serde_json::Number::from_f64).map(serde_json::Value::Number)
+            }),
+            "boolean" => match text.to_lowercase().as_str() {
+                "true" | "yes" | "on" | "1" => Some(serde_json::Value::Bool(true)),
+                "false" | "no" | "off" | "0" => Some(serde_json::Value::Bool(false)),
+                _ => None,
+            },
+            "null" if text.is_empty() || text == "null" => Some(serde_json::Value::Null),
+            "array" | "object" => serde_json::from_str::<serde_json::Value>(&text)
+                .ok()
+                .filter(|parsed| type_matches(name, parsed)),
+            _ => None,
+        };
+        if let Some(coerced) = coerced {
+            return coerced;
+        }
+    }
+    value
+}
+
+/// Coerce a string against `anyOf`/`oneOf` alternatives, trying the
+/// non-null ones first so `"5"` becomes `5` for a nullable integer.
+fn coerce_alternatives(schema: &JsonSchema, value: serde_json::Value) -> serde_json::Value {
+    let Some(alternatives) = schema.alternatives() else {
+        return value;
+    };
+    if alternatives.iter().any(|alt| validate(alt, &value).is_empty()) {
+        return value;
+    }
+    let (nullable, non_null): (Vec<&JsonSchema>, Vec<&JsonSchema>) =
+        alternatives.iter().partition(|alt| alt.type_names() == ["null"]);
+    for alt in non_null.into_iter().chain(nullable) {
+        let coerced = coerce(alt, value.clone());
+        if !coerced.is_string() && validate(alt, &coerced).is_empty() {
+            return coerced;
+        }
+    }
+    value
+}
+
+/// Coerce, fill defaults and validate `args` against the cached schema of
+/// `tool_name` on `url`. Tools that are not cached are passed through.
+pub(crate) fn prepare_arguments(
+    url: &str,
+    tool_name: &str,
+    args: serde_json::Value,
+) -> Result<serde_json::Value, Vec<ValidationError>> {
+    let input_schema = {
+        let registry = SERVER_REGISTRY.lock().unwrap();
+        registry
+            .servers
+            .get(url)
+            .and_then(|server| server.tools.iter().find(|t| t.name == tool_name))
+            .map(|tool| tool.input_schema.clone())
+    };
+    let input_schema = match input_schema {
+        Some(schema) if !schema.is_null() => JsonSchema::from_value(&schema),
+        _ => return Ok(args),
+    };
+    let args = if args.is_null() { json!({}) } else { args };
+    let args = coerce_and_fill(&input_schema, args);
+    let errors = validate(&input_schema, &args);
+    if errors.is_empty() {
+        Ok(args)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Result of checking `structuredContent` against a tool's `outputSchema`.
+#[derive(Debug, Serialize, Clone)]
+pub(crate) struct OutputValidation {
+    pub valid: bool,
+    pub errors: Vec<ValidationError>,
+}
+
+/// Validate the `structuredContent` of a tool result against the cached
+/// `outputSchema`. Returns `None` when the tool declares no output schema.
+pub(crate) fn validate_structured_content(
+    url: &str,
+    tool_name: &str,
+    result: &serde_json::Value,
+) -> Option<OutputValidation> {
+    let output_schema = {
+        let registry = SERVER_REGISTRY.lock().unwrap();
+        registry
+            .servers
+            .get(url)
+            .and_then(|server| server.tools.iter().find(|t| t.name == tool_name))
+            .and_then(|tool| tool.output_schema.clone())
+    }?;
+    let errors = match result.get("structuredContent") {
+        Some(structured) => validate(&JsonSchema::from_value(&output_schema), structured),
+        None => vec![ValidationError::new(
+            "",
+            "structuredContent",
+            "Tool declares an outputSchema but returned no structuredContent".to_string(),
+        )],
+    };
+    Some(OutputValidation {
+        valid: errors.is_empty(),
+        errors,
+    })
+}
+
+/// Check arguments for a cached tool without calling it. Returns
+/// `{ valid, arguments, errors }` where `arguments` are the coerced values.
+#[wasm_bindgen]
+pub fn validate_tool_arguments(url: &str, tool_name: &str, args: JsValue) -> Result<JsValue, JsValue> {
+    let args_value: serde_json::Value = serde_wasm_bindgen::from_value(args)
+        .map_err(|e| JsValue::from_str(&format!("Invalid args: {}", e)))?;
+    let report = match prepare_arguments(url, tool_name, args_value) {
+        Ok(arguments) => json!({ "valid": true, "arguments": arguments, "errors": [] }),
+        Err(errors) => json!({ "valid": false, "arguments": null, "errors": errors }),
+    };
+    Ok(JsValue::from_str(&report.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn coerces_against_non_null_alternatives() {
+        let nullable_int = JsonSchema::parse(&json!({ "anyOf": [{ "type": "integer" }, { "type": "null" }] }));
+        assert_eq!(coerce(&nullable_int, json!("5")), json!(5));
+        assert_eq!(coerce(&nullable_int, json!("")), json!(null));
+        assert_eq!(coerce(&nullable_int, json!("five")), json!("five"));
+
+        let flag_or_count = JsonSchema::parse(&json!({ "oneOf": [{ "type": "boolean" }, { "type": "integer" }] }));
+        assert_eq!(coerce(&flag_or_count, json!("true")), json!(true));
+        assert_eq!(coerce(&flag_or_count, json!("12")), json!(12));
+
+        let text_or_int = JsonSchema::parse(&json!({ "anyOf": [{ "type": "string" }, { "type": "integer" }] }));
+        assert_eq!(coerce(&text_or_int, json!("5")), json!("5"));
+    }
+}
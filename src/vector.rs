@@ -0,0 +1,715 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{self, json};
+use std::cell::{Cell, RefCell};
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::sync::{LazyLock, Mutex};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+
+use crate::memory::{live_event_ids, live_events, query_events, MemoryEvent, MemoryQuery};
+use crate::search::tokenize;
+use crate::tool_call::{run_tool_call, ToolCallOutcome};
+use crate::{debug, info};
+
+/// Where embeddings come from.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub(crate) enum EmbeddingProvider {
+    /// The function registered with `set_embedding_callback`.
+    Js,
+    /// A tool on a registered server, called once per text.
+    Tool {
+        server_url: String,
+        tool_name: String,
+        /// Argument that receives the text.
+        #[serde(default = "default_text_argument")]
+        text_argument: String,
+    },
+    /// Feature hashing of stemmed words. Needs no model, so it suits offline
+    /// tests; it only captures word overlap.
+    Hashing {
+        #[serde(default = "default_dimensions")]
+        dimensions: usize,
+    },
+}
+
+/// Texts sent to the provider at once. The index is saved after each batch,
+/// so an interrupted sync keeps what it embedded.
+const EMBED_BATCH: usize = 32;
+
+/// Events embedded by one sync; the rest wait for the next.
+const SYNC_LIMIT: usize = 512;
+
+fn default_text_argument() -> String {
+    "text".to_string()
+}
+
+fn default_dimensions() -> usize {
+    256
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub(crate) enum IndexKind {
+    /// Exact search over every vector.
+    Flat,
+    /// Approximate search over a hierarchical navigable small-world graph.
+    Hnsw {
+        #[serde(default = "default_m")]
+        m: usize,
+        #[serde(default = "default_ef_construction")]
+        ef_construction: usize,
+        #[serde(default = "default_ef_search")]
+        ef_search: usize,
+    },
+}
+
+fn default_m() -> usize {
+    16
+}
+
+fn default_ef_construction() -> usize {
+    100
+}
+
+fn default_ef_search() -> usize {
+    50
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub(crate) struct VectorConfig {
+    /// No vectors are built without a provider.
+    pub provider: Option<EmbeddingProvider>,
+    pub index: IndexKind,
+    /// Hits returned when a query gives no `k`.
+    pub top_k: usize,
+    /// Hits below this cosine similarity are dropped.
+    pub min_score: f32,
+}
+
+impl Default for VectorConfig {
+    fn default() -> Self {
+        VectorConfig { provider: None, index: IndexKind::Flat, top_k: 5, min_score: 0.0 }
+    }
+}
+
+fn normalize(mut vector: Vec<f32>) -> Vec<f32> {
+    let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|x| *x /= norm);
+    }
+    vector
+}
+
+fn similarity(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+fn fnv1a(text: &str) -> u64 {
+    text.bytes()
+        .fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3))
+}
+
+/// Signed feature hashing of stemmed words.
+pub(crate) fn hashing_embedding(text: &str, dimensions: usize) -> Vec<f32> {
+    let mut vector = vec![0.0; dimensions.max(1)];
+    for term in tokenize(text) {
+        let hash = fnv1a(&term);
+        let slot = (hash % vector.len() as u64) as usize;
+        vector[slot] += if hash >> 63 == 0 { 1.0 } else { -1.0 };
+    }
+    normalize(vector)
+}
+
+/// Distance to a query, ordered for heaps.
+#[derive(Clone, Copy, PartialEq)]
+struct Candidate {
+    distance: f32,
+    node: u32,
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.distance.total_cmp(&other.distance).then(self.node.cmp(&other.node))
+    }
+}
+
+/// Up to `max` of `candidates` (nearest first) to link to, skipping those
+/// closer to a neighbour already chosen than to the node. Taking only the
+/// nearest lets clusters of near-duplicates wall themselves off from the
+/// rest of the graph; ties are kept so exact duplicates do not.
+fn select_neighbours(vectors: &[VectorEntry], candidates: &[Candidate], max: usize) -> Vec<u32> {
+    let mut chosen: Vec<u32> = Vec::with_capacity(max);
+    for candidate in candidates {
+        if chosen.len() == max {
+            break;
+        }
+        let vector = &vectors[candidate.node as usize].vector;
+        if chosen.iter().all(|&n| candidate.distance <= 1.0 - similarity(vector, &vectors[n as usize].vector)) {
+            chosen.push(candidate.node);
+        }
+    }
+    chosen
+}
+
+/// Neighbour lists per node and layer. Removed nodes stay in the graph as
+/// waypoints and are skipped in results.
+struct Hnsw {
+    m: usize,
+    ef_construction: usize,
+    ef_search: usize,
+    links: Vec<Vec<Vec<u32>>>,
+    entry: Option<u32>,
+    top_level: usize,
+    /// xorshift state; fixed seed so rebuilds give the same graph.
+    rng: u64,
+}
+
+impl Hnsw {
+    fn new(m: usize, ef_construction: usize, ef_search: usize) -> Self {
+        Hnsw {
+            m: m.max(2),
+            ef_construction: ef_construction.max(1),
+            ef_search: ef_search.max(1),
+            links: Vec::new(),
+            entry: None,
+            top_level: 0,
+            rng: 0x9e37_79b9_7f4a_7c15,
+        }
+    }
+
+    fn random_level(&mut self) -> usize {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        let uniform = ((self.rng >> 11) as f64 + 1.0) / (1u64 << 53) as f64;
+        (-uniform.ln() / (self.m as f64).ln()).floor() as usize
+    }
+
+    fn max_links(&self, level: usize) -> usize {
+        if level == 0 { 2 * self.m } else { self.m }
+    }
+
+    /// The `ef` nodes closest to `query` reachable from `entries` on one
+    /// layer, nearest first.
+    fn search_layer(&self, vectors: &[VectorEntry], query: &[f32], entries: &[u32], ef: usize, level: usize) -> Vec<Candidate> {
+        let distance = |node: u32| 1.0 - similarity(query, &vectors[node as usize].vector);
+        let mut visited: HashSet<u32> = entries.iter().copied().collect();
+        let mut candidates: BinaryHeap<Reverse<Candidate>> = BinaryHeap::new();
+        let mut nearest: BinaryHeap<Candidate> = BinaryHeap::new();
+        for &node in entries {
+            let candidate = Candidate { distance: distance(node), node };
+            candidates.push(Reverse(candidate));
+            nearest.push(candidate);
+        }
+        while let Some(Reverse(current)) = candidates.pop() {
+            if nearest.len() >= ef && nearest.peek().is_some_and(|worst| current.distance > worst.distance) {
+                break;
+            }
+            for &neighbour in self.links[current.node as usize].get(level).into_iter().flatten() {
+                if !visited.insert(neighbour) {
+                    continue;
+                }
+                let candidate = Candidate { distance: distance(neighbour), node: neighbour };
+                if nearest.len() < ef || nearest.peek().is_some_and(|worst| candidate.distance < worst.distance) {
+                    candidates.push(Reverse(candidate));
+                    nearest.push(candidate);
+                    if nearest.len() > ef {
+                        nearest.pop();
+                    }
+                }
+            }
+        }
+        nearest.into_sorted_vec()
+    }
+
+    /// Descend greedily from the entry point to `level`.
+    fn descend(&self, vectors: &[VectorEntry], query: &[f32], level: usize) -> Option<Vec<u32>> {
+        let mut entry = vec![self.entry?];
+        for layer in (level + 1..=self.top_level).rev() {
+            entry = self.search_layer(vectors, query, &entry, 1, layer).iter().map(|c| c.node).take(1).collect();
+        }
+        Some(entry)
+    }
+
+    fn insert(&mut self, vectors: &[VectorEntry], node: u32) {
+        let level = self.random_level();
+        self.links.push(vec![Vec::new(); level + 1]);
+        let query = &vectors[node as usize].vector;
+        let Some(mut entries) = self.descend(vectors, query, level) else {
+            self.entry = Some(node);
+            self.top_level = level;
+            return;
+        };
+        for layer in (0..=level.min(self.top_level)).rev() {
+            let found = self.search_layer(vectors, query, &entries, self.ef_construction, layer);
+            let max_links = self.max_links(layer);
+            let neighbours = select_neighbours(vectors, &found, max_links);
+            for &neighbour in &neighbours {
+                let links = &mut self.links[neighbour as usize][layer];
+                links.push(node);
+                if links.len() > max_links {
+                    let base = &vectors[neighbour as usize].vector;
+                    let mut ranked: Vec<Candidate> = links
+                        .iter()
+                        .map(|&n| Candidate { distance: 1.0 - similarity(base, &vectors[n as usize].vector), node: n })
+                        .collect();
+                    ranked.sort();
+                    *links = select_neighbours(vectors, &ranked, max_links);
+                }
+            }
+            self.links[node as usize][layer] = neighbours;
+            entries = found.into_iter().map(|c| c.node).collect();
+        }
+        if level > self.top_level {
+            self.entry = Some(node);
+            self.top_level = level;
+        }
+    }
+
+    fn search(&self, vectors: &[VectorEntry], query: &[f32], ef: usize) -> Vec<u32> {
+        let Some(entries) = self.descend(vectors, query, 0) else {
+            return Vec::new();
+        };
+        self.search_layer(vectors, query, &entries, ef.max(self.ef_search), 0).into_iter().map(|c| c.node).collect()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct VectorEntry {
+    /// Memory event id.
+    id: u64,
+    vector: Vec<f32>,
+    #[serde(default, skip_serializing)]
+    removed: bool,
+}
+
+/// Vectors of memory events for the configured provider.
+#[derive(Default)]
+struct VectorIndex {
+    config: VectorConfig,
+    dimensions: Option<usize>,
+    entries: Vec<VectorEntry>,
+    positions: HashMap<u64, usize>,
+    hnsw: Option<Hnsw>,
+}
+
+/// What `get_vector_index`, `restore_vector_index` and the persistence
+/// hook exchange.
+#[derive(Serialize, Deserialize)]
+struct VectorSnapshot {
+    config: VectorConfig,
+    entries: Vec<VectorEntry>,
+}
+
+impl VectorIndex {
+    /// Rebuild positions and the graph from the live entries.
+    fn rebuild(&mut self) {
+        self.entries.retain(|entry| !entry.removed);
+        self.positions = self.entries.iter().enumerate().map(|(i, entry)| (entry.id, i)).collect();
+        self.dimensions = self.entries.first().map(|entry| entry.vector.len());
+        self.hnsw = match self.config.index {
+            IndexKind::Flat => None,
+            IndexKind::Hnsw { m, ef_construction, ef_search } => {
+                let mut hnsw = Hnsw::new(m, ef_construction, ef_search);
+                (0..self.entries.len() as u32).for_each(|node| hnsw.insert(&self.entries, node));
+                Some(hnsw)
+            }
+        };
+    }
+
+    fn insert(&mut self, id: u64, vector: Vec<f32>) -> Result<(), String> {
+        if self.positions.contains_key(&id) {
+            return Ok(());
+        }
+        match self.dimensions {
+            Some(dimensions) if dimensions != vector.len() => {
+                return Err(format!("Embedding has {} dimensions, the index {}", vector.len(), dimensions))
+            }
+            _ => self.dimensions = Some(vector.len()),
+        }
+        self.positions.insert(id, self.entries.len());
+        self.entries.push(VectorEntry { id, vector: normalize(vector), removed: false });
+        if let Some(hnsw) = &mut self.hnsw {
+            hnsw.insert(&self.entries, self.entries.len() as u32 - 1);
+        }
+        Ok(())
+    }
+
+    fn remove(&mut self, id: u64) {
+        if let Some(position) = self.positions.remove(&id) {
+            self.entries[position].removed = true;
+        }
+        if self.entries.len() > 2 * self.positions.len().max(16) {
+            self.rebuild();
+        }
+    }
+
+    /// The `k` entries most similar to `query` among `allowed` ids.
+    fn nearest(&self, query: &[f32], k: usize, allowed: &HashSet<u64>) -> Vec<(u64, f32)> {
+        let admitted = |entry: &VectorEntry| !entry.removed && allowed.contains(&entry.id);
+        let mut scored: Vec<(u64, f32)> = match &self.hnsw {
+            Some(hnsw) => hnsw
+                .search(&self.entries, query, 4 * k)
+                .into_iter()
+                .map(|node| &self.entries[node as usize])
+                .filter(|entry| admitted(entry))
+                .map(|entry| (entry.id, similarity(query, &entry.vector)))
+                .collect(),
+            None => Vec::new(),
+        };
+        // Filters can leave the graph's candidates short; scan instead
+        if scored.len() < k.min(allowed.len()) {
+            scored = self
+                .entries
+                .iter()
+                .filter(|entry| admitted(entry))
+                .map(|entry| (entry.id, similarity(query, &entry.vector)))
+                .collect();
+        }
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+        scored.truncate(k);
+        scored
+    }
+
+    fn snapshot(&self) -> VectorSnapshot {
+        VectorSnapshot { config: self.config.clone(), entries: self.entries.iter().filter(|e| !e.removed).cloned().collect() }
+    }
+}
+
+static VECTOR_INDEX: LazyLock<Mutex<VectorIndex>> = LazyLock::new(|| Mutex::new(VectorIndex::default()));
+
+thread_local! {
+    static EMBED_CALLBACK: RefCell<Option<js_sys::Function>> = const { RefCell::new(None) };
+    static PERSIST_HOOK: RefCell<Option<js_sys::Function>> = const { RefCell::new(None) };
+    static SYNCING: Cell<bool> = const { Cell::new(false) };
+}
+
+fn persist(index: &VectorIndex) {
+    PERSIST_HOOK.with(|hook| {
+        if let Some(hook) = hook.borrow().as_ref() {
+            let snapshot = serde_json::to_string(&index.snapshot()).unwrap_or_default();
+            if let Err(e) = hook.call1(&JsValue::NULL, &JsValue::from_str(&snapshot)) {
+                debug(&format!("Persisting vector index failed: {:?}", e));
+            }
+        }
+    });
+}
+
+/// Read an embedding from a tool result: `structuredContent` as an array
+/// or with an `embedding` field, else the same shapes as JSON text.
+fn embedding_from_result(result: &serde_json::Value) -> Option<Vec<f32>> {
+    let parse = |value: &serde_json::Value| {
+        let vector = value.get("embedding").unwrap_or(value);
+        serde_json::from_value::<Vec<f32>>(vector.clone()).ok()
+    };
+    if let Some(vector) = result.get("structuredContent").and_then(parse) {
+        return Some(vector);
+    }
+    result["content"]
+        .as_array()?
+        .iter()
+        .filter_map(|block| block["text"].as_str())
+        .find_map(|text| serde_json::from_str::<serde_json::Value>(text).ok().as_ref().and_then(parse))
+}
+
+async fn embed(provider: &EmbeddingProvider, texts: &[String]) -> Result<Vec<Vec<f32>>, String> {
+    match provider {
+        EmbeddingProvider::Hashing { dimensions } => Ok(texts.iter().map(|text| hashing_embedding(text, *dimensions)).collect()),
+        EmbeddingProvider::Js => {
+            let callback = EMBED_CALLBACK
+                .with(|slot| slot.borrow().clone())
+                .ok_or_else(|| "No embedding callback registered".to_string())?;
+            let input = serde_json::to_string(texts).unwrap_or_default();
+            let value = callback.call1(&JsValue::NULL, &JsValue::from_str(&input)).map_err(|e| format!("{:?}", e))?;
+            let value = JsFuture::from(js_sys::Promise::resolve(&value)).await.map_err(|e| format!("{:?}", e))?;
+            let vectors: Vec<Vec<f32>> = match value.as_string() {
+                Some(json) => serde_json::from_str(&json).map_err(|e| format!("Invalid embeddings: {}", e))?,
+                None => serde_wasm_bindgen::from_value(value).map_err(|e| format!("Invalid embeddings: {}", e))?,
+            };
+            if vectors.len() != texts.len() {
+                return Err(format!("Expected {} embeddings, got {}", texts.len(), vectors.len()));
+            }
+            Ok(vectors)
+        }
+        EmbeddingProvider::Tool { server_url, tool_name, text_argument } => {
+            let mut vectors = Vec::with_capacity(texts.len());
+            for text in texts {
+                // Simulated vectors would only pollute the index
+                let outcome = run_tool_call(server_url, tool_name, json!({ text_argument: text }), None, None, false).await;
+                let ToolCallOutcome::Success { result, .. } = outcome else {
+                    return Err(format!("Embedding tool failed: {}", outcome.to_json_string()));
+                };
+                vectors.push(embedding_from_result(&result).ok_or_else(|| "Embedding tool returned no vector".to_string())?);
+            }
+            Ok(vectors)
+        }
+    }
+}
+
+/// Bring the index in line with the live memory events: drop vectors of
+/// removed or expired events and embed up to `SYNC_LIMIT` new ones in
+/// batches. Returns how many events were embedded; a sync already running
+/// is not joined.
+async fn sync() -> Result<usize, String> {
+    if SYNCING.with(|syncing| syncing.replace(true)) {
+        return Ok(0);
+    }
+    let result = sync_batches().await;
+    SYNCING.with(|syncing| syncing.set(false));
+    result
+}
+
+async fn sync_batches() -> Result<usize, String> {
+    let events = live_events();
+    let (provider, missing) = {
+        let mut index = VECTOR_INDEX.lock().unwrap();
+        let Some(provider) = index.config.provider.clone() else {
+            return Err("No embedding provider configured".to_string());
+        };
+        let live: HashSet<u64> = events.iter().map(|event| event.id).collect();
+        let stale: Vec<u64> = index.positions.keys().filter(|id| !live.contains(id)).copied().collect();
+        stale.iter().for_each(|id| index.remove(*id));
+        let missing: Vec<MemoryEvent> = events
+            .into_iter()
+            .filter(|event| !index.positions.contains_key(&event.id))
+            .take(SYNC_LIMIT)
+            .collect();
+        if missing.is_empty() && !stale.is_empty() {
+            persist(&index);
+        }
+        (provider, missing)
+    };
+    let mut embedded = 0;
+    for batch in missing.chunks(EMBED_BATCH) {
+        let texts: Vec<String> = batch.iter().map(|event| event.text.clone()).collect();
+        let vectors = embed(&provider, &texts).await?;
+        let mut index = VECTOR_INDEX.lock().unwrap();
+        // The provider may have changed while embedding
+        if index.config.provider.as_ref() != Some(&provider) {
+            break;
+        }
+        for (event, vector) in batch.iter().zip(vectors) {
+            index.insert(event.id, vector)?;
+        }
+        persist(&index);
+        embedded += batch.len();
+    }
+    if embedded > 0 {
+        info(&format!("Embedded {} memory event(s)", embedded));
+    }
+    Ok(embedded)
+}
+
+/// Whether a sync is running and how many live events have no vector yet.
+fn sync_status() -> serde_json::Value {
+    let ids = live_event_ids();
+    let index = VECTOR_INDEX.lock().unwrap();
+    let pending = ids.iter().filter(|id| !index.positions.contains_key(id)).count();
+    json!({
+        "syncing": SYNCING.with(Cell::get),
+        "pending": pending,
+        "indexed": index.positions.len(),
+    })
+}
+
+/// Input to `vector_search`: the text to match, how many hits, and memory
+/// filters (`since`, `until`, `tags`, `text`, `source`, `engram_id`).
+#[derive(Debug, Deserialize, Default)]
+#[serde(default)]
+struct VectorQuery {
+    query: String,
+    k: Option<usize>,
+    min_score: Option<f32>,
+    #[serde(flatten)]
+    filter: MemoryQuery,
+}
+
+async fn search(mut query: VectorQuery) -> Result<serde_json::Value, String> {
+    sync().await?;
+    let (provider, k, min_score) = {
+        let index = VECTOR_INDEX.lock().unwrap();
+        let config = &index.config;
+        (config.provider.clone(), query.k.unwrap_or(config.top_k), query.min_score.unwrap_or(config.min_score))
+    };
+    let provider = provider.ok_or_else(|| "No embedding provider configured".to_string())?;
+    let vector = embed(&provider, std::slice::from_ref(&query.query)).await?.pop().unwrap_or_default();
+    query.filter.limit = None;
+    let events: HashMap<u64, MemoryEvent> = query_events(&query.filter).into_iter().map(|event| (event.id, event)).collect();
+    let allowed: HashSet<u64> = events.keys().copied().collect();
+    let nearest = VECTOR_INDEX.lock().unwrap().nearest(&normalize(vector), k, &allowed);
+    let hits: Vec<serde_json::Value> = nearest
+        .into_iter()
+        .filter(|(_, score)| *score >= min_score)
+        .filter_map(|(id, score)| Some(json!({ "score": score, "event": events.get(&id)? })))
+        .collect();
+    Ok(json!({ "hits": hits }))
+}
+
+/// Takes `{ provider, index, top_k, min_score }`. `provider` is
+/// `{ kind: "js" }`, `{ kind: "tool", server_url, tool_name, text_argument? }`
+/// or `{ kind: "hashing", dimensions? }`; `index` is `{ kind: "flat" }` or
+/// `{ kind: "hnsw", m?, ef_construction?, ef_search? }`. Changing the
+/// provider drops every vector.
+#[wasm_bindgen]
+pub fn set_vector_config(config_json: &str) -> Result<(), JsValue> {
+    let config: VectorConfig = serde_json::from_str(config_json)
+        .map_err(|e| JsValue::from_str(&format!("Invalid vector config: {}", e)))?;
+    let mut index = VECTOR_INDEX.lock().unwrap();
+    if index.config.provider != config.provider {
+        index.entries.clear();
+    }
+    index.config = config;
+    index.rebuild();
+    persist(&index);
+    info("Updated vector memory config");
+    Ok(())
+}
+
+#[wasm_bindgen]
+pub fn get_vector_config() -> String {
+    serde_json::to_string(&VECTOR_INDEX.lock().unwrap().config).unwrap_or_default()
+}
+
+/// Register the function behind the `js` provider: it receives a JSON array
+/// of texts and returns (a promise of) an array of vectors, or its JSON.
+#[wasm_bindgen]
+pub fn set_embedding_callback(callback: Option<js_sys::Function>) {
+    EMBED_CALLBACK.with(|slot| *slot.borrow_mut() = callback);
+}
+
+/// Embed memory events not yet in the index, up to 512 per call; resolves
+/// to how many were.
+#[wasm_bindgen]
+pub fn sync_vector_index() -> js_sys::Promise {
+    wasm_bindgen_futures::future_to_promise(async move {
+        sync().await.map(|count| JsValue::from(count as u32)).map_err(|e| JsValue::from_str(&e))
+    })
+}
+
+/// `{ syncing, pending, indexed }`: whether a sync is running, how many live
+/// events still lack a vector and how many have one.
+#[wasm_bindgen]
+pub fn get_vector_sync_status() -> String {
+    sync_status().to_string()
+}
+
+/// Top-k memory events by cosine similarity to `query`. Takes
+/// `{ query, k?, min_score?, engram_id?, tags?, source?, since?, until?, text? }`
+/// and resolves to `{ hits: [{ score, event }] }`, best first.
+#[wasm_bindgen]
+pub fn vector_search(query_json: &str) -> Result<js_sys::Promise, JsValue> {
+    let query: VectorQuery = serde_json::from_str(query_json)
+        .map_err(|e| JsValue::from_str(&format!("Invalid vector query: {}", e)))?;
+    Ok(wasm_bindgen_futures::future_to_promise(async move {
+        let hits = search(query).await.map_err(|e| JsValue::from_str(&e))?;
+        Ok(JsValue::from_str(&hits.to_string()))
+    }))
+}
+
+/// The config and vectors as JSON, for storage.
+#[wasm_bindgen]
+pub fn get_vector_index() -> String {
+    serde_json::to_string(&VECTOR_INDEX.lock().unwrap().snapshot()).unwrap_or_default()
+}
+
+/// Restore a snapshot saved from `get_vector_index` or the persistence
+/// hook.
+#[wasm_bindgen]
+pub fn restore_vector_index(snapshot_json: &str) -> Result<(), JsValue> {
+    let snapshot: VectorSnapshot = serde_json::from_str(snapshot_json)
+        .map_err(|e| JsValue::from_str(&format!("Invalid vector index: {}", e)))?;
+    let mut index = VECTOR_INDEX.lock().unwrap();
+    index.config = snapshot.config;
+    index.entries = snapshot.entries;
+    index.rebuild();
+    Ok(())
+}
+
+/// Register a function called with the snapshot as JSON whenever the
+/// index changes; pass `null` to unregister.
+#[wasm_bindgen]
+pub fn set_vector_persistence(hook: Option<js_sys::Function>) {
+    PERSIST_HOOK.with(|slot| *slot.borrow_mut() = hook);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn random_vectors(count: usize, dimensions: usize) -> Vec<Vec<f32>> {
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state >> 40) as f32 / (1u64 << 24) as f32 - 0.5
+        };
+        (0..count).map(|_| (0..dimensions).map(|_| next()).collect()).collect()
+    }
+
+    fn index(kind: IndexKind, vectors: &[Vec<f32>]) -> VectorIndex {
+        let mut index = VectorIndex { config: VectorConfig { index: kind, ..Default::default() }, ..Default::default() };
+        index.rebuild();
+        for (id, vector) in vectors.iter().enumerate() {
+            index.insert(id as u64, vector.clone()).unwrap();
+        }
+        index
+    }
+
+    #[test]
+    fn hnsw_recall_matches_flat_search() {
+        let vectors = random_vectors(600, 16);
+        let flat = index(IndexKind::Flat, &vectors);
+        let hnsw = index(IndexKind::Hnsw { m: 8, ef_construction: 64, ef_search: 32 }, &vectors);
+        let allowed: HashSet<u64> = (0..vectors.len() as u64).collect();
+        let (mut found, mut wanted) = (0, 0);
+        for query in random_vectors(650, 16).iter().skip(600).map(|q| normalize(q.clone())) {
+            let exact: HashSet<u64> = flat.nearest(&query, 10, &allowed).into_iter().map(|(id, _)| id).collect();
+            let approximate = hnsw.nearest(&query, 10, &allowed);
+            assert_eq!(approximate.len(), 10);
+            assert!(approximate.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+            found += approximate.iter().filter(|(id, _)| exact.contains(id)).count();
+            wanted += exact.len();
+        }
+        assert!(found as f64 / wanted as f64 >= 0.9, "recall {}/{}", found, wanted);
+    }
+
+    #[test]
+    fn nearest_skips_removed_and_filtered_entries() {
+        let vectors = random_vectors(40, 8);
+        let mut hnsw = index(IndexKind::Hnsw { m: 4, ef_construction: 16, ef_search: 8 }, &vectors);
+        let query = normalize(vectors[7].clone());
+        let all: HashSet<u64> = (0..40).collect();
+        assert_eq!(hnsw.nearest(&query, 1, &all)[0].0, 7);
+        hnsw.remove(7);
+        assert!(hnsw.nearest(&query, 5, &all).iter().all(|(id, _)| *id != 7));
+        // Too few graph hits among the allowed ids falls back to a scan
+        let few: HashSet<u64> = [3, 30].into();
+        let ids: HashSet<u64> = hnsw.nearest(&query, 5, &few).into_iter().map(|(id, _)| id).collect();
+        assert_eq!(ids, few);
+        assert!(hnsw.insert(99, vec![1.0; 3]).is_err());
+    }
+
+    #[test]
+    fn hashing_embedding_is_normalized_and_stemmed() {
+        let embed = |text: &str| hashing_embedding(text, 64);
+        let vector = embed("Running the servers");
+        assert_eq!(vector.len(), 64);
+        assert!((similarity(&vector, &vector) - 1.0).abs() < 1e-5);
+        assert!((similarity(&vector, &embed("run the server")) - 1.0).abs() < 1e-5);
+        assert!(similarity(&vector, &embed("bake a cake")) < 0.9);
+        assert!(embed("").iter().all(|x| *x == 0.0));
+        assert_eq!(hashing_embedding("x", 0).len(), 1);
+    }
+}